@@ -54,7 +54,8 @@ fn get_markdown_path(matches: &clap::ArgMatches) -> Option<PathBuf> {
 /// Get the configuration source based on CLI arguments or default behavior.
 ///
 /// Priority order:
-/// 1. If `--config` is explicitly provided, use that file
+/// 1. If `--config` is explicitly provided, use that file; when it is repeated, the files
+///    are merged, later ones over earlier ones
 /// 2. If `markdown2pdfrc.toml` exists in current directory, use it
 /// 3. Otherwise use default configuration
 ///
@@ -65,10 +66,14 @@ fn get_markdown_path(matches: &clap::ArgMatches) -> Option<PathBuf> {
 /// A `ConfigSource` that specifies where to load configuration from
 fn get_config_source(matches: &clap::ArgMatches) -> markdown2pdf::config::ConfigSource {
     // Check if --config was explicitly provided
-    if let Some(config_file) = matches.get_one::<String>("config") {
-        return markdown2pdf::config::ConfigSource::File(Box::leak(
-            config_file.to_string().into_boxed_str(),
-        ));
+    if let Some(config_files) = matches.get_many::<String>("config") {
+        let mut paths: Vec<&'static str> = config_files
+            .map(|path| &*Box::leak(path.to_string().into_boxed_str()))
+            .collect();
+        if paths.len() == 1 {
+            return markdown2pdf::config::ConfigSource::File(paths.remove(0));
+        }
+        return markdown2pdf::config::ConfigSource::Merged(paths);
     }
 
     // Check if markdown2pdfrc.toml exists in current directory
@@ -80,22 +85,128 @@ fn get_config_source(matches: &clap::ArgMatches) -> markdown2pdf::config::Config
     markdown2pdf::config::ConfigSource::Default
 }
 
-fn get_output_path(matches: &clap::ArgMatches) -> Result<PathBuf, AppError> {
+/// Loads the style configuration, reads the `SOURCE_DATE_EPOCH` timestamp of the
+/// environment and applies the CLI flags that override configuration values
+/// (`--reproducible`, `--create-dirs`, `--max-image-download-size`, `--page-size`,
+/// `--orientation`, `--theme-color`). Also returns the warnings about the configuration,
+/// such as color names missing from its `[palette]`.
+fn load_style(
+    matches: &clap::ArgMatches,
+) -> (
+    markdown2pdf::styling::StyleMatch,
+    Vec<validation::ValidationWarning>,
+) {
+    let (mut style, warnings) =
+        markdown2pdf::config::load_config_from_source_reported(get_config_source(matches));
+    // --reproducible forces deterministic metadata regardless of the configuration file
+    if matches.get_flag("reproducible") {
+        style.document.reproducible = true;
+    }
+    style.document.source_date_epoch = markdown2pdf::config::source_date_epoch();
+    if matches.get_flag("create-dirs") {
+        style.document.create_dirs = true;
+    }
+    if let Some(size) = matches.get_one::<u64>("max-image-download-size") {
+        style.remote_images.max_download_size = Some(*size);
+    }
+    // The values are restricted by clap, so the names always parse
+    if let Some(size) = matches
+        .get_one::<String>("page-size")
+        .and_then(|name| markdown2pdf::styling::PageSize::from_name(name))
+    {
+        style.page_size = size;
+    }
+    if let Some(orientation) = matches
+        .get_one::<String>("orientation")
+        .and_then(|name| markdown2pdf::styling::Orientation::from_name(name))
+    {
+        style.orientation = orientation;
+    }
+    // Not every command defines --theme-color, so look it up loosely
+    if let Some(color) = matches
+        .try_get_one::<(u8, u8, u8)>("theme-color")
+        .ok()
+        .flatten()
+    {
+        style = style.with_theme_color(*color);
+    }
+    (style, warnings)
+}
+
+/// Parses the value of `--theme-color`, a `#RRGGBB` hex color.
+fn parse_theme_color(value: &str) -> Result<(u8, u8, u8), String> {
+    markdown2pdf::config::parse_hex_color(value)
+        .ok_or_else(|| format!("'{}' is not a #RRGGBB color", value))
+}
+
+/// Returns the output path: `--output-template` expanded for the input file, whose `{date}`
+/// follows the reproducible settings of `style`, or `--output`, or `output.pdf`.
+fn get_output_path(
+    matches: &clap::ArgMatches,
+    style: &markdown2pdf::styling::StyleMatch,
+) -> Result<PathBuf, AppError> {
     let current_dir = std::env::current_dir().map_err(|e| AppError::PathError(e.to_string()))?;
 
+    // Not every command defines the template and path arguments, so look them up loosely
+    if let Some(template) = matches
+        .try_get_one::<String>("output-template")
+        .ok()
+        .flatten()
+    {
+        let input = matches.try_get_one::<String>("path").ok().flatten();
+        let date = markdown2pdf::pdf::Pdf::generation_date(style, "%Y-%m-%d");
+        return Ok(current_dir.join(expand_output_template(
+            template,
+            input.map(std::path::Path::new),
+            &date,
+        )));
+    }
+
     Ok(matches
         .get_one::<String>("output")
         .map(|p| current_dir.join(p))
         .unwrap_or_else(|| current_dir.join("output.pdf")))
 }
 
-/// Detects whether the markdown contains a mermaid fenced code block (```mermaid)
+/// Builds an output path from `--output-template`, replacing `{stem}` with the input file
+/// name without its extension, `{dir}` with the input directory and `{date}` with `date`.
+/// Without an input file (`--string`, `--url`) the stem is `output` and the directory `.`.
+fn expand_output_template(template: &str, input: Option<&std::path::Path>, date: &str) -> String {
+    let stem = input
+        .and_then(|path| path.file_stem())
+        .map_or("output".into(), |stem| stem.to_string_lossy());
+    let dir = input
+        .and_then(|path| path.parent())
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .map_or(".".into(), |dir| dir.to_string_lossy());
+    template
+        .replace("{stem}", &stem)
+        .replace("{dir}", &dir)
+        .replace("{date}", date)
+}
+
+/// Formats the time spent per pipeline stage for the `--verbose` summary, one line per stage.
+fn format_stage_timings(timings: &markdown2pdf::StageTimings) -> Vec<String> {
+    [
+        ("Lexing", timings.lexing),
+        ("Font loading", timings.font_loading),
+        ("Highlighting", timings.highlighting),
+        ("Image loading", timings.image_loading),
+        ("PDF rendering", timings.rendering),
+        ("Total", timings.total()),
+    ]
+    .iter()
+    .map(|(stage, duration)| format!("{:<14} {:>8.1} ms", stage, duration.as_secs_f64() * 1000.0))
+    .collect()
+}
+
+/// Detects whether the markdown contains a mermaid fenced code block (```mermaid or ~~~mermaid)
 fn has_mermaid_block(markdown: &str) -> bool {
     for line in markdown.lines() {
         let s = line.trim_start();
-        if s.starts_with("```") {
-            // remainder after backticks
-            let rest = s.trim_start_matches('`').trim_start();
+        if s.starts_with("```") || s.starts_with("~~~") {
+            // remainder after the fence characters
+            let rest = s.trim_start_matches(|c| c == '`' || c == '~').trim_start();
             if rest.to_lowercase().starts_with("mermaid") {
                 return true;
             }
@@ -114,6 +225,20 @@ mod cli_tests {
         assert!(has_mermaid_block(md));
     }
 
+    #[test]
+    fn test_format_stage_timings() {
+        let timings = markdown2pdf::StageTimings {
+            lexing: std::time::Duration::from_micros(1500),
+            rendering: std::time::Duration::from_millis(40),
+            ..Default::default()
+        };
+        let lines = format_stage_timings(&timings);
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[0], "Lexing              1.5 ms");
+        assert_eq!(lines[4], "PDF rendering      40.0 ms");
+        assert_eq!(lines[5], "Total              41.5 ms");
+    }
+
     #[test]
     fn no_mermaid_block() {
         let md = "# Title\n```rust\nfn main() {}\n```";
@@ -136,7 +261,12 @@ fn run(matches: clap::ArgMatches) -> Result<(), AppError> {
 
     let markdown = get_markdown_input(&matches)?;
     let markdown_path = get_markdown_path(&matches);
-    let output_path = get_output_path(&matches)?;
+
+    // Determine the configuration based on CLI args or defaults. It is loaded once so
+    // validation and the output file name can use it as well.
+    let (style, config_warnings) = load_style(&matches);
+
+    let output_path = get_output_path(&matches, &style)?;
     let output_path_str = output_path
         .to_str()
         .ok_or_else(|| AppError::PathError("Invalid output path".to_string()))?;
@@ -147,39 +277,45 @@ fn run(matches: clap::ArgMatches) -> Result<(), AppError> {
         .map(|values| values.map(|s| s.to_string()).collect())
         .unwrap_or_default();
 
-    let font_config = if matches.contains_id("font-path")
-        || matches.contains_id("default-font")
-        || matches.contains_id("code-font")
-        || !fallback_fonts.is_empty()
-    {
-        let custom_paths: Vec<PathBuf> = matches
-            .get_many::<String>("font-path")
-            .map(|values| values.map(PathBuf::from).collect())
-            .unwrap_or_default();
-
-        let default_font = matches
-            .get_one::<String>("default-font")
-            .map(|s| s.to_string());
-
-        let code_font = matches
-            .get_one::<String>("code-font")
-            .map(|s| s.to_string());
-
-        Some(markdown2pdf::fonts::FontConfig {
-            custom_paths,
-            default_font,
-            code_font,
-            fallback_fonts,
-            enable_subsetting: true, // Enable subsetting by default for smaller PDFs
-        })
+    // Environment variables (MD2PDF_*) provide defaults; explicit CLI flags take precedence
+    let mut font_config = markdown2pdf::fonts::FontConfig::from_env();
+
+    if let Some(values) = matches.get_many::<String>("font-path") {
+        font_config.custom_paths = values.map(PathBuf::from).collect();
+    }
+    if let Some(font) = matches.get_one::<String>("default-font") {
+        font_config.default_font = Some(font.to_string());
+    }
+    if let Some(font) = matches.get_one::<String>("code-font") {
+        font_config.code_font = Some(font.to_string());
+    }
+    if !fallback_fonts.is_empty() {
+        font_config.fallback_fonts = fallback_fonts;
+    }
+
+    let font_config = if font_config.has_overrides() {
+        Some(font_config)
     } else {
         None
     };
 
-    // Run validation checks
+    // Run the pre-flight checks of the library, after the warnings about the configuration
+    // files, which the loaded style no longer carries
+    let preflight = || -> Result<Vec<validation::ValidationWarning>, AppError> {
+        let mut warnings = config_warnings;
+        warnings.extend(
+            markdown2pdf::dry_run(
+                &markdown,
+                Some(output_path_str),
+                markdown2pdf::config::ConfigSource::Style(style.clone()),
+                font_config.as_ref(),
+            )
+            .map_err(|e| AppError::ConversionError(e.to_string()))?,
+        );
+        Ok(warnings)
+    };
     if verbosity != Verbosity::Quiet {
-        let warnings =
-            validation::validate_conversion(&markdown, font_config.as_ref(), Some(output_path_str));
+        let warnings = preflight()?;
 
         if !warnings.is_empty() {
             if verbosity == Verbosity::Verbose {
@@ -203,8 +339,7 @@ fn run(matches: clap::ArgMatches) -> Result<(), AppError> {
             return Ok(());
         }
     } else if dry_run {
-        let warnings =
-            validation::validate_conversion(&markdown, font_config.as_ref(), Some(output_path_str));
+        let warnings = preflight()?;
         if warnings.is_empty() {
             return Ok(());
         } else {
@@ -274,10 +409,28 @@ fn run(matches: clap::ArgMatches) -> Result<(), AppError> {
     // Use parse_into_file_with_images if we have a document path (for relative image resolution)
     // Otherwise use the basic parse_into_file
 
-    // Determine configuration source based on CLI args or defaults
-    let config_source = get_config_source(&matches);
-
-    if let Some(path) = markdown_path {
+    let config_source = markdown2pdf::config::ConfigSource::Style(style);
+
+    // The reported variants also time each pipeline stage for the verbose summary
+    let report = if verbosity == Verbosity::Verbose {
+        let report = match &markdown_path {
+            Some(path) => markdown2pdf::parse_into_file_with_images_reported(
+                markdown,
+                output_path_str,
+                path,
+                config_source,
+                font_config.as_ref(),
+            ),
+            None => markdown2pdf::parse_into_file_reported(
+                markdown,
+                output_path_str,
+                config_source,
+                font_config.as_ref(),
+            ),
+        }
+        .map_err(|e| AppError::ConversionError(e.to_string()))?;
+        Some(report)
+    } else if let Some(path) = markdown_path {
         markdown2pdf::parse_into_file_with_images(
             markdown,
             output_path_str,
@@ -286,6 +439,7 @@ fn run(matches: clap::ArgMatches) -> Result<(), AppError> {
             font_config.as_ref(),
         )
         .map_err(|e| AppError::ConversionError(e.to_string()))?;
+        None
     } else {
         markdown2pdf::parse_into_file(
             markdown,
@@ -294,7 +448,8 @@ fn run(matches: clap::ArgMatches) -> Result<(), AppError> {
             font_config.as_ref(),
         )
         .map_err(|e| AppError::ConversionError(e.to_string()))?;
-    }
+        None
+    };
 
     if verbosity != Verbosity::Quiet {
         println!("✅ Successfully saved PDF to {}", output_path_str);
@@ -309,6 +464,11 @@ fn run(matches: clap::ArgMatches) -> Result<(), AppError> {
                     println!("   Size: {:.2} MB", size_kb / 1024.0);
                 }
             }
+            if let Some(report) = &report {
+                for line in format_stage_timings(&report.timings) {
+                    println!("   {}", line);
+                }
+            }
         }
     }
 
@@ -357,15 +517,49 @@ fn main() {
             // Default
             let cmd = Command::new("test").arg(Arg::new("output").short('o').long("output"));
             let matches = cmd.clone().get_matches_from(vec!["test"]);
-            let default_path = get_output_path(&matches).unwrap();
+            let style = markdown2pdf::styling::StyleMatch::default();
+            let default_path = get_output_path(&matches, &style).unwrap();
             assert!(default_path.ends_with("output.pdf"));
 
             // Custom
             let matches = cmd.get_matches_from(vec!["test", "-o", "my.pdf"]);
-            let custom_path = get_output_path(&matches).unwrap();
+            let custom_path = get_output_path(&matches, &style).unwrap();
             assert!(custom_path.ends_with("my.pdf"));
         }
 
+        #[test]
+        fn test_output_template() {
+            let input = std::path::Path::new("docs/guide.md");
+            assert_eq!(
+                expand_output_template("{dir}/{stem}_rendered.pdf", Some(input), "2024-03-01"),
+                "docs/guide_rendered.pdf"
+            );
+            assert_eq!(
+                expand_output_template("out/{stem}-{date}.pdf", Some(input), "2024-03-01"),
+                "out/guide-2024-03-01.pdf"
+            );
+            assert_eq!(
+                expand_output_template("{dir}/{stem}.pdf", None, "2024-03-01"),
+                "./output.pdf"
+            );
+            let cmd = Command::new("test")
+                .arg(Arg::new("path").short('p').long("path"))
+                .arg(Arg::new("output-template").long("output-template"));
+            let matches = cmd.get_matches_from(vec![
+                "test",
+                "-p",
+                "notes.md",
+                "--output-template",
+                "{stem}-{date}.pdf",
+            ]);
+            // Reproducible builds name their output the same on any day
+            let mut style = markdown2pdf::styling::StyleMatch::default();
+            style.document.reproducible = true;
+            assert!(get_output_path(&matches, &style)
+                .unwrap()
+                .ends_with("notes-1970-01-01.pdf"));
+        }
+
         #[test]
         fn test_get_markdown_input_from_string_and_file() {
             // From string
@@ -397,6 +591,20 @@ fn main() {
             assert!(matches.get_flag("list-embedded-fonts"));
         }
 
+        #[test]
+        fn test_list_languages_flag() {
+            let cmd = Command::new("test").arg(
+                Arg::new("list-languages")
+                    .long("list-languages")
+                    .action(clap::ArgAction::SetTrue),
+            );
+            let matches = cmd.get_matches_from(vec!["test", "--list-languages"]);
+            assert!(matches.get_flag("list-languages"));
+            assert!(markdown2pdf::highlighting::supported_languages()
+                .iter()
+                .any(|l| l == "rust"));
+        }
+
         #[test]
         fn test_get_default_configuration_flag() {
             let cmd = Command::new("test")
@@ -410,6 +618,81 @@ fn main() {
             let default = markdown2pdf::styling::StyleMatch::default();
             assert_eq!(parsed.heading_1.size, default.heading_1.size);
             assert_eq!(parsed.mermaid.auto_scale, default.mermaid.auto_scale);
+            assert_eq!(parsed, default);
+        }
+
+        #[test]
+        fn test_page_size_flags_override_config() {
+            let cmd = Command::new("test")
+                .arg(Arg::new("config").short('c').long("config"))
+                .arg(
+                    Arg::new("reproducible")
+                        .long("reproducible")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("create-dirs")
+                        .long("create-dirs")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("max-image-download-size")
+                        .long("max-image-download-size")
+                        .value_parser(clap::value_parser!(u64)),
+                )
+                .arg(Arg::new("page-size").long("page-size"))
+                .arg(Arg::new("orientation").long("orientation"));
+            let matches = cmd.get_matches_from(vec![
+                "test",
+                "--page-size",
+                "letter",
+                "--orientation",
+                "landscape",
+            ]);
+            let (style, _) = load_style(&matches);
+            assert_eq!(style.page_size, markdown2pdf::styling::PageSize::Letter);
+            assert_eq!(
+                style.orientation,
+                markdown2pdf::styling::Orientation::Landscape
+            );
+        }
+
+        #[test]
+        fn test_theme_color_flag() {
+            let cmd = Command::new("test")
+                .arg(Arg::new("config").short('c').long("config"))
+                .arg(
+                    Arg::new("reproducible")
+                        .long("reproducible")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("create-dirs")
+                        .long("create-dirs")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("max-image-download-size")
+                        .long("max-image-download-size")
+                        .value_parser(clap::value_parser!(u64)),
+                )
+                .arg(Arg::new("page-size").long("page-size"))
+                .arg(Arg::new("orientation").long("orientation"))
+                .arg(
+                    Arg::new("theme-color")
+                        .long("theme-color")
+                        .value_parser(parse_theme_color),
+                );
+            let matches = cmd
+                .clone()
+                .get_matches_from(vec!["test", "--theme-color", "#00529B"]);
+            let (style, _) = load_style(&matches);
+            assert_eq!(style.heading_2.text_color, Some((0x00, 0x52, 0x9b)));
+            assert_eq!(style.link.text_color, Some((0x00, 0x52, 0x9b)));
+
+            assert!(cmd
+                .try_get_matches_from(vec!["test", "--theme-color", "blue"])
+                .is_err());
         }
 
         #[test]
@@ -452,6 +735,30 @@ fn main() {
             }
         }
 
+        #[test]
+        fn test_get_config_source_repeated_config() {
+            let cmd = Command::new("test").arg(
+                Arg::new("config")
+                    .short('c')
+                    .long("config")
+                    .action(clap::ArgAction::Append),
+            );
+            let matches = cmd.get_matches_from(vec![
+                "test",
+                "--config",
+                "base.toml",
+                "--config",
+                "overrides.toml",
+            ]);
+
+            match get_config_source(&matches) {
+                markdown2pdf::config::ConfigSource::Merged(paths) => {
+                    assert_eq!(paths, vec!["base.toml", "overrides.toml"]);
+                }
+                _ => panic!("Expected Merged config source"),
+            }
+        }
+
         #[test]
         fn test_get_config_source_default_when_no_args() {
             // Test that Default is returned when no config args and no markdown2pdfrc.toml
@@ -511,37 +818,45 @@ fn main() {
                 .value_name("OUTPUT_PATH")
                 .help("Path to the output PDF file (defaults to ./output.pdf)"),
         )
+        .arg(
+            Arg::new("output-template")
+                .long("output-template")
+                .value_name("TEMPLATE")
+                .conflicts_with("output")
+                .help("Name the output after the input file: {stem}, {dir} and {date} (YYYY-MM-DD) are replaced, e.g. \"{dir}/{stem}_rendered.pdf\""),
+        )
         .arg(
             Arg::new("config")
                 .short('c')
                 .long("config")
                 .value_name("CONFIG_FILE")
-                .help("Path to configuration file (TOML format). Auto-detects markdown2pdfrc.toml if not specified"),
+                .help("Path to configuration file (TOML format). Auto-detects markdown2pdfrc.toml if not specified. Repeat to merge several files, later ones over earlier ones")
+                .action(clap::ArgAction::Append),
         )
         .arg(
             Arg::new("font-path")
                 .long("font-path")
                 .value_name("PATH")
-                .help("Path to custom font directory or font file")
+                .help("Path to custom font directory or font file (overrides MD2PDF_FONT_PATH)")
                 .action(clap::ArgAction::Append),
         )
         .arg(
             Arg::new("default-font")
                 .long("default-font")
                 .value_name("FONT_NAME")
-                .help("Default font family to use (default: helvetica)"),
+                .help("Default font family to use (default: helvetica, overrides MD2PDF_DEFAULT_FONT)"),
         )
         .arg(
             Arg::new("code-font")
                 .long("code-font")
                 .value_name("FONT_NAME")
-                .help("Font for code blocks (default: courier)"),
+                .help("Font for code blocks (default: courier, overrides MD2PDF_CODE_FONT)"),
         )
         .arg(
             Arg::new("fallback-font")
                 .long("fallback-font")
                 .value_name("FONT_NAME")
-                .help("Fallback font for missing characters (can be specified multiple times)")
+                .help("Fallback font for missing characters (can be specified multiple times, overrides MD2PDF_FALLBACK_FONTS)")
                 .action(clap::ArgAction::Append),
         )
         .arg(
@@ -573,6 +888,18 @@ fn main() {
                 .help("List embedded binary fonts and exit")
                 .action(clap::ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("languages-from")
+                .long("languages-from")
+                .value_name("DIR")
+                .help("Load additional .sublime-syntax files from DIR for syntax highlighting"),
+        )
+        .arg(
+            Arg::new("list-languages")
+                .long("list-languages")
+                .help("List code fence languages recognized by syntax highlighting and exit")
+                .action(clap::ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("get-default-configuration")
                 .long("get-default-configuration")
@@ -584,6 +911,48 @@ fn main() {
                 .long("show-missing-glyphs")
                 .help("List missing glyphs detected by font coverage checks before generating PDF")
                 .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("reproducible")
+                .long("reproducible")
+                .help("Produce byte-identical output: fixed timestamps (SOURCE_DATE_EPOCH if set) and a content-derived document ID")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("create-dirs")
+                .long("create-dirs")
+                .help("Create missing directories of the output path instead of failing")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("max-image-download-size")
+                .long("max-image-download-size")
+                .value_name("BYTES")
+                .value_parser(clap::value_parser!(u64))
+                .help("Reject remote images larger than BYTES (a placeholder is rendered instead)"),
+        )
+        .arg(
+            Arg::new("page-size")
+                .long("page-size")
+                .value_name("SIZE")
+                .value_parser(["a3", "a4", "a5", "letter", "legal"])
+                .ignore_case(true)
+                .help("Paper size, overriding [page] size from the configuration"),
+        )
+        .arg(
+            Arg::new("orientation")
+                .long("orientation")
+                .value_name("ORIENTATION")
+                .value_parser(["portrait", "landscape"])
+                .ignore_case(true)
+                .help("Page orientation, overriding [page] orientation from the configuration"),
+        )
+        .arg(
+            Arg::new("theme-color")
+                .long("theme-color")
+                .value_name("#RRGGBB")
+                .value_parser(parse_theme_color)
+                .help("Accent color for headings, links and code block language labels, overriding the configuration"),
         );
 
     let matches = cmd.clone().get_matches();
@@ -597,6 +966,22 @@ fn main() {
         process::exit(0);
     }
 
+    // Register extra syntaxes before anything highlights or lists languages
+    if let Some(dir) = matches.get_one::<String>("languages-from") {
+        if let Err(e) = markdown2pdf::highlighting::add_syntax_from_dir(dir) {
+            error!("[X] {}", e);
+            process::exit(1);
+        }
+    }
+
+    // List the fence languages that highlight, so authors can check their code blocks
+    if matches.get_flag("list-languages") {
+        for language in markdown2pdf::highlighting::supported_languages() {
+            println!("{}", language);
+        }
+        process::exit(0);
+    }
+
     // Print a default configuration TOML and exit if requested
     if matches.get_flag("get-default-configuration") {
         println!("{}", markdown2pdf::config::default_config_toml());
@@ -7,18 +7,119 @@
 //! # Configuration Structure
 //!
 //! The configuration uses TOML format with sections for different element types:
-//! - The `margin` section controls document margins (top, right, bottom, left)
-//! - `heading.1`, `heading.2`, `heading.3` customize heading styles per level
-//! - `text` defines the default text appearance
+//! - The `margin` section controls document margins (top, right, bottom, left);
+//!   `page.first_page_margins` overrides them on the first page
+//! - `page.size` ("a3", "a4", "a5", "letter" or "legal") and `page.orientation`
+//!   ("portrait" or "landscape") set the paper format; `page.min_lines_top` and
+//!   `page.min_lines_bottom` (default 1) set how many lines of a paragraph split across
+//!   pages must start the next page and end the current one
+//! - `page.footer` prints a line in the bottom margin of every page: `show_date = true`
+//!   adds the generation date, formatted by `date_format` (default `"%Y-%m-%d"`, with
+//!   `%Y`, `%m`, `%d`, `%H`, `%M` and `%S` in UTC), and `page_numbers = true` the page
+//!   number; the date follows `document.reproducible` and `SOURCE_DATE_EPOCH`
+//! - `page.header` and `page.footer` take an `image` (path or URL) drawn as a logo in the
+//!   top or bottom margin of every page, placed by `image_alignment` ("left", "center" or
+//!   "right"; left in the header and center in the footer by default) and `image_height`
+//!   in mm (default 6, at most the margin); the footer date and page number move aside
+//!   for a footer logo on their side
+//! - Every `size` is in points, or relative to the `text` size such as `size = "2em"` or
+//!   `size = "150%"`
+//! - `heading.1`, `heading.2`, `heading.3` customize heading styles per level;
+//!   `page_break_before = true` starts each heading of that level on a new page;
+//!   `heading.numbered = true` prefixes headings with section numbers such as `1.2`, and
+//!   `heading.number_from = 2` / `number_to = 3` limit numbering to those levels
+//! - `text` defines the default text appearance; `soft_break = "newline"` turns single
+//!   newlines into line breaks instead of spaces; `quote_style = "en"`, `"fr"` or `"de"`
+//!   sets straight quotes as the quotation marks of the language; `max_width = 360`
+//!   (points) or `max_width = "70ch"` (characters) sets body paragraphs in a narrower
+//!   centered column, while images and tables keep the full width; `fontfile =
+//!   "/path/to/Font.ttf"` sets the text in that font file instead of looking `fontfamily`
+//!   up by name
 //! - `emphasis` handles italic text (*text* or _text_)
 //! - `strong_emphasis` controls bold text styling (**text** or __text__)
-//! - `code` formats both inline code (`code`) and code blocks (``` or ```*)
-//! - `block_quote` styles quoted text (> quote)
-//! - `list_item` formats list entries (- item or * item)
-//! - `link` controls hyperlink appearance ([text](url))
-//! - `image` styles images (![alt](url))
-//! - `table.header` and `table.cell` style table elements
+//! - `code` formats both inline code (`code`) and code blocks (``` or ```*);
+//!   `show_language_label` prints the fence language in the top-right corner of code blocks,
+//!   styled by `code.label`;
+//!   `style = "grayscale"` highlights code blocks in shades of gray instead of colors;
+//!   `keep_together` moves a code block that would split to the next page when it fits there;
+//!   `inline_background` draws inline code on a light rounded box, on by default when
+//!   `backgroundcolor` is set, which then fills the box; `max_lines` cuts longer
+//!   code blocks with a `... (N more lines)` note (default 0, no limit; a single long
+//!   line, as in a minified file, is not cut);
+//!   `fit = "shrink"` reduces the font of blocks with lines wider than the page instead of
+//!   wrapping them
+//! - `block_quote` styles quoted text (> quote); `block_quote.attribution` styles a last
+//!   line starting with an em dash (`— Author`), right-aligned and italic by default
+//! - `list_item` formats list entries (- item or * item); `marker_width` sets the column
+//!   markers are right-aligned in and `indent` the offset per nesting level, both in mm;
+//!   lists nested deeper than `max_nesting` levels are flattened
+//! - `link` controls hyperlink appearance ([text](url)); `style = "footnote"` moves the
+//!   URLs of links to numbered footnotes for print; `base_url` resolves relative link
+//!   targets against a site URL
+//! - `image` styles images (![alt](url)); `base_url` resolves relative image paths
+//!   against a URL; `image.remote` limits remote downloads;
+//!   `fallback_image` replaces images that fail to load, sized like them (a relative path
+//!   is resolved against the configuration file's directory); `upscale = true` enlarges small
+//!   raster images to the image width instead of keeping their natural size;
+//!   `target_dpi` sizes raster images from their pixels for that print resolution;
+//!   `image.gallery` lays consecutive images out in a grid of `columns` with a `gutter`
+//! - `qr.width` sets the width of QR codes rendered from ```` ```qr ```` blocks, in percent
+//!   of the text width (default 25, needs the `qr` feature)
+//! - `table.header` and `table.cell` style table elements; `table.overflow` ("shrink" or
+//!   "scroll") controls tables wider than the page; `table.rotate_if_oversized` turns a
+//!   table that does not fit even at the smallest font onto a landscape page of its own,
+//!   when it fits there
 //! - A `horizontal_rule` section styles divider lines (---)
+//! - `abbreviations` styles abbreviation occurrences and sets `mode` ("glossary" or "first_use");
+//!   `underline` draws a line under them and a `size` other than the `text` size scales
+//!   them relative to the surrounding text
+//! - `kbd` styles `<kbd>` keyboard keys; `backgroundcolor` fills the key box and
+//!   `bordercolor` draws its outline
+//! - `mark` styles `<mark>` highlights; `backgroundcolor` is the highlight color
+//! - `spoiler` styles the content of `||spoiler||` text, shown in a box under a bold
+//!   `label` (default "Spoiler")
+//! - `task_list` colors task list checkboxes (`checkedcolor`, `uncheckedcolor`) and
+//!   the text of completed items (`donecolor`, `strikethrough_done`); `show_progress`
+//!   adds a "Progress: 7/10 completed" line under each task list
+//! - `document` holds output settings such as `reproducible = true`, or
+//!   `title_from_h1 = true` to use a leading `# Title` as the PDF title, or
+//!   `attach_source = true` to embed the markdown source as a file attachment; `lang`
+//!   records the language of the content (e.g. `"en-US"`) for screen readers;
+//!   `create_dirs = true` creates missing directories of the output path;
+//!   `contact_sheet = true` appends an overview of all pages as thumbnails
+//! - `index` controls the index of terms tagged with `<!-- index: term -->` comments,
+//!   listed with their pages at the end of the document: `enabled` and the `title`
+//! - `validation` tunes configuration checks, e.g. `min_contrast = 4.5` for the
+//!   text/background contrast warning
+//! - `spacing` holds document-wide spacing settings: `trim_edges = true` drops the
+//!   spacing before the first element and after the last one
+//! - `redact` hides sensitive text: text matching one of the regular expressions in
+//!   `patterns` is replaced by `mask` characters (default `█`) before rendering
+//! - `palette` defines named colors, e.g. `primary = { r = 0, g = 82, b = 155 }`, that
+//!   any color setting can reference by name: `textcolor = "primary"`; color settings
+//!   also accept a hex string, `textcolor = "#1a2b3c"` or `"#fa0"`
+//! - `fonts.ranges` maps Unicode ranges or script names to fonts, e.g.
+//!   `cjk = "Noto Sans CJK SC"` or `"U+0600-U+06FF" = "Noto Sans Arabic"`
+//!
+//! # Per-Document Overrides
+//!
+//! A document can adjust its own appearance with a `style` block in its front matter,
+//! written in the same TOML format:
+//!
+//! ```markdown
+//! ---
+//! title: Quarterly report
+//! style: |
+//!   [margin]
+//!   left = 20.0
+//! ---
+//! ```
+//!
+//! The block is applied on top of the configuration the conversion was given (the
+//! `--config` file or `markdown2pdfrc.toml`, then the command line flags), so it wins
+//! over both; settings it leaves out keep their configured values. Settings that reach
+//! outside the document, `document.create_dirs` and `image.remote.max_download_size`,
+//! are ignored there with a warning (see [`FRONT_MATTER_REFUSED_KEYS`]).
 //!
 //! # Code Block Styling (Default: Courier New)
 //!
@@ -49,6 +150,9 @@
 //! - `italic` - Italic text (boolean)
 //! - `underline` - Underlined text (boolean)
 //! - `strikethrough` - Strikethrough text (boolean)
+//! - `letterspacing` - Extra space between characters in points (float), for headings
+//! - `text_transform` - "none", "uppercase" or "smallcaps" (string), for headings;
+//!   small caps are set as capitals at a reduced size
 //!
 //! # Configuration Example
 //!
@@ -84,13 +188,28 @@
 //! demonstrates all available styling options.
 
 use crate::styling::{
-    BasicTextStyle, Margins, MermaidConfig, StyleMatch, SvgHeight, SvgImageConfig, SvgWidth,
-    TextAlignment,
+    AbbreviationConfig, AbbreviationMode, BasicTextStyle, CodeFit, CodeHighlightStyle,
+    DocumentConfig, FontRange, FooterConfig, HeaderConfig, HeadingNumbering, ImageGalleryConfig,
+    IndexConfig, KbdConfig, LinkStyle, Margins, MaxWidth, MermaidConfig, Orientation, PageLogo,
+    PageSize, QrConfig, QuoteStyle, RedactConfig, RemoteImageConfig, SoftBreakMode, SpacingConfig,
+    SpoilerConfig, StyleMatch, SvgHeight, SvgImageConfig, SvgWidth, TableOverflow, TaskListConfig,
+    TextAlignment, TextTransform, ValidationConfig,
 };
+use crate::validation::ValidationWarning;
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 use toml::Value;
 
+/// State shared by the section parsers of one configuration document: the named colors
+/// of its `[palette]` section, and the names referenced without being defined there.
+#[derive(Default)]
+struct ParseContext {
+    palette: HashMap<String, (u8, u8, u8)>,
+    unknown_colors: RefCell<Vec<String>>,
+}
+
 /// Configuration source for styling configuration.
 /// Determines where the TOML configuration should be loaded from.
 #[derive(Debug, Clone)]
@@ -101,6 +220,49 @@ pub enum ConfigSource<'a> {
     File(&'a str),
     /// Use embedded TOML configuration string (compile-time embedded)
     Embedded(&'a str),
+    /// Use a JSON configuration string with the same structure as the TOML file
+    #[cfg(feature = "json")]
+    Json(&'a str),
+    /// Use a YAML configuration string with the same structure as the TOML file
+    #[cfg(feature = "yaml")]
+    Yaml(&'a str),
+    /// Use an already built style, e.g. one loaded from another source and adjusted in code
+    Style(StyleMatch),
+    /// Load several configuration files, each applied on top of the ones before it and
+    /// able to reference the `[palette]` colors they define.
+    /// Files that are missing or invalid are skipped
+    Merged(Vec<&'a str>),
+}
+
+/// Parses a `#RRGGBB` or `#RGB` hex color string into RGB values. In the short form each
+/// digit is doubled, so `#fa0` is `#ffaa00`.
+///
+/// # Examples
+/// ```rust
+/// use markdown2pdf::config::parse_hex_color;
+///
+/// assert_eq!(parse_hex_color("#1a2B3c"), Some((0x1a, 0x2b, 0x3c)));
+/// assert_eq!(parse_hex_color("#fa0"), Some((0xff, 0xaa, 0x00)));
+/// assert_eq!(parse_hex_color("1a2b3c"), None);
+/// ```
+pub fn parse_hex_color(s: &str) -> Option<(u8, u8, u8)> {
+    let hex = s.strip_prefix('#')?;
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |digits: &str| u8::from_str_radix(digits, 16).ok();
+    match hex.len() {
+        6 => Some((
+            channel(&hex[0..2])?,
+            channel(&hex[2..4])?,
+            channel(&hex[4..6])?,
+        )),
+        3 => {
+            let short = |i: usize| channel(&hex[i..i + 1]).map(|digit| digit * 17);
+            Some((short(0)?, short(1)?, short(2)?))
+        }
+        _ => None,
+    }
 }
 
 /// Parses an RGB color from a TOML configuration value.
@@ -108,15 +270,45 @@ pub enum ConfigSource<'a> {
 /// The value parameter provides an optional TOML value containing a color object.
 /// The field parameter specifies which color field to parse from the configuration.
 /// Returns the RGB color values as a tuple if parsing succeeds, or None if the color
-/// value is missing or invalid.
-fn parse_color(value: Option<&Value>, field: &str) -> Option<(u8, u8, u8)> {
-    value.and_then(|c| {
-        let color = c.get(field)?;
-        let r = color.get("r")?.as_integer()? as u8;
-        let g = color.get("g")?.as_integer()? as u8;
-        let b = color.get("b")?.as_integer()? as u8;
-        Some((r, g, b))
-    })
+/// value is missing or invalid. A string value is either a `#RRGGBB` / `#RGB` hex color
+/// or the name of a color of the `[palette]` section; names missing from it are recorded
+/// in `ctx` for a validation warning.
+fn parse_color(value: Option<&Value>, field: &str, ctx: &ParseContext) -> Option<(u8, u8, u8)> {
+    let color = value?.get(field)?;
+    let Some(name) = color.as_str().filter(|name| !name.starts_with('#')) else {
+        return parse_rgb(color);
+    };
+    let color = ctx.palette.get(name).copied();
+    let mut unknown = ctx.unknown_colors.borrow_mut();
+    if color.is_none() && !unknown.iter().any(|n| n == name) {
+        unknown.push(name.to_string());
+    }
+    color
+}
+
+/// Parses an inline `{ r, g, b }` color table or a hex color string.
+fn parse_rgb(color: &Value) -> Option<(u8, u8, u8)> {
+    if let Some(hex) = color.as_str() {
+        return parse_hex_color(hex);
+    }
+    let r = color.get("r")?.as_integer()? as u8;
+    let g = color.get("g")?.as_integer()? as u8;
+    let b = color.get("b")?.as_integer()? as u8;
+    Some((r, g, b))
+}
+
+/// Parses the [palette] section: named `{ r, g, b }` colors that other sections reference
+/// by name, e.g. `textcolor = "primary"`. Entries that are not valid colors are skipped.
+fn parse_palette(value: Option<&Value>) -> HashMap<String, (u8, u8, u8)> {
+    value
+        .and_then(|p| p.as_table())
+        .map(|table| {
+            table
+                .iter()
+                .filter_map(|(name, color)| Some((name.clone(), parse_rgb(color)?)))
+                .collect()
+        })
+        .unwrap_or_default()
 }
 
 /// Parses text alignment from TOML configuration.
@@ -151,12 +343,21 @@ fn map_font_family(font: &str) -> Option<&'static str> {
 /// font family and text decorations. Takes an optional TOML value containing
 /// the style configuration and a default style to use for missing properties.
 /// Returns a complete BasicTextStyle with all properties set to either parsed
-/// or default values.
-fn parse_style(value: Option<&Value>, default: BasicTextStyle) -> BasicTextStyle {
+/// or default values. Relative sizes such as `"2em"` or `"150%"` are resolved
+/// against `base_size`, the body text size in points.
+fn parse_style(
+    value: Option<&Value>,
+    default: BasicTextStyle,
+    base_size: u8,
+    ctx: &ParseContext,
+) -> BasicTextStyle {
     let mut style = default.clone();
     if let Some(style_config) = value {
-        if let Some(size) = style_config.get("size").and_then(|v| v.as_integer()) {
-            style.size = size as u8;
+        if let Some(size) = style_config
+            .get("size")
+            .and_then(|v| parse_size(v, base_size))
+        {
+            style.size = size;
         }
 
         if let Some(spacing) = style_config.get("beforespacing").and_then(|v| v.as_float()) {
@@ -167,10 +368,10 @@ fn parse_style(value: Option<&Value>, default: BasicTextStyle) -> BasicTextStyle
             style.after_spacing = spacing as f32;
         }
 
-        if let Some(color) = parse_color(Some(style_config), "textcolor") {
+        if let Some(color) = parse_color(Some(style_config), "textcolor", ctx) {
             style.text_color = Some(color);
         }
-        if let Some(bg_color) = parse_color(Some(style_config), "backgroundcolor") {
+        if let Some(bg_color) = parse_color(Some(style_config), "backgroundcolor", ctx) {
             style.background_color = Some(bg_color);
         }
 
@@ -194,10 +395,47 @@ fn parse_style(value: Option<&Value>, default: BasicTextStyle) -> BasicTextStyle
         if let Some(strikethrough) = style_config.get("strikethrough").and_then(|v| v.as_bool()) {
             style.strikethrough = strikethrough;
         }
+        if let Some(spacing) = style_config
+            .get("letterspacing")
+            .and_then(|v| v.as_float().or_else(|| v.as_integer().map(|i| i as f64)))
+        {
+            style.letter_spacing = spacing as f32;
+        }
+        if let Some(transform) = style_config
+            .get("text_transform")
+            .and_then(|v| v.as_str())
+            .and_then(TextTransform::from_name)
+        {
+            style.text_transform = transform;
+        }
     }
     style
 }
 
+/// Parses a font size: an integer in points, or a string relative to `base_size` in `em`
+/// (`"1.5em"`) or percent (`"150%"`), rounded to the nearest point.
+fn parse_size(value: &Value, base_size: u8) -> Option<u8> {
+    if let Some(size) = value.as_integer() {
+        return Some(size as u8);
+    }
+    let size = value.as_str()?.trim();
+    let factor = if let Some(em) = size.strip_suffix("em") {
+        em.trim().parse::<f32>().ok()?
+    } else if let Some(percent) = size.strip_suffix('%') {
+        percent.trim().parse::<f32>().ok()? / 100.0
+    } else {
+        return None;
+    };
+    if factor <= 0.0 {
+        return None;
+    }
+    Some(
+        (factor * base_size as f32)
+            .round()
+            .clamp(1.0, u8::MAX as f32) as u8,
+    )
+}
+
 /// Parses SVG image configuration from TOML.
 ///
 /// Extracts the [image.svg] section and parses width and height specifications.
@@ -205,9 +443,10 @@ fn parse_style(value: Option<&Value>, default: BasicTextStyle) -> BasicTextStyle
 /// - "100%" for percentage of page width
 /// - "100px" or just "100" for pixel values
 /// - omitted for auto (original size)
+/// - "auto" or omitted for auto (original size)
 /// Height can be specified as:
 /// - "100px" or just "100" for pixel values
-/// - omitted for auto (maintain aspect ratio)
+/// - "auto" or omitted for auto (maintain aspect ratio)
 fn parse_svg_config(value: Option<&Value>, default: SvgImageConfig) -> SvgImageConfig {
     let mut config = default;
 
@@ -215,7 +454,9 @@ fn parse_svg_config(value: Option<&Value>, default: SvgImageConfig) -> SvgImageC
         // Parse width
         if let Some(width_val) = svg_config.get("width") {
             if let Some(width_str) = width_val.as_str() {
-                if width_str.ends_with("%") {
+                if width_str == "auto" {
+                    config.width = SvgWidth::Auto;
+                } else if width_str.ends_with("%") {
                     if let Ok(percent) = width_str.trim_end_matches("%").parse::<f32>() {
                         config.width = SvgWidth::Percentage(percent);
                     }
@@ -232,7 +473,9 @@ fn parse_svg_config(value: Option<&Value>, default: SvgImageConfig) -> SvgImageC
         // Parse height
         if let Some(height_val) = svg_config.get("height") {
             if let Some(height_str) = height_val.as_str() {
-                if height_str.ends_with("px") {
+                if height_str == "auto" {
+                    config.height = SvgHeight::Auto;
+                } else if height_str.ends_with("px") {
                     if let Ok(pixels) = height_str.trim_end_matches("px").parse::<f32>() {
                         config.height = SvgHeight::Pixels(pixels);
                     }
@@ -259,6 +502,21 @@ fn parse_svg_config(value: Option<&Value>, default: SvgImageConfig) -> SvgImageC
     config
 }
 
+/// Parses the [qr] configuration section: `width`, a percentage of the text width such
+/// as `25` or `"25%"`, between 1 and 100.
+fn parse_qr_config(value: Option<&Value>, default: QrConfig) -> QrConfig {
+    let mut config = default;
+    let width = value.and_then(|q| q.get("width")).and_then(|v| {
+        v.as_float()
+            .or_else(|| v.as_integer().map(|i| i as f64))
+            .or_else(|| v.as_str()?.trim().trim_end_matches('%').parse().ok())
+    });
+    if let Some(width) = width.filter(|w| *w > 0.0 && *w <= 100.0) {
+        config.width = width as f32;
+    }
+    config
+}
+
 /// Parses the [mermaid] configuration section.
 ///
 /// - `auto_scale`: float (or int) used by Mermaid renderer for scaling
@@ -288,6 +546,644 @@ fn parse_mermaid_config(value: Option<&Value>, default: MermaidConfig) -> Mermai
     config
 }
 
+/// Parses the [image.remote] configuration section.
+///
+/// - `max_download_size`: integer, maximum size in bytes of a downloaded image
+fn parse_remote_image_config(
+    value: Option<&Value>,
+    default: RemoteImageConfig,
+) -> RemoteImageConfig {
+    let mut config = default;
+    if let Some(r) = value {
+        if let Some(size) = r.get("max_download_size").and_then(|v| v.as_integer()) {
+            config.max_download_size = u64::try_from(size).ok();
+        }
+    }
+    config
+}
+
+/// Parses the [block_quote.attribution] configuration section, present to style quote
+/// attributions. Unset keys fall back to the quote style, italic and right-aligned.
+fn parse_block_quote_attribution(
+    value: Option<&Value>,
+    block_quote: BasicTextStyle,
+    base_size: u8,
+    ctx: &ParseContext,
+) -> Option<BasicTextStyle> {
+    let attribution = value?.get("attribution")?;
+    let default = BasicTextStyle {
+        italic: true,
+        alignment: Some(TextAlignment::Right),
+        ..block_quote
+    };
+    Some(parse_style(Some(attribution), default, base_size, ctx))
+}
+
+/// Parses the [image.gallery] configuration section.
+///
+/// - `columns`: integer, images per row of the grid (0 disables the grid)
+/// - `gutter`: number, space between images in millimeters
+fn parse_image_gallery_config(
+    value: Option<&Value>,
+    default: ImageGalleryConfig,
+) -> ImageGalleryConfig {
+    let mut config = default;
+    if let Some(g) = value {
+        if let Some(columns) = g.get("columns").and_then(|v| v.as_integer()) {
+            config.columns = usize::try_from(columns).unwrap_or(0);
+        }
+        match g.get("gutter") {
+            Some(Value::Integer(gutter)) if *gutter >= 0 => config.gutter = *gutter as f32,
+            Some(Value::Float(gutter)) if *gutter >= 0.0 => config.gutter = *gutter as f32,
+            _ => {}
+        }
+    }
+    config
+}
+
+/// Parses `target_dpi` from the [image] section: the print resolution raster images are
+/// sized for. Integers and floats are accepted; values that are not positive are ignored.
+fn parse_target_dpi(value: Option<&Value>, default: Option<f32>) -> Option<f32> {
+    match value.and_then(|i| i.get("target_dpi")) {
+        Some(Value::Integer(dpi)) if *dpi > 0 => Some(*dpi as f32),
+        Some(Value::Float(dpi)) if *dpi > 0.0 => Some(*dpi as f32),
+        _ => default,
+    }
+}
+
+/// Parses `fallback_image` from the [image] section: the path or URL of the image
+/// shown in place of images that fail to load.
+fn parse_fallback_image(value: Option<&Value>, default: Option<String>) -> Option<String> {
+    value
+        .and_then(|i| i.get("fallback_image"))
+        .and_then(|v| v.as_str())
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+        .map(String::from)
+        .or(default)
+}
+
+/// Parses `base_url` from the [link] or [image] section: the URL relative link targets
+/// or image paths are resolved against.
+fn parse_base_url(value: Option<&Value>, default: Option<String>) -> Option<String> {
+    value
+        .and_then(|section| section.get("base_url"))
+        .and_then(|v| v.as_str())
+        .map(str::trim)
+        .filter(|url| !url.is_empty())
+        .map(String::from)
+        .or(default)
+}
+
+/// Parses the [fonts.ranges] configuration section.
+///
+/// Keys are either a script name (see [`named_unicode_range`]) or a code point range such
+/// as `"U+4E00-U+9FFF"` (a single `"U+00E9"` is accepted too); values are font names.
+/// Invalid entries are ignored. Ranges are ordered narrowest first so that a specific
+/// range wins over a broader one it overlaps.
+fn parse_font_ranges(value: Option<&Value>, default: Vec<FontRange>) -> Vec<FontRange> {
+    let Some(table) = value.and_then(|v| v.as_table()) else {
+        return default;
+    };
+
+    let mut ranges: Vec<FontRange> = table
+        .iter()
+        .filter_map(|(key, font)| {
+            let font = font.as_str()?.trim();
+            if font.is_empty() {
+                return None;
+            }
+            let (start, end) = named_unicode_range(key).or_else(|| parse_unicode_range(key))?;
+            Some(FontRange {
+                start,
+                end,
+                font: font.to_string(),
+            })
+        })
+        .collect();
+    ranges.sort_by_key(|r| (r.end - r.start, r.start));
+    ranges
+}
+
+/// Returns the code point range of a commonly used script name (case-insensitive).
+fn named_unicode_range(name: &str) -> Option<(u32, u32)> {
+    let range = match name.trim().to_ascii_lowercase().as_str() {
+        "greek" => (0x0370, 0x03FF),
+        "cyrillic" => (0x0400, 0x04FF),
+        "hebrew" => (0x0590, 0x05FF),
+        "arabic" => (0x0600, 0x06FF),
+        "devanagari" => (0x0900, 0x097F),
+        "thai" => (0x0E00, 0x0E7F),
+        "hangul" => (0xAC00, 0xD7AF),
+        "kana" => (0x3040, 0x30FF),
+        "cjk" => (0x4E00, 0x9FFF),
+        "emoji" => (0x1F300, 0x1FAFF),
+        _ => return None,
+    };
+    Some(range)
+}
+
+/// Parses `"U+XXXX-U+YYYY"` or `"U+XXXX"` into an inclusive code point range.
+fn parse_unicode_range(key: &str) -> Option<(u32, u32)> {
+    let code_point = |s: &str| {
+        let s = s.trim();
+        let hex = s.strip_prefix("U+").or_else(|| s.strip_prefix("u+"))?;
+        u32::from_str_radix(hex, 16).ok().filter(|&c| c <= 0x10FFFF)
+    };
+
+    let (start, end) = match key.split_once('-') {
+        Some((start, end)) => (code_point(start)?, code_point(end)?),
+        None => {
+            let c = code_point(key)?;
+            (c, c)
+        }
+    };
+    (start <= end).then_some((start, end))
+}
+
+/// Parses the [validation] configuration section.
+///
+/// - `min_contrast`: number, minimum text/background contrast ratio (0 disables the check)
+fn parse_validation_config(value: Option<&Value>, default: ValidationConfig) -> ValidationConfig {
+    let mut config = default;
+    if let Some(v) = value {
+        let min_contrast = v
+            .get("min_contrast")
+            .and_then(|m| m.as_float().or_else(|| m.as_integer().map(|i| i as f64)));
+        if let Some(min_contrast) = min_contrast {
+            config.min_contrast = min_contrast.max(0.0) as f32;
+        }
+    }
+    config
+}
+
+/// Parses the [spacing] configuration section.
+///
+/// - `trim_edges`: bool, drops the spacing before the first and after the last element
+fn parse_spacing_config(value: Option<&Value>, default: SpacingConfig) -> SpacingConfig {
+    let mut config = default;
+    if let Some(trim_edges) = value
+        .and_then(|v| v.get("trim_edges"))
+        .and_then(|v| v.as_bool())
+    {
+        config.trim_edges = trim_edges;
+    }
+    config
+}
+
+/// Parses the [redact] section: `patterns`, a list of regular expressions, and `mask`, the
+/// first character of a string. Patterns are checked when the document is converted.
+fn parse_redact_config(value: Option<&Value>, default: RedactConfig) -> RedactConfig {
+    let mut config = default;
+    if let Some(patterns) = value
+        .and_then(|v| v.get("patterns"))
+        .and_then(|v| v.as_array())
+    {
+        config.patterns = patterns
+            .iter()
+            .filter_map(|p| p.as_str())
+            .filter(|p| !p.is_empty())
+            .map(str::to_string)
+            .collect();
+    }
+    if let Some(mask) = value
+        .and_then(|v| v.get("mask"))
+        .and_then(|v| v.as_str())
+        .and_then(|s| s.chars().next())
+    {
+        config.mask = mask;
+    }
+    config
+}
+
+/// Parses the [abbreviations] configuration section.
+///
+/// - `mode`: "glossary" (default) or "first_use"
+/// - all regular style properties apply to abbreviation occurrences
+fn parse_abbreviation_config(
+    value: Option<&Value>,
+    default: AbbreviationConfig,
+    base_size: u8,
+    ctx: &ParseContext,
+) -> AbbreviationConfig {
+    let mut config = default;
+    if let Some(a) = value {
+        if let Some(mode) = a.get("mode").and_then(|v| v.as_str()) {
+            config.mode = match mode {
+                "first_use" => AbbreviationMode::FirstUse,
+                _ => AbbreviationMode::Glossary,
+            };
+        }
+        config.style = parse_style(Some(a), default.style, base_size, ctx);
+    }
+    config
+}
+
+/// Parses the [kbd] configuration section.
+///
+/// - all regular style properties apply to the key label
+/// - `backgroundcolor` fills the key box
+/// - `bordercolor`: color of the key outline
+fn parse_kbd_config(
+    value: Option<&Value>,
+    default: KbdConfig,
+    base_size: u8,
+    ctx: &ParseContext,
+) -> KbdConfig {
+    let mut config = default;
+    if let Some(k) = value {
+        config.style = parse_style(Some(k), default.style, base_size, ctx);
+        if let Some(color) = parse_color(Some(k), "bordercolor", ctx) {
+            config.border_color = Some(color);
+        }
+    }
+    config
+}
+
+/// Parses the [spoiler] configuration section.
+///
+/// - all regular style properties apply to the spoiler content
+/// - `label`: string, title of the spoiler box
+fn parse_spoiler_config(
+    value: Option<&Value>,
+    default: SpoilerConfig,
+    base_size: u8,
+    ctx: &ParseContext,
+) -> SpoilerConfig {
+    let mut config = default;
+    if let Some(s) = value {
+        config.style = parse_style(Some(s), config.style, base_size, ctx);
+        if let Some(label) = s.get("label").and_then(|v| v.as_str()) {
+            config.label = label.to_string();
+        }
+    }
+    config
+}
+
+/// Parses the [task_list] configuration section.
+///
+/// - `checkedcolor`: color of the box and check mark of completed items
+/// - `uncheckedcolor`: color of the box of open items
+/// - `donecolor`: text color of completed items
+/// - `strikethrough_done`: boolean, strike through the text of completed items
+/// - `show_progress`: boolean, render a completion summary under each task list
+fn parse_task_list_config(
+    value: Option<&Value>,
+    default: TaskListConfig,
+    ctx: &ParseContext,
+) -> TaskListConfig {
+    let mut config = default;
+    if let Some(t) = value {
+        if let Some(color) = parse_color(Some(t), "checkedcolor", ctx) {
+            config.checked_color = Some(color);
+        }
+        if let Some(color) = parse_color(Some(t), "uncheckedcolor", ctx) {
+            config.unchecked_color = Some(color);
+        }
+        if let Some(color) = parse_color(Some(t), "donecolor", ctx) {
+            config.done_color = Some(color);
+        }
+        if let Some(strike) = t.get("strikethrough_done").and_then(|v| v.as_bool()) {
+            config.strikethrough_done = strike;
+        }
+        if let Some(show) = t.get("show_progress").and_then(|v| v.as_bool()) {
+            config.show_progress = show;
+        }
+    }
+    config
+}
+
+/// Parses the `numbered`, `number_from` and `number_to` keys of the [heading] section.
+/// Setting a level bound turns numbering on unless `numbered` says otherwise; bounds
+/// outside 1 to 6, or `number_to` below `number_from`, are ignored.
+fn parse_heading_numbering(value: Option<&Value>, default: HeadingNumbering) -> HeadingNumbering {
+    let mut numbering = default;
+    let Some(h) = value else {
+        return numbering;
+    };
+    let level = |key: &str| {
+        h.get(key)
+            .and_then(|v| v.as_integer())
+            .filter(|level| (1..=6).contains(level))
+            .map(|level| level as usize)
+    };
+    let (from, to) = (level("number_from"), level("number_to"));
+    if from.is_some() || to.is_some() {
+        numbering.enabled = true;
+    }
+    if let Some(enabled) = h.get("numbered").and_then(|v| v.as_bool()) {
+        numbering.enabled = enabled;
+    }
+    let from = from.unwrap_or(numbering.from);
+    let to = to.unwrap_or(numbering.to);
+    if from <= to {
+        numbering.from = from;
+        numbering.to = to;
+    }
+    numbering
+}
+
+/// Parses the `page_break_before` key of the [heading.1], [heading.2] and [heading.3]
+/// sections.
+fn parse_page_break_before(value: Option<&Value>, default: [bool; 3]) -> [bool; 3] {
+    let mut page_breaks = default;
+    for (level, page_break) in page_breaks.iter_mut().enumerate() {
+        let level = (level + 1).to_string();
+        if let Some(enabled) = value
+            .and_then(|h| h.get(level.as_str()))
+            .and_then(|h| h.get("page_break_before"))
+            .and_then(|v| v.as_bool())
+        {
+            *page_break = enabled;
+        }
+    }
+    page_breaks
+}
+
+/// Parses the `soft_break` key of the [text] section.
+///
+/// - `"space"` (default): a single newline joins lines with a space, as in CommonMark
+/// - `"newline"`: every source line starts a new line in the PDF
+fn parse_soft_break(value: Option<&Value>, default: SoftBreakMode) -> SoftBreakMode {
+    match value
+        .and_then(|t| t.get("soft_break"))
+        .and_then(|v| v.as_str())
+    {
+        Some("newline") => SoftBreakMode::Newline,
+        Some("space") => SoftBreakMode::Space,
+        _ => default,
+    }
+}
+
+/// Parses the `quote_style` key of the [text] section.
+///
+/// - `"straight"` (default): quotes are kept as written
+/// - `"en"`, `"fr"`, `"de"`: straight quotes become the quotation marks of the language
+fn parse_quote_style(value: Option<&Value>, default: QuoteStyle) -> QuoteStyle {
+    match value
+        .and_then(|t| t.get("quote_style"))
+        .and_then(|v| v.as_str())
+        .map(str::to_ascii_lowercase)
+        .as_deref()
+    {
+        Some("straight") => QuoteStyle::Straight,
+        Some("en") => QuoteStyle::En,
+        Some("fr") => QuoteStyle::Fr,
+        Some("de") => QuoteStyle::De,
+        _ => default,
+    }
+}
+
+/// Parses the `max_width` key of the [text] section: a width in points, integer or float,
+/// or a number of characters such as `"70ch"`. Values that are not positive are ignored.
+fn parse_max_width(value: Option<&Value>, default: Option<MaxWidth>) -> Option<MaxWidth> {
+    match value.and_then(|t| t.get("max_width")) {
+        Some(Value::Integer(points)) if *points > 0 => Some(MaxWidth::Points(*points as f32)),
+        Some(Value::Float(points)) if *points > 0.0 => Some(MaxWidth::Points(*points as f32)),
+        Some(Value::String(s)) => s
+            .trim()
+            .strip_suffix("ch")
+            .and_then(|chars| chars.trim().parse::<u32>().ok())
+            .filter(|&chars| chars > 0)
+            .map(MaxWidth::Characters)
+            .or(default),
+        _ => default,
+    }
+}
+
+/// Parses a non-negative length in millimeters from the [list_item] section, accepting
+/// integers as well as floats.
+fn parse_list_length(value: Option<&Value>, key: &str, default: f32) -> f32 {
+    value
+        .and_then(|l| l.get(key))
+        .and_then(|v| v.as_float().or_else(|| v.as_integer().map(|i| i as f64)))
+        .filter(|length| *length >= 0.0)
+        .map_or(default, |length| length as f32)
+}
+
+/// Parses `first_page_margins` from the [page] section, e.g.
+/// `first_page_margins = { top = 60.0 }`. Sides that are not given keep the regular
+/// `margins`.
+fn parse_first_page_margins(
+    value: Option<&Value>,
+    margins: Margins,
+    default: Option<Margins>,
+) -> Option<Margins> {
+    let first = match value.and_then(|p| p.get("first_page_margins")) {
+        Some(first) => first,
+        None => return default,
+    };
+    let side = |key: &str, fallback: f32| {
+        first
+            .get(key)
+            .and_then(|v| v.as_float().or_else(|| v.as_integer().map(|i| i as f64)))
+            .map_or(fallback, |v| v as f32)
+    };
+    Some(Margins {
+        top: side("top", margins.top),
+        right: side("right", margins.right),
+        bottom: side("bottom", margins.bottom),
+        left: side("left", margins.left),
+    })
+}
+
+/// Parses the `size` key of the [page] section, e.g. `size = "letter"`.
+fn parse_page_size(value: Option<&Value>, default: PageSize) -> PageSize {
+    value
+        .and_then(|p| p.get("size"))
+        .and_then(|v| v.as_str())
+        .and_then(PageSize::from_name)
+        .unwrap_or(default)
+}
+
+/// Parses a line count of the [page] section such as `min_lines_top = 2`. Counts below 1
+/// keep the default.
+fn parse_min_lines(value: Option<&Value>, key: &str, default: usize) -> usize {
+    value
+        .and_then(|p| p.get(key))
+        .and_then(|v| v.as_integer())
+        .filter(|&lines| lines > 0)
+        .map_or(default, |lines| lines as usize)
+}
+
+/// Parses the logo of the [page.header] or [page.footer] section: `image`,
+/// `image_alignment` (`alignment` by default) and `image_height` in millimeters. Returns
+/// `None` without an `image`.
+fn parse_page_logo(value: Option<&Value>, alignment: TextAlignment) -> Option<PageLogo> {
+    let section = value?;
+    let image = section
+        .get("image")
+        .and_then(|v| v.as_str())
+        .map(str::trim)
+        .filter(|path| !path.is_empty())?;
+    let height = match section.get("image_height") {
+        Some(Value::Integer(height)) if *height > 0 => *height as f32,
+        Some(Value::Float(height)) if *height > 0.0 => *height as f32,
+        _ => crate::styling::DEFAULT_PAGE_LOGO_HEIGHT,
+    };
+    Some(PageLogo {
+        image: image.to_string(),
+        alignment: parse_alignment(section.get("image_alignment")).unwrap_or(alignment),
+        height,
+    })
+}
+
+/// Parses the [page.header] section: the logo `image`, see [`parse_page_logo`].
+fn parse_header_config(value: Option<&Value>, default: HeaderConfig) -> HeaderConfig {
+    HeaderConfig {
+        logo: parse_page_logo(value, TextAlignment::Left).or(default.logo),
+    }
+}
+
+/// Parses the [page.footer] section: `show_date`, `date_format`, `page_numbers`, `size`
+/// and the logo `image`, centered by default to stay clear of the date and the page
+/// number. Sizes outside 1-255 points keep the default.
+fn parse_footer_config(value: Option<&Value>, default: FooterConfig) -> FooterConfig {
+    let mut footer = default;
+    let Some(f) = value else {
+        return footer;
+    };
+    if let Some(logo) = parse_page_logo(value, TextAlignment::Center) {
+        footer.logo = Some(logo);
+    }
+    if let Some(show_date) = f.get("show_date").and_then(|v| v.as_bool()) {
+        footer.show_date = show_date;
+    }
+    if let Some(format) = f.get("date_format").and_then(|v| v.as_str()) {
+        footer.date_format = format.to_string();
+    }
+    if let Some(page_numbers) = f.get("page_numbers").and_then(|v| v.as_bool()) {
+        footer.page_numbers = page_numbers;
+    }
+    if let Some(size) = f
+        .get("size")
+        .and_then(|v| v.as_integer())
+        .filter(|size| (1..=255).contains(size))
+    {
+        footer.size = size as u8;
+    }
+    footer
+}
+
+/// Parses the `orientation` key of the [page] section, `"portrait"` or `"landscape"`.
+fn parse_orientation(value: Option<&Value>, default: Orientation) -> Orientation {
+    value
+        .and_then(|p| p.get("orientation"))
+        .and_then(|v| v.as_str())
+        .and_then(Orientation::from_name)
+        .unwrap_or(default)
+}
+
+/// Parses the `style` key of the [link] section.
+///
+/// - `"inline"` (default): link text is a clickable hyperlink
+/// - `"footnote"`: link text is followed by a footnote marker and the URL is listed with
+///   the footnotes
+/// - `"parenthetical"`: link text is followed by the URL in parentheses
+fn parse_link_style(value: Option<&Value>, default: LinkStyle) -> LinkStyle {
+    match value.and_then(|l| l.get("style")).and_then(|v| v.as_str()) {
+        Some("inline") => LinkStyle::Inline,
+        Some("footnote") => LinkStyle::Footnote,
+        Some("parenthetical") => LinkStyle::Parenthetical,
+        _ => default,
+    }
+}
+
+/// Parses the `overflow` key of the [table] section.
+///
+/// - `"shrink"` (default): columns are sized by their content and the font is reduced
+///   until the longest words fit the page width
+/// - `"scroll"`: columns keep equal widths and the configured sizes
+fn parse_table_overflow(value: Option<&Value>, default: TableOverflow) -> TableOverflow {
+    match value
+        .and_then(|t| t.get("overflow"))
+        .and_then(|v| v.as_str())
+    {
+        Some("shrink") => TableOverflow::Shrink,
+        Some("scroll") => TableOverflow::Scroll,
+        _ => default,
+    }
+}
+
+/// Parses the `fit` key of the [code] section.
+///
+/// - `"wrap"` (default): code keeps its size and long lines wrap
+/// - `"shrink"`: the font of a block is reduced until its widest line fits the text width
+fn parse_code_fit(value: Option<&Value>, default: CodeFit) -> CodeFit {
+    match value.and_then(|c| c.get("fit")).and_then(|v| v.as_str()) {
+        Some("wrap") => CodeFit::Wrap,
+        Some("shrink") => CodeFit::Shrink,
+        _ => default,
+    }
+}
+
+/// Parses `style` from the [code] section: "color" or "grayscale".
+fn parse_code_highlight_style(
+    value: Option<&Value>,
+    default: CodeHighlightStyle,
+) -> CodeHighlightStyle {
+    match value.and_then(|c| c.get("style")).and_then(|v| v.as_str()) {
+        Some("color") => CodeHighlightStyle::Color,
+        Some("grayscale") => CodeHighlightStyle::Grayscale,
+        _ => default,
+    }
+}
+
+/// Parses the [document] configuration section.
+///
+/// - `reproducible`: bool, fixes dates and the document ID for byte-identical output
+/// - `title_from_h1`: bool, uses a leading level 1 heading as the document title
+/// - `attach_source`: bool, embeds the markdown source as a file attachment
+/// - `lang`: string, language tag of the content such as "en-US"
+/// - `create_dirs`: bool, creates missing directories of the output path
+/// - `contact_sheet`: bool, appends a grid of page thumbnails
+fn parse_document_config(value: Option<&Value>, default: DocumentConfig) -> DocumentConfig {
+    let mut config = default;
+    if let Some(d) = value {
+        if let Some(reproducible) = d.get("reproducible").and_then(|v| v.as_bool()) {
+            config.reproducible = reproducible;
+        }
+        if let Some(title_from_h1) = d.get("title_from_h1").and_then(|v| v.as_bool()) {
+            config.title_from_h1 = title_from_h1;
+        }
+        if let Some(attach_source) = d.get("attach_source").and_then(|v| v.as_bool()) {
+            config.attach_source = attach_source;
+        }
+        if let Some(create_dirs) = d.get("create_dirs").and_then(|v| v.as_bool()) {
+            config.create_dirs = create_dirs;
+        }
+        if let Some(contact_sheet) = d.get("contact_sheet").and_then(|v| v.as_bool()) {
+            config.contact_sheet = contact_sheet;
+        }
+        if let Some(lang) = d
+            .get("lang")
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|lang| !lang.is_empty())
+        {
+            config.lang = Some(lang.to_string());
+        }
+    }
+    config
+}
+
+/// Parses the [index] configuration section.
+///
+/// - `enabled`: bool, collect `<!-- index: term -->` tags into an index (default true)
+/// - `title`: string, heading of the index (default "Index")
+fn parse_index_config(value: Option<&Value>, default: IndexConfig) -> IndexConfig {
+    let mut config = default;
+    if let Some(i) = value {
+        if let Some(enabled) = i.get("enabled").and_then(|v| v.as_bool()) {
+            config.enabled = enabled;
+        }
+        if let Some(title) = i.get("title").and_then(|v| v.as_str()) {
+            config.title = title.to_string();
+        }
+    }
+    config
+}
+
 /// Parses a TOML configuration string and returns a complete StyleMatch.
 ///
 /// This function handles the core TOML parsing logic and can be used with both
@@ -333,70 +1229,442 @@ fn parse_mermaid_config(value: Option<&Value>, default: MermaidConfig) -> Mermai
 /// assert_eq!(style.latex.size, 8);
 /// ```
 pub fn parse_config_string(config_str: &str) -> StyleMatch {
-    let config: Value = match toml::from_str(config_str) {
-        Ok(v) => v,
-        Err(_) => return StyleMatch::default(),
-    };
+    match toml::from_str(config_str) {
+        Ok(config) => parse_config_value(&config, StyleMatch::default()),
+        Err(_) => StyleMatch::default(),
+    }
+}
 
-    let default_style = StyleMatch::default();
-    let margins = if let Some(margins) = config.get("margin") {
-        Margins {
-            top: margins.get("top").and_then(|v| v.as_float()).unwrap_or(8.0) as f32,
-            right: margins
-                .get("right")
-                .and_then(|v| v.as_float())
-                .unwrap_or(8.0) as f32,
-            bottom: margins
-                .get("bottom")
-                .and_then(|v| v.as_float())
-                .unwrap_or(8.0) as f32,
-            left: margins
-                .get("left")
-                .and_then(|v| v.as_float())
-                .unwrap_or(8.0) as f32,
-        }
-    } else {
-        default_style.margins
-    };
+/// Parses a JSON configuration string into a StyleMatch.
+///
+/// The JSON document mirrors the TOML file: one object per section, e.g.
+/// `{"heading": {"1": {"size": 18}}, "code": {"fontfamily": "Space Mono"}}`. Invalid JSON,
+/// including `null` values, gives the default style, like invalid TOML does.
+///
+/// # Examples
+/// ```rust
+/// use markdown2pdf::config::parse_config_json;
+///
+/// let style = parse_config_json(r#"{"heading": {"1": {"size": 18, "bold": true}}}"#);
+/// assert_eq!(style.heading_1.size, 18);
+/// ```
+#[cfg(feature = "json")]
+pub fn parse_config_json(config_str: &str) -> StyleMatch {
+    match serde_json::from_str(config_str) {
+        Ok(config) => parse_config_value(&config, StyleMatch::default()),
+        Err(_) => StyleMatch::default(),
+    }
+}
 
-    StyleMatch {
-        margins,
-        heading_1: parse_style(
-            config.get("heading").and_then(|h| h.get("1")),
-            default_style.heading_1,
-        ),
-        heading_2: parse_style(
-            config.get("heading").and_then(|h| h.get("2")),
-            default_style.heading_2,
-        ),
-        heading_3: parse_style(
-            config.get("heading").and_then(|h| h.get("3")),
-            default_style.heading_3,
-        ),
-        emphasis: parse_style(config.get("emphasis"), default_style.emphasis),
-        strong_emphasis: parse_style(config.get("strong_emphasis"), default_style.strong_emphasis),
-        code: parse_style(config.get("code"), default_style.code),
-        block_quote: parse_style(config.get("block_quote"), default_style.block_quote),
-        list_item: parse_style(config.get("list_item"), default_style.list_item),
-        link: parse_style(config.get("link"), default_style.link),
-        image: parse_style(config.get("image"), default_style.image),
-        text: parse_style(config.get("text"), default_style.text),
-        latex: parse_style(config.get("latex"), default_style.latex),
-        table_header: parse_style(
+/// Parses a YAML configuration string into a StyleMatch.
+///
+/// The YAML document mirrors the TOML file, with one mapping per section. Heading levels
+/// are keys like any other, so `heading: {"1": {size: 18}}` sets the level 1 size.
+///
+/// # Examples
+/// ```rust
+/// use markdown2pdf::config::parse_config_yaml;
+///
+/// let style = parse_config_yaml("heading:\n  \"1\":\n    size: 18\n");
+/// assert_eq!(style.heading_1.size, 18);
+/// ```
+#[cfg(feature = "yaml")]
+pub fn parse_config_yaml(config_str: &str) -> StyleMatch {
+    match serde_yaml::from_str(config_str) {
+        Ok(config) => parse_config_value(&config, StyleMatch::default()),
+        Err(_) => StyleMatch::default(),
+    }
+}
+
+/// Applies the settings of a TOML configuration string on top of `base`, leaving the
+/// settings it does not mention as they are. Invalid TOML leaves `base` unchanged.
+///
+/// # Examples
+/// ```rust
+/// use markdown2pdf::config::{merge_config_string, parse_config_string};
+///
+/// let base = parse_config_string("[heading.1]\nsize = 18\n");
+/// let style = merge_config_string(base, "[margin]\nleft = 20.0\n");
+/// assert_eq!(style.margins.left, 20.0);
+/// assert_eq!(style.heading_1.size, 18);
+/// ```
+pub fn merge_config_string(base: StyleMatch, overrides: &str) -> StyleMatch {
+    merge_config_string_reported(base, overrides).0
+}
+
+/// Applies a TOML configuration string on top of `base` like [`merge_config_string`],
+/// also returning warnings about the overrides: color names missing from `[palette]`.
+pub fn merge_config_string_reported(
+    base: StyleMatch,
+    overrides: &str,
+) -> (StyleMatch, Vec<ValidationWarning>) {
+    match toml::from_str(overrides) {
+        Ok(config) => {
+            let (style, unknown_colors) =
+                parse_config_value_reported(&config, base, HashMap::new());
+            (style, palette_warnings(&unknown_colors))
+        }
+        Err(_) => (base, Vec::new()),
+    }
+}
+
+/// Settings the front matter of a document cannot override. They reach outside the
+/// document, creating directories or bounding downloads, so they stay with whoever runs
+/// the conversion: the configuration and the command line.
+pub const FRONT_MATTER_REFUSED_KEYS: [&str; 2] =
+    ["document.create_dirs", "image.remote.max_download_size"];
+
+/// Applies the `style` block of a document's front matter (see [`front_matter_style`])
+/// over `base` like [`merge_config_string_reported`], except for the
+/// [`FRONT_MATTER_REFUSED_KEYS`], which are ignored with a warning each.
+pub fn merge_front_matter_style_reported(
+    base: StyleMatch,
+    overrides: &str,
+) -> (StyleMatch, Vec<ValidationWarning>) {
+    let Ok(mut config) = toml::from_str::<Value>(overrides) else {
+        return (base, Vec::new());
+    };
+    let mut warnings = Vec::new();
+    for path in FRONT_MATTER_REFUSED_KEYS {
+        let (tables, key) = path.rsplit_once('.').unwrap_or(("", path));
+        let table = tables
+            .split('.')
+            .try_fold(&mut config, |value, table| value.get_mut(table));
+        if let Some(Value::Table(table)) = table {
+            if table.remove(key).is_some() {
+                warnings.push(ValidationWarning::refused_front_matter_key(path));
+            }
+        }
+    }
+    let (style, unknown_colors) = parse_config_value_reported(&config, base, HashMap::new());
+    warnings.extend(palette_warnings(&unknown_colors));
+    (style, warnings)
+}
+
+/// Returns the configuration overrides of a document: the `style` block of its front
+/// matter, as TOML.
+///
+/// The block is a YAML literal block (`style: |`) whose indented lines hold the TOML
+/// settings, so it reads the same with or without the `yaml` feature.
+///
+/// # Examples
+/// ```rust
+/// use markdown2pdf::config::front_matter_style;
+///
+/// let markdown = "---\ntitle: Report\nstyle: |\n  [margin]\n  left = 20.0\n---\n# Report\n";
+/// assert_eq!(
+///     front_matter_style(markdown).as_deref(),
+///     Some("[margin]\nleft = 20.0\n")
+/// );
+/// ```
+pub fn front_matter_style(markdown: &str) -> Option<String> {
+    let markdown = crate::markdown::normalize_line_endings(markdown);
+    let front_matter = crate::markdown::split_front_matter(&markdown).0?;
+
+    let mut lines = front_matter.lines();
+    lines.find(|line| {
+        matches!(
+            line.trim_end(),
+            "style: |" | "style: |-" | "style: |+" | "style:|"
+        )
+    })?;
+    let block: Vec<&str> = lines
+        .take_while(|line| line.trim().is_empty() || line.starts_with([' ', '\t']))
+        .collect();
+    let indent = block
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()?;
+
+    let mut style = String::new();
+    for line in block {
+        style.push_str(line.get(indent..).unwrap_or("").trim_end());
+        style.push('\n');
+    }
+    Some(style)
+}
+
+/// Builds a StyleMatch from a parsed configuration document, whatever format it was
+/// written in. Settings the document leaves out are taken from `default_style`.
+fn parse_config_value(config: &Value, default_style: StyleMatch) -> StyleMatch {
+    parse_config_value_reported(config, default_style, HashMap::new()).0
+}
+
+/// Builds a StyleMatch like [`parse_config_value`], also returning the color names the
+/// document references without defining them. Color names can come from `base_palette`,
+/// the palette of the configurations merged before this one, or from the document's own
+/// `[palette]`, which wins for names in both.
+fn parse_config_value_reported(
+    config: &Value,
+    default_style: StyleMatch,
+    base_palette: HashMap<String, (u8, u8, u8)>,
+) -> (StyleMatch, Vec<String>) {
+    let mut palette = base_palette;
+    palette.extend(parse_palette(config.get("palette")));
+    let ctx = ParseContext {
+        palette,
+        ..Default::default()
+    };
+    let margins = if let Some(margins) = config.get("margin") {
+        Margins {
+            top: margins
+                .get("top")
+                .and_then(|v| v.as_float())
+                .map_or(default_style.margins.top, |v| v as f32),
+            right: margins
+                .get("right")
+                .and_then(|v| v.as_float())
+                .map_or(default_style.margins.right, |v| v as f32),
+            bottom: margins
+                .get("bottom")
+                .and_then(|v| v.as_float())
+                .map_or(default_style.margins.bottom, |v| v as f32),
+            left: margins
+                .get("left")
+                .and_then(|v| v.as_float())
+                .map_or(default_style.margins.left, |v| v as f32),
+        }
+    } else {
+        default_style.margins
+    };
+
+    // Relative sizes ("2em", "150%") in other sections are resolved against the text size
+    let text = parse_style(
+        config.get("text"),
+        default_style.text,
+        default_style.text.size,
+        &ctx,
+    );
+    let base_size = text.size;
+    let block_quote = parse_style(
+        config.get("block_quote"),
+        default_style.block_quote,
+        base_size,
+        &ctx,
+    );
+
+    let style = StyleMatch {
+        margins,
+        first_page_margins: parse_first_page_margins(
+            config.get("page"),
+            margins,
+            default_style.first_page_margins,
+        ),
+        page_size: parse_page_size(config.get("page"), default_style.page_size),
+        orientation: parse_orientation(config.get("page"), default_style.orientation),
+        min_lines_top: parse_min_lines(
+            config.get("page"),
+            "min_lines_top",
+            default_style.min_lines_top,
+        ),
+        min_lines_bottom: parse_min_lines(
+            config.get("page"),
+            "min_lines_bottom",
+            default_style.min_lines_bottom,
+        ),
+        header: parse_header_config(
+            config.get("page").and_then(|p| p.get("header")),
+            default_style.header.clone(),
+        ),
+        footer: parse_footer_config(
+            config.get("page").and_then(|p| p.get("footer")),
+            default_style.footer.clone(),
+        ),
+        heading_1: parse_style(
+            config.get("heading").and_then(|h| h.get("1")),
+            default_style.heading_1,
+            base_size,
+            &ctx,
+        ),
+        heading_2: parse_style(
+            config.get("heading").and_then(|h| h.get("2")),
+            default_style.heading_2,
+            base_size,
+            &ctx,
+        ),
+        heading_3: parse_style(
+            config.get("heading").and_then(|h| h.get("3")),
+            default_style.heading_3,
+            base_size,
+            &ctx,
+        ),
+        page_break_before: parse_page_break_before(
+            config.get("heading"),
+            default_style.page_break_before,
+        ),
+        heading_numbering: parse_heading_numbering(
+            config.get("heading"),
+            default_style.heading_numbering,
+        ),
+        emphasis: parse_style(
+            config.get("emphasis"),
+            default_style.emphasis,
+            base_size,
+            &ctx,
+        ),
+        strong_emphasis: parse_style(
+            config.get("strong_emphasis"),
+            default_style.strong_emphasis,
+            base_size,
+            &ctx,
+        ),
+        code: parse_style(config.get("code"), default_style.code, base_size, &ctx),
+        show_code_language: config
+            .get("code")
+            .and_then(|c| c.get("show_language_label"))
+            .and_then(|v| v.as_bool())
+            .unwrap_or(default_style.show_code_language),
+        code_highlight_style: parse_code_highlight_style(
+            config.get("code"),
+            default_style.code_highlight_style,
+        ),
+        code_keep_together: config
+            .get("code")
+            .and_then(|c| c.get("keep_together"))
+            .and_then(|v| v.as_bool())
+            .unwrap_or(default_style.code_keep_together),
+        // A `backgroundcolor` set for code also tints inline code, unless
+        // `inline_background` says otherwise
+        code_inline_background: config
+            .get("code")
+            .and_then(|c| {
+                c.get("inline_background")
+                    .and_then(|v| v.as_bool())
+                    .or_else(|| c.get("backgroundcolor").map(|_| true))
+            })
+            .unwrap_or(default_style.code_inline_background),
+        code_max_lines: config
+            .get("code")
+            .and_then(|c| c.get("max_lines"))
+            .and_then(|v| v.as_integer())
+            .and_then(|v| usize::try_from(v).ok())
+            .unwrap_or(default_style.code_max_lines),
+        code_fit: parse_code_fit(config.get("code"), default_style.code_fit),
+        code_label: parse_style(
+            config.get("code").and_then(|c| c.get("label")),
+            default_style.code_label,
+            base_size,
+            &ctx,
+        ),
+        block_quote,
+        block_quote_attribution: parse_block_quote_attribution(
+            config.get("block_quote"),
+            block_quote,
+            base_size,
+            &ctx,
+        ),
+        list_item: parse_style(
+            config.get("list_item"),
+            default_style.list_item,
+            base_size,
+            &ctx,
+        ),
+        list_marker_width: parse_list_length(
+            config.get("list_item"),
+            "marker_width",
+            default_style.list_marker_width,
+        ),
+        list_indent: parse_list_length(
+            config.get("list_item"),
+            "indent",
+            default_style.list_indent,
+        ),
+        list_max_nesting: config
+            .get("list_item")
+            .and_then(|l| l.get("max_nesting"))
+            .and_then(|v| v.as_integer())
+            .filter(|&depth| depth > 0)
+            .map_or(default_style.list_max_nesting, |depth| depth as usize),
+        link: parse_style(config.get("link"), default_style.link, base_size, &ctx),
+        link_style: parse_link_style(config.get("link"), default_style.link_style),
+        link_base_url: parse_base_url(config.get("link"), default_style.link_base_url),
+        image: parse_style(config.get("image"), default_style.image, base_size, &ctx),
+        image_base_url: parse_base_url(config.get("image"), default_style.image_base_url),
+        text,
+        soft_break: parse_soft_break(config.get("text"), default_style.soft_break),
+        quote_style: parse_quote_style(config.get("text"), default_style.quote_style),
+        text_max_width: parse_max_width(config.get("text"), default_style.text_max_width),
+        text_font_file: config
+            .get("text")
+            .and_then(|t| t.get("fontfile"))
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|path| !path.is_empty())
+            .map(str::to_string)
+            .or_else(|| default_style.text_font_file.clone()),
+        latex: parse_style(config.get("latex"), default_style.latex, base_size, &ctx),
+        table_header: parse_style(
             config.get("table").and_then(|t| t.get("header")),
             default_style.table_header,
+            base_size,
+            &ctx,
         ),
         table_cell: parse_style(
             config.get("table").and_then(|t| t.get("cell")),
             default_style.table_cell,
+            base_size,
+            &ctx,
+        ),
+        table_overflow: parse_table_overflow(config.get("table"), default_style.table_overflow),
+        table_rotate_if_oversized: config
+            .get("table")
+            .and_then(|t| t.get("rotate_if_oversized"))
+            .and_then(|v| v.as_bool())
+            .unwrap_or(default_style.table_rotate_if_oversized),
+        horizontal_rule: parse_style(
+            config.get("horizontal_rule"),
+            default_style.horizontal_rule,
+            base_size,
+            &ctx,
         ),
-        horizontal_rule: parse_style(config.get("horizontal_rule"), default_style.horizontal_rule),
         svg_config: parse_svg_config(
             config.get("image").and_then(|i| i.get("svg")),
             default_style.svg_config,
         ),
         mermaid: parse_mermaid_config(config.get("mermaid"), default_style.mermaid),
-    }
+        qr: parse_qr_config(config.get("qr"), default_style.qr),
+        remote_images: parse_remote_image_config(
+            config.get("image").and_then(|i| i.get("remote")),
+            default_style.remote_images,
+        ),
+        image_gallery: parse_image_gallery_config(
+            config.get("image").and_then(|i| i.get("gallery")),
+            default_style.image_gallery,
+        ),
+        fallback_image: parse_fallback_image(config.get("image"), default_style.fallback_image),
+        image_upscale: config
+            .get("image")
+            .and_then(|i| i.get("upscale"))
+            .and_then(|v| v.as_bool())
+            .unwrap_or(default_style.image_upscale),
+        image_target_dpi: parse_target_dpi(config.get("image"), default_style.image_target_dpi),
+        font_ranges: parse_font_ranges(
+            config.get("fonts").and_then(|f| f.get("ranges")),
+            default_style.font_ranges,
+        ),
+        abbreviations: parse_abbreviation_config(
+            config.get("abbreviations"),
+            default_style.abbreviations,
+            base_size,
+            &ctx,
+        ),
+        kbd: parse_kbd_config(config.get("kbd"), default_style.kbd, base_size, &ctx),
+        mark: parse_style(config.get("mark"), default_style.mark, base_size, &ctx),
+        spoiler: parse_spoiler_config(
+            config.get("spoiler"),
+            default_style.spoiler,
+            base_size,
+            &ctx,
+        ),
+        task_list: parse_task_list_config(config.get("task_list"), default_style.task_list, &ctx),
+        document: parse_document_config(config.get("document"), default_style.document),
+        index: parse_index_config(config.get("index"), default_style.index),
+        validation: parse_validation_config(config.get("validation"), default_style.validation),
+        spacing: parse_spacing_config(config.get("spacing"), default_style.spacing),
+        redact: parse_redact_config(config.get("redact"), default_style.redact.clone()),
+    };
+    (style, ctx.unknown_colors.into_inner())
 }
 
 /// Loads and parses the complete styling configuration based on the provided source.
@@ -443,22 +1711,138 @@ pub fn parse_config_string(config_str: &str) -> StyleMatch {
 /// let style = load_config_from_source(ConfigSource::Embedded(EMBEDDED));
 /// ```
 pub fn load_config_from_source(source: ConfigSource) -> StyleMatch {
-    match source {
-        ConfigSource::Default => StyleMatch::default(),
-        ConfigSource::File(path) => {
-            let config_path = Path::new(path).to_path_buf();
-            let config_str = match fs::read_to_string(config_path) {
-                Ok(s) => s,
-                Err(_) => return StyleMatch::default(),
-            };
-            parse_config_string(&config_str)
+    load_config_from_source_reported(source).0
+}
+
+/// Loads the configuration like [`load_config_from_source`], also returning warnings
+/// about its content: color names that no `[palette]` defines. An already built
+/// [`ConfigSource::Style`] has no warnings.
+///
+/// # Examples
+/// ```rust
+/// use markdown2pdf::config::{ConfigSource, load_config_from_source_reported};
+///
+/// let (_, warnings) =
+///     load_config_from_source_reported(ConfigSource::Embedded("[text]\ntextcolor = \"ink\"\n"));
+/// assert!(warnings[0].message.contains("'ink'"));
+/// ```
+pub fn load_config_from_source_reported(
+    source: ConfigSource,
+) -> (StyleMatch, Vec<ValidationWarning>) {
+    let (style, unknown_colors) = match source {
+        ConfigSource::Default => (StyleMatch::default(), Vec::new()),
+        ConfigSource::File(path) => read_config_file(path)
+            .map(|config| parse_config_file(path, &config, StyleMatch::default(), HashMap::new()))
+            .unwrap_or_default(),
+        ConfigSource::Embedded(content) => toml::from_str(content)
+            .map(|config| {
+                parse_config_value_reported(&config, StyleMatch::default(), HashMap::new())
+            })
+            .unwrap_or_default(),
+        #[cfg(feature = "json")]
+        ConfigSource::Json(content) => serde_json::from_str(content)
+            .map(|config| {
+                parse_config_value_reported(&config, StyleMatch::default(), HashMap::new())
+            })
+            .unwrap_or_default(),
+        #[cfg(feature = "yaml")]
+        ConfigSource::Yaml(content) => serde_yaml::from_str(content)
+            .map(|config| {
+                parse_config_value_reported(&config, StyleMatch::default(), HashMap::new())
+            })
+            .unwrap_or_default(),
+        ConfigSource::Style(style) => (style, Vec::new()),
+        ConfigSource::Merged(paths) => {
+            // Each file can reference the colors of the palettes of the files before it
+            let mut style = StyleMatch::default();
+            let mut palette = HashMap::new();
+            let mut unknown_colors: Vec<String> = Vec::new();
+            for path in paths {
+                let Some(config) = read_config_file(path) else {
+                    continue;
+                };
+                let (merged, unknown) = parse_config_file(path, &config, style, palette.clone());
+                style = merged;
+                palette.extend(parse_palette(config.get("palette")));
+                for name in unknown {
+                    if !unknown_colors.contains(&name) {
+                        unknown_colors.push(name);
+                    }
+                }
+            }
+            (style, unknown_colors)
+        }
+    };
+    (style, palette_warnings(&unknown_colors))
+}
+
+/// Builds the warnings for color names missing from the `[palette]` section.
+fn palette_warnings(unknown_colors: &[String]) -> Vec<ValidationWarning> {
+    unknown_colors
+        .iter()
+        .map(|name| ValidationWarning::unknown_palette_color(name))
+        .collect()
+}
+
+/// Returns the timestamp of the `SOURCE_DATE_EPOCH` environment variable, which asks for
+/// reproducible output following the reproducible-builds convention. Conversion entry
+/// points read it once into [`DocumentConfig::source_date_epoch`]; rendering itself does
+/// not look at the environment.
+pub fn source_date_epoch() -> Option<i64> {
+    std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|v| v.trim().parse::<i64>().ok())
+}
+
+/// Parses the configuration read from the file at `path` over `style`. A relative
+/// `fallback_image` the file sets is resolved against the file's directory, so it does
+/// not depend on the directory the conversion runs in. Also returns the color names the
+/// file references that neither its `[palette]` nor `base_palette` defines.
+fn parse_config_file(
+    path: &str,
+    config: &Value,
+    style: StyleMatch,
+    base_palette: HashMap<String, (u8, u8, u8)>,
+) -> (StyleMatch, Vec<String>) {
+    let (mut style, unknown_colors) = parse_config_value_reported(config, style, base_palette);
+    let sets_fallback = config
+        .get("image")
+        .and_then(|image| image.get("fallback_image"))
+        .is_some();
+    let directory = Path::new(path)
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty());
+    if let (true, Some(directory), Some(image)) =
+        (sets_fallback, directory, style.fallback_image.as_mut())
+    {
+        if !image.contains("://") && Path::new(image.as_str()).is_relative() {
+            *image = directory.join(&*image).to_string_lossy().into_owned();
         }
-        ConfigSource::Embedded(content) => parse_config_string(content),
+    }
+    (style, unknown_colors)
+}
+
+/// Reads and parses a configuration file. JSON and YAML files are recognized by their
+/// extension, anything else is read as TOML. Returns `None` when the file cannot be read
+/// or parsed.
+fn read_config_file(path: &str) -> Option<Value> {
+    let config_str = fs::read_to_string(path).ok()?;
+    match Path::new(path).extension().and_then(|e| e.to_str()) {
+        #[cfg(feature = "json")]
+        Some("json") => serde_json::from_str(&config_str).ok(),
+        #[cfg(feature = "yaml")]
+        Some("yaml" | "yml") => serde_yaml::from_str(&config_str).ok(),
+        _ => toml::from_str(&config_str).ok(),
     }
 }
 
 /// Generate a TOML string representing the defaults from `StyleMatch::default()`.
 /// This keeps the default config DRY by deriving values from the in-code defaults.
+///
+/// Every configurable field is written out, so the output is a complete starting
+/// template; only settings whose default is "unset" (e.g. `first_page_margins`,
+/// `fallback_image` or a color of `None`) are left out, as TOML has no null value.
+/// Parsing the result gives back `StyleMatch::default()`.
 pub fn default_config_toml() -> String {
     use toml::{map::Map, Value};
 
@@ -474,6 +1858,14 @@ pub fn default_config_toml() -> String {
         }
     }
 
+    fn insert_page_logo(section: &mut Map<String, Value>, logo: &PageLogo) {
+        section.insert("image".into(), Value::String(logo.image.clone()));
+        if let Some(alignment) = alignment_to_str(Some(logo.alignment)) {
+            section.insert("image_alignment".into(), Value::String(alignment.into()));
+        }
+        section.insert("image_height".into(), Value::Float(logo.height as f64));
+    }
+
     fn style_to_table(s: crate::styling::BasicTextStyle) -> Value {
         let mut m = Map::new();
         m.insert("size".into(), Value::Integer(s.size as i64));
@@ -484,33 +1876,21 @@ pub fn default_config_toml() -> String {
             c.insert("b".into(), Value::Integer(b as i64));
             m.insert("textcolor".into(), Value::Table(c));
         }
-        if s.before_spacing != 0.0 {
-            m.insert(
-                "beforespacing".into(),
-                Value::Float(s.before_spacing as f64),
-            );
-        }
-        if s.after_spacing != 0.0 {
-            m.insert("afterspacing".into(), Value::Float(s.after_spacing as f64));
-        }
+        m.insert(
+            "beforespacing".into(),
+            Value::Float(s.before_spacing as f64),
+        );
+        m.insert("afterspacing".into(), Value::Float(s.after_spacing as f64));
         if let Some(a) = alignment_to_str(s.alignment) {
             m.insert("alignment".into(), Value::String(a.into()));
         }
         if let Some(f) = s.font_family {
             m.insert("fontfamily".into(), Value::String(f.into()));
         }
-        if s.bold {
-            m.insert("bold".into(), Value::Boolean(true));
-        }
-        if s.italic {
-            m.insert("italic".into(), Value::Boolean(true));
-        }
-        if s.underline {
-            m.insert("underline".into(), Value::Boolean(true));
-        }
-        if s.strikethrough {
-            m.insert("strikethrough".into(), Value::Boolean(true));
-        }
+        m.insert("bold".into(), Value::Boolean(s.bold));
+        m.insert("italic".into(), Value::Boolean(s.italic));
+        m.insert("underline".into(), Value::Boolean(s.underline));
+        m.insert("strikethrough".into(), Value::Boolean(s.strikethrough));
         if let Some((r, g, b)) = s.background_color {
             let mut c = Map::new();
             c.insert("r".into(), Value::Integer(r as i64));
@@ -530,29 +1910,82 @@ pub fn default_config_toml() -> String {
     margin.insert("bottom".into(), Value::Float(def.margins.bottom as f64));
     margin.insert("left".into(), Value::Float(def.margins.left as f64));
     root.insert("margin".into(), Value::Table(margin));
+    let mut page = Map::new();
+    page.insert("size".into(), Value::String(def.page_size.name().into()));
+    page.insert(
+        "orientation".into(),
+        Value::String(def.orientation.name().into()),
+    );
+    page.insert(
+        "min_lines_top".into(),
+        Value::Integer(def.min_lines_top as i64),
+    );
+    page.insert(
+        "min_lines_bottom".into(),
+        Value::Integer(def.min_lines_bottom as i64),
+    );
+    if let Some(first) = def.first_page_margins {
+        let mut margins = Map::new();
+        margins.insert("top".into(), Value::Float(first.top as f64));
+        margins.insert("right".into(), Value::Float(first.right as f64));
+        margins.insert("bottom".into(), Value::Float(first.bottom as f64));
+        margins.insert("left".into(), Value::Float(first.left as f64));
+        page.insert("first_page_margins".into(), Value::Table(margins));
+    }
+    let mut footer = Map::new();
+    footer.insert("show_date".into(), Value::Boolean(def.footer.show_date));
+    footer.insert(
+        "date_format".into(),
+        Value::String(def.footer.date_format.clone()),
+    );
+    footer.insert(
+        "page_numbers".into(),
+        Value::Boolean(def.footer.page_numbers),
+    );
+    footer.insert("size".into(), Value::Integer(def.footer.size as i64));
+    if let Some(logo) = &def.footer.logo {
+        insert_page_logo(&mut footer, logo);
+    }
+    page.insert("footer".into(), Value::Table(footer));
+    if let Some(logo) = &def.header.logo {
+        let mut header = Map::new();
+        insert_page_logo(&mut header, logo);
+        page.insert("header".into(), Value::Table(header));
+    }
+    root.insert("page".into(), Value::Table(page));
 
     // headings
     let mut headings = Map::new();
-    headings.insert(
-        "1".into(),
-        match style_to_table(def.heading_1) {
-            Value::Table(t) => Value::Table(t),
+    let heading_styles = [def.heading_1, def.heading_2, def.heading_3];
+    for (level, style) in heading_styles.into_iter().enumerate() {
+        let letter_spacing = style.letter_spacing;
+        let text_transform = style.text_transform;
+        let mut heading = match style_to_table(style) {
+            Value::Table(t) => t,
             _ => unreachable!(),
-        },
+        };
+        heading.insert("letterspacing".into(), Value::Float(letter_spacing as f64));
+        heading.insert(
+            "text_transform".into(),
+            Value::String(text_transform.name().into()),
+        );
+        heading.insert(
+            "page_break_before".into(),
+            Value::Boolean(def.page_break_before[level]),
+        );
+        headings.insert((level + 1).to_string(), Value::Table(heading));
+    }
+    headings.insert(
+        "numbered".into(),
+        Value::Boolean(def.heading_numbering.enabled),
     );
     headings.insert(
-        "2".into(),
-        match style_to_table(def.heading_2) {
-            Value::Table(t) => Value::Table(t),
-            _ => unreachable!(),
-        },
+        "number_from".into(),
+        Value::Integer(def.heading_numbering.from as i64),
     );
     headings.insert(
-        "3".into(),
-        match style_to_table(def.heading_3) {
-            Value::Table(t) => Value::Table(t),
-            _ => unreachable!(),
-        },
+        "number_to".into(),
+        Value::Integer(def.heading_numbering.to as i64),
     );
     root.insert("heading".into(), Value::Table(headings));
 
@@ -562,21 +1995,146 @@ pub fn default_config_toml() -> String {
         "strong_emphasis".into(),
         style_to_table(def.strong_emphasis),
     );
-    root.insert("code".into(), style_to_table(def.code));
-    root.insert("block_quote".into(), style_to_table(def.block_quote));
-    root.insert("list_item".into(), style_to_table(def.list_item));
-    root.insert("link".into(), style_to_table(def.link));
-    root.insert("image".into(), style_to_table(def.image));
+    let mut code = match style_to_table(def.code) {
+        Value::Table(t) => t,
+        _ => unreachable!(),
+    };
+    code.insert(
+        "show_language_label".into(),
+        Value::Boolean(def.show_code_language),
+    );
+    code.insert(
+        "style".into(),
+        Value::String(
+            match def.code_highlight_style {
+                CodeHighlightStyle::Color => "color",
+                CodeHighlightStyle::Grayscale => "grayscale",
+            }
+            .into(),
+        ),
+    );
+    code.insert(
+        "keep_together".into(),
+        Value::Boolean(def.code_keep_together),
+    );
+    code.insert(
+        "inline_background".into(),
+        Value::Boolean(def.code_inline_background),
+    );
+    code.insert(
+        "max_lines".into(),
+        Value::Integer(def.code_max_lines as i64),
+    );
+    code.insert(
+        "fit".into(),
+        Value::String(
+            match def.code_fit {
+                CodeFit::Wrap => "wrap",
+                CodeFit::Shrink => "shrink",
+            }
+            .into(),
+        ),
+    );
+    code.insert("label".into(), style_to_table(def.code_label));
+    root.insert("code".into(), Value::Table(code));
+    let mut block_quote = match style_to_table(def.block_quote) {
+        Value::Table(t) => t,
+        _ => unreachable!(),
+    };
+    if let Some(attribution) = def.block_quote_attribution {
+        block_quote.insert("attribution".into(), style_to_table(attribution));
+    }
+    root.insert("block_quote".into(), Value::Table(block_quote));
+    let mut list_item = match style_to_table(def.list_item) {
+        Value::Table(t) => t,
+        _ => unreachable!(),
+    };
+    list_item.insert(
+        "marker_width".into(),
+        Value::Float(def.list_marker_width as f64),
+    );
+    list_item.insert("indent".into(), Value::Float(def.list_indent as f64));
+    list_item.insert(
+        "max_nesting".into(),
+        Value::Integer(def.list_max_nesting as i64),
+    );
+    root.insert("list_item".into(), Value::Table(list_item));
+    let mut link = match style_to_table(def.link) {
+        Value::Table(t) => t,
+        _ => unreachable!(),
+    };
+    link.insert(
+        "style".into(),
+        Value::String(
+            match def.link_style {
+                LinkStyle::Inline => "inline",
+                LinkStyle::Footnote => "footnote",
+                LinkStyle::Parenthetical => "parenthetical",
+            }
+            .into(),
+        ),
+    );
+    if let Some(url) = &def.link_base_url {
+        link.insert("base_url".into(), Value::String(url.clone()));
+    }
+    root.insert("link".into(), Value::Table(link));
     root.insert("latex".into(), style_to_table(def.latex));
+    root.insert(
+        "horizontal_rule".into(),
+        style_to_table(def.horizontal_rule),
+    );
 
-    // image.svg
-    let mut image = Map::new();
+    // image, image.svg
+    let mut image = match style_to_table(def.image) {
+        Value::Table(t) => t,
+        _ => unreachable!(),
+    };
     let mut svg = Map::new();
+    svg.insert(
+        "width".into(),
+        Value::String(match def.svg_config.width {
+            crate::styling::SvgWidth::Percentage(percent) => format!("{}%", percent),
+            crate::styling::SvgWidth::Pixels(pixels) => format!("{}px", pixels),
+            crate::styling::SvgWidth::Auto => "auto".into(),
+        }),
+    );
+    svg.insert(
+        "height".into(),
+        Value::String(match def.svg_config.height {
+            crate::styling::SvgHeight::Pixels(pixels) => format!("{}px", pixels),
+            crate::styling::SvgHeight::Auto => "auto".into(),
+        }),
+    );
     svg.insert(
         "scale_factor".into(),
         Value::Float(def.svg_config.scale_factor as f64),
     );
     image.insert("svg".into(), Value::Table(svg));
+    let mut gallery = Map::new();
+    gallery.insert(
+        "columns".into(),
+        Value::Integer(def.image_gallery.columns as i64),
+    );
+    gallery.insert(
+        "gutter".into(),
+        Value::Float(def.image_gallery.gutter as f64),
+    );
+    image.insert("gallery".into(), Value::Table(gallery));
+    if let Some(size) = def.remote_images.max_download_size {
+        let mut remote = Map::new();
+        remote.insert("max_download_size".into(), Value::Integer(size as i64));
+        image.insert("remote".into(), Value::Table(remote));
+    }
+    if let Some(path) = &def.fallback_image {
+        image.insert("fallback_image".into(), Value::String(path.clone()));
+    }
+    if let Some(url) = &def.image_base_url {
+        image.insert("base_url".into(), Value::String(url.clone()));
+    }
+    image.insert("upscale".into(), Value::Boolean(def.image_upscale));
+    if let Some(dpi) = def.image_target_dpi {
+        image.insert("target_dpi".into(), Value::Float(dpi as f64));
+    }
     root.insert("image".into(), Value::Table(image));
 
     // mermaid
@@ -591,8 +2149,214 @@ pub fn default_config_toml() -> String {
     );
     root.insert("mermaid".into(), Value::Table(mer));
 
+    // qr
+    let mut qr = Map::new();
+    qr.insert("width".into(), Value::Float(def.qr.width as f64));
+    root.insert("qr".into(), Value::Table(qr));
+
+    // abbreviations
+    let mut abbreviations = match style_to_table(def.abbreviations.style) {
+        Value::Table(t) => t,
+        _ => unreachable!(),
+    };
+    abbreviations.insert(
+        "mode".into(),
+        Value::String(
+            match def.abbreviations.mode {
+                crate::styling::AbbreviationMode::FirstUse => "first_use",
+                crate::styling::AbbreviationMode::Glossary => "glossary",
+            }
+            .into(),
+        ),
+    );
+    root.insert("abbreviations".into(), Value::Table(abbreviations));
+
+    // kbd
+    let mut kbd = match style_to_table(def.kbd.style) {
+        Value::Table(t) => t,
+        _ => unreachable!(),
+    };
+    if let Some((r, g, b)) = def.kbd.border_color {
+        let mut c = Map::new();
+        c.insert("r".into(), Value::Integer(r as i64));
+        c.insert("g".into(), Value::Integer(g as i64));
+        c.insert("b".into(), Value::Integer(b as i64));
+        kbd.insert("bordercolor".into(), Value::Table(c));
+    }
+    root.insert("kbd".into(), Value::Table(kbd));
+    root.insert("mark".into(), style_to_table(def.mark));
+
+    // spoiler
+    let mut spoiler = match style_to_table(def.spoiler.style) {
+        Value::Table(t) => t,
+        _ => unreachable!(),
+    };
+    spoiler.insert("label".into(), Value::String(def.spoiler.label.clone()));
+    root.insert("spoiler".into(), Value::Table(spoiler));
+
+    // task_list
+    let color_table = |(r, g, b): (u8, u8, u8)| {
+        let mut c = Map::new();
+        c.insert("r".into(), Value::Integer(r as i64));
+        c.insert("g".into(), Value::Integer(g as i64));
+        c.insert("b".into(), Value::Integer(b as i64));
+        Value::Table(c)
+    };
+    let mut task_list = Map::new();
+    for (key, color) in [
+        ("checkedcolor", def.task_list.checked_color),
+        ("uncheckedcolor", def.task_list.unchecked_color),
+        ("donecolor", def.task_list.done_color),
+    ] {
+        if let Some(color) = color {
+            task_list.insert(key.into(), color_table(color));
+        }
+    }
+    task_list.insert(
+        "strikethrough_done".into(),
+        Value::Boolean(def.task_list.strikethrough_done),
+    );
+    task_list.insert(
+        "show_progress".into(),
+        Value::Boolean(def.task_list.show_progress),
+    );
+    root.insert("task_list".into(), Value::Table(task_list));
+
+    // document
+    let mut document = Map::new();
+    document.insert(
+        "reproducible".into(),
+        Value::Boolean(def.document.reproducible),
+    );
+    document.insert(
+        "title_from_h1".into(),
+        Value::Boolean(def.document.title_from_h1),
+    );
+    document.insert(
+        "attach_source".into(),
+        Value::Boolean(def.document.attach_source),
+    );
+    document.insert(
+        "create_dirs".into(),
+        Value::Boolean(def.document.create_dirs),
+    );
+    document.insert(
+        "contact_sheet".into(),
+        Value::Boolean(def.document.contact_sheet),
+    );
+    if let Some(lang) = &def.document.lang {
+        document.insert("lang".into(), Value::String(lang.clone()));
+    }
+    root.insert("document".into(), Value::Table(document));
+
+    // index
+    let mut index = Map::new();
+    index.insert("enabled".into(), Value::Boolean(def.index.enabled));
+    index.insert("title".into(), Value::String(def.index.title.clone()));
+    root.insert("index".into(), Value::Table(index));
+
+    // validation
+    let mut validation = Map::new();
+    validation.insert(
+        "min_contrast".into(),
+        Value::Float(def.validation.min_contrast as f64),
+    );
+    root.insert("validation".into(), Value::Table(validation));
+
+    // spacing
+    let mut spacing = Map::new();
+    spacing.insert("trim_edges".into(), Value::Boolean(def.spacing.trim_edges));
+    root.insert("spacing".into(), Value::Table(spacing));
+
+    // redact
+    let mut redact = Map::new();
+    redact.insert(
+        "patterns".into(),
+        Value::Array(
+            def.redact
+                .patterns
+                .iter()
+                .map(|p| Value::String(p.clone()))
+                .collect(),
+        ),
+    );
+    redact.insert("mask".into(), Value::String(def.redact.mask.to_string()));
+    root.insert("redact".into(), Value::Table(redact));
+
     // text
-    root.insert("text".into(), style_to_table(def.text));
+    let mut text = match style_to_table(def.text) {
+        Value::Table(t) => t,
+        _ => unreachable!(),
+    };
+    text.insert(
+        "soft_break".into(),
+        Value::String(
+            match def.soft_break {
+                crate::styling::SoftBreakMode::Space => "space",
+                crate::styling::SoftBreakMode::Newline => "newline",
+            }
+            .into(),
+        ),
+    );
+    text.insert(
+        "quote_style".into(),
+        Value::String(
+            match def.quote_style {
+                QuoteStyle::Straight => "straight",
+                QuoteStyle::En => "en",
+                QuoteStyle::Fr => "fr",
+                QuoteStyle::De => "de",
+            }
+            .into(),
+        ),
+    );
+    match def.text_max_width {
+        Some(MaxWidth::Points(points)) => {
+            text.insert("max_width".into(), Value::Float(points as f64));
+        }
+        Some(MaxWidth::Characters(chars)) => {
+            text.insert("max_width".into(), Value::String(format!("{}ch", chars)));
+        }
+        None => {}
+    }
+    if let Some(path) = &def.text_font_file {
+        text.insert("fontfile".into(), Value::String(path.clone()));
+    }
+    root.insert("text".into(), Value::Table(text));
+
+    // table
+    let mut table = Map::new();
+    table.insert("header".into(), style_to_table(def.table_header));
+    table.insert("cell".into(), style_to_table(def.table_cell));
+    table.insert(
+        "overflow".into(),
+        Value::String(
+            match def.table_overflow {
+                TableOverflow::Shrink => "shrink",
+                TableOverflow::Scroll => "scroll",
+            }
+            .into(),
+        ),
+    );
+    table.insert(
+        "rotate_if_oversized".into(),
+        Value::Boolean(def.table_rotate_if_oversized),
+    );
+    root.insert("table".into(), Value::Table(table));
+
+    // fonts.ranges
+    if !def.font_ranges.is_empty() {
+        let mut ranges = Map::new();
+        for range in &def.font_ranges {
+            ranges.insert(
+                format!("U+{:04X}-U+{:04X}", range.start, range.end),
+                Value::String(range.font.clone()),
+            );
+        }
+        let mut fonts = Map::new();
+        fonts.insert("ranges".into(), Value::Table(ranges));
+        root.insert("fonts".into(), Value::Table(fonts));
+    }
 
     // horizontal rule
     root.insert(
@@ -618,12 +2382,12 @@ mod tests {
         )
         .unwrap();
         assert_eq!(
-            parse_color(Some(&color_toml), "color"),
+            parse_color(Some(&color_toml), "color", &ParseContext::default()),
             Some((255, 128, 64))
         );
 
         // Test missing color
-        assert_eq!(parse_color(None, "color"), None);
+        assert_eq!(parse_color(None, "color", &ParseContext::default()), None);
 
         // Test invalid color (missing components)
         let invalid_color: Value = toml::from_str(
@@ -632,7 +2396,77 @@ mod tests {
         "#,
         )
         .unwrap();
-        assert_eq!(parse_color(Some(&invalid_color), "color"), None);
+        assert_eq!(
+            parse_color(Some(&invalid_color), "color", &ParseContext::default()),
+            None
+        );
+    }
+
+    // Helper function returning the color names a configuration references without
+    // defining them in its palette
+    fn unknown_colors(config: &str) -> Vec<String> {
+        let config = toml::from_str(config).unwrap();
+        parse_config_value_reported(&config, StyleMatch::default(), HashMap::new()).1
+    }
+
+    #[test]
+    fn test_parse_hex_colors() {
+        let config = "[palette]\nprimary = \"#00529B\"\n\n[heading.1]\ntextcolor = \"#1a2b3c\"\n\n[code]\nbackgroundcolor = \"#fa0\"\n\n[link]\ntextcolor = \"primary\"\n\n[kbd]\nbordercolor = \"#12345\"\n";
+        let style = parse_config_string(config);
+        assert_eq!(style.heading_1.text_color, Some((0x1a, 0x2b, 0x3c)));
+        assert_eq!(style.code.background_color, Some((0xff, 0xaa, 0x00)));
+        assert_eq!(style.link.text_color, Some((0x00, 0x52, 0x9b)));
+        // Malformed hex keeps the default and is not mistaken for a palette name
+        assert_eq!(
+            style.kbd.border_color,
+            StyleMatch::default().kbd.border_color
+        );
+        assert!(unknown_colors(config).is_empty());
+    }
+
+    #[test]
+    fn test_parse_palette_colors() {
+        let config = r#"
+        [palette]
+        primary = { r = 0, g = 82, b = 155 }
+        paper = { r = 250, g = 250, b = 245 }
+
+        [heading.1]
+        textcolor = "primary"
+
+        [code]
+        textcolor = { r = 10, g = 20, b = 30 }
+        backgroundcolor = "paper"
+
+        [link]
+        textcolor = "accent"
+
+        [kbd]
+        bordercolor = "accent"
+        "#;
+        let style = parse_config_string(config);
+        assert_eq!(style.heading_1.text_color, Some((0, 82, 155)));
+        assert_eq!(style.code.text_color, Some((10, 20, 30)));
+        assert_eq!(style.code.background_color, Some((250, 250, 245)));
+        // Unknown names keep the default color and are reported once
+        assert_eq!(style.link.text_color, StyleMatch::default().link.text_color);
+        assert_eq!(unknown_colors(config), vec!["accent".to_string()]);
+
+        // The palette belongs to the configuration it is defined in
+        assert_eq!(
+            unknown_colors("[heading.1]\ntextcolor = \"primary\"\n"),
+            vec!["primary".to_string()]
+        );
+        assert!(unknown_colors(&default_config_toml()).is_empty());
+
+        // Unknown names are reported as validation warnings
+        let (_, warnings) = load_config_from_source_reported(ConfigSource::Embedded(config));
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(
+            warnings[0].kind,
+            crate::validation::WarningKind::UnknownPaletteColor
+        );
+        assert!(warnings[0].message.contains("'accent'"));
     }
 
     #[test]
@@ -715,6 +2549,8 @@ mod tests {
         let parsed_style = parse_style(
             Some(&style_toml.get("style").unwrap()),
             default_style.clone(),
+            default_style.size,
+            &ParseContext::default(),
         );
 
         assert_eq!(parsed_style.size, 14);
@@ -744,6 +2580,8 @@ mod tests {
         let parsed_style = parse_style(
             Some(&partial_style.get("style").unwrap()),
             default_style.clone(),
+            default_style.size,
+            &ParseContext::default(),
         );
 
         assert_eq!(parsed_style.size, 16);
@@ -770,6 +2608,8 @@ mod tests {
         let parsed_style = parse_style(
             Some(&invalid_style.get("style").unwrap()),
             default_style.clone(),
+            default_style.size,
+            &ParseContext::default(),
         );
 
         // Should fall back to default values
@@ -813,8 +2653,19 @@ mod tests {
     }
 
     #[test]
-    fn test_parse_mermaid_config() {
-        // Defaults come from StyleMatch::default
+    fn test_parse_qr_config() {
+        assert_eq!(StyleMatch::default().qr.width, 25.0);
+        assert_eq!(parse_config_string("[qr]\nwidth = 40\n").qr.width, 40.0);
+        assert_eq!(
+            parse_config_string("[qr]\nwidth = \"15%\"\n").qr.width,
+            15.0
+        );
+        assert_eq!(parse_config_string("[qr]\nwidth = 150\n").qr.width, 25.0);
+    }
+
+    #[test]
+    fn test_parse_mermaid_config() {
+        // Defaults come from StyleMatch::default
         let default_style = StyleMatch::default();
         assert_eq!(default_style.mermaid.auto_scale, 2.0);
         assert_eq!(default_style.mermaid.max_ratio, 1.0);
@@ -847,6 +2698,771 @@ mod tests {
         assert_eq!(style2.mermaid.max_ratio, 1.0);
     }
 
+    #[test]
+    fn test_parse_abbreviation_config() {
+        let default_style = StyleMatch::default();
+        assert_eq!(default_style.abbreviations.mode, AbbreviationMode::Glossary);
+
+        let cfg = r#"
+            [abbreviations]
+            mode = "first_use"
+            italic = true
+            textcolor = { r = 10, g = 20, b = 30 }
+        "#;
+        let style = parse_config_string(cfg);
+        assert_eq!(style.abbreviations.mode, AbbreviationMode::FirstUse);
+        assert!(style.abbreviations.style.italic);
+        assert_eq!(style.abbreviations.style.text_color, Some((10, 20, 30)));
+        // Unspecified properties keep their defaults
+        assert_eq!(
+            style.abbreviations.style.size,
+            default_style.abbreviations.style.size
+        );
+    }
+
+    #[test]
+    fn test_parse_index_config() {
+        assert_eq!(StyleMatch::default().index, IndexConfig::default());
+        assert!(StyleMatch::default().index.enabled);
+
+        let style = parse_config_string("[index]\nenabled = false\ntitle = \"Register\"\n");
+        assert!(!style.index.enabled);
+        assert_eq!(style.index.title, "Register");
+
+        let style = parse_config_string(&default_config_toml());
+        assert_eq!(style.index, IndexConfig::default());
+    }
+
+    #[test]
+    fn test_parse_document_config() {
+        assert!(!StyleMatch::default().document.reproducible);
+
+        let style = parse_config_string("[document]\nreproducible = true\n");
+        assert!(style.document.reproducible);
+
+        // A prebuilt style is passed through unchanged
+        let loaded = load_config_from_source(ConfigSource::Style(style));
+        assert!(loaded.document.reproducible);
+
+        assert!(!StyleMatch::default().document.title_from_h1);
+        let style = parse_config_string("[document]\ntitle_from_h1 = true\n");
+        assert!(style.document.title_from_h1);
+        assert!(!style.document.reproducible);
+
+        assert!(!StyleMatch::default().document.attach_source);
+        let style = parse_config_string("[document]\nattach_source = true\n");
+        assert!(style.document.attach_source);
+        assert!(
+            !parse_config_string(&default_config_toml())
+                .document
+                .attach_source
+        );
+
+        assert!(!StyleMatch::default().document.create_dirs);
+        let style = parse_config_string("[document]\ncreate_dirs = true\n");
+        assert!(style.document.create_dirs);
+
+        assert!(!StyleMatch::default().document.contact_sheet);
+        let style = parse_config_string("[document]\ncontact_sheet = true\n");
+        assert!(style.document.contact_sheet);
+
+        assert_eq!(StyleMatch::default().document.lang, None);
+        let style = parse_config_string("[document]\nlang = \"de-CH\"\n");
+        assert_eq!(style.document.lang.as_deref(), Some("de-CH"));
+        let style = parse_config_string("[document]\nlang = \" \"\n");
+        assert_eq!(style.document.lang, None);
+    }
+
+    #[test]
+    fn test_parse_remote_image_config() {
+        assert_eq!(StyleMatch::default().remote_images.max_download_size, None);
+
+        let style = parse_config_string("[image.remote]\nmax_download_size = 1048576\n");
+        assert_eq!(style.remote_images.max_download_size, Some(1_048_576));
+
+        // Negative sizes are ignored
+        let style = parse_config_string("[image.remote]\nmax_download_size = -1\n");
+        assert_eq!(style.remote_images.max_download_size, None);
+    }
+
+    #[test]
+    fn test_parse_code_label() {
+        let default = StyleMatch::default();
+        assert!(!default.show_code_language);
+
+        let style = parse_config_string(
+            "[code]\nsize = 9\nshow_language_label = true\n\n[code.label]\nsize = 7\ntextcolor = { r = 200, g = 0, b = 0 }\n",
+        );
+        assert!(style.show_code_language);
+        assert_eq!(style.code.size, 9);
+        assert_eq!(style.code_label.size, 7);
+        assert_eq!(style.code_label.text_color, Some((200, 0, 0)));
+        assert_eq!(
+            style.code_label.background_color,
+            default.code_label.background_color
+        );
+
+        let style = parse_config_string(&default_config_toml());
+        assert!(!style.show_code_language);
+        assert_eq!(style.code_label.size, default.code_label.size);
+        assert_eq!(style.code.size, default.code.size);
+    }
+
+    #[test]
+    fn test_parse_fallback_image() {
+        assert_eq!(StyleMatch::default().fallback_image, None);
+
+        let style = parse_config_string("[image]\nfallback_image = \"assets/missing.png\"\n");
+        assert_eq!(style.fallback_image.as_deref(), Some("assets/missing.png"));
+
+        let style = parse_config_string("[image]\nfallback_image = \"\"\n");
+        assert_eq!(style.fallback_image, None);
+
+        // A path in a configuration file is relative to the file; URLs are kept
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("style.toml");
+        std::fs::write(&file, "[image]\nfallback_image = \"assets/missing.png\"\n").unwrap();
+        let style = load_config_from_source(ConfigSource::File(file.to_str().unwrap()));
+        assert_eq!(
+            style.fallback_image.map(std::path::PathBuf::from),
+            Some(dir.path().join("assets/missing.png"))
+        );
+        std::fs::write(
+            &file,
+            "[image]\nfallback_image = \"https://example.com/missing.png\"\n",
+        )
+        .unwrap();
+        let style = load_config_from_source(ConfigSource::File(file.to_str().unwrap()));
+        assert_eq!(
+            style.fallback_image.as_deref(),
+            Some("https://example.com/missing.png")
+        );
+    }
+
+    #[test]
+    fn test_parse_base_url() {
+        let style = StyleMatch::default();
+        assert_eq!(style.link_base_url, None);
+        assert_eq!(style.image_base_url, None);
+
+        let style = parse_config_string(
+            "[link]\nbase_url = \"https://site.example/docs/\"\n\n[image]\nbase_url = \" \"\n",
+        );
+        assert_eq!(
+            style.link_base_url.as_deref(),
+            Some("https://site.example/docs/")
+        );
+        assert_eq!(style.image_base_url, None);
+    }
+
+    #[test]
+    fn test_parse_image_upscale() {
+        assert!(!StyleMatch::default().image_upscale);
+        assert!(parse_config_string("[image]\nupscale = true\n").image_upscale);
+        assert!(!parse_config_string("[image]\nsize = 8\n").image_upscale);
+    }
+
+    #[test]
+    fn test_parse_image_gallery_config() {
+        assert_eq!(
+            StyleMatch::default().image_gallery,
+            ImageGalleryConfig::default()
+        );
+
+        let style = parse_config_string("[image.gallery]\ncolumns = 3\ngutter = 4\n");
+        assert_eq!(style.image_gallery.columns, 3);
+        assert_eq!(style.image_gallery.gutter, 4.0);
+
+        let style = parse_config_string("[image.gallery]\ncolumns = -2\ngutter = -1.0\n");
+        assert_eq!(style.image_gallery.columns, 0);
+        assert_eq!(style.image_gallery.gutter, 2.0);
+
+        let style = parse_config_string(&default_config_toml());
+        assert_eq!(style.image_gallery, ImageGalleryConfig::default());
+    }
+
+    #[test]
+    fn test_parse_image_target_dpi() {
+        assert_eq!(StyleMatch::default().image_target_dpi, None);
+        let style = parse_config_string("[image]\ntarget_dpi = 300\n");
+        assert_eq!(style.image_target_dpi, Some(300.0));
+        let style = parse_config_string("[image]\ntarget_dpi = 150.5\n");
+        assert_eq!(style.image_target_dpi, Some(150.5));
+        let style = parse_config_string("[image]\ntarget_dpi = 0\n");
+        assert_eq!(style.image_target_dpi, None);
+    }
+
+    #[test]
+    fn test_parse_text_max_width() {
+        assert_eq!(StyleMatch::default().text_max_width, None);
+        let style = parse_config_string("[text]\nmax_width = 360\n");
+        assert_eq!(style.text_max_width, Some(MaxWidth::Points(360.0)));
+        let style = parse_config_string("[text]\nmax_width = \"70ch\"\n");
+        assert_eq!(style.text_max_width, Some(MaxWidth::Characters(70)));
+        let style = parse_config_string("[text]\nmax_width = \"wide\"\n");
+        assert_eq!(style.text_max_width, None);
+        let style = parse_config_string("[text]\nmax_width = 0\n");
+        assert_eq!(style.text_max_width, None);
+    }
+
+    #[test]
+    fn test_parse_page_break_before() {
+        assert_eq!(StyleMatch::default().page_break_before, [false; 3]);
+
+        let style = parse_config_string(
+            "[heading.1]\nsize = 20\npage_break_before = true\n[heading.3]\npage_break_before = true\n",
+        );
+        assert_eq!(style.page_break_before, [true, false, true]);
+        assert_eq!(style.heading_1.size, 20);
+    }
+
+    #[test]
+    fn test_merge_config_string() {
+        let base = parse_config_string("[margin]\ntop = 12.0\n[heading.1]\nsize = 18\n");
+        let style = merge_config_string(base.clone(), "[margin]\nleft = 20.0\n[code]\nsize = 9\n");
+        assert_eq!(style.margins.left, 20.0);
+        assert_eq!(style.margins.top, 12.0);
+        assert_eq!(style.heading_1.size, 18);
+        assert_eq!(style.code.size, 9);
+        assert_eq!(merge_config_string(base.clone(), "not = [toml"), base);
+    }
+
+    #[test]
+    fn test_front_matter_style() {
+        let markdown = "---\ntitle: Report\nstyle: |\n    [margin]\n    left = 20.0\n\n    [heading.1]\n    size = 20\nauthor: Me\n---\nText";
+        let style = front_matter_style(markdown).unwrap();
+        assert_eq!(style, "[margin]\nleft = 20.0\n\n[heading.1]\nsize = 20\n");
+        assert_eq!(parse_config_string(&style).heading_1.size, 20);
+
+        assert_eq!(front_matter_style("---\ntitle: Report\n---\nText"), None);
+        assert_eq!(front_matter_style("style: |\n  [margin]\n"), None);
+    }
+
+    #[test]
+    fn test_parse_heading_text_transform() {
+        assert_eq!(
+            StyleMatch::default().heading_1.text_transform,
+            TextTransform::None
+        );
+        let style = parse_config_string(
+            "[heading.1]\ntext_transform = \"uppercase\"\n[heading.2]\ntext_transform = \"SmallCaps\"\n[heading.3]\ntext_transform = \"capitalize\"\n",
+        );
+        assert_eq!(style.heading_1.text_transform, TextTransform::Uppercase);
+        assert_eq!(style.heading_2.text_transform, TextTransform::SmallCaps);
+        assert_eq!(style.heading_3.text_transform, TextTransform::None);
+    }
+
+    #[test]
+    fn test_parse_heading_letter_spacing() {
+        assert_eq!(StyleMatch::default().heading_1.letter_spacing, 0.0);
+
+        let style = parse_config_string(
+            "[heading.1]\nletterspacing = 1.5\n[heading.2]\nletterspacing = 2\n",
+        );
+        assert_eq!(style.heading_1.letter_spacing, 1.5);
+        assert_eq!(style.heading_2.letter_spacing, 2.0);
+        assert_eq!(style.heading_3.letter_spacing, 0.0);
+    }
+
+    #[test]
+    fn test_parse_spoiler_config() {
+        let style = parse_config_string("[spoiler]\nlabel = \"Plot twist\"\nitalic = true\n");
+        assert_eq!(style.spoiler.label, "Plot twist");
+        assert!(style.spoiler.style.italic);
+        assert_eq!(
+            style.spoiler.style.before_spacing,
+            SpoilerConfig::default().style.before_spacing
+        );
+
+        let style = parse_config_string("[spoiler]\nsize = 10\n");
+        assert_eq!(style.spoiler.label, "Spoiler");
+        assert_eq!(style.spoiler.style.size, 10);
+    }
+
+    #[test]
+    fn test_parse_spacing_config() {
+        assert!(!StyleMatch::default().spacing.trim_edges);
+
+        let style = parse_config_string("[spacing]\ntrim_edges = true\n");
+        assert!(style.spacing.trim_edges);
+
+        // Non-boolean values keep the default
+        let style = parse_config_string("[spacing]\ntrim_edges = \"yes\"\n");
+        assert!(!style.spacing.trim_edges);
+    }
+
+    #[test]
+    fn test_parse_validation_config() {
+        assert_eq!(StyleMatch::default().validation.min_contrast, 3.0);
+
+        let style = parse_config_string("[validation]\nmin_contrast = 4.5\n");
+        assert_eq!(style.validation.min_contrast, 4.5);
+
+        let style = parse_config_string("[validation]\nmin_contrast = 0\n");
+        assert_eq!(style.validation.min_contrast, 0.0);
+    }
+
+    #[test]
+    fn test_parse_kbd_config() {
+        let default = StyleMatch::default().kbd;
+        assert_eq!(default.style.background_color, Some((240, 240, 240)));
+
+        let style =
+            parse_config_string("[kbd]\nsize = 7\nbordercolor = { r = 10, g = 20, b = 30 }\n");
+        assert_eq!(style.kbd.style.size, 7);
+        assert_eq!(style.kbd.border_color, Some((10, 20, 30)));
+        assert_eq!(
+            style.kbd.style.background_color,
+            default.style.background_color
+        );
+
+        let style = parse_config_string(&default_config_toml());
+        assert_eq!(style.kbd, default);
+    }
+
+    #[test]
+    fn test_parse_task_list_config() {
+        let default = StyleMatch::default().task_list;
+        assert!(!default.strikethrough_done);
+
+        let style = parse_config_string(
+            "[task_list]\nstrikethrough_done = true\ndonecolor = { r = 150, g = 150, b = 150 }\n",
+        );
+        assert!(style.task_list.strikethrough_done);
+        assert!(!style.task_list.show_progress);
+        assert_eq!(style.task_list.done_color, Some((150, 150, 150)));
+        assert_eq!(style.task_list.checked_color, default.checked_color);
+
+        let style = parse_config_string(&default_config_toml());
+        assert_eq!(style.task_list, default);
+    }
+
+    #[test]
+    fn test_parse_font_ranges() {
+        assert!(StyleMatch::default().font_ranges.is_empty());
+
+        let style = parse_config_string(
+            r#"
+            [fonts.ranges]
+            cjk = "Noto Sans CJK SC"
+            "U+0600-U+06FF" = "Noto Sans Arabic"
+            "U+4E2D" = "Special Han"
+            "U+0700-U+0600" = "Reversed"
+            "not a range" = "Ignored"
+            "#,
+        );
+        assert_eq!(style.font_ranges.len(), 3);
+        // Narrowest range first
+        assert_eq!(style.font_ranges[0].font, "Special Han");
+        assert_eq!(
+            (style.font_ranges[1].start, style.font_ranges[1].end),
+            (0x0600, 0x06FF)
+        );
+        assert_eq!(style.font_ranges[2].font, "Noto Sans CJK SC");
+        assert!(style.font_ranges[2].contains('中'));
+        assert!(!style.font_ranges[2].contains('a'));
+    }
+
+    #[test]
+    fn test_parse_list_layout() {
+        let default = StyleMatch::default();
+        let style = parse_config_string("[list_item]\nmarker_width = 6.5\nindent = 4\n");
+        assert_eq!(style.list_marker_width, 6.5);
+        assert_eq!(style.list_indent, 4.0);
+
+        let style = parse_config_string("[list_item]\nmarker_width = -1.0\n");
+        assert_eq!(style.list_marker_width, default.list_marker_width);
+
+        let style = parse_config_string(&default_config_toml());
+        assert_eq!(style.list_marker_width, default.list_marker_width);
+        assert_eq!(style.list_indent, default.list_indent);
+        assert_eq!(style.list_max_nesting, 32);
+
+        let style = parse_config_string("[list_item]\nmax_nesting = 4\n");
+        assert_eq!(style.list_max_nesting, 4);
+        let style = parse_config_string("[list_item]\nmax_nesting = 0\n");
+        assert_eq!(style.list_max_nesting, default.list_max_nesting);
+    }
+
+    #[test]
+    fn test_parse_first_page_margins() {
+        assert_eq!(StyleMatch::default().first_page_margins, None);
+
+        let style = parse_config_string(
+            "[margin]\ntop = 10.0\nright = 12.0\nbottom = 10.0\nleft = 12.0\n\n[page]\nfirst_page_margins = { top = 60.0, left = 20 }\n",
+        );
+        assert_eq!(
+            style.first_page_margins,
+            Some(Margins {
+                top: 60.0,
+                right: 12.0,
+                bottom: 10.0,
+                left: 20.0,
+            })
+        );
+        assert_eq!(style.margins.top, 10.0);
+
+        assert_eq!(
+            parse_config_string(&default_config_toml()).first_page_margins,
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_relative_sizes() {
+        let base = Value::Integer(10);
+        assert_eq!(parse_size(&base, 12), Some(10));
+        assert_eq!(parse_size(&Value::String("2em".into()), 12), Some(24));
+        assert_eq!(parse_size(&Value::String("1.25 em".into()), 12), Some(15));
+        assert_eq!(parse_size(&Value::String("150%".into()), 10), Some(15));
+        assert_eq!(parse_size(&Value::String("large".into()), 10), None);
+        assert_eq!(parse_size(&Value::String("-1em".into()), 10), None);
+
+        let style = parse_config_string(
+            "[text]\nsize = 10\n\n[heading.1]\nsize = \"2em\"\n\n[code]\nsize = \"90%\"\n",
+        );
+        assert_eq!(style.text.size, 10);
+        assert_eq!(style.heading_1.size, 20);
+        assert_eq!(style.code.size, 9);
+        // Sections that are not configured keep their point sizes
+        assert_eq!(style.heading_2.size, StyleMatch::default().heading_2.size);
+
+        // Without a [text] size, relative sizes use the default text size
+        let style = parse_config_string("[heading.1]\nsize = \"200%\"\n");
+        assert_eq!(style.heading_1.size, StyleMatch::default().text.size * 2);
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn test_parse_config_json() {
+        let json = r#"{
+            "heading": {"1": {"size": 18, "textcolor": {"r": 10, "g": 20, "b": 30}}},
+            "code": {"fontfamily": "Space Mono", "show_language_label": true},
+            "link": {"style": "footnote"}
+        }"#;
+        let toml = "[heading.1]\nsize = 18\ntextcolor = { r = 10, g = 20, b = 30 }\n\n[code]\nfontfamily = \"Space Mono\"\nshow_language_label = true\n\n[link]\nstyle = \"footnote\"\n";
+        assert_eq!(parse_config_json(json), parse_config_string(toml));
+        assert_eq!(
+            load_config_from_source(ConfigSource::Json(json))
+                .heading_1
+                .size,
+            18
+        );
+
+        // JSON files are recognized by their extension
+        let dir = tempfile::tempdir().unwrap();
+        let tmp = dir.path().join("config.json");
+        std::fs::write(&tmp, json).unwrap();
+        let style = load_config_from_source(ConfigSource::File(tmp.to_str().unwrap()));
+        assert_eq!(style.heading_1.text_color, Some((10, 20, 30)));
+
+        assert_eq!(parse_config_json("{\"text\": null}"), StyleMatch::default());
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_parse_config_yaml() {
+        let yaml = "heading:\n  \"1\":\n    size: 18\n    bold: true\ntable:\n  overflow: scroll\n";
+        let style = parse_config_yaml(yaml);
+        assert_eq!(style.heading_1.size, 18);
+        assert!(style.heading_1.bold);
+        assert_eq!(style.table_overflow, TableOverflow::Scroll);
+        assert_eq!(
+            load_config_from_source(ConfigSource::Yaml(yaml))
+                .heading_1
+                .size,
+            18
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let tmp = dir.path().join("config.yml");
+        std::fs::write(&tmp, yaml).unwrap();
+        let style = load_config_from_source(ConfigSource::File(tmp.to_str().unwrap()));
+        assert_eq!(style.heading_1.size, 18);
+    }
+
+    #[test]
+    fn test_parse_link_style() {
+        assert_eq!(StyleMatch::default().link_style, LinkStyle::Inline);
+
+        let style = parse_config_string("[link]\nsize = 9\nstyle = \"footnote\"\n");
+        assert_eq!(style.link_style, LinkStyle::Footnote);
+        assert_eq!(style.link.size, 9);
+
+        let style = parse_config_string("[link]\nstyle = \"parenthetical\"\n");
+        assert_eq!(style.link_style, LinkStyle::Parenthetical);
+
+        let style = parse_config_string("[link]\nstyle = \"sidenote\"\n");
+        assert_eq!(style.link_style, LinkStyle::Inline);
+    }
+
+    #[test]
+    fn test_parse_heading_numbering() {
+        assert!(!StyleMatch::default().heading_numbering.enabled);
+
+        let style = parse_config_string("[heading]\nnumbered = true\n");
+        assert_eq!(
+            style.heading_numbering,
+            HeadingNumbering {
+                enabled: true,
+                from: 1,
+                to: 6
+            }
+        );
+
+        // Level bounds turn numbering on
+        let style = parse_config_string("[heading]\nnumber_from = 2\nnumber_to = 3\n");
+        assert_eq!(
+            style.heading_numbering,
+            HeadingNumbering {
+                enabled: true,
+                from: 2,
+                to: 3
+            }
+        );
+
+        let style = parse_config_string("[heading]\nnumbered = false\nnumber_from = 2\n");
+        assert!(!style.heading_numbering.enabled);
+
+        let style = parse_config_string("[heading]\nnumber_from = 3\nnumber_to = 2\n");
+        assert_eq!(
+            (style.heading_numbering.from, style.heading_numbering.to),
+            (1, 6)
+        );
+    }
+
+    #[test]
+    fn test_parse_footer_config() {
+        let style = StyleMatch::default();
+        assert!(!style.footer.is_enabled());
+
+        let style = parse_config_string(
+            "[page.footer]\nshow_date = true\ndate_format = \"Generated on %d/%m/%Y\"\nsize = 9\n",
+        );
+        assert!(style.footer.show_date);
+        assert!(!style.footer.page_numbers);
+        assert_eq!(style.footer.date_format, "Generated on %d/%m/%Y");
+        assert_eq!(style.footer.size, 9);
+
+        let style = parse_config_string("[page.footer]\npage_numbers = true\nsize = 0\n");
+        assert!(style.footer.is_enabled());
+        assert_eq!(style.footer.size, 8);
+    }
+
+    #[test]
+    fn test_parse_page_logo() {
+        let style = StyleMatch::default();
+        assert_eq!(style.header.logo, None);
+        assert_eq!(style.footer.logo, None);
+
+        let style = parse_config_string(
+            "[page.header]\nimage = \"assets/logo.png\"\nimage_alignment = \"right\"\nimage_height = 12\n\n[page.footer]\nimage = \"seal.svg\"\nimage_height = -1\n",
+        );
+        assert_eq!(
+            style.header.logo,
+            Some(PageLogo {
+                image: "assets/logo.png".to_string(),
+                alignment: TextAlignment::Right,
+                height: 12.0,
+            })
+        );
+        let footer_logo = style.footer.logo.unwrap();
+        assert_eq!(footer_logo.image, "seal.svg");
+        assert_eq!(footer_logo.alignment, TextAlignment::Center);
+        assert_eq!(footer_logo.height, crate::styling::DEFAULT_PAGE_LOGO_HEIGHT);
+        // A logo alone prints no footer line
+        assert!(!style.footer.is_enabled());
+
+        let style = parse_config_string("[page.header]\nimage = \"\"\n");
+        assert_eq!(style.header.logo, None);
+    }
+
+    #[test]
+    fn test_parse_min_lines() {
+        let style = StyleMatch::default();
+        assert_eq!((style.min_lines_top, style.min_lines_bottom), (1, 1));
+
+        let style = parse_config_string("[page]\nmin_lines_top = 2\nmin_lines_bottom = 3\n");
+        assert_eq!((style.min_lines_top, style.min_lines_bottom), (2, 3));
+
+        let style = parse_config_string("[page]\nmin_lines_top = 0\nmin_lines_bottom = -2\n");
+        assert_eq!((style.min_lines_top, style.min_lines_bottom), (1, 1));
+    }
+
+    #[test]
+    fn test_parse_page_size() {
+        let style = StyleMatch::default();
+        assert_eq!(style.page_size, PageSize::A4);
+        assert_eq!(style.page_dimensions(), (210.0, 297.0));
+
+        let style = parse_config_string("[page]\nsize = \"Letter\"\norientation = \"landscape\"\n");
+        assert_eq!(style.page_size, PageSize::Letter);
+        assert_eq!(style.orientation, Orientation::Landscape);
+        assert_eq!(style.page_dimensions(), (279.4, 215.9));
+
+        // Unknown names keep the defaults
+        let style = parse_config_string("[page]\nsize = \"b5\"\norientation = \"sideways\"\n");
+        assert_eq!(style.page_size, PageSize::A4);
+        assert_eq!(style.orientation, Orientation::Portrait);
+
+        let parsed = parse_config_string(&default_config_toml());
+        assert_eq!(parsed.page_size, PageSize::A4);
+        assert_eq!(parsed.orientation, Orientation::Portrait);
+    }
+
+    #[test]
+    fn test_parse_code_highlight_style() {
+        assert_eq!(
+            StyleMatch::default().code_highlight_style,
+            CodeHighlightStyle::Color
+        );
+
+        let style = parse_config_string("[code]\nstyle = \"grayscale\"\n");
+        assert_eq!(style.code_highlight_style, CodeHighlightStyle::Grayscale);
+
+        let style = parse_config_string("[code]\nstyle = \"sepia\"\n");
+        assert_eq!(style.code_highlight_style, CodeHighlightStyle::Color);
+
+        let style = parse_config_string(&default_config_toml());
+        assert_eq!(style.code_highlight_style, CodeHighlightStyle::Color);
+    }
+
+    #[test]
+    fn test_parse_code_keep_together() {
+        assert!(!StyleMatch::default().code_keep_together);
+
+        let style = parse_config_string("[code]\nkeep_together = true\n");
+        assert!(style.code_keep_together);
+
+        let style = parse_config_string(&default_config_toml());
+        assert!(!style.code_keep_together);
+    }
+
+    #[test]
+    fn test_parse_code_inline_background() {
+        assert!(!StyleMatch::default().code_inline_background);
+
+        let style = parse_config_string("[code]\ninline_background = true\n");
+        assert!(style.code_inline_background);
+
+        let style = parse_config_string(&default_config_toml());
+        assert!(!style.code_inline_background);
+
+        // A configured background color turns the box on, unless it is turned off
+        let style = parse_config_string("[code]\nbackgroundcolor = \"#f6f8fa\"\n");
+        assert!(style.code_inline_background);
+        assert_eq!(style.code.background_color, Some((246, 248, 250)));
+        let style = parse_config_string(
+            "[code]\nbackgroundcolor = \"#f6f8fa\"\ninline_background = false\n",
+        );
+        assert!(!style.code_inline_background);
+    }
+
+    #[test]
+    fn test_parse_code_fit() {
+        assert_eq!(StyleMatch::default().code_fit, CodeFit::Wrap);
+
+        let style = parse_config_string("[code]\nfit = \"shrink\"\n");
+        assert_eq!(style.code_fit, CodeFit::Shrink);
+        let style = parse_config_string("[code]\nfit = \"clip\"\n");
+        assert_eq!(style.code_fit, CodeFit::Wrap);
+    }
+
+    #[test]
+    fn test_parse_code_max_lines() {
+        assert_eq!(StyleMatch::default().code_max_lines, 0);
+
+        let style = parse_config_string("[code]\nmax_lines = 2000\n");
+        assert_eq!(style.code_max_lines, 2000);
+        let style = merge_config_string(style, "[code]\nmax_lines = -5\n");
+        assert_eq!(style.code_max_lines, 2000);
+
+        let style = parse_config_string(&default_config_toml());
+        assert_eq!(style.code_max_lines, 0);
+    }
+
+    #[test]
+    fn test_parse_block_quote_attribution() {
+        assert!(StyleMatch::default().block_quote_attribution.is_none());
+        let style = parse_config_string("[block_quote]\nsize = 9\n");
+        assert!(style.block_quote_attribution.is_none());
+
+        let style = parse_config_string(
+            "[block_quote]\nsize = 9\nitalic = false\n\n[block_quote.attribution]\nbold = true\n",
+        );
+        let attribution = style.block_quote_attribution.unwrap();
+        assert_eq!(attribution.size, 9);
+        assert!(attribution.bold);
+        assert!(attribution.italic);
+        assert_eq!(attribution.alignment, Some(TextAlignment::Right));
+        assert!(!style.block_quote.italic);
+    }
+
+    #[test]
+    fn test_parse_table_overflow() {
+        assert_eq!(StyleMatch::default().table_overflow, TableOverflow::Shrink);
+
+        let style = parse_config_string("[table]\noverflow = \"scroll\"\n");
+        assert_eq!(style.table_overflow, TableOverflow::Scroll);
+
+        let style = parse_config_string("[table]\noverflow = \"wrap\"\n");
+        assert_eq!(style.table_overflow, TableOverflow::Shrink);
+
+        let style = parse_config_string(&default_config_toml());
+        assert_eq!(style.table_overflow, TableOverflow::Shrink);
+    }
+
+    #[test]
+    fn test_parse_table_rotate_if_oversized() {
+        assert!(!StyleMatch::default().table_rotate_if_oversized);
+
+        let style = parse_config_string("[table]\nrotate_if_oversized = true\n");
+        assert!(style.table_rotate_if_oversized);
+
+        let style = parse_config_string(&default_config_toml());
+        assert!(!style.table_rotate_if_oversized);
+    }
+
+    #[test]
+    fn test_parse_soft_break() {
+        assert_eq!(StyleMatch::default().soft_break, SoftBreakMode::Space);
+
+        let style = parse_config_string("[text]\nsize = 9\nsoft_break = \"newline\"\n");
+        assert_eq!(style.soft_break, SoftBreakMode::Newline);
+        assert_eq!(style.text.size, 9);
+
+        let style = parse_config_string("[text]\nsoft_break = \"bogus\"\n");
+        assert_eq!(style.soft_break, SoftBreakMode::Space);
+    }
+
+    #[test]
+    fn test_parse_text_font_file() {
+        assert_eq!(StyleMatch::default().text_font_file, None);
+
+        let style = parse_config_string("[text]\nfontfile = \"fonts/Inter.ttf\"\n");
+        assert_eq!(style.text_font_file.as_deref(), Some("fonts/Inter.ttf"));
+        let merged = merge_config_string(style, "[text]\nsize = 9\n");
+        assert_eq!(merged.text_font_file.as_deref(), Some("fonts/Inter.ttf"));
+
+        let style = parse_config_string("[text]\nfontfile = \"  \"\n");
+        assert_eq!(style.text_font_file, None);
+    }
+
+    #[test]
+    fn test_parse_quote_style() {
+        assert_eq!(StyleMatch::default().quote_style, QuoteStyle::Straight);
+        for (value, expected) in [
+            ("en", QuoteStyle::En),
+            ("FR", QuoteStyle::Fr),
+            ("de", QuoteStyle::De),
+            ("straight", QuoteStyle::Straight),
+            ("klingon", QuoteStyle::Straight),
+        ] {
+            let style = parse_config_string(&format!("[text]\nquote_style = \"{}\"\n", value));
+            assert_eq!(style.quote_style, expected, "quote_style = {}", value);
+        }
+    }
+
     #[test]
     fn test_parse_config_string_invalid_toml() {
         let invalid_config = "this is not valid toml {{{";
@@ -960,4 +3576,128 @@ mod tests {
         let _ = std::fs::remove_file(&tmp);
         assert_eq!(style2.heading_1.size, default.heading_1.size);
     }
+
+    #[test]
+    fn test_load_merged_config_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().join("base.toml");
+        let overrides = dir.path().join("overrides.toml");
+        std::fs::write(&base, "[heading.1]\nsize = 18\n\n[margin]\nleft = 15.0\n").unwrap();
+        std::fs::write(&overrides, "[margin]\nleft = 20.0\n").unwrap();
+
+        let style = load_config_from_source(ConfigSource::Merged(vec![
+            base.to_str().unwrap(),
+            "nonexistent.toml",
+            overrides.to_str().unwrap(),
+        ]));
+
+        // Later files win, settings they leave out come from the earlier ones
+        assert_eq!(style.margins.left, 20.0);
+        assert_eq!(style.heading_1.size, 18);
+        assert_eq!(style.margins.top, StyleMatch::default().margins.top);
+
+        // Colors of an earlier palette can be referenced by later files
+        std::fs::write(
+            &base,
+            "[palette]\nprimary = \"#00529B\"\nink = \"#111111\"\n",
+        )
+        .unwrap();
+        std::fs::write(
+            &overrides,
+            "[palette]\nink = \"#222222\"\n\n[link]\ntextcolor = \"primary\"\n\n[text]\ntextcolor = \"ink\"\n",
+        )
+        .unwrap();
+        let (style, warnings) = load_config_from_source_reported(ConfigSource::Merged(vec![
+            base.to_str().unwrap(),
+            overrides.to_str().unwrap(),
+        ]));
+        assert_eq!(style.link.text_color, Some((0x00, 0x52, 0x9b)));
+        // A later palette redefines a name for the files from there on
+        assert_eq!(style.text.text_color, Some((0x22, 0x22, 0x22)));
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_default_config_toml_covers_every_field() {
+        let s = default_config_toml();
+        assert_eq!(parse_config_string(&s), StyleMatch::default());
+
+        // Every section and key is spelled out, not just left to the parser's defaults
+        let value: Value = toml::from_str(&s).unwrap();
+        for path in [
+            "margin.top",
+            "page.size",
+            "page.orientation",
+            "page.min_lines_top",
+            "page.min_lines_bottom",
+            "page.footer.show_date",
+            "page.footer.date_format",
+            "page.footer.page_numbers",
+            "page.footer.size",
+            "heading.1.page_break_before",
+            "heading.numbered",
+            "heading.number_from",
+            "heading.number_to",
+            "heading.2.letterspacing",
+            "heading.1.text_transform",
+            "heading.3.bold",
+            "emphasis.italic",
+            "strong_emphasis.bold",
+            "code.fontfamily",
+            "code.show_language_label",
+            "code.style",
+            "code.keep_together",
+            "code.inline_background",
+            "code.max_lines",
+            "code.fit",
+            "code.label.size",
+            "block_quote.size",
+            "list_item.marker_width",
+            "list_item.indent",
+            "list_item.max_nesting",
+            "link.underline",
+            "link.style",
+            "image.size",
+            "image.upscale",
+            "image.svg.width",
+            "image.svg.height",
+            "image.svg.scale_factor",
+            "image.gallery.columns",
+            "image.gallery.gutter",
+            "latex.size",
+            "horizontal_rule.size",
+            "text.soft_break",
+            "text.quote_style",
+            "text.strikethrough",
+            "table.header.size",
+            "table.cell.size",
+            "table.overflow",
+            "table.rotate_if_oversized",
+            "mermaid.auto_scale",
+            "mermaid.max_ratio",
+            "qr.width",
+            "abbreviations.mode",
+            "kbd.bordercolor",
+            "spoiler.label",
+            "mark.backgroundcolor",
+            "task_list.strikethrough_done",
+            "task_list.show_progress",
+            "document.reproducible",
+            "document.title_from_h1",
+            "document.attach_source",
+            "document.create_dirs",
+            "document.contact_sheet",
+            "index.enabled",
+            "index.title",
+            "validation.min_contrast",
+            "spacing.trim_edges",
+            "redact.patterns",
+            "redact.mask",
+        ] {
+            let found = path
+                .split('.')
+                .try_fold(&value, |value, key| value.get(key));
+            assert!(found.is_some(), "default configuration is missing {}", path);
+        }
+    }
 }
@@ -1,4 +1,4 @@
-use crate::markdown::Token;
+use crate::markdown::{CriticMarkup, Token};
 
 impl Token {
     /// Saves tokens to a JSON file for visualization.
@@ -282,6 +282,13 @@ impl Token {
                 )
             }
 
+            Token::SoftBreak => {
+                format!(
+                    "{}{{\n{}\"type\": \"SoftBreak\"\n{}}}",
+                    indent, inner_indent, indent
+                )
+            }
+
             Token::Newline => {
                 format!(
                     "{}{{\n{}\"type\": \"Newline\"\n{}}}",
@@ -318,6 +325,152 @@ impl Token {
                     indent
                 )
             }
+
+            Token::AbbreviationDefinition(abbr, expansion) => {
+                format!(
+                    "{}{{\n{}\"type\": \"AbbreviationDefinition\",\n{}\"abbreviation\": \"{}\",\n{}\"expansion\": \"{}\"\n{}}}",
+                    indent,
+                    inner_indent,
+                    inner_indent,
+                    abbr.replace("\"", "\\\""),
+                    inner_indent,
+                    expansion.replace("\"", "\\\""),
+                    indent
+                )
+            }
+
+            Token::Anchor(name) => {
+                format!(
+                    "{}{{\n{}\"type\": \"Anchor\",\n{}\"name\": \"{}\"\n{}}}",
+                    indent,
+                    inner_indent,
+                    inner_indent,
+                    name.replace("\"", "\\\""),
+                    indent
+                )
+            }
+
+            Token::FootnoteInline(content) => {
+                let mut result = format!("{}{{\n", indent);
+                result.push_str(&format!("{}\"type\": \"FootnoteInline\",\n", inner_indent));
+                result.push_str(&format!("{}\"content\": [\n", inner_indent));
+
+                for (i, token) in content.iter().enumerate() {
+                    result.push_str(&token.to_readable_json(indent_level + 2));
+                    if i < content.len() - 1 {
+                        result.push(',');
+                    }
+                    result.push('\n');
+                }
+
+                result.push_str(&format!("{}]\n", inner_indent));
+                result.push_str(&format!("{}}}", indent));
+                result
+            }
+
+            Token::Admonition { kind, content } => {
+                let mut result = format!("{}{{\n", indent);
+                result.push_str(&format!("{}\"type\": \"Admonition\",\n", inner_indent));
+                result.push_str(&format!("{}\"kind\": \"{}\",\n", inner_indent, kind));
+                result.push_str(&format!("{}\"content\": [\n", inner_indent));
+
+                for (i, token) in content.iter().enumerate() {
+                    result.push_str(&token.to_readable_json(indent_level + 2));
+                    if i < content.len() - 1 {
+                        result.push(',');
+                    }
+                    result.push('\n');
+                }
+
+                result.push_str(&format!("{}]\n", inner_indent));
+                result.push_str(&format!("{}}}", indent));
+                result
+            }
+
+            Token::TaskCheckbox(checked) => {
+                format!(
+                    "{}{{\n{}\"type\": \"TaskCheckbox\",\n{}\"checked\": {}\n{}}}",
+                    indent, inner_indent, inner_indent, checked, indent
+                )
+            }
+
+            Token::TabGroup(tabs) => {
+                let tab_indent = "  ".repeat(indent_level + 2);
+                let field_indent = "  ".repeat(indent_level + 3);
+                let mut result = format!("{}{{\n", indent);
+                result.push_str(&format!("{}\"type\": \"TabGroup\",\n", inner_indent));
+                result.push_str(&format!("{}\"tabs\": [\n", inner_indent));
+
+                for (i, (title, content)) in tabs.iter().enumerate() {
+                    result.push_str(&format!("{}{{\n", tab_indent));
+                    result.push_str(&format!(
+                        "{}\"title\": \"{}\",\n",
+                        field_indent,
+                        title.replace("\"", "\\\"")
+                    ));
+                    result.push_str(&format!("{}\"content\": [\n", field_indent));
+                    for (j, token) in content.iter().enumerate() {
+                        result.push_str(&token.to_readable_json(indent_level + 4));
+                        if j < content.len() - 1 {
+                            result.push(',');
+                        }
+                        result.push('\n');
+                    }
+                    result.push_str(&format!("{}]\n", field_indent));
+                    result.push_str(&format!("{}}}", tab_indent));
+                    if i < tabs.len() - 1 {
+                        result.push(',');
+                    }
+                    result.push('\n');
+                }
+
+                result.push_str(&format!("{}]\n", inner_indent));
+                result.push_str(&format!("{}}}", indent));
+                result
+            }
+
+            Token::Kbd(key) => {
+                format!(
+                    "{}{{\n{}\"type\": \"Kbd\",\n{}\"key\": \"{}\"\n{}}}",
+                    indent,
+                    inner_indent,
+                    inner_indent,
+                    key.replace("\"", "\\\""),
+                    indent
+                )
+            }
+
+            Token::Mark(content)
+            | Token::Ins(content)
+            | Token::Del(content)
+            | Token::Spoiler(content)
+            | Token::Critic(_, content) => {
+                let kind = match self {
+                    Token::Mark(_) => "Mark",
+                    Token::Ins(_) => "Ins",
+                    Token::Spoiler(_) => "Spoiler",
+                    Token::Critic(CriticMarkup::Addition, _) => "CriticAddition",
+                    Token::Critic(CriticMarkup::Deletion, _) => "CriticDeletion",
+                    Token::Critic(CriticMarkup::Highlight, _) => "CriticHighlight",
+                    Token::Critic(CriticMarkup::Substitution, _) => "CriticSubstitution",
+                    _ => "Del",
+                };
+                let mut result = format!("{}{{\n", indent);
+                result.push_str(&format!("{}\"type\": \"{}\",\n", inner_indent, kind));
+                result.push_str(&format!("{}\"content\": [\n", inner_indent));
+
+                for (i, token) in content.iter().enumerate() {
+                    result.push_str(&token.to_readable_json(indent_level + 2));
+                    if i < content.len() - 1 {
+                        result.push(',');
+                    }
+                    result.push('\n');
+                }
+
+                result.push_str(&format!("{}]\n", inner_indent));
+                result.push_str(&format!("{}}}", indent));
+                result
+            }
         }
     }
 
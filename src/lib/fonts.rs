@@ -1,7 +1,7 @@
 use log::{debug, error, info, warn};
 use std::fs;
 use std::panic;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use fontdb::Database;
@@ -242,6 +242,67 @@ impl Default for FontConfig {
     }
 }
 
+impl FontConfig {
+    /// Builds a font configuration from environment variables.
+    ///
+    /// This lets deployments (e.g. containers with fonts at a known path) select fonts
+    /// without code changes. The following variables are read:
+    /// * `MD2PDF_DEFAULT_FONT` - default text font
+    /// * `MD2PDF_CODE_FONT` - code font
+    /// * `MD2PDF_FONT_PATH` - font directories or files, colon-separated (semicolon on Windows)
+    /// * `MD2PDF_FALLBACK_FONTS` - comma-separated fallback font names
+    ///
+    /// Unset or empty variables leave the corresponding default untouched. Values set
+    /// explicitly afterwards (for instance from CLI flags) take precedence over the
+    /// environment.
+    ///
+    /// # Example
+    /// ```rust
+    /// use markdown2pdf::fonts::FontConfig;
+    ///
+    /// let mut config = FontConfig::from_env();
+    /// // Explicit settings override the environment
+    /// config.code_font = Some("DejaVu Sans Mono".to_string());
+    /// ```
+    pub fn from_env() -> Self {
+        Self::from_lookup(|key| std::env::var(key).ok())
+    }
+
+    /// Returns true if any font selection differs from the defaults.
+    pub fn has_overrides(&self) -> bool {
+        !self.custom_paths.is_empty()
+            || self.default_font.is_some()
+            || self.code_font.is_some()
+            || !self.fallback_fonts.is_empty()
+    }
+
+    /// Builds a font configuration from an arbitrary variable lookup.
+    fn from_lookup<F>(lookup: F) -> Self
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let non_empty = |key: &str| lookup(key).filter(|v| !v.trim().is_empty());
+
+        let mut config = Self::default();
+        config.default_font = non_empty("MD2PDF_DEFAULT_FONT").map(|v| v.trim().to_string());
+        config.code_font = non_empty("MD2PDF_CODE_FONT").map(|v| v.trim().to_string());
+        if let Some(paths) = non_empty("MD2PDF_FONT_PATH") {
+            config.custom_paths = std::env::split_paths(&paths)
+                .filter(|p| !p.as_os_str().is_empty())
+                .collect();
+        }
+        if let Some(fonts) = non_empty("MD2PDF_FALLBACK_FONTS") {
+            config.fallback_fonts = fonts
+                .split(',')
+                .map(|f| f.trim())
+                .filter(|f| !f.is_empty())
+                .map(|f| f.to_string())
+                .collect();
+        }
+        config
+    }
+}
+
 /// Attempts to load a built-in PDF font family using only the PDF built-in fonts
 /// without any system font dependencies. This ensures consistent character spacing
 /// across all platforms and avoids kerning issues.
@@ -519,6 +580,44 @@ pub fn load_system_font_family_simple(name: &str) -> Result<FontFamily<FontData>
     ))
 }
 
+/// Loads a font family from a single font file, bypassing name-based discovery, as set
+/// by `[text] fontfile`. The file provides all four variants; when `text` is given, the
+/// font is subset to its characters.
+///
+/// # Returns
+/// * `Ok(FontFamily<FontData>)` if the file holds a usable font
+/// * `Err(Error)` if the file cannot be read or is not a valid font
+pub fn load_font_file(path: &Path, text: Option<&str>) -> Result<FontFamily<FontData>, Error> {
+    let bytes = fs::read(path).map_err(|e| {
+        Error::new(
+            format!("Could not read font file {}: {}", path.display(), e),
+            ErrorKind::InvalidFont,
+        )
+    })?;
+    let is_valid =
+        panic::catch_unwind(|| Font::try_from_vec(bytes.clone()).is_some()).unwrap_or(false);
+    if !is_valid {
+        return Err(Error::new(
+            format!(
+                "{} is not a valid TrueType or OpenType font",
+                path.display()
+            ),
+            ErrorKind::InvalidFont,
+        ));
+    }
+
+    let shared = Arc::new(bytes);
+    let mk = || FontData::new_shared(shared.clone(), None);
+    let family = FontFamily {
+        regular: mk()?,
+        bold: mk()?,
+        italic: mk()?,
+        bold_italic: mk()?,
+    };
+    eprintln!("✓ Loaded font file {}", path.display());
+    apply_subsetting_if_enabled(family, text.is_some(), text)
+}
+
 /// Attempts to load a font family from custom paths first, then falls back to system fonts.
 /// This function searches user-specified directories or files before looking in system fonts.
 ///
@@ -1014,6 +1113,91 @@ fn subset_single_font(font: &FontData, text: &str) -> Result<FontData, Error> {
     FontData::new_shared(Arc::new(subset_data), None)
 }
 
+/// Size savings of subsetting one font for a document, as reported by [`subset_report`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubsetInfo {
+    /// Font name as configured
+    pub font: String,
+    /// Size of the full font in bytes
+    pub original_size: usize,
+    /// Size of the font subset to the characters of the document in bytes
+    pub subset_size: usize,
+    /// Share of the original size saved by subsetting, in percent
+    pub reduction: f64,
+}
+
+impl SubsetInfo {
+    /// Creates the report of a font, computing the reduction from the two sizes.
+    pub fn new(font: &str, original_size: usize, subset_size: usize) -> Self {
+        let reduction = if original_size == 0 {
+            0.0
+        } else {
+            (original_size as f64 - subset_size as f64) / original_size as f64 * 100.0
+        };
+        Self {
+            font: font.to_string(),
+            original_size,
+            subset_size,
+            reduction,
+        }
+    }
+}
+
+/// Reports how much subsetting shrinks each font of `config` for `markdown`, without
+/// generating the PDF.
+///
+/// The default font, the code font and the fallback fonts are loaded from the custom
+/// paths or the system and subset to the characters of the document, whether or not
+/// `enable_subsetting` is set, so the report shows what the option saves. Fonts embedded
+/// in the binary and fonts that cannot be loaded or subset are left out.
+///
+/// # Example
+/// ```rust,no_run
+/// use markdown2pdf::fonts::{subset_report, FontConfig};
+///
+/// let config = FontConfig {
+///     default_font: Some("Noto Sans".to_string()),
+///     ..FontConfig::default()
+/// };
+/// for info in subset_report("# Report\n\nHello мир!", &config) {
+///     println!("{}: {:.1}% smaller", info.font, info.reduction);
+/// }
+/// ```
+pub fn subset_report(markdown: &str, config: &FontConfig) -> Vec<SubsetInfo> {
+    let text = crate::markdown::Lexer::new(markdown.to_string())
+        .parse()
+        .map(|tokens| crate::markdown::Token::collect_all_text(&tokens))
+        .unwrap_or_else(|_| markdown.to_string());
+    if text.is_empty() {
+        return Vec::new();
+    }
+
+    let mut names: Vec<&str> = Vec::new();
+    for name in config
+        .default_font
+        .iter()
+        .chain(config.code_font.iter())
+        .chain(config.fallback_fonts.iter())
+    {
+        if !names.contains(&name.as_str()) {
+            names.push(name);
+        }
+    }
+
+    names
+        .into_iter()
+        .filter(|name| find_embedded_family_and_name(name).is_none())
+        .filter_map(|name| {
+            let family = load_custom_font_family(name, &config.custom_paths).ok()?;
+            let original = family.regular.get_data().ok()?;
+            let subset = genpdfi_extended::subsetting::subset_font(original, &text)
+                .map_err(|e| debug!("Could not subset font '{}': {}", name, e))
+                .ok()?;
+            Some(SubsetInfo::new(name, original.len(), subset.len()))
+        })
+        .collect()
+}
+
 /// Formats a byte size in a human-readable format.
 fn format_size(bytes: usize) -> String {
     if bytes < 1024 {
@@ -1185,6 +1369,40 @@ pub fn missing_glyphs_for_family(
     Ok(missing)
 }
 
+/// Measures text set in the regular variant of a font family, for layout decisions that
+/// must be made before the document is rendered.
+pub struct TextMeasure {
+    font: Option<Font<'static>>,
+}
+
+impl TextMeasure {
+    /// Prepares measurements for `family`. Fonts without accessible data (such as the
+    /// built-in PDF fonts) fall back to an average glyph width estimate.
+    pub fn new(family: &FontFamily<FontData>) -> Self {
+        let font = family
+            .regular
+            .get_data()
+            .ok()
+            .and_then(|data| Font::try_from_vec(data.to_vec()));
+        Self { font }
+    }
+
+    /// Returns the width of `text` at `font_size` points, in millimeters.
+    pub fn width(&self, text: &str, font_size: f32) -> f32 {
+        const MM_PER_PT: f32 = 25.4 / 72.0;
+        let points = match &self.font {
+            Some(font) => {
+                let scale = rusttype::Scale::uniform(font_size);
+                text.chars()
+                    .map(|c| font.glyph(c).scaled(scale).h_metrics().advance_width)
+                    .sum()
+            }
+            None => text.chars().count() as f32 * font_size * 0.5,
+        };
+        points * MM_PER_PT
+    }
+}
+
 /// Best-effort report: for a set of fonts (from config or defaults) returns a vector of (font_name, missing_chars)
 pub fn report_missing_glyphs(
     text: &str,
@@ -1258,6 +1476,30 @@ pub fn extract_primary_fonts(
     }
 }
 
+/// Returns the fallback fonts of a font fallback chain family as font families, in chain
+/// order. Styles with fewer fallbacks than the regular style reuse the regular fallback.
+pub fn extract_fallback_fonts(
+    chain_family: &FontFamily<genpdfi_extended::fonts::FontFallbackChain>,
+) -> Vec<FontFamily<FontData>> {
+    chain_family
+        .regular
+        .fallbacks()
+        .iter()
+        .enumerate()
+        .map(|(idx, regular)| {
+            let variant = |chain: &genpdfi_extended::fonts::FontFallbackChain| {
+                chain.fallbacks().get(idx).unwrap_or(regular).clone()
+            };
+            FontFamily {
+                regular: regular.clone(),
+                bold: variant(&chain_family.bold),
+                italic: variant(&chain_family.italic),
+                bold_italic: variant(&chain_family.bold_italic),
+            }
+        })
+        .collect()
+}
+
 /// Returns a list of sensible default fallback fonts for the given primary font.
 ///
 /// These fallbacks are tried in order when characters are missing from the primary font:
@@ -1591,6 +1833,18 @@ mod fonts_integration_tests {
         assert!(data.len() > 0);
     }
 
+    #[test]
+    fn test_text_measure_width() {
+        let family = load_custom_font_family("DejaVuSans", &[fonts_dir()]).unwrap();
+        let measure = TextMeasure::new(&family);
+        let short = measure.width("iii", 10.0);
+        let long = measure.width("WWW", 10.0);
+        assert!(short > 0.0 && short < long);
+        // Width scales with the font size
+        assert!((measure.width("WWW", 20.0) - 2.0 * long).abs() < 0.01);
+        assert_eq!(measure.width("", 10.0), 0.0);
+    }
+
     #[test]
     fn test_find_font_variant_in_paths_finds_variants() {
         let fonts = fonts_dir();
@@ -1704,6 +1958,67 @@ mod fonts_integration_tests {
         );
     }
 
+    #[test]
+    fn test_extract_fallback_fonts() {
+        let fonts = fonts_dir();
+        let chain = load_font_with_fallback_chain(
+            "Noto Sans",
+            &vec!["CMU Typewriter Text".to_string()],
+            &[fonts],
+            None,
+        )
+        .unwrap();
+
+        let fallbacks = extract_fallback_fonts(&chain);
+        assert_eq!(fallbacks.len(), chain.regular.fallbacks().len());
+        if let Some(first) = fallbacks.first() {
+            assert_eq!(
+                first.regular.get_data().unwrap().len(),
+                chain.regular.fallbacks()[0].get_data().unwrap().len()
+            );
+        }
+    }
+
+    #[test]
+    fn test_subset_report() {
+        let info = SubsetInfo::new("Font", 1000, 250);
+        assert_eq!(info.reduction, 75.0);
+        assert_eq!(SubsetInfo::new("Empty", 0, 0).reduction, 0.0);
+
+        // Embedded fonts are never subset, so they are not reported
+        let mut cfg = FontConfig::default();
+        cfg.default_font = Some("Helvetica".to_string());
+        cfg.code_font = Some("Courier".to_string());
+        assert!(subset_report("Hello world", &cfg).is_empty());
+        assert!(subset_report("", &FontConfig::default()).is_empty());
+    }
+
+    #[test]
+    fn test_font_config_from_lookup() {
+        let vars: std::collections::HashMap<&str, &str> = [
+            ("MD2PDF_DEFAULT_FONT", "Noto Sans"),
+            ("MD2PDF_CODE_FONT", " "),
+            ("MD2PDF_FALLBACK_FONTS", "DejaVu Sans, Noto Color Emoji,"),
+        ]
+        .into_iter()
+        .collect();
+
+        let cfg = FontConfig::from_lookup(|k| vars.get(k).map(|v| v.to_string()));
+        assert_eq!(cfg.default_font.as_deref(), Some("Noto Sans"));
+        // Blank values are ignored
+        assert!(cfg.code_font.is_none());
+        assert!(cfg.custom_paths.is_empty());
+        assert_eq!(
+            cfg.fallback_fonts,
+            vec!["DejaVu Sans".to_string(), "Noto Color Emoji".to_string()]
+        );
+        assert!(cfg.enable_subsetting);
+        assert!(cfg.has_overrides());
+
+        let empty = FontConfig::from_lookup(|_| None);
+        assert!(!empty.has_overrides());
+    }
+
     #[test]
     fn test_load_font_with_config_with_fallbacks() {
         let fonts = fonts_dir();
@@ -2,9 +2,11 @@ use lazy_static::lazy_static;
 /// Syntax highlighting module using syntect
 /// Provides colored code blocks similar to GitHub's rendering
 use std::collections::HashMap;
+use std::path::Path;
+use std::sync::RwLock;
 use syntect::easy::HighlightLines;
 use syntect::highlighting::Color;
-use syntect::parsing::{SyntaxDefinition, SyntaxSet, SyntaxSetBuilder};
+use syntect::parsing::{SyntaxDefinition, SyntaxReference, SyntaxSet, SyntaxSetBuilder};
 use log::{debug, info, warn, error};
 
 /// A color representation suitable for PDF rendering using RGB values.
@@ -171,6 +173,63 @@ lazy_static! {
     static ref SYNTAX_SET: SyntaxSet = SyntaxSet::load_defaults_newlines();
     static ref THEME_SET: syntect::highlighting::ThemeSet =
         syntect::highlighting::ThemeSet::load_defaults();
+    // Syntaxes registered at runtime with add_syntax_from_dir, looked up first
+    static ref RUNTIME_SYNTAX_SET: RwLock<Option<&'static SyntaxSet>> = RwLock::new(None);
+}
+
+/// Loads every `.sublime-syntax` file in `dir` and its subdirectories and registers the
+/// syntaxes for highlighting. Fence languages then match the new syntaxes by name or file
+/// extension ahead of the embedded ones, except for built-in aliases such as `rs` or `sh`,
+/// and [`supported_languages`] lists them.
+///
+/// Syntaxes from earlier calls stay registered. Each call builds a new syntax set that
+/// lives for the rest of the program, so this is meant to be called once at startup.
+///
+/// # Returns
+///
+/// * `Ok(usize)` with the number of syntaxes loaded from `dir`
+/// * `Err(String)` if the directory cannot be read or a syntax file is invalid
+pub fn add_syntax_from_dir<P: AsRef<Path>>(dir: P) -> Result<usize, String> {
+    let dir = dir.as_ref();
+    let mut builder = match runtime_syntax_set() {
+        Some(set) => set.clone().into_builder(),
+        None => SyntaxSetBuilder::new(),
+    };
+    let before = builder.syntaxes().len();
+    builder
+        .add_from_folder(dir, true)
+        .map_err(|e| format!("Failed to load syntaxes from {}: {}", dir.display(), e))?;
+    let added = builder.syntaxes().len() - before;
+
+    let set: &'static SyntaxSet = Box::leak(Box::new(builder.build()));
+    *RUNTIME_SYNTAX_SET
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(set);
+    info!("Loaded {} syntaxes from {}", added, dir.display());
+    Ok(added)
+}
+
+/// The syntaxes registered with [`add_syntax_from_dir`], if any
+fn runtime_syntax_set() -> Option<&'static SyntaxSet> {
+    *RUNTIME_SYNTAX_SET
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// The syntax sets in lookup order: runtime, custom and default syntaxes
+fn syntax_sets() -> Vec<&'static SyntaxSet> {
+    runtime_syntax_set()
+        .into_iter()
+        .chain([&*CUSTOM_SYNTAX_SET, &*SYNTAX_SET])
+        .collect()
+}
+
+/// Returns the syntax set `syntax` belongs to, which highlighting must use
+fn syntax_set_of(syntax: &SyntaxReference) -> &'static SyntaxSet {
+    syntax_sets()
+        .into_iter()
+        .find(|set| set.syntaxes().iter().any(|s| std::ptr::eq(s, syntax)))
+        .unwrap_or(&*SYNTAX_SET)
 }
 
 /// Load custom syntaxes from embedded .sublime-syntax files using SyntaxSetBuilder
@@ -257,6 +316,77 @@ fn get_syntax_mapping() -> HashMap<&'static str, &'static str> {
     map
 }
 
+/// Returns every language identifier that selects a syntax for highlighting.
+///
+/// The list contains the aliases of the built-in mapping (such as `rs` or `sh`) plus the
+/// lowercased names and file extensions of all syntaxes in the custom and default syntax
+/// sets. Identifiers are sorted and unique. Any other fence language falls back to plain text.
+///
+/// # Examples
+///
+/// ```
+/// use markdown2pdf::highlighting::supported_languages;
+///
+/// let languages = supported_languages();
+/// assert!(languages.iter().any(|l| l == "rust"));
+/// assert!(languages.iter().any(|l| l == "tsx"));
+/// ```
+pub fn supported_languages() -> Vec<String> {
+    let mut languages: Vec<String> = get_syntax_mapping()
+        .into_iter()
+        .filter(|(_, name)| find_syntax_by_name(name).is_some())
+        .map(|(alias, _)| alias.to_string())
+        .collect();
+
+    for syntax in syntax_sets().into_iter().flat_map(|set| set.syntaxes()) {
+        languages.push(syntax.name.to_lowercase());
+        languages.extend(syntax.file_extensions.iter().map(|ext| ext.to_lowercase()));
+    }
+
+    languages.sort();
+    languages.dedup();
+    languages
+}
+
+/// Returns whether `language` selects a syntax, i.e. whether a fence with this info string
+/// is highlighted rather than rendered as plain text. Matches the identifiers listed by
+/// [`supported_languages`], ignoring case.
+///
+/// # Examples
+///
+/// ```
+/// use markdown2pdf::highlighting::is_supported_language;
+///
+/// assert!(is_supported_language("JSON"));
+/// assert!(!is_supported_language("jspn"));
+/// ```
+pub fn is_supported_language(language: &str) -> bool {
+    let language = language.trim();
+    if language.is_empty() {
+        return false;
+    }
+    get_syntax_mapping()
+        .get(language.to_lowercase().as_str())
+        .map_or(false, |name| find_syntax_by_name(name).is_some())
+        || SYNTAX_SET.find_syntax_by_name(language).is_some()
+        || find_syntax_by_token(language).is_some()
+}
+
+/// Looks up a syntax by its exact name in the runtime, custom, then default syntax set
+fn find_syntax_by_name(name: &str) -> Option<&'static SyntaxReference> {
+    syntax_sets()
+        .into_iter()
+        .find_map(|set| set.find_syntax_by_name(name))
+}
+
+/// Looks up a syntax by file extension or case-insensitive name in the runtime, custom,
+/// then default syntax set
+fn find_syntax_by_token(token: &str) -> Option<&'static SyntaxReference> {
+    syntax_sets()
+        .into_iter()
+        .find_map(|set| set.find_syntax_by_token(token))
+}
+
 /// Highlights code using syntax highlighting rules based on the specified language.
 ///
 /// This function applies syntax highlighting to source code, breaking it into
@@ -324,6 +454,54 @@ pub fn highlight_code(code: &str, language: &str) -> Vec<HighlightedToken> {
     highlight_code_with_syntect(code, language)
 }
 
+/// Highlights code and renders it as HTML, one `<span>` per token.
+///
+/// The tokens are the ones [`highlight_code`] produces for the PDF output, each
+/// wrapped in a span with an inline `color` (and `font-weight` or `font-style` when the
+/// theme sets them). Text is HTML-escaped and line breaks are kept as `\n`, so the result
+/// belongs in a `<pre>` element.
+///
+/// # Examples
+///
+/// ```
+/// use markdown2pdf::highlighting::highlight_to_html;
+///
+/// let html = highlight_to_html("let x = 1 < 2;", "rust");
+/// assert!(html.starts_with("<span style=\"color:#"));
+/// assert!(html.contains("&lt;"));
+/// ```
+pub fn highlight_to_html(code: &str, language: &str) -> String {
+    let mut html = String::new();
+    for token in highlight_code(code, language) {
+        if token.text == "\n" {
+            html.push('\n');
+            continue;
+        }
+
+        let (r, g, b) = token.color.as_rgb_u8();
+        html.push_str(&format!("<span style=\"color:#{:02x}{:02x}{:02x}", r, g, b));
+        if token.bold {
+            html.push_str(";font-weight:bold");
+        }
+        if token.italic {
+            html.push_str(";font-style:italic");
+        }
+        html.push_str("\">");
+        for c in token.text.chars() {
+            match c {
+                '&' => html.push_str("&amp;"),
+                '<' => html.push_str("&lt;"),
+                '>' => html.push_str("&gt;"),
+                '"' => html.push_str("&quot;"),
+                '\'' => html.push_str("&#39;"),
+                _ => html.push(c),
+            }
+        }
+        html.push_str("</span>");
+    }
+    html
+}
+
 /// Core syntax highlighting using syntect
 fn highlight_code_with_syntect(code: &str, language: &str) -> Vec<HighlightedToken> {
     let language_lower = language.to_lowercase();
@@ -333,8 +511,14 @@ fn highlight_code_with_syntect(code: &str, language: &str) -> Vec<HighlightedTok
         .get(language_lower.as_str())
         .copied()
         .unwrap_or_else(|| {
-            if SYNTAX_SET.find_syntax_by_name(&language).is_some() {
+            if let Some(syntax) =
+                runtime_syntax_set().and_then(|set| set.find_syntax_by_token(language))
+            {
+                syntax.name.as_str()
+            } else if SYNTAX_SET.find_syntax_by_name(&language).is_some() {
                 language
+            } else if let Some(syntax) = find_syntax_by_token(language) {
+                syntax.name.as_str()
             } else if SYNTAX_SET.find_syntax_by_first_line(code).is_some() {
                 return "";
             } else {
@@ -348,9 +532,7 @@ fn highlight_code_with_syntect(code: &str, language: &str) -> Vec<HighlightedTok
             .or_else(|| SYNTAX_SET.find_syntax_by_first_line(code))
             .unwrap_or_else(|| SYNTAX_SET.find_syntax_plain_text())
     } else {
-        let found = CUSTOM_SYNTAX_SET
-            .find_syntax_by_name(syntax_name)
-            .or_else(|| SYNTAX_SET.find_syntax_by_name(syntax_name));
+        let found = find_syntax_by_name(syntax_name);
         if found.is_none() {
             eprintln!(
                 "Warning: Could not find syntax '{}' for language '{}', using plain text",
@@ -368,14 +550,13 @@ fn highlight_code_with_syntect(code: &str, language: &str) -> Vec<HighlightedTok
         .or_else(|| THEME_SET.themes.values().next())
         .expect("No themes available");
 
+    let syntax_set = syntax_set_of(syntax);
     let mut highlighter = HighlightLines::new(syntax, theme);
     let mut tokens = Vec::new();
 
     for line in code.lines() {
-        // Try custom syntax set first, then default
         let ranges = highlighter
-            .highlight_line(line, &CUSTOM_SYNTAX_SET)
-            .or_else(|_| highlighter.highlight_line(line, &SYNTAX_SET))
+            .highlight_line(line, syntax_set)
             .unwrap_or_default();
 
         for (style, text) in ranges {
@@ -926,6 +1107,39 @@ function TemperatureCard() {
         assert_eq!(mapping.get("powershell"), Some(&"PowerShell")); // PowerShell has custom grammar
     }
 
+    #[test]
+    fn test_supported_languages() {
+        let languages = supported_languages();
+        // Mapping aliases, custom grammars and default syntaxes are all listed
+        for expected in ["rs", "sh", "typescript", "powershell", "python", "java"] {
+            assert!(
+                languages.iter().any(|l| l == expected),
+                "missing {}",
+                expected
+            );
+        }
+        assert!(languages.windows(2).all(|w| w[0] < w[1]));
+        // Every listed identifier selects a real syntax
+        assert!(languages.iter().all(|l| get_syntax_mapping()
+            .get(l.as_str())
+            .map_or(false, |name| find_syntax_by_name(name).is_some())
+            || find_syntax_by_token(l).is_some()));
+        assert!(languages.iter().all(|l| *l == l.to_lowercase()));
+    }
+
+    #[test]
+    fn test_is_supported_language() {
+        assert!(is_supported_language("rust"));
+        assert!(is_supported_language("Rust"));
+        assert!(is_supported_language("tsx"));
+        assert!(is_supported_language("json"));
+        assert!(!is_supported_language("jspn"));
+        assert!(!is_supported_language(""));
+        for language in supported_languages() {
+            assert!(is_supported_language(&language), "{}", language);
+        }
+    }
+
     #[test]
     fn test_highlight_preserves_whitespace() {
         let code = "  let x = 42;";
@@ -966,6 +1180,72 @@ World
         );
     }
 
+    #[test]
+    fn test_highlight_font_styles_from_theme() {
+        // InspiredGitHub sets comments in italic and keywords in bold
+        let tokens = highlight_code("// entry point\nfn main() { let x = 1; return x; }", "rust");
+        assert!(tokens
+            .iter()
+            .any(|t| t.text.contains("entry point") && t.italic));
+        assert!(tokens
+            .iter()
+            .any(|t| ["fn", "let", "return"].contains(&t.text.as_str()) && t.bold));
+        // Plain punctuation carries no emphasis
+        assert!(tokens
+            .iter()
+            .any(|t| t.text.contains(';') && !t.bold && !t.italic));
+    }
+
+    #[test]
+    fn test_add_syntax_from_dir() {
+        let dir = std::env::temp_dir().join(format!("md2pdf-syntaxes-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("Frob.sublime-syntax"),
+            "%YAML 1.2\n---\nname: Frob\nfile_extensions: [frob]\nscope: source.frob\ncontexts:\n  main:\n    - match: \\bfrobnicate\\b\n      scope: keyword.control.frob\n",
+        )
+        .unwrap();
+
+        assert!(!is_supported_language("frob"));
+        assert_eq!(add_syntax_from_dir(&dir), Ok(1));
+        assert!(is_supported_language("frob"));
+        assert!(supported_languages().iter().any(|l| l == "frob"));
+
+        let tokens = highlight_code("frobnicate now", "frob");
+        let keyword = tokens.iter().find(|t| t.text == "frobnicate").unwrap();
+        let plain = tokens.iter().find(|t| t.text.contains("now")).unwrap();
+        assert_ne!(keyword.color.as_rgb_u8(), plain.color.as_rgb_u8());
+
+        assert!(add_syntax_from_dir(dir.join("missing")).is_err());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_highlight_to_html() {
+        let html = highlight_to_html("// a < b\nfn main() {}", "rust");
+        let lines: Vec<&str> = html.lines().collect();
+        assert_eq!(lines.len(), 2);
+        // Comments are italic in the theme, keywords bold
+        assert!(lines[0].contains("font-style:italic"));
+        assert!(lines[0].contains("a &lt; b"));
+        assert!(lines[1].contains(";font-weight:bold\">"));
+
+        // Stripping the markup gives back the code
+        let mut text = String::new();
+        let mut in_tag = false;
+        for c in html.chars() {
+            match c {
+                '<' => in_tag = true,
+                '>' => in_tag = false,
+                _ if !in_tag => text.push(c),
+                _ => {}
+            }
+        }
+        assert_eq!(text.replace("&lt;", "<"), "// a < b\nfn main() {}");
+
+        assert!(highlight_to_html("\"'&", "text").contains(">&quot;&#39;&amp;</span>"));
+    }
+
     #[test]
     fn test_highlight_text_plain() {
         let tokens = highlight_code(TEXT_CODE, "text");
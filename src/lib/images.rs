@@ -69,6 +69,36 @@ impl ImageFormat {
         }
     }
 
+    /// Detect image format from the leading bytes of the image data.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use markdown2pdf::images::ImageFormat;
+    /// assert_eq!(ImageFormat::from_bytes(b"\x89PNG\r\n\x1a\n"), Some(ImageFormat::Png));
+    /// assert_eq!(ImageFormat::from_bytes(b"<svg xmlns=\"http://www.w3.org/2000/svg\"/>"), Some(ImageFormat::Svg));
+    /// assert_eq!(ImageFormat::from_bytes(b"II*\0"), None);
+    /// ```
+    pub fn from_bytes(bytes: &[u8]) -> Option<ImageFormat> {
+        if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            Some(ImageFormat::Jpeg)
+        } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(ImageFormat::Png)
+        } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+            Some(ImageFormat::Gif)
+        } else if bytes.len() >= 12 && bytes.starts_with(b"RIFF") && &bytes[8..12] == b"WEBP" {
+            Some(ImageFormat::WebP)
+        } else {
+            let head = String::from_utf8_lossy(&bytes[..bytes.len().min(512)]);
+            let head = head.trim_start_matches('\u{feff}').trim_start();
+            if head.starts_with("<svg") || (head.starts_with("<?xml") && head.contains("<svg")) {
+                Some(ImageFormat::Svg)
+            } else {
+                None
+            }
+        }
+    }
+
     /// Get the MIME type for this image format.
     ///
     /// # Example
@@ -100,6 +130,10 @@ pub enum ImageError {
     PathResolutionError(String),
     /// Unsupported image format
     UnsupportedFormat(String),
+    /// Remote image is larger than the configured download limit
+    TooLarge(String),
+    /// Image data could not be decoded, including decoder panics on malformed files
+    DecodeError(String),
 }
 
 impl std::fmt::Display for ImageError {
@@ -109,6 +143,8 @@ impl std::fmt::Display for ImageError {
             ImageError::DownloadError(e) => write!(f, "Failed to download image: {}", e),
             ImageError::PathResolutionError(e) => write!(f, "Failed to resolve path: {}", e),
             ImageError::UnsupportedFormat(e) => write!(f, "Unsupported image format: {}", e),
+            ImageError::TooLarge(e) => write!(f, "Image too large: {}", e),
+            ImageError::DecodeError(e) => write!(f, "Failed to decode image: {}", e),
         }
     }
 }
@@ -126,6 +162,145 @@ pub struct ImageData {
     pub source: String,
 }
 
+/// Resolution in dots per inch assumed for raster images that do not record their own.
+pub const DEFAULT_DPI: f32 = 96.0;
+
+impl ImageData {
+    /// Returns the width and height in pixels of a raster image, read from its header.
+    ///
+    /// Returns `None` for SVG images and for headers that cannot be read.
+    pub fn pixel_size(&self) -> Option<(u32, u32)> {
+        let b = &self.bytes;
+        match self.format {
+            ImageFormat::Png => Some((be_u32(b, 16)?, be_u32(b, 20)?)),
+            ImageFormat::Gif => Some((le_u16(b, 6)? as u32, le_u16(b, 8)? as u32)),
+            ImageFormat::Jpeg => jpeg_size(b),
+            ImageFormat::WebP => webp_size(b),
+            ImageFormat::Svg => None,
+        }
+    }
+
+    /// Returns the resolution recorded in the image in dots per inch: the PNG `pHYs`
+    /// chunk or the JPEG JFIF density. Returns `None` when the image records none.
+    pub fn dpi(&self) -> Option<f32> {
+        let b = &self.bytes;
+        let dpi = match self.format {
+            ImageFormat::Png => {
+                let mut offset = 8;
+                loop {
+                    let length = be_u32(b, offset)? as usize;
+                    let kind = b.get(offset + 4..offset + 8)?;
+                    if kind == b"IDAT" {
+                        return None;
+                    }
+                    if kind == b"pHYs" {
+                        // Pixels per unit, unit 1 being the meter
+                        if *b.get(offset + 16)? != 1 {
+                            return None;
+                        }
+                        break be_u32(b, offset + 8)? as f32 * 0.0254;
+                    }
+                    // Length, type, data and CRC
+                    offset += 12 + length;
+                }
+            }
+            ImageFormat::Jpeg => {
+                if b.get(2..4)? != [0xFF, 0xE0] || b.get(6..11)? != b"JFIF\0" {
+                    return None;
+                }
+                let density = be_u16(b, 14)? as f32;
+                match *b.get(13)? {
+                    1 => density,
+                    2 => density * 2.54,
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        };
+        Some(dpi).filter(|dpi| *dpi > 0.0)
+    }
+
+    /// Returns the width and height in millimeters of the image at its natural size:
+    /// its pixel size at its recorded resolution, or at [`DEFAULT_DPI`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use markdown2pdf::images::{ImageData, ImageFormat};
+    ///
+    /// let mut bytes = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+    /// bytes.extend_from_slice(&96u32.to_be_bytes());
+    /// bytes.extend_from_slice(&48u32.to_be_bytes());
+    /// let image = ImageData { bytes, format: ImageFormat::Png, source: "icon.png".into() };
+    /// let (width, height) = image.natural_size_mm().unwrap();
+    /// assert!((width - 25.4).abs() < 0.01 && (height - 12.7).abs() < 0.01);
+    /// ```
+    pub fn natural_size_mm(&self) -> Option<(f32, f32)> {
+        let (width, height) = self.pixel_size()?;
+        let dpi = self.dpi().unwrap_or(DEFAULT_DPI);
+        Some((width as f32 * 25.4 / dpi, height as f32 * 25.4 / dpi))
+    }
+}
+
+fn be_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    let b = bytes.get(offset..offset + 2)?;
+    Some(u16::from_be_bytes([b[0], b[1]]))
+}
+
+fn be_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    let b = bytes.get(offset..offset + 4)?;
+    Some(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn le_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    let b = bytes.get(offset..offset + 2)?;
+    Some(u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn le_u24(bytes: &[u8], offset: usize) -> Option<u32> {
+    let b = bytes.get(offset..offset + 3)?;
+    Some((b[0] as u32) | ((b[1] as u32) << 8) | ((b[2] as u32) << 16))
+}
+
+/// Reads the frame size from the first start-of-frame segment of a JPEG image.
+fn jpeg_size(bytes: &[u8]) -> Option<(u32, u32)> {
+    let mut offset = 2;
+    loop {
+        if *bytes.get(offset)? != 0xFF {
+            return None;
+        }
+        let marker = *bytes.get(offset + 1)?;
+        match marker {
+            // Fill bytes before a marker
+            0xFF => offset += 1,
+            // SOF0 to SOF15, except DHT, JPG and DAC which share the range
+            0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => {
+                let height = be_u16(bytes, offset + 5)? as u32;
+                let width = be_u16(bytes, offset + 7)? as u32;
+                return Some((width, height));
+            }
+            _ => offset += 2 + be_u16(bytes, offset + 2)? as usize,
+        }
+    }
+}
+
+/// Reads the canvas size of a lossy (`VP8 `), lossless (`VP8L`) or extended (`VP8X`)
+/// WebP image.
+fn webp_size(bytes: &[u8]) -> Option<(u32, u32)> {
+    match bytes.get(12..16)? {
+        b"VP8 " => Some((
+            (le_u16(bytes, 26)? & 0x3FFF) as u32,
+            (le_u16(bytes, 28)? & 0x3FFF) as u32,
+        )),
+        b"VP8L" => {
+            let bits = le_u24(bytes, 21)? | ((*bytes.get(24)? as u32) << 24);
+            Some(((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1))
+        }
+        b"VP8X" => Some((le_u24(bytes, 24)? + 1, le_u24(bytes, 27)? + 1)),
+        _ => None,
+    }
+}
+
 /// Manages loading and caching of images for a document.
 ///
 /// Provides automatic path resolution for relative paths and handles both
@@ -137,6 +312,8 @@ pub struct ImageLoader {
     cache: HashMap<String, ImageData>,
     /// Whether to enable remote image downloading
     allow_remote: bool,
+    /// Maximum number of bytes accepted for a remote image (`None` = unlimited)
+    max_download_size: Option<u64>,
 }
 
 impl ImageLoader {
@@ -165,9 +342,27 @@ impl ImageLoader {
             base_dir,
             cache: HashMap::new(),
             allow_remote: true,
+            max_download_size: None,
         }
     }
 
+    /// Limit the size of remote image downloads.
+    ///
+    /// Downloads are streamed and aborted as soon as they exceed `limit` bytes, so a huge
+    /// or malicious remote image cannot exhaust memory. `None` removes the limit.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use markdown2pdf::images::ImageLoader;
+    /// let mut loader = ImageLoader::new(None);
+    /// loader.set_max_download_size(Some(5 * 1024 * 1024));
+    /// // Remote images larger than 5 MiB are rejected
+    /// ```
+    pub fn set_max_download_size(&mut self, limit: Option<u64>) {
+        self.max_download_size = limit;
+    }
+
     /// Enable or disable remote image downloading.
     ///
     /// # Example
@@ -256,9 +451,13 @@ impl ImageLoader {
 
         let resolved = self.resolve_path(url_or_path)?;
 
-        // Detect format
-        let format = ImageFormat::from_path(&resolved)
-            .ok_or_else(|| ImageError::UnsupportedFormat(resolved.clone()))?;
+        // Detect format from the extension; formats we know we cannot embed fail early
+        let format = ImageFormat::from_path(&resolved);
+        if format.is_none() {
+            if let Some(name) = unsupported_format_from_path(&resolved) {
+                return Err(unsupported_format(name, &resolved));
+            }
+        }
 
         // Load the image
         let data = if resolved.starts_with("http://") || resolved.starts_with("https://") {
@@ -272,6 +471,20 @@ impl ImageLoader {
             self.load_local(&resolved)?
         };
 
+        // Without a known extension (e.g. remote URLs with query strings) sniff the content
+        let format = match format.or_else(|| ImageFormat::from_bytes(&data)) {
+            Some(format) => format,
+            None => {
+                return Err(match unsupported_format_from_bytes(&data) {
+                    Some(name) => unsupported_format(name, &resolved),
+                    None => ImageError::UnsupportedFormat(format!(
+                        "{} is not a JPEG, PNG, GIF, WebP or SVG image",
+                        resolved
+                    )),
+                })
+            }
+        };
+
         let image_data = ImageData {
             bytes: data,
             format,
@@ -320,15 +533,38 @@ impl ImageLoader {
         }
         #[cfg(feature = "fetch")]
         {
+            use std::io::Read;
+
             let client = reqwest::blocking::Client::new();
             let response = client.get(url).send().map_err(|e| {
                 ImageError::DownloadError(format!("Failed to download {}: {}", url, e))
             })?;
 
+            let Some(limit) = self.max_download_size else {
+                return response.bytes().map(|b| b.to_vec()).map_err(|e| {
+                    ImageError::DownloadError(format!("Failed to read response: {}", e))
+                });
+            };
+
+            // Reject early when the server announces an oversized body
+            if let Some(length) = response.content_length() {
+                if length > limit {
+                    return Err(too_large(url, limit));
+                }
+            }
+
+            // The announced length may be missing or wrong, so also cap the stream itself
+            let mut bytes = Vec::new();
             response
-                .bytes()
-                .map(|b| b.to_vec())
-                .map_err(|e| ImageError::DownloadError(format!("Failed to read response: {}", e)))
+                .take(limit + 1)
+                .read_to_end(&mut bytes)
+                .map_err(|e| {
+                    ImageError::DownloadError(format!("Failed to read response: {}", e))
+                })?;
+            if bytes.len() as u64 > limit {
+                return Err(too_large(url, limit));
+            }
+            Ok(bytes)
         }
 
         #[cfg(not(feature = "fetch"))]
@@ -340,6 +576,60 @@ impl ImageLoader {
     }
 }
 
+/// Names image formats that cannot be embedded, based on the file extension.
+fn unsupported_format_from_path(path: &str) -> Option<&'static str> {
+    let lower = path.to_lowercase();
+    let extension = lower.rsplit('.').next()?;
+    match extension {
+        "tif" | "tiff" => Some("TIFF"),
+        "bmp" | "dib" => Some("BMP"),
+        "avif" => Some("AVIF"),
+        "heic" | "heif" => Some("HEIC"),
+        "ico" => Some("ICO"),
+        "jxl" => Some("JPEG XL"),
+        "psd" => Some("PSD"),
+        _ => None,
+    }
+}
+
+/// Names image formats that cannot be embedded, based on their magic bytes.
+fn unsupported_format_from_bytes(bytes: &[u8]) -> Option<&'static str> {
+    let brand = bytes.get(4..12);
+    if bytes.starts_with(b"II*\0") || bytes.starts_with(b"MM\0*") {
+        Some("TIFF")
+    } else if bytes.starts_with(b"BM") {
+        Some("BMP")
+    } else if brand == Some(b"ftypavif") || brand == Some(b"ftypavis") {
+        Some("AVIF")
+    } else if brand.map_or(false, |b| {
+        b.starts_with(b"ftyphei") || b.starts_with(b"ftypmif1")
+    }) {
+        Some("HEIC")
+    } else if bytes.starts_with(&[0, 0, 1, 0]) {
+        Some("ICO")
+    } else if bytes.starts_with(&[0xFF, 0x0A]) || bytes.starts_with(b"\0\0\0\x0cJXL ") {
+        Some("JPEG XL")
+    } else if bytes.starts_with(b"8BPS") {
+        Some("PSD")
+    } else {
+        None
+    }
+}
+
+/// Builds the error for an image in a format that cannot be embedded.
+fn unsupported_format(name: &str, path: &str) -> ImageError {
+    ImageError::UnsupportedFormat(format!(
+        "{} images are not supported ({}); convert it to PNG or JPEG",
+        name, path
+    ))
+}
+
+/// Builds the error returned when a download exceeds the configured limit
+#[cfg_attr(not(feature = "fetch"), allow(dead_code))]
+fn too_large(url: &str, limit: u64) -> ImageError {
+    ImageError::TooLarge(format!("{} exceeds the {} byte download limit", url, limit))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -415,4 +705,125 @@ mod tests {
         let result = loader.load("nonexistent.jpg");
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_image_format_from_bytes() {
+        assert_eq!(
+            ImageFormat::from_bytes(&[0xFF, 0xD8, 0xFF, 0xE0]),
+            Some(ImageFormat::Jpeg)
+        );
+        assert_eq!(
+            ImageFormat::from_bytes(b"GIF89a...."),
+            Some(ImageFormat::Gif)
+        );
+        assert_eq!(
+            ImageFormat::from_bytes(b"RIFF\0\0\0\0WEBPVP8 "),
+            Some(ImageFormat::WebP)
+        );
+        assert_eq!(
+            ImageFormat::from_bytes(b"<?xml version=\"1.0\"?>\n<svg></svg>"),
+            Some(ImageFormat::Svg)
+        );
+        assert_eq!(ImageFormat::from_bytes(b"BM\0\0"), None);
+        assert_eq!(ImageFormat::from_bytes(b""), None);
+    }
+
+    #[test]
+    fn test_unsupported_format_detection() {
+        assert_eq!(unsupported_format_from_path("scan.TIFF"), Some("TIFF"));
+        assert_eq!(unsupported_format_from_path("icon.bmp"), Some("BMP"));
+        assert_eq!(unsupported_format_from_path("photo.avif"), Some("AVIF"));
+        assert_eq!(unsupported_format_from_path("photo.png"), None);
+        assert_eq!(unsupported_format_from_path("README"), None);
+
+        assert_eq!(unsupported_format_from_bytes(b"II*\0rest"), Some("TIFF"));
+        assert_eq!(unsupported_format_from_bytes(b"MM\0*rest"), Some("TIFF"));
+        assert_eq!(
+            unsupported_format_from_bytes(b"\0\0\0\x1cftypavif"),
+            Some("AVIF")
+        );
+        assert_eq!(
+            unsupported_format_from_bytes(b"\0\0\0\x18ftypheic"),
+            Some("HEIC")
+        );
+        assert_eq!(unsupported_format_from_bytes(b"plain text"), None);
+    }
+
+    #[test]
+    fn test_load_reports_unsupported_formats() {
+        let mut loader = ImageLoader::new(None);
+        loader.allow_remote = false;
+
+        // Rejected by extension without touching the filesystem
+        let err = loader.load("missing-scan.tiff").unwrap_err();
+        assert!(matches!(err, ImageError::UnsupportedFormat(_)));
+        assert_eq!(
+            err.to_string(),
+            "Unsupported image format: TIFF images are not supported (missing-scan.tiff); convert it to PNG or JPEG"
+        );
+
+        // Files without an extension are identified by their content
+        let dir = tempfile::tempdir().unwrap();
+        let bmp = dir.path().join("diagram");
+        std::fs::write(&bmp, b"BM\x36\0\0\0").unwrap();
+        let err = loader.load(bmp.to_str().unwrap()).unwrap_err();
+        assert!(err.to_string().contains("BMP images are not supported"));
+
+        let png = dir.path().join("badge");
+        std::fs::write(&png, b"\x89PNG\r\n\x1a\n").unwrap();
+        assert_eq!(
+            loader.load(png.to_str().unwrap()).unwrap().format,
+            ImageFormat::Png
+        );
+    }
+
+    #[test]
+    fn test_natural_image_size() {
+        let image = |format, bytes: Vec<u8>| ImageData {
+            bytes,
+            format,
+            source: String::new(),
+        };
+
+        let png = std::fs::read("tests/images/triangle-161210_1280.png").unwrap();
+        let png = image(ImageFormat::Png, png);
+        assert_eq!(png.pixel_size().map(|(width, _)| width), Some(1280));
+
+        // JFIF header at 72 dpi followed by a baseline frame of 144 x 36 pixels
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10];
+        jpeg.extend_from_slice(b"JFIF\0\x01\x01\x01\x00\x48\x00\x48\x00\x00");
+        jpeg.extend_from_slice(&[0xFF, 0xC0, 0x00, 0x11, 0x08, 0x00, 0x24, 0x00, 0x90]);
+        let jpeg = image(ImageFormat::Jpeg, jpeg);
+        assert_eq!(jpeg.pixel_size(), Some((144, 36)));
+        assert_eq!(jpeg.dpi(), Some(72.0));
+        let (width, height) = jpeg.natural_size_mm().unwrap();
+        assert!((width - 50.8).abs() < 0.01 && (height - 12.7).abs() < 0.01);
+
+        let gif = image(ImageFormat::Gif, b"GIF89a\x60\x00\x30\x00".to_vec());
+        assert_eq!(gif.pixel_size(), Some((96, 48)));
+        assert_eq!(gif.dpi(), None);
+
+        assert_eq!(
+            image(ImageFormat::Svg, b"<svg/>".to_vec()).pixel_size(),
+            None
+        );
+        assert_eq!(
+            image(ImageFormat::Png, b"\x89PNG".to_vec()).pixel_size(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_too_large_error_message() {
+        let err = too_large("https://example.com/huge.png", 1024);
+        assert!(matches!(err, ImageError::TooLarge(_)));
+        assert_eq!(
+            err.to_string(),
+            "Image too large: https://example.com/huge.png exceeds the 1024 byte download limit"
+        );
+
+        let mut loader = ImageLoader::new(None);
+        loader.set_max_download_size(Some(1024));
+        assert_eq!(loader.max_download_size, Some(1024));
+    }
 }
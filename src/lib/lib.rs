@@ -113,6 +113,8 @@ pub mod highlighting;
 pub mod images;
 
 pub mod markdown;
+#[cfg(feature = "math-rust")]
+pub mod math;
 pub mod pdf;
 pub mod styling;
 pub mod validation;
@@ -122,6 +124,7 @@ use markdown::*;
 use pdf::Pdf;
 use std::error::Error;
 use std::fmt;
+use std::time::{Duration, Instant};
 
 /// Represents errors that can occur during the markdown-to-pdf conversion process.
 /// This includes both parsing failures and PDF generation issues.
@@ -244,6 +247,146 @@ impl MdpError {
     }
 }
 
+/// Summary of a successful conversion, returned by [`parse_into_file_reported`] and
+/// [`parse_into_file_with_images_reported`].
+///
+/// Warnings that would otherwise only reach the log (images that failed to load,
+/// font substitutions, characters without glyphs, elements that could not be
+/// rendered) are collected here so callers such as CI jobs can assert on them.
+#[derive(Debug, Clone)]
+pub struct ConversionReport {
+    /// Non-fatal problems encountered while loading fonts and rendering
+    pub warnings: Vec<validation::ValidationWarning>,
+    /// Size of the generated PDF in bytes
+    pub output_size: usize,
+    /// Number of pages in the generated PDF
+    pub page_count: usize,
+    /// Time spent in each stage of the conversion
+    pub timings: StageTimings,
+}
+
+/// Time spent in the stages of a conversion, as reported in [`ConversionReport::timings`].
+///
+/// Highlighting and image loading happen while the document is rendered; `rendering`
+/// excludes them.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StageTimings {
+    /// Parsing the markdown into tokens
+    pub lexing: Duration,
+    /// Loading, subsetting and checking fonts
+    pub font_loading: Duration,
+    /// Syntax highlighting code blocks
+    pub highlighting: Duration,
+    /// Reading and downloading images
+    pub image_loading: Duration,
+    /// Laying out the document and writing the PDF
+    pub rendering: Duration,
+}
+
+impl StageTimings {
+    /// Returns the time spent in all stages together.
+    pub fn total(&self) -> Duration {
+        self.lexing + self.font_loading + self.highlighting + self.image_loading + self.rendering
+    }
+}
+
+impl ConversionReport {
+    /// Returns true if no warnings were collected during the conversion.
+    pub fn is_clean(&self) -> bool {
+        self.warnings.is_empty()
+    }
+}
+
+/// Loads the configuration of a conversion and applies the `style` block of the
+/// document's front matter on top of it.
+fn load_document_style(markdown: &str, config: config::ConfigSource) -> styling::StyleMatch {
+    load_document_style_reported(markdown, config).0
+}
+
+/// Loads the configuration of a conversion like [`load_document_style`], also returning
+/// the warnings about the configuration and the front matter overrides.
+fn load_document_style_reported(
+    markdown: &str,
+    config: config::ConfigSource,
+) -> (styling::StyleMatch, Vec<validation::ValidationWarning>) {
+    let (style, mut warnings) = config::load_config_from_source_reported(config);
+    let mut style = match config::front_matter_style(markdown) {
+        Some(overrides) => {
+            let (style, overrides_warnings) =
+                config::merge_front_matter_style_reported(style, &overrides);
+            warnings.extend(overrides_warnings);
+            style
+        }
+        None => style,
+    };
+    if style.document.source_date_epoch.is_none() {
+        style.document.source_date_epoch = config::source_date_epoch();
+    }
+    (style, warnings)
+}
+
+/// Checks that the font file pinned by `[text] fontfile` exists, so a missing file fails
+/// the conversion instead of quietly falling back to another font.
+fn check_font_file(style: &styling::StyleMatch) -> Result<(), MdpError> {
+    let Some(path) = &style.text_font_file else {
+        return Ok(());
+    };
+    if std::path::Path::new(path).is_file() {
+        return Ok(());
+    }
+    Err(MdpError::FontError {
+        font_name: path.clone(),
+        message: "Font file not found".to_string(),
+        suggestion: "Check the [text] fontfile path; relative paths are resolved from the \
+                     current directory"
+            .to_string(),
+    })
+}
+
+/// Checks that the directory of the output `path` exists. With `[document] create_dirs`
+/// it is created along with any missing parents instead.
+fn prepare_output_dir(path: &str, create_dirs: bool) -> Result<(), MdpError> {
+    let Some(parent) = std::path::Path::new(path).parent() else {
+        return Ok(());
+    };
+    if parent.as_os_str().is_empty() || parent.exists() {
+        return Ok(());
+    }
+    if create_dirs {
+        return std::fs::create_dir_all(parent).map_err(|e| MdpError::IoError {
+            message: format!("Could not create the output directory: {}", e),
+            path: parent.display().to_string(),
+            suggestion: "Check that you have write permissions for this location".to_string(),
+        });
+    }
+    Err(MdpError::IoError {
+        message: "Output directory does not exist".to_string(),
+        path: parent.display().to_string(),
+        suggestion: format!(
+            "Create the directory first: mkdir -p {}, or pass --create-dirs",
+            parent.display()
+        ),
+    })
+}
+
+/// Lexes `markdown` into tokens, turning lexer errors into [`MdpError::ParseError`] with a
+/// hint at the usual cause.
+fn lex(markdown: &str) -> Result<Vec<Token>, MdpError> {
+    Lexer::new(markdown.to_string()).parse().map_err(|e| {
+        let msg = format!("{:?}", e);
+        MdpError::ParseError {
+            message: msg.clone(),
+            position: None,
+            suggestion: Some(if msg.contains("UnexpectedEndOfInput") {
+                "Check for unclosed code blocks (```), links, or image tags".to_string()
+            } else {
+                "Verify your Markdown syntax is valid. Try testing with a simpler document first."
+                    .to_string()
+            }),
+        }
+    })
+}
+
 /// Transforms Markdown content into a styled PDF document and saves it to the specified path.
 /// This function provides a high-level interface for converting Markdown to PDF with configurable
 /// styling through TOML configuration files.
@@ -300,37 +443,14 @@ pub fn parse_into_file(
     config: config::ConfigSource,
     font_config: Option<&fonts::FontConfig>,
 ) -> Result<(), MdpError> {
-    // Validate output path exists
-    if let Some(parent) = std::path::Path::new(path).parent() {
-        if !parent.as_os_str().is_empty() && !parent.exists() {
-            return Err(MdpError::IoError {
-                message: format!("Output directory does not exist"),
-                path: parent.display().to_string(),
-                suggestion: format!("Create the directory first: mkdir -p {}", parent.display()),
-            });
-        }
-    }
-
-    let mut lexer = Lexer::new(markdown);
-    let tokens = lexer.parse().map_err(|e| {
-        let msg = format!("{:?}", e);
-        MdpError::ParseError {
-            message: msg.clone(),
-            position: None,
-            suggestion: Some(if msg.contains("UnexpectedEndOfInput") {
-                "Check for unclosed code blocks (```), links, or image tags".to_string()
-            } else {
-                "Verify your Markdown syntax is valid. Try testing with a simpler document first."
-                    .to_string()
-            }),
-        }
-    })?;
-
-    let style = config::load_config_from_source(config);
-    let pdf = Pdf::new(tokens, style, font_config);
-    let document = pdf.render_into_document();
-
-    if let Some(err) = Pdf::render(document, path) {
+    let tokens = lex(&markdown)?;
+
+    let style = load_document_style(&markdown, config);
+    check_font_file(&style)?;
+    prepare_output_dir(path, style.document.create_dirs)?;
+    let mut pdf = Pdf::new(tokens, style, font_config);
+    pdf.set_source(markdown);
+    if let Some(err) = pdf.render_pdf_to_file(path) {
         return Err(MdpError::PdfError {
             message: err.clone(),
             path: Some(path.to_string()),
@@ -347,6 +467,122 @@ pub fn parse_into_file(
     Ok(())
 }
 
+/// Transforms Markdown content into a PDF file like [`parse_into_file`], and returns a
+/// [`ConversionReport`] describing the result.
+///
+/// # Arguments
+/// * `markdown` - The Markdown content to convert
+/// * `path` - The output file path for the generated PDF
+/// * `config` - Configuration source (Default, File path, or Embedded TOML)
+/// * `font_config` - Optional font configuration with custom paths and font overrides
+///
+/// # Returns
+/// * `Ok(ConversionReport)` with collected warnings, output size, page count and timings
+/// * `Err(MdpError)` if errors occur during parsing, styling, or file operations
+///
+/// # Example
+/// ```rust
+/// use std::error::Error;
+/// use markdown2pdf::config::ConfigSource;
+///
+/// fn example() -> Result<(), Box<dyn Error>> {
+///     let markdown = "# Report\n![Missing](missing.png)".to_string();
+///     let report = markdown2pdf::parse_into_file_reported(
+///         markdown,
+///         "output.pdf",
+///         ConfigSource::Default,
+///         None,
+///     )?;
+///     for warning in &report.warnings {
+///         println!("{}", warning);
+///     }
+///     println!("{} page(s), {} bytes", report.page_count, report.output_size);
+///     Ok(())
+/// }
+/// ```
+pub fn parse_into_file_reported(
+    markdown: String,
+    path: &str,
+    config: config::ConfigSource,
+    font_config: Option<&fonts::FontConfig>,
+) -> Result<ConversionReport, MdpError> {
+    convert_reported(markdown, path, None, config, font_config)
+}
+
+/// Transforms Markdown content with image support into a PDF file like
+/// [`parse_into_file_with_images`], and returns a [`ConversionReport`] describing the result.
+///
+/// # Arguments
+/// * `markdown` - The Markdown content to convert
+/// * `output_path` - The output file path for the generated PDF
+/// * `markdown_path` - Path to the markdown document (for resolving relative image references)
+/// * `config` - Configuration source (Default, File path, or Embedded TOML)
+/// * `font_config` - Optional font configuration with custom paths and font overrides
+///
+/// # Returns
+/// * `Ok(ConversionReport)` with collected warnings, output size, page count and timings
+/// * `Err(MdpError)` if errors occur during parsing, image loading, or file operations
+pub fn parse_into_file_with_images_reported(
+    markdown: String,
+    output_path: &str,
+    markdown_path: &std::path::Path,
+    config: config::ConfigSource,
+    font_config: Option<&fonts::FontConfig>,
+) -> Result<ConversionReport, MdpError> {
+    convert_reported(
+        markdown,
+        output_path,
+        Some(markdown_path),
+        config,
+        font_config,
+    )
+}
+
+/// Runs the conversion behind the `*_reported` functions, timing each pipeline stage.
+fn convert_reported(
+    markdown: String,
+    path: &str,
+    markdown_path: Option<&std::path::Path>,
+    config: config::ConfigSource,
+    font_config: Option<&fonts::FontConfig>,
+) -> Result<ConversionReport, MdpError> {
+    let lexing_start = Instant::now();
+    let tokens = lex(&markdown)?;
+    let lexing = lexing_start.elapsed();
+
+    let style = load_document_style(&markdown, config);
+    check_font_file(&style)?;
+    prepare_output_dir(path, style.document.create_dirs)?;
+    let mut pdf = Pdf::with_document_path(tokens, style, font_config, markdown_path);
+    pdf.set_source(markdown);
+
+    let rendering_start = Instant::now();
+    let bytes = pdf.render_pdf().map_err(MdpError::pdf_error)?;
+    let rendering = rendering_start.elapsed();
+
+    std::fs::write(path, &bytes).map_err(|e| MdpError::IoError {
+        message: e.to_string(),
+        path: path.to_string(),
+        suggestion: "Check that you have write permissions for this location".to_string(),
+    })?;
+
+    let page_count = lopdf::Document::load_mem(&bytes)
+        .map(|doc| doc.get_pages().len())
+        .unwrap_or(0);
+
+    let mut timings = pdf.timings();
+    timings.lexing = lexing;
+    // Highlighting and image loading happen while rendering; report them on their own
+    timings.rendering = rendering.saturating_sub(timings.highlighting + timings.image_loading);
+
+    Ok(ConversionReport {
+        warnings: pdf.warnings(),
+        output_size: bytes.len(),
+        page_count,
+        timings,
+    })
+}
+
 /// Transforms Markdown content with image support into a styled PDF document and saves it to the specified path.
 ///
 /// This is a variant of `parse_into_file` that supports resolving relative image paths based on the
@@ -388,37 +624,14 @@ pub fn parse_into_file_with_images(
     config: config::ConfigSource,
     font_config: Option<&fonts::FontConfig>,
 ) -> Result<(), MdpError> {
-    // Validate output path exists
-    if let Some(parent) = std::path::Path::new(output_path).parent() {
-        if !parent.as_os_str().is_empty() && !parent.exists() {
-            return Err(MdpError::IoError {
-                message: format!("Output directory does not exist"),
-                path: parent.display().to_string(),
-                suggestion: format!("Create the directory first: mkdir -p {}", parent.display()),
-            });
-        }
-    }
-
-    let mut lexer = Lexer::new(markdown);
-    let tokens = lexer.parse().map_err(|e| {
-        let msg = format!("{:?}", e);
-        MdpError::ParseError {
-            message: msg.clone(),
-            position: None,
-            suggestion: Some(if msg.contains("UnexpectedEndOfInput") {
-                "Check for unclosed code blocks (```), links, or image tags".to_string()
-            } else {
-                "Verify your Markdown syntax is valid. Try testing with a simpler document first."
-                    .to_string()
-            }),
-        }
-    })?;
-
-    let style = config::load_config_from_source(config);
-    let pdf = Pdf::with_document_path(tokens, style, font_config, Some(markdown_path));
-    let document = pdf.render_into_document();
-
-    if let Some(err) = Pdf::render(document, output_path) {
+    let tokens = lex(&markdown)?;
+
+    let style = load_document_style(&markdown, config);
+    check_font_file(&style)?;
+    prepare_output_dir(output_path, style.document.create_dirs)?;
+    let mut pdf = Pdf::with_document_path(tokens, style, font_config, Some(markdown_path));
+    pdf.set_source(markdown);
+    if let Some(err) = pdf.render_pdf_to_file(output_path) {
         return Err(MdpError::PdfError {
             message: err.clone(),
             path: Some(output_path.to_string()),
@@ -479,26 +692,13 @@ pub fn parse_into_bytes(
     config: config::ConfigSource,
     font_config: Option<&fonts::FontConfig>,
 ) -> Result<Vec<u8>, MdpError> {
-    let mut lexer = Lexer::new(markdown);
-    let tokens = lexer.parse().map_err(|e| {
-        let msg = format!("{:?}", e);
-        MdpError::ParseError {
-            message: msg.clone(),
-            position: None,
-            suggestion: Some(if msg.contains("UnexpectedEndOfInput") {
-                "Check for unclosed code blocks (```), links, or image tags".to_string()
-            } else {
-                "Verify your Markdown syntax is valid. Try testing with a simpler document first."
-                    .to_string()
-            }),
-        }
-    })?;
-
-    let style = config::load_config_from_source(config);
-    let pdf = Pdf::new(tokens, style, font_config);
-    let document = pdf.render_into_document();
+    let tokens = lex(&markdown)?;
 
-    Pdf::render_to_bytes(document).map_err(|err| MdpError::PdfError {
+    let style = load_document_style(&markdown, config);
+    check_font_file(&style)?;
+    let mut pdf = Pdf::new(tokens, style, font_config);
+    pdf.set_source(markdown);
+    pdf.render_pdf().map_err(|err| MdpError::PdfError {
         message: err,
         path: None,
         suggestion: Some("Check available memory and try with a smaller document".to_string()),
@@ -540,32 +740,92 @@ pub fn parse_into_bytes_with_images(
     config: config::ConfigSource,
     font_config: Option<&fonts::FontConfig>,
 ) -> Result<Vec<u8>, MdpError> {
-    let mut lexer = Lexer::new(markdown);
-    let tokens = lexer.parse().map_err(|e| {
-        let msg = format!("{:?}", e);
-        MdpError::ParseError {
-            message: msg.clone(),
-            position: None,
-            suggestion: Some(if msg.contains("UnexpectedEndOfInput") {
-                "Check for unclosed code blocks (```), links, or image tags".to_string()
-            } else {
-                "Verify your Markdown syntax is valid. Try testing with a simpler document first."
-                    .to_string()
-            }),
-        }
-    })?;
+    let tokens = lex(&markdown)?;
 
-    let style = config::load_config_from_source(config);
-    let pdf = Pdf::with_document_path(tokens, style, font_config, Some(markdown_path));
-    let document = pdf.render_into_document();
-
-    Pdf::render_to_bytes(document).map_err(|err| MdpError::PdfError {
+    let style = load_document_style(&markdown, config);
+    check_font_file(&style)?;
+    let mut pdf = Pdf::with_document_path(tokens, style, font_config, Some(markdown_path));
+    pdf.set_source(markdown);
+    pdf.render_pdf().map_err(|err| MdpError::PdfError {
         message: err,
         path: None,
         suggestion: Some("Check available memory and try with a smaller document".to_string()),
     })
 }
 
+/// Runs the pre-flight checks of a conversion without rendering, like the CLI `--dry-run`.
+///
+/// The Markdown is lexed and the configuration loaded (with the document's front matter
+/// `style`), reporting color names missing from `[palette]`, then checked with
+/// [`validation::validate_conversion_with_style`]. Editors and CI
+/// pipelines can report the same warnings as the CLI without producing a PDF.
+///
+/// # Arguments
+/// * `markdown` - The Markdown content to check
+/// * `output_path` - Optional path the PDF would be written to, checked for a missing
+///   directory
+/// * `config` - Configuration source (Default, File path, or Embedded TOML)
+/// * `font_config` - Optional font configuration with custom paths and font overrides
+///
+/// # Returns
+/// * `Ok(Vec<ValidationWarning>)` with the warnings, empty when no issues were found
+/// * `Err(MdpError)` if the Markdown cannot be parsed or the `[text] fontfile` is missing,
+///   which would fail the conversion
+///
+/// # Example
+/// ```rust
+/// use markdown2pdf::config::ConfigSource;
+///
+/// let markdown = "| A | B |\n|---|---|\n| 1 |\n";
+/// let warnings = markdown2pdf::dry_run(markdown, None, ConfigSource::Default, None).unwrap();
+/// for warning in &warnings {
+///     println!("{}", warning);
+/// }
+/// ```
+pub fn dry_run(
+    markdown: &str,
+    output_path: Option<&str>,
+    config: config::ConfigSource,
+    font_config: Option<&fonts::FontConfig>,
+) -> Result<Vec<validation::ValidationWarning>, MdpError> {
+    lex(markdown)?;
+
+    let (style, mut warnings) = load_document_style_reported(markdown, config);
+    check_font_file(&style)?;
+    warnings.extend(validation::validate_conversion_with_style(
+        markdown,
+        font_config,
+        output_path,
+        &style,
+    ));
+    Ok(warnings)
+}
+
+/// Converts Markdown content to plain text, dropping its formatting.
+///
+/// The text is what the PDF would read: headings, paragraphs, list items, quotes, code
+/// and table cells, without markup, link targets, comments or footnotes. It suits search
+/// indexes or email bodies sent alongside the PDF. See [`markdown::Token::plain_text`] for the
+/// layout of blocks, lists and tables.
+///
+/// # Arguments
+/// * `markdown` - The Markdown content to convert
+///
+/// # Returns
+/// * `Ok(String)` containing the plain text
+/// * `Err(MdpError)` if the Markdown cannot be parsed
+///
+/// # Example
+/// ```rust
+/// let text = markdown2pdf::to_plain_text("# Title\n\nSome **bold** [link](https://example.com).")
+///     .unwrap();
+/// assert_eq!(text, "Title\n\nSome bold link.");
+/// ```
+pub fn to_plain_text(markdown: &str) -> Result<String, MdpError> {
+    let tokens = lex(markdown)?;
+    Ok(Token::plain_text(&tokens))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -584,6 +844,73 @@ mod tests {
         fs::remove_file("test_output.pdf").unwrap();
     }
 
+    #[test]
+    fn test_to_plain_text() {
+        let markdown = "# Report\n\nSome *emphasis* and `code`.<!-- hidden -->\n\n- one\n- two\n\n| A | B |\n|---|---|\n| 1 | 2 |\n";
+        let text = to_plain_text(markdown).unwrap();
+        assert!(text.starts_with("Report\n\nSome emphasis and code."));
+        assert!(text.contains("- one"));
+        assert!(text.contains("- two"));
+        assert!(text.contains("A\tB\n1\t2"));
+        assert!(!text.contains("hidden"));
+        assert!(!text.contains('*'));
+
+        assert!(matches!(
+            to_plain_text("![Invalid"),
+            Err(MdpError::ParseError { .. })
+        ));
+    }
+
+    #[test]
+    fn test_front_matter_style_overrides_config() {
+        let markdown =
+            "---\ntitle: Wide\nstyle: |\n  [page]\n  orientation = \"landscape\"\n---\n# Wide\n";
+        let style = load_document_style(
+            markdown,
+            config::ConfigSource::Embedded("[page]\nsize = \"letter\"\n"),
+        );
+        assert_eq!(style.page_size, styling::PageSize::Letter);
+        assert_eq!(style.orientation, styling::Orientation::Landscape);
+
+        // Without a style block the configuration is used as is
+        let style = load_document_style("---\ntitle: Plain\n---\n", config::ConfigSource::Default);
+        assert_eq!(style, styling::StyleMatch::default());
+
+        // Keys reaching outside the document are refused with a warning each
+        let markdown = concat!(
+            "---\nstyle: |\n",
+            "  [document]\n  create_dirs = true\n",
+            "  [image.remote]\n  max_download_size = 1\n",
+            "  [margin]\n  left = 20.0\n---\n",
+        );
+        let (style, warnings) =
+            load_document_style_reported(markdown, config::ConfigSource::Default);
+        assert!(!style.document.create_dirs);
+        assert_eq!(style.remote_images.max_download_size, None);
+        assert_eq!(style.margins.left, 20.0);
+        let refused: Vec<&str> = warnings
+            .iter()
+            .filter(|w| w.kind == validation::WarningKind::RefusedFrontMatterKey)
+            .map(|w| w.message.as_str())
+            .collect();
+        assert_eq!(refused.len(), 2);
+        assert!(refused[0].contains("document.create_dirs"));
+        assert!(refused[1].contains("image.remote.max_download_size"));
+    }
+
+    #[test]
+    fn test_missing_font_file() {
+        let result = parse_into_bytes(
+            "Text".to_string(),
+            config::ConfigSource::Embedded("[text]\nfontfile = \"missing/NoSuchFont.ttf\"\n"),
+            None,
+        );
+        assert!(matches!(
+            result,
+            Err(MdpError::FontError { font_name, .. }) if font_name == "missing/NoSuchFont.ttf"
+        ));
+    }
+
     #[test]
     fn test_invalid_markdown() {
         let markdown = "![Invalid".to_string();
@@ -611,6 +938,77 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_create_dirs_creates_missing_output_directory() {
+        let root = std::env::temp_dir().join(format!("md2pdf-create-dirs-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let output = root.join("nested/tree/output.pdf");
+        let output = output.to_str().unwrap();
+
+        // Missing directories are an error by default
+        let result = parse_into_file(
+            "# Test".to_string(),
+            output,
+            config::ConfigSource::Default,
+            None,
+        );
+        assert!(matches!(result, Err(MdpError::IoError { .. })));
+        assert!(!root.exists());
+
+        parse_into_file(
+            "# Test".to_string(),
+            output,
+            config::ConfigSource::Embedded("[document]\ncreate_dirs = true\n"),
+            None,
+        )
+        .unwrap();
+        assert!(fs::metadata(output).unwrap().len() > 0);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_parse_into_file_reported() {
+        let markdown = "# Report\n\n![Missing](does_not_exist.png)".to_string();
+        let report = parse_into_file_reported(
+            markdown,
+            "test_reported_output.pdf",
+            config::ConfigSource::Default,
+            None,
+        )
+        .unwrap();
+        let written = fs::metadata("test_reported_output.pdf").unwrap().len();
+        fs::remove_file("test_reported_output.pdf").unwrap();
+
+        assert_eq!(report.output_size as u64, written);
+        assert_eq!(report.page_count, 1);
+        assert!(!report.is_clean());
+        assert!(report
+            .warnings
+            .iter()
+            .any(|w| w.kind == validation::WarningKind::MissingImage));
+    }
+
+    #[test]
+    fn test_parse_into_file_with_images_reported_timings() {
+        let markdown =
+            "# Timed\n\n```rust\nfn main() {}\n```\n\n![Ruler](images/triangle-ruler-1016726_640.png)"
+                .to_string();
+        let report = parse_into_file_with_images_reported(
+            markdown,
+            "test_timed_output.pdf",
+            std::path::Path::new("tests/document.md"),
+            config::ConfigSource::Default,
+            None,
+        )
+        .unwrap();
+        fs::remove_file("test_timed_output.pdf").unwrap();
+
+        let timings = report.timings;
+        assert!(timings.highlighting > Duration::ZERO);
+        assert!(timings.image_loading > Duration::ZERO);
+        assert!(timings.total() >= timings.rendering + timings.lexing);
+    }
+
     #[test]
     fn test_basic_markdown_to_bytes() {
         let markdown = "# Test\nHello world".to_string();
@@ -649,6 +1047,34 @@ mod tests {
         fs::remove_file("test_embedded_output.pdf").unwrap();
     }
 
+    #[test]
+    fn test_attach_source_embeds_markdown() {
+        const EMBEDDED_CONFIG: &str = "[document]\nattach_source = true\n";
+        let markdown = "# Archived\nKeep the source.".to_string();
+        let bytes = parse_into_bytes(
+            markdown.clone(),
+            config::ConfigSource::Embedded(EMBEDDED_CONFIG),
+            None,
+        )
+        .unwrap();
+
+        let doc = lopdf::Document::load_mem(&bytes).unwrap();
+        let root_id = doc.trailer.get(b"Root").unwrap().as_reference().unwrap();
+        let names = doc.get_dictionary(root_id).unwrap().get(b"Names").unwrap();
+        let files = names.as_dict().unwrap().get(b"EmbeddedFiles").unwrap();
+        let entries = files.as_dict().unwrap().get(b"Names").unwrap();
+        assert_eq!(
+            entries.as_array().unwrap()[0].as_str().unwrap(),
+            b"document.md"
+        );
+
+        // Without the option nothing is attached
+        let plain = parse_into_bytes(markdown, config::ConfigSource::Default, None).unwrap();
+        let doc = lopdf::Document::load_mem(&plain).unwrap();
+        let root_id = doc.trailer.get(b"Root").unwrap().as_reference().unwrap();
+        assert!(doc.get_dictionary(root_id).unwrap().get(b"AF").is_err());
+    }
+
     #[test]
     fn test_embedded_config_bytes_output() {
         const EMBEDDED_CONFIG: &str = r#"
@@ -1031,6 +1457,49 @@ This markdown file is optimized for quick performance testing.
         assert!(s5.contains("📁 Path: /path/to"));
     }
 
+    #[test]
+    fn test_dry_run() {
+        let check = |markdown: &str| dry_run(markdown, None, config::ConfigSource::Default, None);
+        let markdown = "# Report\n\n| A | B |\n|---|---|\n| 1 |\n\n```jspn\n{}\n```\n";
+        let warnings = check(markdown).unwrap();
+        let kinds: Vec<_> = warnings.iter().map(|w| w.kind.clone()).collect();
+        assert!(kinds.contains(&validation::WarningKind::TableColumnMismatch));
+        assert!(kinds.contains(&validation::WarningKind::UnknownCodeLanguage));
+
+        assert!(check("# Clean\n\nText.").unwrap().is_empty());
+        // The output path is checked when given
+        let warnings = dry_run(
+            "# Clean\n\nText.",
+            Some("missing-dry-run-dir/out.pdf"),
+            config::ConfigSource::Default,
+            None,
+        )
+        .unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].message.contains("missing-dry-run-dir"));
+
+        // Configuration problems are reported with the document ones
+        let warnings = dry_run(
+            "---\nstyle: |\n  [link]\n  textcolor = \"accent\"\n---\nText.",
+            None,
+            config::ConfigSource::Embedded("[text]\ntextcolor = \"ink\"\n"),
+            None,
+        )
+        .unwrap();
+        let unknown: Vec<_> = warnings
+            .iter()
+            .filter(|w| w.kind == validation::WarningKind::UnknownPaletteColor)
+            .collect();
+        assert_eq!(unknown.len(), 2);
+        assert!(unknown[0].message.contains("'ink'"));
+        assert!(unknown[1].message.contains("'accent'"));
+
+        assert!(matches!(
+            check("<!-- unclosed comment"),
+            Err(MdpError::ParseError { .. })
+        ));
+    }
+
     #[test]
     fn test_parse_into_file_unexpected_end_of_input_suggestion() {
         // Unclosed HTML comment triggers UnexpectedEndOfInput in lexer
@@ -42,6 +42,8 @@
 //!         └── url: String
 
 use genpdfi_extended::Alignment;
+use std::collections::HashMap;
+use std::rc::Rc;
 /// Parsing context — determines which tokens are valid in the current location.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ParseContext {
@@ -98,14 +100,56 @@ pub enum Token {
     },
     /// Line break (2+ spaces followed by newline, or \ at end of line)
     LineBreak,
+    /// Single newline inside a paragraph, rendered as a space or a line break
+    SoftBreak,
     /// Newline (paragraph separator)
     Newline,
     /// Horizontal rule (---)
     HorizontalRule,
+    /// Abbreviation definition with abbreviation and expansion (e.g., *[HTML]: HyperText Markup Language)
+    AbbreviationDefinition(String, String),
+    /// Explicit link target from an HTML `<a id="...">` or `<a name="...">` tag
+    Anchor(String),
+    /// Keyboard key from an HTML `<kbd>...</kbd>` tag
+    Kbd(String),
+    /// Highlighted text from an HTML `<mark>...</mark>` tag
+    Mark(Vec<Token>),
+    /// Inserted (underlined) text from an HTML `<ins>...</ins>` tag
+    Ins(Vec<Token>),
+    /// Deleted (struck-through) text from an HTML `<del>...</del>` tag
+    Del(Vec<Token>),
+    /// Inline footnote (`^[note text]`), numbered in order of appearance
+    FootnoteInline(Vec<Token>),
+    /// Task list checkbox (`[ ]` or `[x]`) opening a list item; true when checked
+    TaskCheckbox(bool),
+    /// Group of tabs from a `:::tabs` directive, as (title, content) pairs
+    TabGroup(Vec<(String, Vec<Token>)>),
+    /// reStructuredText admonition (`.. note::`) with its kind, lowercased, and content.
+    /// Only produced with the `rst-admonitions` feature
+    Admonition { kind: String, content: Vec<Token> },
+    /// Spoiler (`||text||`), always revealed in the PDF
+    Spoiler(Vec<Token>),
+    /// CriticMarkup tracked change (`{++added++}`, `{--removed--}`, `{==highlighted==}`,
+    /// `{~~old~>new~~}`) and its content. Only produced with the `critic-markup` feature
+    Critic(CriticMarkup, Vec<Token>),
     /// Unknown or malformed token
     Unknown(String),
 }
 
+/// Kind of a CriticMarkup tracked change, see [`Token::Critic`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CriticMarkup {
+    /// Inserted text, `{++added++}`
+    Addition,
+    /// Deleted text, `{--removed--}`
+    Deletion,
+    /// Highlighted text, `{==highlighted==}`
+    Highlight,
+    /// Replaced text, `{~~old~>new~~}`, whose content is a deletion of the old text
+    /// followed by an addition of the new one
+    Substitution,
+}
+
 impl Token {
     /// Recursively extracts all text content from a token and its nested tokens.
     /// This is useful for collecting all characters used in a document for font subsetting.
@@ -164,7 +208,36 @@ impl Token {
             Token::ImageWithLink(alt, _, _) => result.push_str(alt),
             Token::HtmlComment(comment) => result.push_str(comment),
             Token::Unknown(text) => result.push_str(text),
-            Token::LineBreak | Token::Newline | Token::HorizontalRule => {
+            Token::Kbd(key) => result.push_str(key),
+            Token::FootnoteInline(content)
+            | Token::Mark(content)
+            | Token::Ins(content)
+            | Token::Del(content)
+            | Token::Spoiler(content)
+            | Token::Critic(_, content) => {
+                for token in content {
+                    token.collect_text_recursive(result);
+                }
+            }
+            Token::TabGroup(tabs) => {
+                for (title, content) in tabs {
+                    result.push_str(title);
+                    for token in content {
+                        token.collect_text_recursive(result);
+                    }
+                }
+            }
+            Token::Admonition { content, .. } => {
+                for token in content {
+                    token.collect_text_recursive(result);
+                }
+            }
+            Token::SoftBreak => result.push(' '),
+            Token::LineBreak
+            | Token::Newline
+            | Token::HorizontalRule
+            | Token::Anchor(_)
+            | Token::TaskCheckbox(_) => {
                 // These don't contain text
             }
             Token::Table {
@@ -191,8 +264,650 @@ impl Token {
             Token::Math { content, .. } => {
                 result.push_str(content);
             }
+            Token::AbbreviationDefinition(abbr, expansion) => {
+                result.push_str(abbr);
+                result.push_str(expansion);
+            }
+        }
+    }
+
+    /// Renders tokens as plain text, dropping formatting, link targets, comments and
+    /// definitions. Blocks are separated by blank lines, list items keep a `-` or number
+    /// marker, and table cells are separated by tabs.
+    ///
+    /// # Example
+    /// ```
+    /// use markdown2pdf::markdown::Token;
+    ///
+    /// let tokens = vec![
+    ///     Token::Heading(vec![Token::Text("Title".to_string())], 1),
+    ///     Token::StrongEmphasis(vec![Token::Text("Bold".to_string())]),
+    ///     Token::Text(" body".to_string()),
+    /// ];
+    /// assert_eq!(Token::plain_text(&tokens), "Title\n\nBold body");
+    /// ```
+    pub fn plain_text(tokens: &[Token]) -> String {
+        let mut result = String::new();
+        for token in tokens {
+            token.plain_text_recursive(&mut result, 0);
+        }
+
+        // Drop trailing spaces and keep at most one blank line between blocks
+        let mut text = String::new();
+        let mut blank_lines = 0;
+        for line in result.lines().map(str::trim_end) {
+            if line.is_empty() {
+                blank_lines += 1;
+                continue;
+            }
+            if !text.is_empty() {
+                text.push_str(if blank_lines > 0 { "\n\n" } else { "\n" });
+            }
+            text.push_str(line);
+            blank_lines = 0;
+        }
+        text
+    }
+
+    fn plain_text_recursive(&self, result: &mut String, depth: usize) {
+        let nested = |tokens: &[Token], result: &mut String| {
+            for token in tokens {
+                token.plain_text_recursive(result, depth);
+            }
+        };
+        match self {
+            Token::Text(text) | Token::Unknown(text) | Token::Kbd(text) => result.push_str(text),
+            Token::Link(text, _) | Token::Image(text, _) | Token::ImageWithLink(text, _, _) => {
+                result.push_str(text)
+            }
+            Token::Emphasis { content, .. }
+            | Token::StrongEmphasis(content)
+            | Token::Mark(content)
+            | Token::Ins(content)
+            | Token::Del(content)
+            | Token::Spoiler(content)
+            | Token::Critic(_, content) => nested(content, result),
+            Token::Math { content, display } => {
+                if *display {
+                    result.push_str("\n\n");
+                }
+                result.push_str(content);
+                if *display {
+                    result.push_str("\n\n");
+                }
+            }
+            Token::Code(_, content) if content.contains('\n') => {
+                result.push_str("\n\n");
+                result.push_str(content);
+                result.push_str("\n\n");
+            }
+            Token::Code(_, content) => result.push_str(content),
+            Token::Heading(content, _) => {
+                result.push_str("\n\n");
+                nested(content, result);
+                result.push_str("\n\n");
+            }
+            Token::BlockQuote(text) => {
+                let content = Lexer::new(text.clone())
+                    .parse_with_context(ParseContext::Inline)
+                    .unwrap_or_else(|_| vec![Token::Text(text.clone())]);
+                result.push('\n');
+                nested(&content, result);
+                result.push('\n');
+            }
+            Token::ListItem {
+                content,
+                ordered,
+                number,
+            } => {
+                if !result.ends_with('\n') {
+                    result.push('\n');
+                }
+                result.push_str(&"  ".repeat(depth));
+                if *ordered {
+                    result.push_str(&format!("{}. ", number.unwrap_or(1)));
+                } else {
+                    result.push_str("- ");
+                }
+                for token in content {
+                    token.plain_text_recursive(result, depth + 1);
+                }
+                result.push('\n');
+            }
+            Token::TaskCheckbox(checked) => result.push_str(if *checked { "[x] " } else { "[ ] " }),
+            Token::Table { headers, rows, .. } => {
+                result.push_str("\n\n");
+                for row in std::iter::once(headers).chain(rows) {
+                    for (i, cell) in row.iter().enumerate() {
+                        if i > 0 {
+                            result.push('\t');
+                        }
+                        let mut text = String::new();
+                        nested(cell, &mut text);
+                        result.push_str(text.trim());
+                    }
+                    result.push('\n');
+                }
+                result.push('\n');
+            }
+            Token::TabGroup(tabs) => {
+                for (title, content) in tabs {
+                    result.push_str("\n\n");
+                    result.push_str(title);
+                    result.push_str("\n\n");
+                    nested(content, result);
+                }
+                result.push_str("\n\n");
+            }
+            Token::Admonition { content, .. } => {
+                result.push_str("\n\n");
+                nested(content, result);
+                result.push_str("\n\n");
+            }
+            Token::SoftBreak if !result.is_empty() && !result.ends_with('\n') => result.push(' '),
+            Token::SoftBreak => {}
+            Token::LineBreak => result.push('\n'),
+            Token::Newline | Token::HorizontalRule => result.push_str("\n\n"),
+            Token::HtmlComment(_)
+            | Token::FootnoteInline(_)
+            | Token::AbbreviationDefinition(..)
+            | Token::Anchor(_)
+            | Token::TableAlignment(_) => {
+                // Not part of the running text
+            }
+        }
+    }
+
+    /// Removes a level 1 heading that opens the document and returns its text.
+    ///
+    /// Only a heading preceded by nothing but blank lines, comments, definitions and
+    /// anchors is taken. Anchors inside the heading are kept in its place so links to
+    /// them still resolve.
+    ///
+    /// # Example
+    /// ```
+    /// use markdown2pdf::markdown::Token;
+    ///
+    /// let mut tokens = vec![
+    ///     Token::Heading(vec![Token::Text("User Guide".to_string())], 1),
+    ///     Token::Text("Body".to_string()),
+    /// ];
+    ///
+    /// assert_eq!(Token::take_title(&mut tokens).as_deref(), Some("User Guide"));
+    /// assert_eq!(tokens, vec![Token::Text("Body".to_string())]);
+    /// ```
+    pub fn take_title(tokens: &mut Vec<Token>) -> Option<String> {
+        let idx = tokens.iter().position(|token| {
+            !matches!(
+                token,
+                Token::Newline
+                    | Token::SoftBreak
+                    | Token::HtmlComment(_)
+                    | Token::AbbreviationDefinition(..)
+                    | Token::Anchor(_)
+            )
+        })?;
+        let Token::Heading(content, 1) = &tokens[idx] else {
+            return None;
+        };
+
+        let title = Token::collect_all_text(content).trim().to_string();
+        if title.is_empty() {
+            return None;
+        }
+        let anchors: Vec<Token> = Token::collect_anchors(content)
+            .into_iter()
+            .map(Token::Anchor)
+            .collect();
+        tokens.splice(idx..=idx, anchors);
+        Some(title)
+    }
+
+    /// Collects all abbreviation definitions from a token stream, in document order.
+    ///
+    /// Definitions nested inside list items are included. When the same abbreviation
+    /// is defined more than once, the first definition wins.
+    ///
+    /// # Example
+    /// ```
+    /// use markdown2pdf::markdown::Token;
+    ///
+    /// let tokens = vec![Token::AbbreviationDefinition(
+    ///     "HTML".to_string(),
+    ///     "HyperText Markup Language".to_string(),
+    /// )];
+    ///
+    /// let abbreviations = Token::collect_abbreviations(&tokens);
+    /// assert_eq!(abbreviations[0].0, "HTML");
+    /// ```
+    pub fn collect_abbreviations(tokens: &[Token]) -> Vec<(String, String)> {
+        let mut result: Vec<(String, String)> = Vec::new();
+        for token in tokens {
+            match token {
+                Token::AbbreviationDefinition(abbr, expansion) => {
+                    if !result.iter().any(|(existing, _)| existing == abbr) {
+                        result.push((abbr.clone(), expansion.clone()));
+                    }
+                }
+                Token::ListItem { content, .. } => {
+                    for (abbr, expansion) in Token::collect_abbreviations(content) {
+                        if !result.iter().any(|(existing, _)| *existing == abbr) {
+                            result.push((abbr, expansion));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        result
+    }
+
+    /// Returns the term of an `<!-- index: term -->` comment, which tags its position for
+    /// the index at the end of the document.
+    ///
+    /// # Example
+    /// ```
+    /// use markdown2pdf::markdown::Token;
+    ///
+    /// let tag = Token::HtmlComment(" index: borrow checker ".to_string());
+    /// assert_eq!(tag.index_term(), Some("borrow checker"));
+    /// assert_eq!(Token::HtmlComment(" TODO ".to_string()).index_term(), None);
+    /// ```
+    pub fn index_term(&self) -> Option<&str> {
+        match self {
+            Token::HtmlComment(comment) => comment
+                .trim()
+                .strip_prefix("index:")
+                .map(str::trim)
+                .filter(|term| !term.is_empty()),
+            _ => None,
+        }
+    }
+
+    /// Collects the distinct index terms tagged in a token stream (see
+    /// [`Token::index_term`]), sorted alphabetically ignoring case.
+    pub fn collect_index_terms(tokens: &[Token]) -> Vec<String> {
+        fn collect(tokens: &[Token], result: &mut Vec<String>) {
+            for token in tokens {
+                match token {
+                    Token::HtmlComment(_) => {
+                        if let Some(term) = token.index_term() {
+                            result.push(term.to_string());
+                        }
+                    }
+                    Token::Heading(nested, _)
+                    | Token::StrongEmphasis(nested)
+                    | Token::Mark(nested)
+                    | Token::Ins(nested)
+                    | Token::Del(nested)
+                    | Token::FootnoteInline(nested)
+                    | Token::Spoiler(nested)
+                    | Token::Critic(_, nested)
+                    | Token::Emphasis {
+                        content: nested, ..
+                    }
+                    | Token::ListItem {
+                        content: nested, ..
+                    }
+                    | Token::Admonition {
+                        content: nested, ..
+                    } => collect(nested, result),
+                    Token::TabGroup(tabs) => {
+                        for (_, content) in tabs {
+                            collect(content, result);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let mut terms = Vec::new();
+        collect(tokens, &mut terms);
+        terms.sort_by_cached_key(|term| (term.to_lowercase(), term.clone()));
+        terms.dedup();
+        terms
+    }
+
+    /// Replaces the straight quotes in the text of a token stream with typographic
+    /// quotation marks.
+    ///
+    /// `marks` holds the opening and closing double quotes, then the opening and closing
+    /// single quotes. A quote opens after whitespace, an opening bracket or dash, or at
+    /// the start of a block, and closes otherwise; a `'` between two letters is an
+    /// apostrophe and becomes `’`. Code, math and URLs are left alone.
+    ///
+    /// # Example
+    /// ```
+    /// use markdown2pdf::markdown::Token;
+    ///
+    /// let mut tokens = vec![Token::Text("\"Don't,\" she said.".to_string())];
+    /// Token::replace_quotes(&mut tokens, ["“", "”", "‘", "’"]);
+    /// assert_eq!(tokens, vec![Token::Text("“Don’t,” she said.".to_string())]);
+    /// ```
+    pub fn replace_quotes(tokens: &mut [Token], marks: [&str; 4]) {
+        let mut previous = None;
+        replace_quotes_in(tokens, &marks, &mut previous);
+    }
+
+    /// Replaces every match of `patterns` in the text of `tokens` with as many `mask`
+    /// characters as the match has, for `[redact]`. Every text that can end up in the PDF
+    /// is redacted: paragraphs and the text nested in emphasis, headings, list items,
+    /// quotes, tables and notes, link text and targets, image alt text, code, math,
+    /// `<kbd>` keys, tab titles, abbreviation definitions, anchors and HTML comments such
+    /// as index terms. Image sources are kept so the images still load.
+    ///
+    /// # Example
+    /// ```
+    /// use markdown2pdf::markdown::Token;
+    ///
+    /// let patterns = [regex::Regex::new(r"ACME-\d+").unwrap()];
+    /// let mut tokens = vec![Token::Text("See ACME-42.".to_string())];
+    /// Token::redact(&mut tokens, &patterns, '█');
+    /// assert_eq!(tokens, vec![Token::Text("See ███████.".to_string())]);
+    /// ```
+    pub fn redact(tokens: &mut [Token], patterns: &[regex::Regex], mask: char) {
+        let redact = |text: &mut String| *text = redact_text(text, patterns, mask);
+        for token in tokens {
+            match token {
+                Token::Text(text)
+                | Token::BlockQuote(text)
+                | Token::Code(_, text)
+                | Token::Math { content: text, .. }
+                | Token::Kbd(text)
+                | Token::Anchor(text)
+                | Token::HtmlComment(text)
+                | Token::Unknown(text)
+                | Token::Image(text, _) => redact(text),
+                Token::Link(text, url) | Token::ImageWithLink(text, _, url) => {
+                    redact(text);
+                    redact(url);
+                }
+                Token::AbbreviationDefinition(abbreviation, expansion) => {
+                    redact(abbreviation);
+                    redact(expansion);
+                }
+                Token::Emphasis { content, .. }
+                | Token::StrongEmphasis(content)
+                | Token::Mark(content)
+                | Token::Ins(content)
+                | Token::Del(content)
+                | Token::Spoiler(content)
+                | Token::Critic(_, content)
+                | Token::FootnoteInline(content)
+                | Token::Heading(content, _)
+                | Token::ListItem { content, .. }
+                | Token::Admonition { content, .. } => Token::redact(content, patterns, mask),
+                Token::Table { headers, rows, .. } => {
+                    for cell in headers.iter_mut().chain(rows.iter_mut().flatten()) {
+                        Token::redact(cell, patterns, mask);
+                    }
+                }
+                Token::TabGroup(tabs) => {
+                    for (title, content) in tabs {
+                        redact(title);
+                        Token::redact(content, patterns, mask);
+                    }
+                }
+                Token::TableAlignment(_)
+                | Token::LineBreak
+                | Token::SoftBreak
+                | Token::Newline
+                | Token::HorizontalRule
+                | Token::TaskCheckbox(_) => {}
+            }
+        }
+    }
+
+    /// Resolves the relative URLs of links and images against base URLs, so content
+    /// written for a website keeps working links in the PDF.
+    ///
+    /// Links are resolved against `link_base` and images against `image_base`; the link
+    /// of a linked image uses `link_base`. URLs with a scheme (`https:`, `mailto:`),
+    /// protocol-relative URLs and `#fragment` links are left as written. A path starting
+    /// with `/` replaces the path of the base, other paths are resolved against its
+    /// directory, as in a browser.
+    ///
+    /// # Example
+    /// ```
+    /// use markdown2pdf::markdown::Token;
+    ///
+    /// let mut tokens = vec![Token::Link("Guide".to_string(), "/docs/page".to_string())];
+    /// Token::resolve_urls(&mut tokens, Some("https://site.example/blog/"), None);
+    /// assert_eq!(
+    ///     tokens,
+    ///     vec![Token::Link("Guide".to_string(), "https://site.example/docs/page".to_string())]
+    /// );
+    /// ```
+    pub fn resolve_urls(tokens: &mut [Token], link_base: Option<&str>, image_base: Option<&str>) {
+        let resolve = |url: &mut String, base: Option<&str>| {
+            if let Some(base) = base {
+                *url = resolve_url(base, url);
+            }
+        };
+        for token in tokens {
+            match token {
+                Token::Link(_, url) => resolve(url, link_base),
+                Token::Image(_, url) => resolve(url, image_base),
+                Token::ImageWithLink(_, image, link) => {
+                    resolve(image, image_base);
+                    resolve(link, link_base);
+                }
+                Token::Emphasis { content, .. }
+                | Token::StrongEmphasis(content)
+                | Token::Mark(content)
+                | Token::Ins(content)
+                | Token::Del(content)
+                | Token::Spoiler(content)
+                | Token::Critic(_, content)
+                | Token::FootnoteInline(content)
+                | Token::Heading(content, _)
+                | Token::ListItem { content, .. }
+                | Token::Admonition { content, .. } => {
+                    Token::resolve_urls(content, link_base, image_base)
+                }
+                Token::Table { headers, rows, .. } => {
+                    for cell in headers.iter_mut().chain(rows.iter_mut().flatten()) {
+                        Token::resolve_urls(cell, link_base, image_base);
+                    }
+                }
+                Token::TabGroup(tabs) => {
+                    for (_, content) in tabs {
+                        Token::resolve_urls(content, link_base, image_base);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Collects the names of all explicit anchors in a token stream, in document order.
+    ///
+    /// Anchors nested inside headings, emphasis, list items and table cells are included.
+    ///
+    /// # Example
+    /// ```
+    /// use markdown2pdf::markdown::Token;
+    ///
+    /// let tokens = vec![Token::Heading(vec![Token::Anchor("intro".to_string())], 1)];
+    /// assert_eq!(Token::collect_anchors(&tokens), vec!["intro".to_string()]);
+    /// ```
+    pub fn collect_anchors(tokens: &[Token]) -> Vec<String> {
+        let mut result = Vec::new();
+        for token in tokens {
+            match token {
+                Token::Anchor(name) => result.push(name.clone()),
+                Token::Heading(nested, _)
+                | Token::StrongEmphasis(nested)
+                | Token::Mark(nested)
+                | Token::Ins(nested)
+                | Token::Del(nested)
+                | Token::Spoiler(nested)
+                | Token::Critic(_, nested)
+                | Token::Emphasis {
+                    content: nested, ..
+                }
+                | Token::ListItem {
+                    content: nested, ..
+                } => result.extend(Token::collect_anchors(nested)),
+                Token::Table { headers, rows, .. } => {
+                    for cell in headers.iter().chain(rows.iter().flatten()) {
+                        result.extend(Token::collect_anchors(cell));
+                    }
+                }
+                Token::TabGroup(tabs) => {
+                    for (_, content) in tabs {
+                        result.extend(Token::collect_anchors(content));
+                    }
+                }
+                Token::Admonition { content, .. } => result.extend(Token::collect_anchors(content)),
+                _ => {}
+            }
+        }
+        result
+    }
+}
+
+/// Replaces quotes in `tokens` for [`Token::replace_quotes`]. `previous` is the last
+/// character of the text before the tokens, `None` at the start of a block.
+fn replace_quotes_in(tokens: &mut [Token], marks: &[&str; 4], previous: &mut Option<char>) {
+    for token in tokens {
+        match token {
+            Token::Text(text) | Token::Link(text, _) => {
+                *text = replace_quotes_in_text(text, marks, previous);
+            }
+            Token::Emphasis { content, .. }
+            | Token::StrongEmphasis(content)
+            | Token::Mark(content)
+            | Token::Ins(content)
+            | Token::Del(content)
+            | Token::Spoiler(content)
+            | Token::Critic(_, content) => replace_quotes_in(content, marks, previous),
+            Token::FootnoteInline(content) => replace_quotes_in(content, marks, &mut None),
+            Token::Heading(content, _)
+            | Token::ListItem { content, .. }
+            | Token::Admonition { content, .. } => {
+                replace_quotes_in(content, marks, &mut None);
+                *previous = None;
+            }
+            Token::Table { headers, rows, .. } => {
+                for cell in headers.iter_mut().chain(rows.iter_mut().flatten()) {
+                    replace_quotes_in(cell, marks, &mut None);
+                }
+                *previous = None;
+            }
+            Token::TabGroup(tabs) => {
+                for (_, content) in tabs {
+                    replace_quotes_in(content, marks, &mut None);
+                }
+                *previous = None;
+            }
+            Token::Code(..) | Token::Math { .. } | Token::Kbd(_) | Token::Image(..) => {
+                // A quote right after code closes, like one after a word
+                *previous = Some('x');
+            }
+            Token::SoftBreak => *previous = Some(' '),
+            Token::Newline | Token::LineBreak | Token::HorizontalRule | Token::BlockQuote(_) => {
+                *previous = None;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Replaces every match of `patterns` in `text` with as many `mask` characters as the
+/// match has, for [`Token::redact`] and the attached markdown source.
+pub(crate) fn redact_text(text: &str, patterns: &[regex::Regex], mask: char) -> String {
+    let mut text = text.to_string();
+    for pattern in patterns {
+        if pattern.is_match(&text) {
+            text = pattern
+                .replace_all(&text, |caps: &regex::Captures| {
+                    mask.to_string().repeat(caps[0].chars().count())
+                })
+                .into_owned();
+        }
+    }
+    text
+}
+
+/// Resolves one URL against a base URL for [`Token::resolve_urls`].
+fn resolve_url(base: &str, url: &str) -> String {
+    let has_scheme = url.split_once(':').is_some_and(|(scheme, _)| {
+        scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+    });
+    if url.is_empty() || has_scheme || url.starts_with('#') || url.starts_with("//") {
+        return url.to_string();
+    }
+
+    // Split the base into its origin (`https://host`) and path
+    let path_start = base
+        .find("://")
+        .map(|scheme_end| scheme_end + 3)
+        .map(|host_start| {
+            base[host_start..]
+                .find('/')
+                .map_or(base.len(), |i| host_start + i)
+        })
+        .unwrap_or(0);
+    let (origin, base_path) = base.split_at(path_start);
+    if url.starts_with('/') {
+        return format!("{}{}", origin, url);
+    }
+
+    let mut segments: Vec<&str> = base_path.split('/').collect();
+    // The last segment of the base is a file name, or empty after a trailing slash
+    segments.pop();
+    if segments.is_empty() && !origin.is_empty() {
+        segments.push("");
+    }
+    for segment in url.split('/') {
+        match segment {
+            "." => {}
+            ".." => {
+                // The empty segment before the first `/` is the root and stays
+                if segments.last().is_some_and(|s| !s.is_empty()) {
+                    segments.pop();
+                }
+            }
+            _ => segments.push(segment),
         }
     }
+    format!("{}{}", origin, segments.join("/"))
+}
+
+/// Replaces the quotes of one text for [`Token::replace_quotes`], updating `previous`.
+fn replace_quotes_in_text(text: &str, marks: &[&str; 4], previous: &mut Option<char>) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let opens = previous.map_or(true, |p| {
+            p.is_whitespace() || "([{-\u{2013}\u{2014}/".contains(p)
+        });
+        let (mark, after) = match c {
+            '\'' if previous.is_some_and(char::is_alphanumeric)
+                && chars.peek().is_some_and(|next| next.is_alphanumeric()) =>
+            {
+                ("\u{2019}", c)
+            }
+            '"' | '\'' => {
+                let index = (if c == '"' { 0 } else { 2 }) + usize::from(!opens);
+                // Text after a quotation mark follows it like it follows a bracket
+                (marks[index], if opens { '(' } else { ')' })
+            }
+            _ => {
+                result.push(c);
+                *previous = Some(c);
+                continue;
+            }
+        };
+        result.push_str(mark);
+        *previous = Some(after);
+    }
+    result
 }
 
 /// Error types that can occur during lexical analysis
@@ -212,20 +927,54 @@ pub struct Lexer {
     input: Vec<char>,
     /// Current position in the input stream
     position: usize,
+    /// Number of `<a id=...>` tags still waiting for their `</a>`
+    open_anchors: usize,
+    /// Number of HTML tables around the lexed text, for the cells of nested tables
+    table_depth: usize,
+    /// Text of the footnote definitions (`[^id]: note`) of the document, by label
+    footnotes: Rc<HashMap<String, String>>,
 }
 
 impl Lexer {
-    /// Creates a new lexer instance from input string
+    /// Creates a new lexer instance from input string.
+    ///
+    /// A leading UTF-8 byte order mark is dropped and Windows (`\r\n`) and old Mac (`\r`)
+    /// line endings are normalized to `\n`, so files saved by any editor tokenize alike
+    /// (see [`normalize_line_endings`]).
     pub fn new(input: String) -> Self {
         Lexer {
-            input: input.chars().collect(),
+            input: normalize_line_endings(&input).chars().collect(),
             position: 0,
+            open_anchors: 0,
+            table_depth: 0,
+            footnotes: Rc::new(HashMap::new()),
+        }
+    }
+
+    /// Creates a lexer for a part of the input, such as a table cell, that resolves
+    /// footnote references like this one.
+    fn sub_lexer(&self, input: String) -> Lexer {
+        Lexer {
+            footnotes: Rc::clone(&self.footnotes),
+            ..Lexer::new(input)
         }
     }
 
     /// Parses the entire input string into a sequence of tokens.
     /// Returns a Result containing either a Vec of parsed tokens or a LexerError.
+    ///
+    /// Footnote references (`[^id]`) become [`Token::FootnoteInline`] notes holding the
+    /// text of their definition (`[^id]: note`), numbered with the inline footnotes; a
+    /// label referenced twice gets a note each time. Definitions produce no tokens.
+    ///
+    /// A front matter block opening the document is not part of the content and is
+    /// skipped (see [`split_front_matter`]).
     pub fn parse(&mut self) -> Result<Vec<Token>, LexerError> {
+        let input: String = self.input.iter().collect();
+        if let (Some(_), content) = split_front_matter(&input) {
+            self.input = content.chars().collect();
+        }
+        self.footnotes = Rc::new(self.collect_footnote_definitions());
         self.parse_with_context(ParseContext::Root)
     }
 
@@ -322,6 +1071,13 @@ impl Lexer {
         let current_char = self.current_char();
         let is_line_start = self.is_at_line_start();
 
+        // The closing tag of an anchor that wrapped some text produces no token
+        if self.is_html_anchor_close() {
+            self.position += 4;
+            self.open_anchors -= 1;
+            return Ok(None);
+        }
+
         // Helper closures to check whether a certain token is allowed in this context.
         let allow_block_tokens = |context: ParseContext| -> bool {
             // Block tokens are allowed in Root, ListItem, BlockQuote.
@@ -331,11 +1087,48 @@ impl Lexer {
             )
         };
 
+        // Footnote definitions (`[^id]: note`) are rendered where they are referenced
+        if current_char == '[' && is_line_start && allow_block_tokens(ctx) {
+            if let Some((_, _, end)) = self.scan_footnote_definition(self.position) {
+                self.position = end;
+                return Ok(None);
+            }
+        }
+
+        // Reference link definitions (`[id]: url`) are not part of the text
+        if current_char == '['
+            && is_line_start
+            && allow_block_tokens(ctx)
+            && self.is_reference_definition_start()
+        {
+            self.read_until_newline();
+            if self.position < self.input.len() && self.current_char() == '\n' {
+                self.advance();
+            }
+            return Ok(None);
+        }
+
         let token = match current_char {
             '#' if is_line_start && allow_block_tokens(ctx) => self.parse_heading()?,
+            '*' if is_line_start
+                && allow_block_tokens(ctx)
+                && self.is_abbreviation_definition_start() =>
+            {
+                self.parse_abbreviation_definition()?
+            }
             '*' if is_line_start && allow_block_tokens(ctx) && self.is_list_marker('*') => {
                 self.parse_list_item(false, 0, ctx)?
             }
+            '~' if is_line_start && allow_block_tokens(ctx) && self.tilde_fence_len() >= 3 => {
+                self.parse_tilde_fence()?
+            }
+            ':' if is_line_start && allow_block_tokens(ctx) && self.is_tab_group_start() => {
+                self.parse_tab_group()?
+            }
+            #[cfg(feature = "rst-admonitions")]
+            '.' if is_line_start && allow_block_tokens(ctx) && self.admonition_kind().is_some() => {
+                self.parse_admonition()?
+            }
             // Check for math blocks before emphasis ($ must come before * and _)
             '$' => self.parse_math()?,
             '*' | '_' => self.parse_emphasis()?,
@@ -355,6 +1148,7 @@ impl Lexer {
                     self.parse_text(ctx)?
                 }
             }
+            '[' if self.scan_footnote_reference().is_some() => self.parse_footnote_reference()?,
             '[' => self.parse_link()?,
             '!' => {
                 // Check if this is a valid image start (! followed by [)
@@ -365,14 +1159,30 @@ impl Lexer {
                 }
             }
             '<' if self.is_html_comment_start() => self.parse_html_comment()?,
-            '\n' => self.parse_newline()?,
-            '|' if is_line_start => {
-                if self.is_table_start() {
-                    self.parse_table()?
-                } else {
-                    self.parse_text(ctx)?
-                }
+            '<' if is_line_start && allow_block_tokens(ctx) && self.scan_html_table().is_some() => {
+                self.parse_html_table()?
+            }
+            '<' if matches!(ctx, ParseContext::TableCell)
+                && self.table_depth <= MAX_HTML_TABLE_NESTING
+                && self.scan_html_table().is_some() =>
+            {
+                self.parse_html_table()?
+            }
+            '<' if matches!(ctx, ParseContext::TableCell)
+                && self.scan_html_line_break().is_some() =>
+            {
+                self.position += self.scan_html_line_break().unwrap_or(1);
+                Token::LineBreak
             }
+            '<' if self.scan_html_anchor().is_some() => self.parse_html_anchor()?,
+            '<' if self.scan_kbd().is_some() => self.parse_kbd()?,
+            '<' if self.scan_inline_html().is_some() => self.parse_inline_html()?,
+            '^' if self.scan_inline_footnote().is_some() => self.parse_inline_footnote()?,
+            '\n' => self.parse_newline()?,
+            '|' if is_line_start && self.is_table_start() => self.parse_table()?,
+            '|' if self.scan_spoiler().is_some() => self.parse_spoiler()?,
+            #[cfg(feature = "critic-markup")]
+            '{' if self.scan_critic().is_some() => self.parse_critic()?,
             _ => self.parse_text(ctx)?,
         };
 
@@ -391,6 +1201,27 @@ impl Lexer {
         Ok(Token::Heading(content, level))
     }
 
+    /// Parses an abbreviation definition line (*[ABBR]: expansion).
+    /// The trailing newline is consumed so the definition does not leave whitespace behind.
+    fn parse_abbreviation_definition(&mut self) -> Result<Token, LexerError> {
+        self.advance(); // skip '*'
+        self.advance(); // skip '['
+        let abbr = self.read_until_char(']');
+        self.advance(); // skip ']'
+        self.advance(); // skip ':'
+        self.skip_whitespace();
+        let expansion = self.read_until_newline();
+
+        if self.position < self.input.len() && self.current_char() == '\n' {
+            self.advance();
+        }
+
+        Ok(Token::AbbreviationDefinition(
+            abbr.trim().to_string(),
+            expansion.trim().to_string(),
+        ))
+    }
+
     /// Parses emphasis tokens (* or _) with support for multiple levels (1-3).
     /// Ensures proper matching of opening and closing delimiters.
     /// Follows CommonMark rules: underscore emphasis requires flanking whitespace/punctuation.
@@ -453,22 +1284,14 @@ impl Lexer {
     fn parse_code(&mut self) -> Result<Token, LexerError> {
         let start_backticks = self.count_backticks();
 
-        // Single backtick case
-        if start_backticks == 1 {
-            let mut content = String::new();
-
-            // Read until either a closing backtick or end of input
-            while self.position < self.input.len() {
-                let ch = self.current_char();
-                if ch == '`' {
-                    self.advance(); // skip closing backtick
-                    break;
-                }
-                content.push(ch);
-                self.advance();
-            }
-
-            return Ok(Token::Code(String::new(), content));
+        // A run of three or more backticks whose info string has no backtick opens a fence;
+        // anything else is an inline code span closed by a run of the same length
+        let info_has_backtick = self.input[self.position..]
+            .iter()
+            .take_while(|&&c| c != '\n')
+            .any(|&c| c == '`');
+        if start_backticks < 3 || info_has_backtick {
+            return Ok(self.parse_code_span(start_backticks));
         }
 
         // Multi-line code block case
@@ -481,6 +1304,11 @@ impl Lexer {
             if current_backticks == start_backticks {
                 break;
             }
+            // Shorter or longer backtick runs are part of the code
+            content.push_str(&"`".repeat(current_backticks));
+            if self.position >= self.input.len() {
+                break;
+            }
 
             content.push(self.current_char());
             self.advance();
@@ -499,6 +1327,186 @@ impl Lexer {
         ))
     }
 
+    /// Parses an inline code span after its opening run of `backticks` backticks.
+    ///
+    /// Following CommonMark, the span ends at the next run of exactly the same length, so
+    /// shorter runs (e.g. the single backtick in ``` `` a`b `` ```) are kept as content.
+    /// Line endings become spaces, and one leading and trailing space is stripped when both
+    /// are present. Without a closing run the backticks are literal text.
+    fn parse_code_span(&mut self, backticks: usize) -> Token {
+        let content_start = self.position;
+        let mut i = content_start;
+
+        while i < self.input.len() {
+            match self.input[i] {
+                '`' => {
+                    let run = self.input[i..].iter().take_while(|&&c| c == '`').count();
+                    if run == backticks {
+                        let raw: String = self.input[content_start..i].iter().collect();
+                        self.position = i + run;
+                        return Token::Code(String::new(), normalize_code_span(&raw));
+                    }
+                    i += run;
+                }
+                // Code spans do not continue past a blank line
+                '\n' if self.input.get(i + 1) == Some(&'\n') => break,
+                _ => i += 1,
+            }
+        }
+
+        Token::Text("`".repeat(backticks))
+    }
+
+    /// Parses a `~~~`-fenced code block with an optional language info string.
+    /// The block ends at a line holding at least as many tildes as the opening fence
+    /// (indented by at most three spaces), so backtick fences inside are kept verbatim.
+    fn parse_tilde_fence(&mut self) -> Result<Token, LexerError> {
+        let fence_len = self.tilde_fence_len();
+        self.position += fence_len;
+        self.skip_whitespace();
+        let language = self.read_until_newline();
+        if self.current_char() == '\n' {
+            self.advance();
+        }
+
+        let mut lines = Vec::new();
+        while self.position < self.input.len() {
+            let line = self.read_until_newline();
+            if is_closing_tilde_fence(&line, fence_len) {
+                break;
+            }
+            lines.push(line);
+            if self.current_char() == '\n' {
+                self.advance();
+            }
+        }
+
+        Ok(Token::Code(
+            language.trim().to_string(),
+            lines.join("\n").trim_end().to_string(),
+        ))
+    }
+
+    /// Checks whether the current line opens a `:::tabs` group
+    fn is_tab_group_start(&self) -> bool {
+        let line: String = self.input[self.position..]
+            .iter()
+            .take_while(|&&c| c != '\n')
+            .collect();
+        line.trim()
+            .strip_prefix(":::")
+            .is_some_and(|name| name.trim().eq_ignore_ascii_case("tabs"))
+    }
+
+    /// Parses a `:::tabs` group up to its closing `:::` line.
+    ///
+    /// Each tab starts with a `== Title` (or `@tab Title`) line and holds the block content
+    /// up to the next tab. Lines inside fenced code are kept verbatim, so a `:::` there does
+    /// not close the group. Content before the first title forms an untitled tab.
+    fn parse_tab_group(&mut self) -> Result<Token, LexerError> {
+        self.read_until_newline();
+
+        let mut tabs: Vec<(String, Vec<String>)> = Vec::new();
+        let mut fence: Option<(char, usize)> = None;
+        while self.position < self.input.len() {
+            self.advance(); // newline ending the previous line
+            let line = self.read_until_newline();
+            let trimmed = line.trim();
+
+            if let Some((fence_char, fence_len)) = fence {
+                if trimmed.len() >= fence_len && trimmed.chars().all(|c| c == fence_char) {
+                    fence = None;
+                }
+            } else if trimmed == ":::" {
+                break;
+            } else if let Some(title) = tab_title(trimmed) {
+                tabs.push((title, Vec::new()));
+                continue;
+            } else {
+                fence = fence_marker(trimmed);
+            }
+
+            match tabs.last_mut() {
+                Some((_, lines)) => lines.push(line),
+                None if !trimmed.is_empty() => tabs.push((String::new(), vec![line])),
+                None => {}
+            }
+        }
+
+        let mut group = Vec::new();
+        for (title, lines) in tabs {
+            let mut tab_lexer = self.sub_lexer(lines.join("\n"));
+            group.push((title, tab_lexer.parse_with_context(ParseContext::Root)?));
+        }
+        Ok(Token::TabGroup(group))
+    }
+
+    /// Returns the lowercased kind of the reStructuredText admonition opened by the current
+    /// line, such as `note` for `.. note::`
+    #[cfg(feature = "rst-admonitions")]
+    fn admonition_kind(&self) -> Option<String> {
+        let line: String = self.input[self.position..]
+            .iter()
+            .take_while(|&&c| c != '\n')
+            .collect();
+        let (name, _) = line.strip_prefix(".. ")?.split_once("::")?;
+        let kind = name.trim().to_ascii_lowercase();
+        RST_ADMONITIONS.contains(&kind.as_str()).then_some(kind)
+    }
+
+    /// Parses a reStructuredText admonition. Text after the `::` starts its content, which
+    /// continues over the following indented lines, blank lines included, up to the first
+    /// line that is not indented.
+    #[cfg(feature = "rst-admonitions")]
+    fn parse_admonition(&mut self) -> Result<Token, LexerError> {
+        let kind = self
+            .admonition_kind()
+            .ok_or_else(|| LexerError::UnknownToken("Expected admonition".to_string()))?;
+        let first_line = self.read_until_newline();
+        let first_line = first_line
+            .split_once("::")
+            .map_or("", |(_, rest)| rest.trim());
+
+        let mut lines = Vec::new();
+        while self.position < self.input.len() {
+            let line_end = self.position;
+            self.advance(); // newline ending the previous line
+            let line = self.read_until_newline();
+            if !line.trim().is_empty() && !line.starts_with([' ', '\t']) {
+                self.position = line_end;
+                break;
+            }
+            lines.push(line);
+        }
+        while lines.last().is_some_and(|line| line.trim().is_empty()) {
+            lines.pop();
+        }
+
+        let indent = lines
+            .iter()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.len() - line.trim_start().len())
+            .min()
+            .unwrap_or(0);
+        let mut body = Vec::new();
+        if !first_line.is_empty() {
+            body.push(first_line);
+        }
+        body.extend(lines.iter().map(|line| line.get(indent..).unwrap_or("")));
+
+        let mut body_lexer = self.sub_lexer(body.join("\n"));
+        let content = body_lexer.parse_with_context(ParseContext::Root)?;
+        Ok(Token::Admonition { kind, content })
+    }
+
+    /// Counts consecutive tildes at the current position without consuming them
+    fn tilde_fence_len(&self) -> usize {
+        self.input[self.position..]
+            .iter()
+            .take_while(|&&c| c == '~')
+            .count()
+    }
+
     /// Helper method to count consecutive backticks
     fn count_backticks(&mut self) -> usize {
         let mut count = 0;
@@ -747,7 +1755,7 @@ impl Lexer {
     /// Parses a newline token or line break.
     /// - LineBreak (2+ spaces before newline): explicit line break within paragraph
     /// - Newline (double newline / empty line): paragraph separator
-    /// - No token returned for single newline: treated as whitespace
+    /// - SoftBreak (single newline): rendered as a space or a line break per `[text] soft_break`
     fn parse_newline(&mut self) -> Result<Token, LexerError> {
         // Check if we have trailing spaces before this newline (line break)
         let mut space_count = 0;
@@ -789,11 +1797,9 @@ impl Lexer {
             // Double newline = paragraph break
             Ok(Token::Newline)
         } else {
-            // Single newline = treat as whitespace, don't create token
-            // The previous text token and next text token will be joined by space
-            // Actually, we need to return something to continue parsing
-            // Return a Text token with a space to maintain word separation
-            Ok(Token::Text(" ".to_string()))
+            // Single newline = soft break; the renderer joins the lines with a space
+            // (CommonMark) or starts a new line, depending on `[text] soft_break`
+            Ok(Token::SoftBreak)
         }
     }
 
@@ -855,6 +1861,431 @@ impl Lexer {
         }
     }
 
+    /// Parses an HTML anchor tag (`<a id="ref">`, `<a name="ref">` or `<a id="ref"/>`).
+    /// An immediately following `</a>` is consumed; otherwise the closing tag is skipped
+    /// when it is reached, so the wrapped text is rendered normally.
+    fn parse_html_anchor(&mut self) -> Result<Token, LexerError> {
+        let (name, len, self_closing) = self
+            .scan_html_anchor()
+            .ok_or_else(|| LexerError::UnknownToken("Expected HTML anchor".to_string()))?;
+        self.position += len;
+
+        if !self_closing {
+            self.open_anchors += 1;
+            if self.is_html_anchor_close() {
+                self.position += 4;
+                self.open_anchors -= 1;
+            }
+        }
+
+        Ok(Token::Anchor(name))
+    }
+
+    /// Checks whether an `<a>` tag carrying an `id` or `name` attribute starts at the
+    /// current position. Returns the anchor name, the tag length and whether the tag
+    /// is self-closing.
+    fn scan_html_anchor(&self) -> Option<(String, usize, bool)> {
+        let rest = &self.input[self.position..];
+        if rest.len() < 3
+            || rest[0] != '<'
+            || !rest[1].eq_ignore_ascii_case(&'a')
+            || !rest[2].is_whitespace()
+        {
+            return None;
+        }
+
+        let end = rest.iter().position(|&c| c == '>' || c == '\n')?;
+        if rest[end] != '>' {
+            return None;
+        }
+
+        let tag: String = rest[2..end].iter().collect();
+        let (attributes, self_closing) = match tag.trim_end().strip_suffix('/') {
+            Some(attributes) => (attributes.to_string(), true),
+            None => (tag, false),
+        };
+        let name =
+            html_attribute(&attributes, "id").or_else(|| html_attribute(&attributes, "name"))?;
+
+        Some((name, end + 1, self_closing))
+    }
+
+    /// Parses a `<kbd>key</kbd>` keyboard key.
+    fn parse_kbd(&mut self) -> Result<Token, LexerError> {
+        let (key, len) = self
+            .scan_kbd()
+            .ok_or_else(|| LexerError::UnknownToken("Expected <kbd> element".to_string()))?;
+        self.position += len;
+        Ok(Token::Kbd(key))
+    }
+
+    /// Checks whether a `<br>`, `<br/>` or `<br />` tag starts at the current position and
+    /// returns its length.
+    fn scan_html_line_break(&self) -> Option<usize> {
+        let rest = &self.input[self.position..];
+        let is_br = rest.len() >= 3
+            && rest[..3]
+                .iter()
+                .zip("<br".chars())
+                .all(|(a, b)| a.eq_ignore_ascii_case(&b));
+        if !is_br {
+            return None;
+        }
+        let mut end = 3;
+        while end < rest.len() && rest[end] == ' ' {
+            end += 1;
+        }
+        if rest.get(end) == Some(&'/') {
+            end += 1;
+        }
+        (rest.get(end) == Some(&'>')).then_some(end + 1)
+    }
+
+    /// Checks whether a `<kbd>` element closed on the same line starts at the current
+    /// position. Returns the trimmed key label and the length of the whole element.
+    fn scan_kbd(&self) -> Option<(String, usize)> {
+        const OPEN: &str = "<kbd>";
+        const CLOSE: &str = "</kbd>";
+
+        let rest = &self.input[self.position..];
+        let starts_with = |offset: usize, tag: &str| {
+            rest.len() >= offset + tag.len()
+                && rest[offset..offset + tag.len()]
+                    .iter()
+                    .zip(tag.chars())
+                    .all(|(a, b)| a.eq_ignore_ascii_case(&b))
+        };
+        if !starts_with(0, OPEN) {
+            return None;
+        }
+
+        let mut end = OPEN.len();
+        while end < rest.len() && rest[end] != '\n' {
+            if starts_with(end, CLOSE) {
+                let key: String = rest[OPEN.len()..end].iter().collect();
+                let key = key.trim();
+                if key.is_empty() {
+                    return None;
+                }
+                return Some((key.to_string(), end + CLOSE.len()));
+            }
+            end += 1;
+        }
+        None
+    }
+
+    /// Parses a `<mark>`, `<ins>` or `<del>` element. Its content may contain inline markup.
+    fn parse_inline_html(&mut self) -> Result<Token, LexerError> {
+        let (tag, inner, len) = self
+            .scan_inline_html()
+            .ok_or_else(|| LexerError::UnknownToken("Expected inline HTML element".to_string()))?;
+        self.position += len;
+
+        let mut inner_lexer = self.sub_lexer(inner);
+        let content = inner_lexer.parse_with_context(ParseContext::Inline)?;
+        Ok(match tag {
+            "mark" => Token::Mark(content),
+            "ins" => Token::Ins(content),
+            _ => Token::Del(content),
+        })
+    }
+
+    /// Checks whether a `<mark>`, `<ins>` or `<del>` element closed on the same line starts
+    /// at the current position. Returns the lowercase tag name, the raw content and the
+    /// length of the whole element.
+    fn scan_inline_html(&self) -> Option<(&'static str, String, usize)> {
+        let rest = &self.input[self.position..];
+        let starts_with = |offset: usize, tag: &str| {
+            rest.len() >= offset + tag.len()
+                && rest[offset..offset + tag.len()]
+                    .iter()
+                    .zip(tag.chars())
+                    .all(|(a, b)| a.eq_ignore_ascii_case(&b))
+        };
+        let tag = ["mark", "ins", "del"]
+            .into_iter()
+            .find(|tag| starts_with(0, &format!("<{}>", tag)))?;
+
+        let open = tag.len() + 2;
+        let close = format!("</{}>", tag);
+        let mut end = open;
+        while end < rest.len() && rest[end] != '\n' {
+            if starts_with(end, &close) {
+                let content: String = rest[open..end].iter().collect();
+                if content.trim().is_empty() {
+                    return None;
+                }
+                return Some((tag, content, end + close.len()));
+            }
+            end += 1;
+        }
+        None
+    }
+
+    /// Collects the footnote definitions opening a line outside fenced code blocks. The
+    /// first definition of a label wins.
+    fn collect_footnote_definitions(&self) -> HashMap<String, String> {
+        let mut definitions = HashMap::new();
+        let mut in_fence = false;
+        let mut pos = 0;
+        while pos < self.input.len() {
+            let line_end = self.input[pos..]
+                .iter()
+                .position(|&c| c == '\n')
+                .map_or(self.input.len(), |end| pos + end);
+            let line: String = self.input[pos..line_end].iter().collect();
+            let line = line.trim_start();
+            if line.starts_with("```") || line.starts_with("~~~") {
+                in_fence = !in_fence;
+            } else if !in_fence {
+                if let Some((label, text, end)) = self.scan_footnote_definition(pos) {
+                    definitions.entry(label).or_insert(text);
+                    pos = end;
+                    continue;
+                }
+            }
+            pos = line_end + 1;
+        }
+        definitions
+    }
+
+    /// Checks whether a footnote definition (`[^id]: note`) starts at `pos` and returns
+    /// its label, its text and the position just past it. Indented lines following the
+    /// definition continue its text.
+    fn scan_footnote_definition(&self, pos: usize) -> Option<(String, String, usize)> {
+        let (label, mut end) = self.scan_footnote_label(pos)?;
+        if self.input.get(end) != Some(&':') {
+            return None;
+        }
+        end += 1;
+        let mut lines = Vec::new();
+        loop {
+            let line_end = self.input[end..]
+                .iter()
+                .position(|&c| c == '\n')
+                .map_or(self.input.len(), |offset| end + offset);
+            let line: String = self.input[end..line_end].iter().collect();
+            lines.push(line.trim().to_string());
+            end = (line_end + 1).min(self.input.len());
+            let next = &self.input[end..];
+            let continues = next.first().is_some_and(|&c| c == ' ' || c == '\t')
+                && next
+                    .iter()
+                    .take_while(|&&c| c != '\n')
+                    .any(|c| !c.is_whitespace());
+            if !continues {
+                break;
+            }
+        }
+        Some((label, lines.join("\n"), end))
+    }
+
+    /// Checks whether a footnote label (`[^id]`) starts at `pos` and returns the label and
+    /// the position just past its closing bracket.
+    fn scan_footnote_label(&self, pos: usize) -> Option<(String, usize)> {
+        if self.input.get(pos) != Some(&'[') || self.input.get(pos + 1) != Some(&'^') {
+            return None;
+        }
+        let start = pos + 2;
+        let mut end = start;
+        while end < self.input.len() && !matches!(self.input[end], ']' | '[') {
+            if self.input[end].is_whitespace() {
+                return None;
+            }
+            end += 1;
+        }
+        if end == start || self.input.get(end) != Some(&']') {
+            return None;
+        }
+        Some((self.input[start..end].iter().collect(), end + 1))
+    }
+
+    /// Checks whether a reference to a defined footnote starts at the current position
+    /// and returns its label and the position just past it.
+    fn scan_footnote_reference(&self) -> Option<(String, usize)> {
+        self.scan_footnote_label(self.position)
+            .filter(|(label, _)| self.footnotes.contains_key(label))
+    }
+
+    /// Parses a footnote reference (`[^id]`) into the note of its definition. Notes do
+    /// not resolve the references they contain.
+    fn parse_footnote_reference(&mut self) -> Result<Token, LexerError> {
+        let (label, end) = self
+            .scan_footnote_reference()
+            .ok_or_else(|| LexerError::UnknownToken("Expected footnote reference".to_string()))?;
+        self.position = end;
+
+        let mut note_lexer = Lexer::new(self.footnotes[&label].clone());
+        let content = note_lexer.parse_with_context(ParseContext::Inline)?;
+        Ok(Token::FootnoteInline(content))
+    }
+
+    /// Parses an inline footnote (`^[note text]`). The note may contain inline markup,
+    /// including nested brackets such as links.
+    fn parse_inline_footnote(&mut self) -> Result<Token, LexerError> {
+        let end = self
+            .scan_inline_footnote()
+            .ok_or_else(|| LexerError::UnknownToken("Expected inline footnote".to_string()))?;
+        let note: String = self.input[self.position + 2..end - 1].iter().collect();
+        self.position = end;
+
+        let mut note_lexer = self.sub_lexer(note);
+        let content = note_lexer.parse_with_context(ParseContext::Inline)?;
+        Ok(Token::FootnoteInline(content))
+    }
+
+    /// Checks whether an inline footnote starts at the current position and returns the
+    /// position just past its closing bracket. The note must not be empty or span a
+    /// blank line.
+    fn scan_inline_footnote(&self) -> Option<usize> {
+        let rest = &self.input[self.position..];
+        if rest.len() < 3 || rest[0] != '^' || rest[1] != '[' {
+            return None;
+        }
+
+        let mut depth = 0;
+        let mut i = 1;
+        while i < rest.len() {
+            match rest[i] {
+                // Skip the escaped character so `\]` does not close the note
+                '\\' => i += 1,
+                '[' => depth += 1,
+                ']' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        let note: String = rest[2..i].iter().collect();
+                        return (!note.trim().is_empty()).then_some(self.position + i + 1);
+                    }
+                }
+                '\n' if rest.get(i + 1) == Some(&'\n') => return None,
+                _ => {}
+            }
+            i += 1;
+        }
+        None
+    }
+
+    /// Parses a spoiler (`||text||`) into its inline content.
+    fn parse_spoiler(&mut self) -> Result<Token, LexerError> {
+        let end = self
+            .scan_spoiler()
+            .ok_or_else(|| LexerError::UnknownToken("Expected spoiler".to_string()))?;
+        let text: String = self.input[self.position + 2..end - 2].iter().collect();
+        self.position = end;
+
+        let mut spoiler_lexer = self.sub_lexer(text);
+        let content = spoiler_lexer.parse_with_context(ParseContext::Inline)?;
+        Ok(Token::Spoiler(content))
+    }
+
+    /// Checks whether a spoiler starts at the current position and returns the position
+    /// just past its closing `||`. Like emphasis, the text must not start or end with
+    /// whitespace, and it must close on the same line.
+    fn scan_spoiler(&self) -> Option<usize> {
+        let rest = &self.input[self.position..];
+        if rest.len() < 5 || rest[0] != '|' || rest[1] != '|' {
+            return None;
+        }
+        if rest[2].is_whitespace() || rest[2] == '|' {
+            return None;
+        }
+        let mut i = 3;
+        while i + 1 < rest.len() && rest[i] != '\n' {
+            if rest[i] == '|' && rest[i + 1] == '|' {
+                return (!rest[i - 1].is_whitespace()).then_some(self.position + i + 2);
+            }
+            i += 1;
+        }
+        None
+    }
+
+    /// Parses a CriticMarkup change (`{++added++}`, `{--removed--}`, `{==highlighted==}` or
+    /// `{~~old~>new~~}`) into its inline content.
+    #[cfg(feature = "critic-markup")]
+    fn parse_critic(&mut self) -> Result<Token, LexerError> {
+        let (change, end) = self
+            .scan_critic()
+            .ok_or_else(|| LexerError::UnknownToken("Expected CriticMarkup".to_string()))?;
+        let text: String = self.input[self.position + 3..end - 3].iter().collect();
+        self.position = end;
+
+        let inline = |text: &str| {
+            self.sub_lexer(text.to_string())
+                .parse_with_context(ParseContext::Inline)
+        };
+        let content = match change {
+            CriticMarkup::Substitution => {
+                let (old, new) = text.split_once("~>").unwrap_or((&text, ""));
+                vec![
+                    Token::Critic(CriticMarkup::Deletion, inline(old)?),
+                    Token::Critic(CriticMarkup::Addition, inline(new)?),
+                ]
+            }
+            _ => inline(&text)?,
+        };
+        Ok(Token::Critic(change, content))
+    }
+
+    /// Checks whether a CriticMarkup change starts at the current position and returns its
+    /// kind and the position just past its closing mark. Like spoilers, it must close on
+    /// the same line; a substitution must contain `~>`.
+    #[cfg(feature = "critic-markup")]
+    fn scan_critic(&self) -> Option<(CriticMarkup, usize)> {
+        let rest = &self.input[self.position..];
+        if rest.len() < 7 || rest[0] != '{' || rest[1] != rest[2] {
+            return None;
+        }
+        let (mark, change) = match rest[1] {
+            '+' => ('+', CriticMarkup::Addition),
+            '-' => ('-', CriticMarkup::Deletion),
+            '=' => ('=', CriticMarkup::Highlight),
+            '~' => ('~', CriticMarkup::Substitution),
+            _ => return None,
+        };
+        let mut i = 3;
+        while i + 2 < rest.len() && rest[i] != '\n' {
+            if rest[i] == mark && rest[i + 1] == mark && rest[i + 2] == '}' {
+                if i == 3 {
+                    return None;
+                }
+                let is_substitution = rest[3..i].windows(2).any(|w| matches!(w, ['~', '>']));
+                if change == CriticMarkup::Substitution && !is_substitution {
+                    return None;
+                }
+                return Some((change, self.position + i + 3));
+            }
+            i += 1;
+        }
+        None
+    }
+
+    /// Checks whether a task list checkbox (`[ ]`, `[x]` or `[X]` followed by whitespace)
+    /// starts at the current position and returns whether it is checked.
+    fn task_checkbox(&self) -> Option<bool> {
+        let rest = &self.input[self.position..];
+        if rest.len() < 3 || rest[0] != '[' || rest[2] != ']' {
+            return None;
+        }
+        if !rest.get(3).map_or(true, |c| c.is_whitespace()) {
+            return None;
+        }
+        match rest[1] {
+            ' ' => Some(false),
+            'x' | 'X' => Some(true),
+            _ => None,
+        }
+    }
+
+    /// Checks if current position is at the `</a>` closing an open anchor
+    fn is_html_anchor_close(&self) -> bool {
+        self.open_anchors > 0
+            && self.input[self.position..]
+                .iter()
+                .take(4)
+                .collect::<String>()
+                .eq_ignore_ascii_case("</a>")
+    }
+
     /// Checks if current position is at the start of a line
     fn is_at_line_start(&self) -> bool {
         self.position == 0 || self.input.get(self.position - 1) == Some(&'\n')
@@ -926,8 +2357,26 @@ impl Lexer {
                 }
             }
 
+            '^' => self.scan_inline_footnote().is_some(),
+
+            '|' => self.scan_spoiler().is_some(),
+
+            #[cfg(feature = "critic-markup")]
+            '{' => self.scan_critic().is_some(),
+
             '<' => {
-                if matches!(ctx, ParseContext::Root) {
+                if self.scan_html_anchor().is_some()
+                    || self.is_html_anchor_close()
+                    || self.scan_kbd().is_some()
+                    || self.scan_inline_html().is_some()
+                    || (matches!(ctx, ParseContext::TableCell)
+                        && self.scan_html_line_break().is_some())
+                    || (matches!(ctx, ParseContext::TableCell)
+                        && self.table_depth <= MAX_HTML_TABLE_NESTING
+                        && self.scan_html_table().is_some())
+                {
+                    true
+                } else if matches!(ctx, ParseContext::Root) {
                     self.is_html_comment_start()
                 } else {
                     false
@@ -991,7 +2440,11 @@ impl Lexer {
         None
     }
 
-    /// Parses a list item, handling both ordered and unordered types
+    /// Parses a list item, handling both ordered and unordered types.
+    ///
+    /// Lines indented past the marker continue the item: directly following lines join
+    /// its paragraph with a `SoftBreak`, and text after blank lines starts a new paragraph
+    /// of the item, separated by a `Newline`.
     fn parse_list_item(
         &mut self,
         ordered: bool,
@@ -1015,6 +2468,11 @@ impl Lexer {
         self.skip_whitespace();
 
         let mut content = Vec::new();
+        if let Some(checked) = self.task_checkbox() {
+            self.position += 3;
+            self.skip_whitespace();
+            content.push(Token::TaskCheckbox(checked));
+        }
         while self.position < self.input.len() && self.current_char() != '\n' {
             if let Some(token) = self.next_token(ParseContext::ListItem)? {
                 content.push(token);
@@ -1027,6 +2485,18 @@ impl Lexer {
         }
 
         while self.position < self.input.len() {
+            let mut paragraph_break = false;
+            if let Some(next_line) = self.next_non_blank_line() {
+                // Blank lines end the item unless more indented content follows them
+                let line_start = self.position;
+                self.position = next_line;
+                if self.get_current_indent() <= indent_level {
+                    self.position = line_start;
+                    break;
+                }
+                paragraph_break = true;
+            }
+
             let current_indent = self.get_current_indent();
             if current_indent <= indent_level {
                 // Back to same or lower indentation level, exit nested parsing
@@ -1040,19 +2510,29 @@ impl Lexer {
                         content.push(self.parse_list_item(false, current_indent, parent_ctx)?);
                     }
                 }
-                '*' => {
-                    if self.is_list_marker('*') {
-                        content.push(self.parse_list_item(false, current_indent, parent_ctx)?);
+                '*' if self.is_list_marker('*') => {
+                    content.push(self.parse_list_item(false, current_indent, parent_ctx)?);
+                }
+                '0'..='9' if self.check_ordered_list_marker().is_some() => {
+                    content.push(self.parse_list_item(true, current_indent, parent_ctx)?);
+                }
+                // Block elements are left to the main lexer
+                '`' | '~' | '>' | '|' | '#' => break,
+                _ => {
+                    content.push(if paragraph_break {
+                        Token::Newline
                     } else {
-                        break;
+                        Token::SoftBreak
+                    });
+                    while self.position < self.input.len() && self.current_char() != '\n' {
+                        if let Some(token) = self.next_token(ParseContext::ListItem)? {
+                            content.push(token);
+                        }
                     }
-                }
-                '0'..='9' => {
-                    if self.check_ordered_list_marker().is_some() {
-                        content.push(self.parse_list_item(true, current_indent, parent_ctx)?);
+                    if self.position < self.input.len() && self.current_char() == '\n' {
+                        self.advance();
                     }
                 }
-                _ => break,
             }
         }
 
@@ -1063,6 +2543,69 @@ impl Lexer {
         })
     }
 
+    /// Checks whether an HTML `<table>` element starts at the current position and
+    /// returns its length in characters, up to and including `</table>`.
+    fn scan_html_table(&self) -> Option<usize> {
+        let rest: String = self.input[self.position..].iter().collect();
+        let lower = rest.to_ascii_lowercase();
+        let after = lower.strip_prefix("<table")?;
+        if !after.starts_with(|c: char| c == '>' || c.is_whitespace()) {
+            return None;
+        }
+        let end = html_table_end(&lower, 0)?;
+        Some(rest[..end].chars().count())
+    }
+
+    /// Parses an HTML table into a [`Token::Table`]. The first row is the header row and
+    /// the `align` attributes of its cells set the column alignments. A cell spanning
+    /// several columns with `colspan` is followed by empty cells, since table layouts
+    /// cannot merge cells. A table inside a cell becomes a [`Token::Table`] among the cell
+    /// tokens, up to `MAX_HTML_TABLE_NESTING` levels deep. Other tags and attributes are
+    /// ignored.
+    fn parse_html_table(&mut self) -> Result<Token, LexerError> {
+        let len = self
+            .scan_html_table()
+            .ok_or_else(|| LexerError::UnknownToken("Expected <table> element".to_string()))?;
+        let html: String = self.input[self.position..self.position + len]
+            .iter()
+            .collect();
+        self.position += len;
+
+        let mut rows = Vec::new();
+        let mut aligns = Vec::new();
+        for (row_index, cells) in html_table_rows(&html).into_iter().enumerate() {
+            let mut row = Vec::new();
+            for cell in cells {
+                let content = cell
+                    .content
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let mut cell_lexer = self.sub_lexer(content);
+                cell_lexer.table_depth = self.table_depth + 1;
+                row.push(cell_lexer.parse_with_context(ParseContext::TableCell)?);
+                row.extend((1..cell.colspan).map(|_| Vec::new()));
+                if row_index == 0 {
+                    aligns.extend(std::iter::repeat(cell.align).take(cell.colspan));
+                }
+            }
+            rows.push(row);
+        }
+
+        let columns = rows.iter().map(Vec::len).max().unwrap_or(0).max(1);
+        for row in &mut rows {
+            row.resize(columns, Vec::new());
+        }
+        aligns.resize(columns, Alignment::Left);
+        let mut rows = rows.into_iter();
+        let headers = rows.next().unwrap_or_else(|| vec![Vec::new(); columns]);
+        Ok(Token::Table {
+            headers,
+            aligns,
+            rows: rows.collect(),
+        })
+    }
+
     /// Checks if the current posisiton is the start of a table
     fn is_table_start(&self) -> bool {
         let rest: String = self.input[self.position..].iter().collect();
@@ -1112,7 +2655,7 @@ impl Lexer {
         // Convert header strings to token vectors
         let mut headers = Vec::new();
         for cell in header_cells {
-            let mut cell_lexer = Lexer::new(cell);
+            let mut cell_lexer = self.sub_lexer(cell);
             let parsed = cell_lexer.parse_with_context(ParseContext::TableCell)?;
             headers.push(parsed);
         }
@@ -1134,7 +2677,7 @@ impl Lexer {
             let mut row_tokens = Vec::new();
             for cell in cell_texts {
                 // FIX: large unbreakable words don't fit in cells
-                let mut cell_lexer = Lexer::new(cell);
+                let mut cell_lexer = self.sub_lexer(cell);
                 let parsed = cell_lexer.parse_with_context(ParseContext::TableCell)?;
                 row_tokens.push(parsed);
             }
@@ -1152,6 +2695,24 @@ impl Lexer {
         })
     }
 
+    /// Returns the start of the next line with content when the current line is blank, or
+    /// `None` when the current line has content or only blank lines remain.
+    fn next_non_blank_line(&self) -> Option<usize> {
+        let mut pos = self.position;
+        let mut line_start = None;
+        while pos < self.input.len() {
+            match self.input[pos] {
+                ' ' | '\t' => pos += 1,
+                '\n' => {
+                    pos += 1;
+                    line_start = Some(pos);
+                }
+                _ => return line_start,
+            }
+        }
+        None
+    }
+
     /// Gets the current line's indentation level
     fn get_current_indent(&self) -> usize {
         let mut count = 0;
@@ -1168,20 +2729,320 @@ impl Lexer {
         count
     }
 
-    /// Checks if the given character at the current position is a list marker
-    /// A list marker is followed by whitespace (space or tab)
-    fn is_list_marker(&self, marker: char) -> bool {
-        if self.current_char() != marker {
+    /// Checks if the current position starts an abbreviation definition (*[ABBR]: ...)
+    /// The abbreviation must be non-empty and closed on the same line.
+    fn is_abbreviation_definition_start(&self) -> bool {
+        if self.current_char() != '*' || self.input.get(self.position + 1) != Some(&'[') {
             return false;
         }
 
-        if self.position + 1 < self.input.len() {
-            let next_char = self.input[self.position + 1];
-            next_char == ' ' || next_char == '\t'
-        } else {
-            false
+        let mut pos = self.position + 2;
+        while pos < self.input.len() && self.input[pos] != ']' && self.input[pos] != '\n' {
+            pos += 1;
         }
+
+        pos > self.position + 2
+            && self.input.get(pos) == Some(&']')
+            && self.input.get(pos + 1) == Some(&':')
     }
+
+    /// Checks if the current position starts a reference link definition (`[id]: url`).
+    /// The label must be non-empty and closed on the same line, and a destination must
+    /// follow the colon. Footnote labels (`[^1]:`) are not definitions.
+    fn is_reference_definition_start(&self) -> bool {
+        if self.current_char() != '[' || self.input.get(self.position + 1) == Some(&'^') {
+            return false;
+        }
+
+        let mut pos = self.position + 1;
+        while pos < self.input.len() && !matches!(self.input[pos], ']' | '[' | '\n') {
+            pos += 1;
+        }
+        if pos == self.position + 1
+            || self.input.get(pos) != Some(&']')
+            || self.input.get(pos + 1) != Some(&':')
+        {
+            return false;
+        }
+
+        self.input[pos + 2..]
+            .iter()
+            .take_while(|&&c| c != '\n')
+            .any(|c| !c.is_whitespace())
+    }
+
+    /// Checks if the given character at the current position is a list marker
+    /// A list marker is followed by whitespace (space or tab)
+    fn is_list_marker(&self, marker: char) -> bool {
+        if self.current_char() != marker {
+            return false;
+        }
+
+        if self.position + 1 < self.input.len() {
+            let next_char = self.input[self.position + 1];
+            next_char == ' ' || next_char == '\t'
+        } else {
+            false
+        }
+    }
+}
+
+/// Drops a leading UTF-8 byte order mark and turns Windows (`\r\n`) and old Mac (`\r`)
+/// line endings into `\n`.
+pub(crate) fn normalize_line_endings(input: &str) -> String {
+    let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+    input.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+/// Splits a front matter block off the start of a document and returns its body along
+/// with the content that follows.
+///
+/// The block opens with a `---` line and closes with a `---` or `...` line, and holds
+/// `key: value` lines, indented continuation lines, list items and comments. Input that
+/// does not open with such a block is returned whole.
+///
+/// # Examples
+/// ```rust
+/// use markdown2pdf::markdown::split_front_matter;
+///
+/// let (front_matter, content) = split_front_matter("---\ntitle: Report\n---\n# Report\n");
+/// assert_eq!(front_matter, Some("title: Report\n"));
+/// assert_eq!(content, "# Report\n");
+/// ```
+pub fn split_front_matter(input: &str) -> (Option<&str>, &str) {
+    let Some(body) = input.strip_prefix("---\n") else {
+        return (None, input);
+    };
+    let mut offset = 0;
+    for line in body.split_inclusive('\n') {
+        let fence = line.trim_end();
+        if fence == "---" || fence == "..." {
+            if offset == 0 {
+                // An empty block is a horizontal rule followed by another
+                return (None, input);
+            }
+            return (Some(&body[..offset]), &body[offset + line.len()..]);
+        }
+        let is_entry = line.trim().is_empty()
+            || line.starts_with([' ', '\t', '#', '-'])
+            || line
+                .split_once(':')
+                .is_some_and(|(key, _)| !key.is_empty() && !key.contains(char::is_whitespace));
+        if !is_entry {
+            return (None, input);
+        }
+        offset += line.len();
+    }
+    (None, input)
+}
+
+/// Normalizes the content of an inline code span as CommonMark specifies
+fn normalize_code_span(raw: &str) -> String {
+    let content = raw.replace('\n', " ");
+    if content.len() >= 2
+        && content.starts_with(' ')
+        && content.ends_with(' ')
+        && !content.chars().all(|c| c == ' ')
+    {
+        content[1..content.len() - 1].to_string()
+    } else {
+        content
+    }
+}
+
+/// Returns the fence character and length when a trimmed line opens a code fence
+fn fence_marker(line: &str) -> Option<(char, usize)> {
+    let first = line.chars().next().filter(|&c| c == '`' || c == '~')?;
+    let len = line.chars().take_while(|&c| c == first).count();
+    (len >= 3).then_some((first, len))
+}
+
+/// Admonition directives of reStructuredText recognized with the `rst-admonitions` feature
+#[cfg(feature = "rst-admonitions")]
+const RST_ADMONITIONS: [&str; 9] = [
+    "attention",
+    "caution",
+    "danger",
+    "error",
+    "hint",
+    "important",
+    "note",
+    "tip",
+    "warning",
+];
+
+/// Returns the title of a `== Title` or `@tab Title` line starting a tab
+fn tab_title(line: &str) -> Option<String> {
+    let title = line
+        .strip_prefix("== ")
+        .or_else(|| line.strip_prefix("@tab "))?
+        .trim();
+    (!title.is_empty()).then(|| title.to_string())
+}
+
+/// Checks whether a line closes a tilde fence opened with `fence_len` tildes
+fn is_closing_tilde_fence(line: &str, fence_len: usize) -> bool {
+    let trimmed = line.trim_start_matches(' ');
+    line.len() - trimmed.len() <= 3
+        && trimmed.chars().take_while(|&c| c == '~').count() >= fence_len
+        && trimmed.trim_start_matches('~').trim().is_empty()
+}
+
+/// Upper bound for `colspan`, so a typo cannot allocate thousands of columns.
+const MAX_HTML_COLSPAN: usize = 64;
+
+/// Levels of HTML tables nested in table cells that become tables; deeper ones are left
+/// as text.
+const MAX_HTML_TABLE_NESTING: usize = 2;
+
+/// A `<td>` or `<th>` cell of an HTML table.
+struct HtmlTableCell {
+    content: String,
+    colspan: usize,
+    align: Alignment,
+}
+
+/// Splits the HTML of a table into rows of cells, skipping rows without cells.
+fn html_table_rows(html: &str) -> Vec<Vec<HtmlTableCell>> {
+    let mut lower = html.to_ascii_lowercase();
+    // The rows and cells of nested tables belong to them: blank their tags out of the
+    // search text, keeping the offsets into `html`
+    if let Some(outer) = find_html_tag(&lower, "table", 0) {
+        let mut pos = outer + "<table".len();
+        while let Some(start) = find_html_tag(&lower, "table", pos) {
+            let end = html_table_end(&lower, start).unwrap_or(lower.len());
+            lower.replace_range(start..end, &" ".repeat(end - start));
+            pos = end;
+        }
+    }
+    let mut rows = Vec::new();
+    let mut pos = 0;
+    while let Some(start) = find_html_tag(&lower, "tr", pos) {
+        let end = lower[start..]
+            .find("</tr>")
+            .map(|e| start + e)
+            .or_else(|| find_html_tag(&lower, "tr", start + 3))
+            .unwrap_or(lower.len());
+
+        let mut cells = Vec::new();
+        let mut cell_pos = start;
+        loop {
+            let td = find_html_tag(&lower, "td", cell_pos).filter(|&i| i < end);
+            let th = find_html_tag(&lower, "th", cell_pos).filter(|&i| i < end);
+            let Some(cell_start) = td.into_iter().chain(th).min() else {
+                break;
+            };
+            let Some(tag_end) = lower[cell_start..end].find('>').map(|e| cell_start + e) else {
+                break;
+            };
+            let close = format!("</{}>", &lower[cell_start + 1..cell_start + 3]);
+            let content_end = lower[tag_end..end]
+                .find(&close)
+                .map(|e| tag_end + e)
+                .unwrap_or(end);
+            let attributes = &html[cell_start + 3..tag_end];
+            let colspan = html_attribute(attributes, "colspan")
+                .and_then(|value| value.trim().parse().ok())
+                .unwrap_or(1)
+                .clamp(1, MAX_HTML_COLSPAN);
+            let align = match html_attribute(attributes, "align")
+                .map(|value| value.to_ascii_lowercase())
+                .as_deref()
+            {
+                Some("center") => Alignment::Center,
+                Some("right") => Alignment::Right,
+                _ => Alignment::Left,
+            };
+            cells.push(HtmlTableCell {
+                content: html[tag_end + 1..content_end].to_string(),
+                colspan,
+                align,
+            });
+            cell_pos = content_end;
+        }
+
+        if !cells.is_empty() {
+            rows.push(cells);
+        }
+        pos = end;
+    }
+    rows
+}
+
+/// Returns the offset just past the `</table>` closing the table that opens at `start`
+/// in already lowercased HTML, skipping the tables nested in it.
+fn html_table_end(lower: &str, start: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut pos = start;
+    loop {
+        let close = pos + lower.get(pos..)?.find("</table>")?;
+        match find_html_tag(lower, "table", pos) {
+            Some(open) if open < close => {
+                depth += 1;
+                pos = open + "<table".len();
+            }
+            _ => {
+                depth -= 1;
+                pos = close + "</table>".len();
+                if depth == 0 {
+                    return Some(pos);
+                }
+            }
+        }
+    }
+}
+
+/// Finds the next `<name>` or `<name ...>` tag in already lowercased HTML.
+fn find_html_tag(lower: &str, name: &str, from: usize) -> Option<usize> {
+    let open = format!("<{}", name);
+    let mut pos = from;
+    while let Some(offset) = lower.get(pos..)?.find(&open) {
+        let start = pos + offset;
+        let next = lower[start + open.len()..].chars().next();
+        if matches!(next, Some(c) if c == '>' || c.is_whitespace()) {
+            return Some(start);
+        }
+        pos = start + open.len();
+    }
+    None
+}
+
+/// Extracts the value of an attribute from the inside of an HTML tag.
+/// Attribute names are matched case-insensitively and values may be quoted or bare.
+fn html_attribute(attributes: &str, key: &str) -> Option<String> {
+    let mut rest = attributes.trim_start();
+    while !rest.is_empty() {
+        let name_end = rest
+            .find(|c: char| c == '=' || c.is_whitespace())
+            .unwrap_or(rest.len());
+        let name = &rest[..name_end];
+        rest = rest[name_end..].trim_start();
+
+        let value = match rest.strip_prefix('=') {
+            Some(after_eq) => {
+                let after_eq = after_eq.trim_start();
+                let (value, remainder) = match after_eq.chars().next() {
+                    Some(quote @ ('"' | '\'')) => {
+                        let body = &after_eq[1..];
+                        let close = body.find(quote)?;
+                        (&body[..close], &body[close + 1..])
+                    }
+                    _ => {
+                        let end = after_eq.find(char::is_whitespace).unwrap_or(after_eq.len());
+                        (&after_eq[..end], &after_eq[end..])
+                    }
+                };
+                rest = remainder.trim_start();
+                Some(value)
+            }
+            None => None,
+        };
+
+        if name.eq_ignore_ascii_case(key) {
+            return value.filter(|v| !v.is_empty()).map(str::to_string);
+        }
+    }
+    None
 }
 
 #[cfg(test)]
@@ -1222,6 +3083,17 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_bom_and_crlf_input() {
+        let heading = Token::Heading(vec![Token::Text("Title".to_string())], 1);
+        assert_eq!(parse("\u{feff}# Title"), vec![heading.clone()]);
+
+        let lf = parse("# Title\n\nSome text\n- item\n");
+        assert_eq!(parse("\u{feff}# Title\r\n\r\nSome text\r\n- item\r\n"), lf);
+        assert_eq!(parse("# Title\r\rSome text\r- item\r"), lf);
+        assert_eq!(lf[0], heading);
+    }
+
     #[test]
     fn test_emphasis() {
         let tests = vec![
@@ -1359,6 +3231,50 @@ mod tests {
         assert_eq!(parse(input), expected);
     }
 
+    #[test]
+    fn test_multi_paragraph_list_items() {
+        let input = "- First paragraph\n\n  Second paragraph\n  - Nested\n- Next";
+        let expected = vec![
+            Token::ListItem {
+                content: vec![
+                    Token::Text("First paragraph".to_string()),
+                    Token::Newline,
+                    Token::Text("Second paragraph".to_string()),
+                    Token::ListItem {
+                        content: vec![Token::Text("Nested".to_string())],
+                        ordered: false,
+                        number: None,
+                    },
+                ],
+                ordered: false,
+                number: None,
+            },
+            Token::ListItem {
+                content: vec![Token::Text("Next".to_string())],
+                ordered: false,
+                number: None,
+            },
+        ];
+        assert_eq!(parse(input), expected);
+
+        // An indented line right after the item continues its paragraph; an unindented
+        // line after a blank line ends the list
+        let tokens = parse("1. One\n   two\n\nAfter");
+        assert_eq!(
+            tokens[0],
+            Token::ListItem {
+                content: vec![
+                    Token::Text("One".to_string()),
+                    Token::SoftBreak,
+                    Token::Text("two".to_string()),
+                ],
+                ordered: true,
+                number: Some(1),
+            }
+        );
+        assert!(tokens.contains(&Token::Text("After".to_string())));
+    }
+
     #[test]
     fn test_links() {
         let tests = vec![
@@ -1640,6 +3556,122 @@ A paragraph with `code` and [link](url).
         );
     }
 
+    #[test]
+    fn test_table_cell_line_breaks() {
+        let input = "| Step | Notes |\n|---|---|\n| 1 | first<br>second<BR />third |";
+        let tokens = parse(input);
+        let Token::Table { rows, .. } = &tokens[0] else {
+            panic!("expected a table, got {:?}", tokens);
+        };
+        assert_eq!(
+            rows[0][1],
+            vec![
+                Token::Text("first".to_string()),
+                Token::LineBreak,
+                Token::Text("second".to_string()),
+                Token::LineBreak,
+                Token::Text("third".to_string()),
+            ]
+        );
+
+        // Outside of tables the tag is left alone
+        assert!(!parse("a<br>b").contains(&Token::LineBreak));
+    }
+
+    #[test]
+    fn test_html_nested_table() {
+        let input = "<table><tr><th>Key</th><th>Values</th></tr>\n<tr><td>a</td><td>Pairs: <table><tr><td>x</td><td>1</td></tr><tr><td>y</td><td>2</td></tr></table></td></tr>\n<tr><td>b</td><td>none</td></tr></table>";
+        let tokens = parse(input);
+        let Some(Token::Table { rows, .. }) = tokens.first() else {
+            panic!("expected a table, got {:?}", tokens);
+        };
+        // The nested rows stay in their own table
+        assert_eq!(rows.len(), 2);
+        assert!(matches!(&rows[0][1][0], Token::Text(text) if text.trim() == "Pairs:"));
+        let Some(Token::Table {
+            headers,
+            rows: nested,
+            ..
+        }) = rows[0][1].get(1)
+        else {
+            panic!("expected a nested table, got {:?}", rows[0][1]);
+        };
+        assert_eq!(
+            headers,
+            &vec![
+                vec![Token::Text("x".to_string())],
+                vec![Token::Text("1".to_string())]
+            ]
+        );
+        assert_eq!(
+            nested,
+            &vec![vec![
+                vec![Token::Text("y".to_string())],
+                vec![Token::Text("2".to_string())]
+            ]]
+        );
+        assert_eq!(rows[1][1], vec![Token::Text("none".to_string())]);
+
+        // Tables nested deeper than the limit are not parsed as tables
+        let deep = (0..4).fold("core".to_string(), |inner, _| {
+            format!("<table><tr><td>{}</td></tr></table>", inner)
+        });
+        fn table_depth(tokens: &[Token]) -> usize {
+            tokens
+                .iter()
+                .map(|token| match token {
+                    Token::Table { headers, .. } => {
+                        1 + headers
+                            .iter()
+                            .map(|cell| table_depth(cell))
+                            .max()
+                            .unwrap_or(0)
+                    }
+                    _ => 0,
+                })
+                .max()
+                .unwrap_or(0)
+        }
+        assert_eq!(table_depth(&parse(&deep)), 1 + MAX_HTML_TABLE_NESTING);
+    }
+
+    #[test]
+    fn test_html_table_colspan() {
+        let input = r#"<table>
+  <tr><th>Name</th><th align="right">Score</th><th>Notes</th></tr>
+  <tr><td>Alice</td><td colspan="2">*absent*</td></tr>
+  <tr><td>Bob</td><td>7</td></tr>
+</table>"#;
+
+        let tokens = parse(input);
+        assert_eq!(
+            tokens,
+            vec![Token::Table {
+                headers: vec![
+                    vec![Token::Text("Name".to_string())],
+                    vec![Token::Text("Score".to_string())],
+                    vec![Token::Text("Notes".to_string())],
+                ],
+                aligns: vec![Alignment::Left, Alignment::Right, Alignment::Left],
+                rows: vec![
+                    vec![
+                        vec![Token::Text("Alice".to_string())],
+                        vec![Token::Emphasis {
+                            level: 1,
+                            content: vec![Token::Text("absent".to_string())],
+                        }],
+                        vec![],
+                    ],
+                    vec![
+                        vec![Token::Text("Bob".to_string())],
+                        vec![Token::Text("7".to_string())],
+                        vec![],
+                    ],
+                ],
+            }]
+        );
+    }
+
     #[test]
     fn test_inline_math() {
         let tests = vec![
@@ -1710,4 +3742,633 @@ A paragraph with `code` and [link](url).
             assert_eq!(result, expected, "Failed for input: {}", input);
         }
     }
+
+    #[test]
+    fn test_abbreviation_definitions() {
+        let tokens = parse("*[HTML]: HyperText Markup Language\nHTML is great");
+        assert_eq!(
+            tokens,
+            vec![
+                Token::AbbreviationDefinition(
+                    "HTML".to_string(),
+                    "HyperText Markup Language".to_string()
+                ),
+                Token::Text("HTML is great".to_string()),
+            ]
+        );
+
+        let abbreviations = Token::collect_abbreviations(&parse(
+            "*[W3C]: World Wide Web Consortium\n*[W3C]: Duplicate\n",
+        ));
+        assert_eq!(
+            abbreviations,
+            vec![("W3C".to_string(), "World Wide Web Consortium".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_front_matter() {
+        let tokens =
+            parse("---\ntitle: Report\nstyle: |\n  [margin]\n  left = 20.0\n---\n# Report\n");
+        assert_eq!(
+            tokens.first(),
+            Some(&Token::Heading(vec![Token::Text("Report".to_string())], 1))
+        );
+        // Only the document is stripped: nested content, like a block quote or a table
+        // cell, lexed with parse_with_context keeps a block of the same shape
+        let nested = Lexer::new("---\nkey: value\n---\n".to_string())
+            .parse_with_context(ParseContext::Root)
+            .unwrap();
+        assert!(nested.contains(&Token::HorizontalRule));
+
+        let (front_matter, content) = split_front_matter("---\nkey: value\n...\nText");
+        assert_eq!(front_matter, Some("key: value\n"));
+        assert_eq!(content, "Text");
+        // Rules around text and unclosed blocks are content
+        for input in [
+            "---\nSome text\n---\n",
+            "---\n---\n",
+            "---\nkey: value\n",
+            "Text\n---\n",
+        ] {
+            assert_eq!(split_front_matter(input), (None, input));
+        }
+    }
+
+    #[test]
+    fn test_reference_definitions_produce_no_tokens() {
+        let tokens = parse(
+            "[home]: https://example.com\n[docs]: <https://docs.rs> \"Docs\"\nSome text\n\n[a]: /a\n",
+        );
+        assert!(!tokens.iter().any(|t| matches!(t, Token::Link(..))));
+        assert_eq!(Token::plain_text(&tokens), "Some text");
+
+        // Links and a bracket without a destination stay in the text; footnote
+        // definitions are left to the notes
+        let tokens = parse("[see](https://example.com): more\n[label]:\n[^1]: note");
+        assert_eq!(
+            tokens.first(),
+            Some(&Token::Link(
+                "see".to_string(),
+                "https://example.com".to_string()
+            ))
+        );
+        assert!(Token::plain_text(&tokens).contains("label"));
+        assert!(!Token::plain_text(&tokens).contains("note"));
+    }
+
+    #[test]
+    fn test_html_anchors() {
+        let tokens = parse("See <a id=\"ref\"></a>here");
+        assert!(tokens.contains(&Token::Anchor("ref".to_string())));
+        assert!(Token::collect_all_text(&tokens).contains("here"));
+
+        let tokens = parse("<a name='top'>Top</a> of page");
+        assert_eq!(tokens[0], Token::Anchor("top".to_string()));
+        assert!(!Token::collect_all_text(&tokens).contains("</a>"));
+
+        let tokens = parse("## Title <a id=title/>");
+        assert_eq!(Token::collect_anchors(&tokens), vec!["title".to_string()]);
+
+        // Tags without id or name are not anchors
+        let tokens = parse("<a href=\"x\">");
+        assert!(Token::collect_anchors(&tokens).is_empty());
+
+        assert_eq!(
+            html_attribute(r#" class="x" ID = "main" "#, "id"),
+            Some("main".to_string())
+        );
+    }
+
+    #[test]
+    fn test_take_title() {
+        let mut tokens = parse("<!-- draft -->\n# Guide <a id=\"top\"></a>\n\nText\n# Other");
+        assert_eq!(Token::take_title(&mut tokens).as_deref(), Some("Guide"));
+        assert_eq!(Token::collect_anchors(&tokens), vec!["top".to_string()]);
+        let headings = tokens
+            .iter()
+            .filter(|t| matches!(t, Token::Heading(..)))
+            .count();
+        assert_eq!(headings, 1);
+
+        // A document that does not open with a level 1 heading keeps its headings
+        for input in ["Intro\n# Title", "## Section\n", ""] {
+            let mut tokens = parse(input);
+            let original = tokens.clone();
+            assert_eq!(Token::take_title(&mut tokens), None);
+            assert_eq!(tokens, original);
+        }
+    }
+
+    #[test]
+    fn test_task_list_items() {
+        let tokens = parse("- [x] Done\n- [ ] Todo\n- [link](url)\n- [y] Other");
+        let items: Vec<&Vec<Token>> = tokens
+            .iter()
+            .filter_map(|t| match t {
+                Token::ListItem { content, .. } => Some(content),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(items.len(), 4);
+        assert_eq!(items[0][0], Token::TaskCheckbox(true));
+        assert_eq!(items[0][1], Token::Text("Done".to_string()));
+        assert_eq!(items[1][0], Token::TaskCheckbox(false));
+        // Links and other bracketed text are not checkboxes
+        assert!(items[2..]
+            .iter()
+            .all(|item| !item.iter().any(|t| matches!(t, Token::TaskCheckbox(_)))));
+    }
+
+    #[test]
+    fn test_inline_html_tags() {
+        let tokens = parse("A <mark>key **point**</mark>, <INS>added</INS> and <del>gone</del>.");
+        assert!(tokens.contains(&Token::Mark(vec![
+            Token::Text("key ".to_string()),
+            Token::StrongEmphasis(vec![Token::Text("point".to_string())]),
+        ])));
+        assert!(tokens.contains(&Token::Ins(vec![Token::Text("added".to_string())])));
+        assert!(tokens.contains(&Token::Del(vec![Token::Text("gone".to_string())])));
+        assert_eq!(
+            Token::collect_all_text(&tokens),
+            "A key point, added and gone."
+        );
+
+        // Unclosed, empty or multi-line elements stay text
+        for input in ["<mark>open", "<del></del>", "<ins>one\ntwo</ins>"] {
+            assert!(!parse(input)
+                .iter()
+                .any(|t| matches!(t, Token::Mark(_) | Token::Ins(_) | Token::Del(_))));
+        }
+    }
+
+    #[test]
+    fn test_kbd_elements() {
+        let tokens = parse("Press <kbd>Ctrl</kbd>+<KBD> C </KBD> to copy");
+        let keys: Vec<&str> = tokens
+            .iter()
+            .filter_map(|t| match t {
+                Token::Kbd(key) => Some(key.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(keys, vec!["Ctrl", "C"]);
+        let text = Token::collect_all_text(&tokens);
+        assert!(text.contains("Press ") && text.contains(" to copy"));
+        assert!(!text.contains("kbd"));
+
+        let tokens = parse("**<kbd>⌘</kbd>**");
+        assert!(matches!(
+            &tokens[0],
+            Token::StrongEmphasis(inner) if inner.contains(&Token::Kbd("⌘".to_string()))
+        ));
+
+        // Unclosed or empty elements stay literal text
+        assert!(!parse("<kbd>Ctrl\n</kbd>")
+            .iter()
+            .any(|t| matches!(t, Token::Kbd(_))));
+        assert!(!parse("<kbd></kbd>")
+            .iter()
+            .any(|t| matches!(t, Token::Kbd(_))));
+    }
+
+    #[test]
+    fn test_redact() {
+        let patterns = [
+            regex::Regex::new(r"\bsecret\b").unwrap(),
+            regex::Regex::new(r"\d{3}-\d{4}").unwrap(),
+        ];
+        let mut tokens = vec![
+            Token::Heading(vec![Token::Text("The secret plan".to_string())], 1),
+            Token::Emphasis {
+                level: 1,
+                content: vec![Token::Text("Call 555-1234".to_string())],
+            },
+            Token::Code("".to_string(), "secret".to_string()),
+        ];
+        Token::redact(&mut tokens, &patterns, '█');
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Heading(vec![Token::Text("The ██████ plan".to_string())], 1),
+                Token::Emphasis {
+                    level: 1,
+                    content: vec![Token::Text("Call ████████".to_string())],
+                },
+                Token::Code("".to_string(), "██████".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_redact_every_text_token() {
+        let patterns = [regex::Regex::new(r"secret").unwrap()];
+        let text = |s: &str| s.to_string();
+        let cases = vec![
+            (
+                Token::Link(text("secret page"), text("https://secret.example")),
+                Token::Link(text("██████ page"), text("https://██████.example")),
+            ),
+            (
+                Token::Image(text("secret diagram"), text("secret.png")),
+                Token::Image(text("██████ diagram"), text("secret.png")),
+            ),
+            (
+                Token::ImageWithLink(text("secret"), text("logo.png"), text("#secret")),
+                Token::ImageWithLink(text("██████"), text("logo.png"), text("#██████")),
+            ),
+            (
+                Token::Code(text(""), text("let secret = 1;")),
+                Token::Code(text(""), text("let ██████ = 1;")),
+            ),
+            (
+                Token::Code(text("rust"), text("// secret\nfn main() {}")),
+                Token::Code(text("rust"), text("// ██████\nfn main() {}")),
+            ),
+            (
+                Token::Math {
+                    content: text("\\text{secret}"),
+                    display: false,
+                },
+                Token::Math {
+                    content: text("\\text{██████}"),
+                    display: false,
+                },
+            ),
+            (Token::Kbd(text("secret")), Token::Kbd(text("██████"))),
+            (Token::Anchor(text("secret")), Token::Anchor(text("██████"))),
+            (
+                Token::HtmlComment(text("index: secret")),
+                Token::HtmlComment(text("index: ██████")),
+            ),
+            (
+                Token::Unknown(text("secret")),
+                Token::Unknown(text("██████")),
+            ),
+            (
+                Token::AbbreviationDefinition(text("SP"), text("secret project")),
+                Token::AbbreviationDefinition(text("SP"), text("██████ project")),
+            ),
+            (
+                Token::TabGroup(vec![(text("secret"), vec![Token::Text(text("secret"))])]),
+                Token::TabGroup(vec![(text("██████"), vec![Token::Text(text("██████"))])]),
+            ),
+        ];
+        for (token, expected) in cases {
+            let mut tokens = vec![token.clone()];
+            Token::redact(&mut tokens, &patterns, '█');
+            assert_eq!(tokens, vec![expected], "redacting {:?}", token);
+        }
+    }
+
+    #[test]
+    fn test_resolve_urls() {
+        let base = "https://site.example/docs/guide/index.html";
+        assert_eq!(resolve_url(base, "/about"), "https://site.example/about");
+        assert_eq!(
+            resolve_url(base, "setup.html#install"),
+            "https://site.example/docs/guide/setup.html#install"
+        );
+        assert_eq!(
+            resolve_url(base, "../img/logo.png"),
+            "https://site.example/docs/img/logo.png"
+        );
+        assert_eq!(
+            resolve_url("https://site.example", "page"),
+            "https://site.example/page"
+        );
+        assert_eq!(
+            resolve_url("https://site.example/", "./a/../b"),
+            "https://site.example/b"
+        );
+        for url in [
+            "https://other.example/x",
+            "mailto:a@b.c",
+            "#intro",
+            "//cdn.example/x",
+            "",
+        ] {
+            assert_eq!(resolve_url(base, url), url);
+        }
+
+        let mut tokens = vec![
+            Token::Link("Home".to_string(), "/".to_string()),
+            Token::ListItem {
+                content: vec![Token::Image("Logo".to_string(), "logo.png".to_string())],
+                ordered: false,
+                number: None,
+            },
+            Token::ImageWithLink(
+                "Badge".to_string(),
+                "badge.svg".to_string(),
+                "ci".to_string(),
+            ),
+        ];
+        Token::resolve_urls(
+            &mut tokens,
+            Some("https://site.example/docs/"),
+            Some("https://cdn.example/assets/"),
+        );
+        assert_eq!(
+            tokens[0],
+            Token::Link("Home".to_string(), "https://site.example/".to_string())
+        );
+        assert_eq!(
+            tokens[1],
+            Token::ListItem {
+                content: vec![Token::Image(
+                    "Logo".to_string(),
+                    "https://cdn.example/assets/logo.png".to_string()
+                )],
+                ordered: false,
+                number: None,
+            }
+        );
+        assert_eq!(
+            tokens[2],
+            Token::ImageWithLink(
+                "Badge".to_string(),
+                "https://cdn.example/assets/badge.svg".to_string(),
+                "https://site.example/docs/ci".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_inline_footnotes() {
+        let tokens = parse("Claim^[See *the* [source](https://example.com).] stands.");
+        let notes: Vec<&Vec<Token>> = tokens
+            .iter()
+            .filter_map(|t| match t {
+                Token::FootnoteInline(content) => Some(content),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(notes.len(), 1);
+        assert!(notes[0].contains(&Token::Link(
+            "source".to_string(),
+            "https://example.com".to_string()
+        )));
+        assert!(notes[0]
+            .iter()
+            .any(|t| matches!(t, Token::Emphasis { level: 1, .. })));
+        assert!(Token::collect_all_text(&tokens).contains(" stands."));
+
+        // A caret without a bracket, an empty note or an unclosed one is plain text
+        for input in ["x^2 and 2^[", "Empty ^[] note", "a^[unclosed\n\nnext"] {
+            assert!(!parse(input)
+                .iter()
+                .any(|t| matches!(t, Token::FootnoteInline(_))));
+        }
+    }
+
+    #[test]
+    fn test_reference_footnotes() {
+        let notes = |tokens: &[Token]| -> Vec<Vec<Token>> {
+            tokens
+                .iter()
+                .filter_map(|t| match t {
+                    Token::FootnoteInline(content) => Some(content.clone()),
+                    _ => None,
+                })
+                .collect()
+        };
+        let tokens =
+            parse("Claim[^src] and more^[inline].\n\n[^src]: See *the*\n    source.\n\nAfter.");
+        let found = notes(&tokens);
+        assert_eq!(found.len(), 2);
+        assert!(found[0]
+            .iter()
+            .any(|t| matches!(t, Token::Emphasis { level: 1, .. })));
+        assert!(Token::collect_all_text(&found[0]).contains("source."));
+        assert_eq!(found[1], vec![Token::Text("inline".to_string())]);
+        // The definition leaves no text behind
+        let text = Token::collect_all_text(&tokens);
+        assert!(!text.contains("[^src]"));
+        assert!(!text.contains("See"));
+        assert!(text.contains("After."));
+
+        // References resolve inside table cells too
+        let tokens = parse("| A |\n|---|\n| x[^n] |\n\n[^n]: Note");
+        let Some(Token::Table { rows, .. }) = tokens.first() else {
+            panic!("expected a table, got {:?}", tokens);
+        };
+        assert_eq!(notes(&rows[0][0]).len(), 1);
+
+        // An undefined label and a definition inside a code block stay text
+        let tokens = parse("Missing[^none]\n\n```\n[^none]: code\n```");
+        assert!(notes(&tokens).is_empty());
+        assert!(Token::collect_all_text(&tokens).contains("[^none]"));
+    }
+
+    #[test]
+    fn test_replace_quotes() {
+        let en = ["\u{201C}", "\u{201D}", "\u{2018}", "\u{2019}"];
+        let quoted = |input: &str, marks: [&str; 4]| {
+            let mut tokens = parse(input);
+            Token::replace_quotes(&mut tokens, marks);
+            tokens
+        };
+
+        // Quotes around formatting open and close across the tokens
+        let tokens = quoted("He said \"*never*\" and 'rock'n'roll'.", en);
+        assert_eq!(
+            Token::collect_all_text(&tokens),
+            "He said “never” and ‘rock’n’roll’."
+        );
+
+        // Code keeps its straight quotes
+        let tokens = quoted("Run `echo \"hi\"` now", en);
+        assert!(tokens.contains(&Token::Code(String::new(), "echo \"hi\"".to_string())));
+
+        let fr = ["\u{AB}\u{A0}", "\u{A0}\u{BB}", "\u{201C}", "\u{201D}"];
+        let tokens = quoted("Il dit \"oui\".", fr);
+        assert_eq!(
+            Token::collect_all_text(&tokens),
+            "Il dit «\u{A0}oui\u{A0}»."
+        );
+
+        // Every block starts with an opening quote
+        let de = ["\u{201E}", "\u{201C}", "\u{201A}", "\u{2018}"];
+        let tokens = quoted("# Titel\n\"Ja\", sagte er.", de);
+        assert_eq!(
+            tokens.last(),
+            Some(&Token::Text("„Ja“, sagte er.".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_spoilers() {
+        let tokens = parse("The killer is ||the *butler*|| after all.");
+        assert!(tokens.contains(&Token::Spoiler(vec![
+            Token::Text("the ".to_string()),
+            Token::Emphasis {
+                level: 1,
+                content: vec![Token::Text("butler".to_string())],
+            },
+        ])));
+        assert!(Token::collect_all_text(&tokens).contains("after all."));
+        assert!(matches!(
+            parse("||Spoiler at the start||")[0],
+            Token::Spoiler(_)
+        ));
+
+        // Tables, spaced or unclosed bars and empty spoilers stay what they were
+        let table = parse("|| a | b |\n|---|---|---|\n| 1 | 2 | 3 |");
+        assert!(matches!(table[0], Token::Table { .. }));
+        for input in ["a || b || c", "x ||open", "||||", "||a\nb||"] {
+            assert!(!parse(input).iter().any(|t| matches!(t, Token::Spoiler(_))));
+        }
+    }
+
+    #[test]
+    fn test_tab_group() {
+        let input = ":::tabs\n== Rust\n```rust\nfn main() {}\n```\n@tab Shell\n~~~sh\necho ':::'\n:::\n~~~\n:::\nAfter";
+        let tokens = parse(input);
+        let Token::TabGroup(tabs) = &tokens[0] else {
+            panic!("expected a tab group, got {:?}", tokens[0]);
+        };
+
+        let titles: Vec<&str> = tabs.iter().map(|(title, _)| title.as_str()).collect();
+        assert_eq!(titles, ["Rust", "Shell"]);
+        assert!(tabs[0]
+            .1
+            .iter()
+            .any(|t| matches!(t, Token::Code(lang, _) if lang == "rust")));
+        // A `:::` line inside a fence belongs to the code
+        assert!(tabs[1].1.contains(&Token::Code(
+            "sh".to_string(),
+            "echo ':::'\n:::".to_string()
+        )));
+        assert!(tokens.contains(&Token::Text("After".to_string())));
+
+        // Without the directive name the line is plain text
+        assert!(!parse(":::note\ntext\n:::")
+            .iter()
+            .any(|t| matches!(t, Token::TabGroup(_))));
+    }
+
+    #[cfg(feature = "rst-admonitions")]
+    #[test]
+    fn test_rst_admonitions() {
+        let input = ".. note:: Keep this\n   in mind.\n\n   - First\n   - Second\n\n.. WARNING::\n\n    Careful\nAfter";
+        let tokens = parse(input);
+        let admonitions: Vec<_> = tokens
+            .iter()
+            .filter_map(|t| match t {
+                Token::Admonition { kind, content } => Some((kind.as_str(), content)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(admonitions.len(), 2);
+
+        let (kind, content) = admonitions[0];
+        assert_eq!(kind, "note");
+        assert!(content.contains(&Token::Text("Keep this".to_string())));
+        assert!(content.contains(&Token::Text("in mind.".to_string())));
+        let items = content
+            .iter()
+            .filter(|t| matches!(t, Token::ListItem { .. }))
+            .count();
+        assert_eq!(items, 2);
+
+        let (kind, content) = admonitions[1];
+        assert_eq!(kind, "warning");
+        assert!(content.contains(&Token::Text("Careful".to_string())));
+        // The first unindented line ends the admonition
+        assert!(tokens.contains(&Token::Text("After".to_string())));
+
+        // Other directives stay plain text
+        assert!(!parse(".. image:: logo.png")
+            .iter()
+            .any(|t| matches!(t, Token::Admonition { .. })));
+    }
+
+    #[cfg(feature = "critic-markup")]
+    #[test]
+    fn test_critic_markup() {
+        let text = |t: &str| vec![Token::Text(t.to_string())];
+        let tokens = parse("We {++now++} {--never--} ship {==on Fridays==}.");
+        assert!(tokens.contains(&Token::Critic(CriticMarkup::Addition, text("now"))));
+        assert!(tokens.contains(&Token::Critic(CriticMarkup::Deletion, text("never"))));
+        assert!(tokens.contains(&Token::Critic(CriticMarkup::Highlight, text("on Fridays"))));
+
+        let tokens = parse("Version {~~1.0~>2.0~~} is out");
+        assert!(tokens.contains(&Token::Critic(
+            CriticMarkup::Substitution,
+            vec![
+                Token::Critic(CriticMarkup::Deletion, text("1.0")),
+                Token::Critic(CriticMarkup::Addition, text("2.0")),
+            ]
+        )));
+
+        // Empty, unclosed, multi-line and arrowless changes stay plain text
+        for input in ["{++++}", "{++open", "{--a\nb--}", "{~~old new~~}", "{+a+}"] {
+            assert!(!parse(input).iter().any(|t| matches!(t, Token::Critic(..))));
+        }
+    }
+
+    #[test]
+    fn test_collect_index_terms() {
+        let tokens = parse(
+            "# Ownership <!-- index: ownership -->\n\nThe borrow checker<!-- index: Borrow checker --> runs.\n\n- Moves <!-- index: ownership -->\n<!-- not an index tag -->\n<!-- index: -->",
+        );
+        assert_eq!(
+            Token::collect_index_terms(&tokens),
+            vec!["Borrow checker".to_string(), "ownership".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_tilde_fenced_code_block() {
+        let tokens = parse("~~~markdown\n```rust\nfn main() {}\n```\n~~~\nAfter");
+        assert_eq!(
+            tokens[0],
+            Token::Code(
+                "markdown".to_string(),
+                "```rust\nfn main() {}\n```".to_string()
+            )
+        );
+        assert!(tokens.contains(&Token::Text("After".to_string())));
+
+        // A shorter tilde run does not close a longer fence
+        let tokens = parse("~~~~\nline one\n~~~\nline two\n~~~~");
+        assert_eq!(
+            tokens[0],
+            Token::Code(String::new(), "line one\n~~~\nline two".to_string())
+        );
+    }
+
+    #[test]
+    fn test_single_newline_is_soft_break() {
+        assert_eq!(
+            parse("First line\nSecond line"),
+            vec![
+                Token::Text("First line".to_string()),
+                Token::SoftBreak,
+                Token::Text("Second line".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_inline_code_backtick_runs() {
+        let code = |s: &str| vec![Token::Code(String::new(), s.to_string())];
+
+        assert_eq!(parse("`a`"), code("a"));
+        assert_eq!(parse("`` a`b ``"), code("a`b"));
+        assert_eq!(parse("``` ``x`` ```"), code("``x``"));
+        // A space-padded span keeps its inner spaces
+        assert_eq!(parse("``  ``"), code("  "));
+
+        let tokens = parse("Use `` `code` `` here");
+        assert!(tokens.contains(&Token::Code(String::new(), "`code`".to_string())));
+        assert!(Token::collect_all_text(&tokens).contains("here"));
+
+        // An unmatched run is literal text
+        let tokens = parse("``not code`");
+        assert!(tokens
+            .iter()
+            .all(|t| !matches!(t, Token::Code(_, c) if c.contains("not code"))));
+    }
 }
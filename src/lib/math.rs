@@ -0,0 +1,284 @@
+//! Pure-Rust math rendering for the `math-rust` feature.
+//!
+//! This module sets math as Unicode text in the document fonts, so inline (`$...$`) and
+//! display (`$$...$$`) math work without the native toolchain required by the `latex`
+//! feature. LaTeX is parsed by [`pulldown_latex`] and AsciiMath (fenced ```` ```asciimath ````
+//! blocks) by [`mathemascii`]; both produce MathML, which is then laid out on one line:
+//!
+//! - Identifiers, numbers, operators and text as written, with spaces around relations
+//!   and binary operators
+//! - Superscripts and subscripts, using Unicode super/subscript characters when every
+//!   character has one and `^(...)` / `_(...)` otherwise; limits as scripts
+//! - Fractions as `a/b` and roots as `√x`, `∛x` or `ⁿ√x`, with parentheses around
+//!   operands of more than one term
+//! - Accents as combining characters (`x̂`, `v⃗`)
+//! - Matrix rows separated by `;`, their cells by `,`
+//!
+//! An expression the parser rejects, such as an unknown command, is kept as written so
+//! nothing silently disappears.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use markdown2pdf::math::{asciimath_to_unicode, to_unicode};
+//!
+//! assert_eq!(to_unicode("E = mc^2"), "E = mc²");
+//! assert_eq!(to_unicode(r"\alpha + \beta \leq \frac{1}{2}"), "α + β ≤ 1/2");
+//! assert_eq!(asciimath_to_unicode("x^2 + y^2"), "x² + y²");
+//! ```
+
+use roxmltree::Node;
+
+/// Converts a LaTeX math expression to Unicode text.
+pub fn to_unicode(latex: &str) -> String {
+    let latex = latex.trim();
+    let storage = pulldown_latex::Storage::new();
+    let parser = pulldown_latex::Parser::new(latex, &storage);
+    let mut mathml = String::new();
+    pulldown_latex::push_mathml(&mut mathml, parser, pulldown_latex::RenderConfig::default())
+        .ok()
+        .and_then(|_| mathml_to_unicode(&mathml))
+        .unwrap_or_else(|| latex.to_string())
+}
+
+/// Converts an AsciiMath expression to Unicode text.
+pub fn asciimath_to_unicode(asciimath: &str) -> String {
+    let asciimath = asciimath.trim();
+    let mathml = mathemascii::render_mathml(mathemascii::parse(asciimath));
+    mathml_to_unicode(&mathml).unwrap_or_else(|| asciimath.to_string())
+}
+
+/// Lays MathML out as Unicode text, or returns `None` if it does not parse or reports
+/// an error.
+fn mathml_to_unicode(mathml: &str) -> Option<String> {
+    let document = roxmltree::Document::parse(mathml).ok()?;
+    if document
+        .descendants()
+        .any(|node| node.has_tag_name("merror"))
+    {
+        return None;
+    }
+    // Collapse the runs of spaces left around operators
+    Some(
+        node_text(document.root_element())
+            .split(' ')
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join(" "),
+    )
+}
+
+/// Lays out a MathML element and its children.
+fn node_text(node: Node) -> String {
+    let children: Vec<String> = node
+        .children()
+        .filter(|child| child.is_element())
+        .map(node_text)
+        .collect();
+    let child = |index: usize| children.get(index).map_or("", String::as_str);
+    match node.tag_name().name() {
+        "mi" => {
+            // Function names such as `sin` are set apart from their argument
+            let name = leaf_text(node);
+            if name.chars().count() > 1 {
+                format!("{} ", name)
+            } else {
+                name
+            }
+        }
+        "mn" | "mtext" | "ms" => leaf_text(node),
+        "mo" => {
+            let operator = leaf_text(node);
+            if operator.chars().count() == 1 && SPACED_OPERATORS.contains(operator.as_str()) {
+                format!(" {} ", operator)
+            } else {
+                operator
+            }
+        }
+        "mspace" => " ".to_string(),
+        "msup" => format!("{}{}", child(0), script(child(1), superscript, '^')),
+        "msub" | "munder" => format!("{}{}", child(0), script(child(1), subscript, '_')),
+        "msubsup" | "munderover" => format!(
+            "{}{}{}",
+            child(0),
+            script(child(1), subscript, '_'),
+            script(child(2), superscript, '^')
+        ),
+        "mover" => match accent(child(1)) {
+            Some(mark) if child(0).chars().count() == 1 => format!("{}{}", child(0), mark),
+            _ => format!("{}{}", child(0), script(child(1), superscript, '^')),
+        },
+        "mfrac" => format!("{}/{}", group(child(0)), group(child(1))),
+        "msqrt" => format!("√{}", group(&children.concat())),
+        "mroot" => match child(1).trim() {
+            "3" => format!("∛{}", group(child(0))),
+            "4" => format!("∜{}", group(child(0))),
+            index => format!("{}√{}", script(index, superscript, '^'), group(child(0))),
+        },
+        "mtable" => children.join("; "),
+        "mtr" | "mlabeledtr" => children.join(", "),
+        "annotation" | "annotation-xml" => String::new(),
+        _ => children.concat(),
+    }
+}
+
+/// Operators set with a space on each side: relations, arrows and binary operators.
+const SPACED_OPERATORS: &str = "=<>≤≥≠≈≡∼≃≅∝≪≫∈∉∋⊂⊆⊃⊇⊥∥→←↔⇒⇐⇔↦+−±∓×÷·∗∘⊕⊗∪∩∧∨";
+
+/// Returns the text of a token element, without the invisible function application,
+/// times, separator and plus operators.
+fn leaf_text(node: Node) -> String {
+    node.text()
+        .unwrap_or("")
+        .chars()
+        .filter(|c| !('\u{2061}'..='\u{2064}').contains(c))
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Maps an accent drawn over a single character to its combining mark.
+fn accent(mark: &str) -> Option<char> {
+    Some(match mark.trim() {
+        "^" | "ˆ" => '\u{302}',
+        "~" | "˜" => '\u{303}',
+        "¯" | "‾" | "_" => '\u{304}',
+        "˙" | "." => '\u{307}',
+        "¨" | ".." => '\u{308}',
+        "→" | "⃗" => '\u{20D7}',
+        _ => return None,
+    })
+}
+
+/// Wraps a fraction or root operand in parentheses unless it is a single term.
+fn group(text: &str) -> String {
+    let text = text.trim();
+    if text.chars().all(|c| c.is_alphanumeric() || c == '.') || text.chars().count() == 1 {
+        text.to_string()
+    } else {
+        format!("({})", text)
+    }
+}
+
+/// Renders a superscript or subscript with Unicode characters when possible, or as
+/// `^(...)` / `_(...)` when some character has no script form. Spaces around operators
+/// are dropped, as scripts are set tight.
+fn script(text: &str, map: fn(char) -> Option<char>, marker: char) -> String {
+    let text: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    match text.chars().map(map).collect::<Option<String>>() {
+        Some(mapped) if !mapped.is_empty() => mapped,
+        _ if text.chars().count() == 1 => format!("{}{}", marker, text),
+        _ => format!("{}({})", marker, text),
+    }
+}
+
+fn superscript(c: char) -> Option<char> {
+    Some(match c {
+        '0' => '⁰',
+        '1' => '¹',
+        '2' => '²',
+        '3' => '³',
+        '4' => '⁴',
+        '5' => '⁵',
+        '6' => '⁶',
+        '7' => '⁷',
+        '8' => '⁸',
+        '9' => '⁹',
+        '+' => '⁺',
+        '-' | '−' => '⁻',
+        '=' => '⁼',
+        '(' => '⁽',
+        ')' => '⁾',
+        'n' => 'ⁿ',
+        'i' => 'ⁱ',
+        'T' => 'ᵀ',
+        '′' => '′',
+        _ => return None,
+    })
+}
+
+fn subscript(c: char) -> Option<char> {
+    Some(match c {
+        '0' => '₀',
+        '1' => '₁',
+        '2' => '₂',
+        '3' => '₃',
+        '4' => '₄',
+        '5' => '₅',
+        '6' => '₆',
+        '7' => '₇',
+        '8' => '₈',
+        '9' => '₉',
+        '+' => '₊',
+        '-' | '−' => '₋',
+        '=' => '₌',
+        '(' => '₍',
+        ')' => '₎',
+        'a' => 'ₐ',
+        'e' => 'ₑ',
+        'i' => 'ᵢ',
+        'j' => 'ⱼ',
+        'k' => 'ₖ',
+        'n' => 'ₙ',
+        'o' => 'ₒ',
+        'x' => 'ₓ',
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_symbols_and_scripts() {
+        assert_eq!(to_unicode("E = mc^2"), "E = mc²");
+        assert_eq!(to_unicode("x_{i+1} = x_i^{n}"), "xᵢ₊₁ = xᵢⁿ");
+        assert_eq!(to_unicode("e^{i\\pi} + 1 = 0"), "e^(iπ) + 1 = 0");
+        assert_eq!(to_unicode("\\alpha \\leq \\beta"), "α ≤ β");
+        let quantified = to_unicode("\\forall x \\in A");
+        assert!(quantified.starts_with('∀') && quantified.ends_with("x ∈ A"));
+    }
+
+    #[test]
+    fn test_fractions_and_roots() {
+        assert_eq!(to_unicode("\\frac{1}{2}"), "1/2");
+        assert_eq!(to_unicode("\\frac{a+b}{c}"), "(a + b)/c");
+        assert_eq!(to_unicode("\\sqrt{x^2 + y^2}"), "√(x² + y²)");
+        assert_eq!(to_unicode("\\sqrt[3]{8}"), "∛8");
+        assert_eq!(to_unicode("\\sqrt[n]{x}"), "ⁿ√x");
+    }
+
+    #[test]
+    fn test_text_and_invalid_input() {
+        assert_eq!(to_unicode("\\text{speed} = v"), "speed = v");
+        // Input the parser rejects is kept as written
+        assert_eq!(to_unicode("\\unknowncmd{x}"), "\\unknowncmd{x}");
+        assert_eq!(to_unicode(""), "");
+    }
+
+    #[test]
+    fn test_asciimath() {
+        assert_eq!(asciimath_to_unicode("x^2 + y^2"), "x² + y²");
+        assert_eq!(asciimath_to_unicode("alpha <= beta"), "α ≤ β");
+        assert_eq!(asciimath_to_unicode("1/2"), "1/2");
+        let root = asciimath_to_unicode("sqrt(x)");
+        assert!(root.starts_with('√') && root.contains('x'));
+    }
+
+    #[test]
+    fn test_mathml_layout() {
+        let mathml = "<math><munderover><mo>∑</mo><mrow><mi>i</mi><mo>=</mo><mn>1</mn></mrow>\
+                      <mi>n</mi></munderover><mover><mi>x</mi><mo>^</mo></mover>\
+                      <mtable><mtr><mtd><mn>1</mn></mtd><mtd><mn>0</mn></mtd></mtr>\
+                      <mtr><mtd><mn>0</mn></mtd><mtd><mn>1</mn></mtd></mtr></mtable></math>";
+        assert_eq!(
+            mathml_to_unicode(mathml).as_deref(),
+            Some("∑ᵢ₌₁ⁿx\u{302}1, 0; 0, 1")
+        );
+        assert_eq!(
+            mathml_to_unicode("<math><merror><mtext>bad</mtext></merror></math>"),
+            None
+        );
+    }
+}
@@ -19,21 +19,433 @@
 use crate::{
     fonts::load_unicode_system_font,
     highlighting,
-    styling::{StyleMatch, SvgWidth},
+    markdown::CriticMarkup,
+    styling::{
+        AbbreviationMode, CodeFit, CodeHighlightStyle, FontRange, LinkStyle, MaxWidth,
+        SoftBreakMode, StyleMatch, SvgWidth, TableOverflow, TextTransform,
+    },
+    validation::ValidationWarning,
     Token,
 };
 use genpdfi_extended::{
     fonts::{FontData, FontFamily},
-    Alignment, Document, Scale,
+    Alignment, Document, Element as _, Scale,
 };
 use log::{info, warn};
-use std::cell::RefCell;
+use std::cell::{Cell, OnceCell, RefCell};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 
 thread_local! {
     /// Thread-local storage for the current code font override during rendering
     /// This allows passing the code font through the rendering call stack without
     /// major structural changes.
     static CURRENT_CODE_FONT_OVERRIDE: RefCell<Option<genpdfi_extended::fonts::FontFamily<genpdfi_extended::fonts::Font>>> = RefCell::new(None);
+
+    /// Document fonts registered for `[fonts.ranges]`, indexed like `Pdf::range_font_families`
+    static CURRENT_RANGE_FONTS: RefCell<Vec<genpdfi_extended::fonts::FontFamily<genpdfi_extended::fonts::Font>>> = RefCell::new(Vec::new());
+}
+
+/// URI scheme of the invisible link markers emitted for explicit anchors.
+/// [`Pdf::resolve_anchors`] turns each marker into a named destination.
+const ANCHOR_URI_PREFIX: &str = "md2pdf-anchor:";
+
+/// URI scheme of the link markers emitted around `<kbd>` keys and task list checkboxes,
+/// followed by the fill and border colors. [`Pdf::draw_decorations`] draws a rounded box
+/// behind each marker.
+const KBD_URI_PREFIX: &str = "md2pdf-kbd:";
+
+/// URI scheme of the invisible link markers emitted for `<!-- index: term -->` tags,
+/// followed by the position of the term in the sorted list of index terms.
+/// [`Pdf::index_markers`] reads the page of each marker and removes it.
+const INDEX_URI_PREFIX: &str = "md2pdf-index:";
+
+/// URI scheme of the link markers emitted around struck-through text, followed by the line
+/// color. [`Pdf::draw_decorations`] draws a line through each marker.
+const STRIKE_URI_PREFIX: &str = "md2pdf-strike:";
+
+/// URI scheme of the link markers emitted around underlined `<ins>` text, followed by the
+/// line color. [`Pdf::draw_decorations`] draws a line under each marker.
+const UNDERLINE_URI_PREFIX: &str = "md2pdf-underline:";
+
+/// URI scheme of the link markers emitted around the text runs of headings with
+/// `letterspacing`, followed by the spacing in points. [`Pdf::draw_decorations`] sets the
+/// character spacing of the text lines starting inside each marker.
+const TRACKING_URI_PREFIX: &str = "md2pdf-tracking:";
+
+/// Fill of the box behind inline code with `[code] inline_background` when `[code]` has no
+/// background color, the light gray used by GitHub.
+const INLINE_CODE_BACKGROUND: (u8, u8, u8) = (239, 241, 243);
+
+/// Color of CriticMarkup additions (`{++added++}`), underlined in the same color.
+const CRITIC_ADDITION_COLOR: (u8, u8, u8) = (0, 128, 0);
+
+/// Color of CriticMarkup deletions (`{--removed--}`), struck through in the same color.
+const CRITIC_DELETION_COLOR: (u8, u8, u8) = (192, 0, 0);
+
+/// A color cell of [`Pdf`], such as the strike color, with the value to give it while some
+/// content is rendered.
+type ColorState<'a> = (&'a Cell<Option<(u8, u8, u8)>>, Option<(u8, u8, u8)>);
+
+/// Left indent of block quotes in millimeters.
+const BLOCK_QUOTE_INDENT_MM: f32 = 6.0;
+
+/// Horizontal room in millimeters reserved per table column for the frame and cell spacing.
+const TABLE_CELL_PADDING_MM: f32 = 2.0;
+
+/// Smallest font size in points that wide tables are shrunk to.
+const TABLE_MIN_FONT_SIZE: u8 = 4;
+
+/// Smallest font size in points that code blocks are shrunk to with `[code] fit = "shrink"`.
+const CODE_MIN_FONT_SIZE: u8 = 5;
+
+/// Largest fraction of the text width a raster image is rendered at.
+const RASTER_IMAGE_MAX_WIDTH: f32 = 0.8;
+
+/// Millimeters per typographic point.
+const PT_TO_MM: f32 = 0.3528;
+
+/// Height in millimeters [`Pdf::estimate_page_count`] assumes for an image or diagram.
+const ESTIMATED_IMAGE_HEIGHT_MM: f32 = 60.0;
+
+/// Space in millimeters between a footer logo and the date or page number moved aside
+/// for it.
+const FOOTER_LOGO_GAP_MM: f32 = 2.0;
+
+/// Thumbnails per row on the `[document] contact_sheet` pages.
+const CONTACT_SHEET_COLUMNS: usize = 4;
+
+/// Margin around the grid of contact sheet thumbnails, in points.
+const CONTACT_SHEET_MARGIN_PT: f32 = 36.0;
+
+/// Space between contact sheet thumbnails, in points.
+const CONTACT_SHEET_GAP_PT: f32 = 12.0;
+
+/// Page decorator for `[page] first_page_margins`, `[page.header]` and `[page.footer]`: the
+/// first page gets its own margins, all following pages the regular `[margin]` settings,
+/// and every page the footer line and logos.
+struct FirstPageDecorator {
+    first: crate::styling::Margins,
+    rest: crate::styling::Margins,
+    page: usize,
+    footer: Option<PageFooter>,
+    logos: Vec<MarginLogo>,
+}
+
+impl genpdfi_extended::PageDecorator for FirstPageDecorator {
+    fn decorate_page<'a>(
+        &mut self,
+        context: &genpdfi_extended::Context,
+        mut area: genpdfi_extended::render::Area<'a>,
+        style: genpdfi_extended::style::Style,
+    ) -> Result<genpdfi_extended::render::Area<'a>, genpdfi_extended::error::Error> {
+        self.page += 1;
+        let margins = if self.page == 1 {
+            self.first
+        } else {
+            self.rest
+        };
+        if let Some(footer) = &self.footer {
+            let logo = self
+                .logos
+                .iter()
+                .find(|logo| !logo.header)
+                .and_then(|logo| logo.span(margins));
+            footer.print(context, &area, margins, logo, style.clone())?;
+        }
+        for logo in &mut self.logos {
+            logo.draw(context, &area, margins, style.clone())?;
+        }
+        area.add_margins(page_margins(margins));
+        Ok(area)
+    }
+}
+
+/// The `[page.footer]` line, printed in the middle of the bottom margin: the date on the
+/// left, the page number on the right, each moved aside for a footer logo in its way.
+struct PageFooter {
+    date: Option<String>,
+    page_numbers: bool,
+    size: u8,
+    /// Page dimensions in millimeters
+    page_size: (f32, f32),
+    /// Pages decorated so far, shared by the documents of all orientation sections so the
+    /// numbering continues across them
+    pages: Rc<Cell<usize>>,
+}
+
+impl PageFooter {
+    /// Prints the footer line. `logo` is the left edge and the width in millimeters of the
+    /// footer logo, see [`MarginLogo::span`].
+    fn print(
+        &self,
+        context: &genpdfi_extended::Context,
+        area: &genpdfi_extended::render::Area<'_>,
+        margins: crate::styling::Margins,
+        logo: Option<(f32, f32)>,
+        style: genpdfi_extended::style::Style,
+    ) -> Result<(), genpdfi_extended::error::Error> {
+        let page = self.pages.get() + 1;
+        self.pages.set(page);
+
+        let style = style.with_font_size(self.size);
+        let (width, height) = self.page_size;
+        // Points to millimeters, to center the line in the margin
+        let text_height = self.size as f32 * 0.3528;
+        let top = genpdfi_extended::Mm::from(height - (margins.bottom + text_height) / 2.0);
+        let mm = |value: f32| genpdfi_extended::Mm::from(value);
+        // Left and right edges of the logo, which the texts must not cross
+        let logo = logo.map(|(left, width)| (mm(left), mm(left + width)));
+        if let Some(date) = &self.date {
+            let mut left = mm(margins.left);
+            let right = left + style.str_width(&context.font_cache, date);
+            if let Some((_, end)) = logo.filter(|&(start, end)| left < end && start < right) {
+                left = end + mm(FOOTER_LOGO_GAP_MM);
+            }
+            let position = genpdfi_extended::Position::new(left, top);
+            area.print_str(&context.font_cache, position, style.clone(), date)?;
+        }
+        if self.page_numbers {
+            let number = page.to_string();
+            let number_width = style.str_width(&context.font_cache, &number);
+            let right = mm(width - margins.right);
+            let mut left = right - number_width;
+            if let Some((start, _)) = logo.filter(|&(start, end)| left < end && start < right) {
+                left = start - mm(FOOTER_LOGO_GAP_MM) - number_width;
+            }
+            let position = genpdfi_extended::Position::new(left, top);
+            area.print_str(&context.font_cache, position, style, &number)?;
+        }
+        Ok(())
+    }
+}
+
+/// A `[page.header]` or `[page.footer]` logo, drawn in the middle of the top or bottom
+/// margin at the configured height, or the height of the margin when that is smaller.
+struct MarginLogo {
+    image: genpdfi_extended::elements::Image,
+    /// Width divided by height of the image
+    aspect_ratio: f32,
+    height: f32,
+    alignment: crate::styling::TextAlignment,
+    /// Whether the logo is in the top margin rather than the bottom one
+    header: bool,
+    /// Page dimensions in millimeters
+    page_size: (f32, f32),
+}
+
+impl MarginLogo {
+    /// Returns the left edge and the width in millimeters of the logo on pages with
+    /// `margins`, or `None` when it has no room to be drawn.
+    fn span(&self, margins: crate::styling::Margins) -> Option<(f32, f32)> {
+        use crate::styling::TextAlignment;
+
+        let page_width = self.page_size.0;
+        let margin = if self.header {
+            margins.top
+        } else {
+            margins.bottom
+        };
+        let height = self.height.min(margin);
+        let width = (height * self.aspect_ratio).min(page_width - margins.left - margins.right);
+        if height <= 0.0 || width <= 0.0 {
+            return None;
+        }
+        let left = match self.alignment {
+            TextAlignment::Center => (page_width - width) / 2.0,
+            TextAlignment::Right => page_width - margins.right - width,
+            TextAlignment::Left | TextAlignment::Justify => margins.left,
+        };
+        Some((left, width))
+    }
+
+    fn draw(
+        &mut self,
+        context: &genpdfi_extended::Context,
+        area: &genpdfi_extended::render::Area<'_>,
+        margins: crate::styling::Margins,
+        style: genpdfi_extended::style::Style,
+    ) -> Result<(), genpdfi_extended::error::Error> {
+        let page_height = self.page_size.1;
+        let margin = if self.header {
+            margins.top
+        } else {
+            margins.bottom
+        };
+        let height = self.height.min(margin);
+        let Some((left, width)) = self.span(margins) else {
+            return Ok(());
+        };
+        let top = if self.header {
+            (margin - height) / 2.0
+        } else {
+            page_height - (margin + height) / 2.0
+        };
+
+        let mut logo_area = area.clone();
+        logo_area.add_offset(genpdfi_extended::Position::new(
+            genpdfi_extended::Mm::from(left),
+            genpdfi_extended::Mm::from(top),
+        ));
+        // The image is sized by the width; the area reaches down to the page edge so
+        // rounding never leaves it too short to draw the image
+        logo_area.set_size(genpdfi_extended::Size::new(width, page_height - top));
+        genpdfi_extended::Element::render(&mut self.image, context, logo_area, style)?;
+        Ok(())
+    }
+}
+
+/// A code block laid out with `[code] keep_together`: when it does not fit in the room
+/// left on the page but fits on an empty one, it moves to the next page instead of
+/// splitting. Blocks taller than a page split as usual.
+struct KeepTogether<E> {
+    element: E,
+    /// Estimated height of the element in millimeters
+    height: f32,
+    /// Height of the printable area of a page in millimeters
+    page_height: f32,
+    /// Whether the element was already offered a page, so it never moves twice
+    placed: bool,
+}
+
+impl<E: genpdfi_extended::Element> genpdfi_extended::Element for KeepTogether<E> {
+    fn render(
+        &mut self,
+        context: &genpdfi_extended::Context,
+        area: genpdfi_extended::render::Area<'_>,
+        style: genpdfi_extended::style::Style,
+    ) -> Result<genpdfi_extended::RenderResult, genpdfi_extended::error::Error> {
+        let fits = area.size().height >= genpdfi_extended::Mm::from(self.height);
+        if !self.placed && !fits && self.height <= self.page_height {
+            self.placed = true;
+            // Rendering nothing with more to come continues on a new page
+            return Ok(genpdfi_extended::RenderResult {
+                has_more: true,
+                ..Default::default()
+            });
+        }
+        self.placed = true;
+        self.element.render(context, area, style)
+    }
+}
+
+/// A code block with `[code] show_language_label`: the label paragraph is drawn over the
+/// top of the first area the block is rendered in, so it sits in the block's corner
+/// rather than on a line of its own.
+struct CornerLabel {
+    element: genpdfi_extended::elements::LinearLayout,
+    label: Option<genpdfi_extended::elements::Paragraph>,
+}
+
+impl genpdfi_extended::Element for CornerLabel {
+    fn render(
+        &mut self,
+        context: &genpdfi_extended::Context,
+        area: genpdfi_extended::render::Area<'_>,
+        style: genpdfi_extended::style::Style,
+    ) -> Result<genpdfi_extended::RenderResult, genpdfi_extended::error::Error> {
+        let result = self.element.render(context, area.clone(), style.clone())?;
+        // Only label the area the block starts in, not one it was pushed out of
+        if result.size.height > genpdfi_extended::Mm::from(0.0) {
+            if let Some(mut label) = self.label.take() {
+                label.render(context, area, style)?;
+            }
+        }
+        Ok(result)
+    }
+}
+
+/// A body paragraph laid out with `[page] min_lines_top` or `min_lines_bottom`: when it
+/// does not fit in the room left on the page, it breaks early enough to carry at least
+/// `min_top` lines to the next page, or moves there whole when fewer than `min_bottom`
+/// lines would stay behind.
+struct KeepLines<E> {
+    element: E,
+    /// Text of the paragraph, wrapped with the fonts of the document to count its lines
+    text: String,
+    /// Font size of the paragraph text in points
+    size: u8,
+    /// Millimeters the paragraph is narrower than the area it is given
+    inset: f32,
+    min_top: usize,
+    min_bottom: usize,
+    /// Whether the element was already offered a page, so it is only adjusted once
+    placed: bool,
+}
+
+impl<E> KeepLines<E> {
+    /// Counts the lines the text wraps to in `width`, measuring words as the paragraph
+    /// lays them out: greedily, with the widths of the fonts in `context`.
+    fn measured_lines(
+        &self,
+        context: &genpdfi_extended::Context,
+        style: &genpdfi_extended::style::Style,
+        width: genpdfi_extended::Mm,
+    ) -> usize {
+        let space = style.str_width(&context.font_cache, " ");
+        let mut lines = 0;
+        for text_line in self.text.trim().lines() {
+            lines += 1;
+            let mut used = genpdfi_extended::Mm::from(0.0);
+            for (i, word) in text_line.split_whitespace().enumerate() {
+                let word_width = style.str_width(&context.font_cache, word);
+                if i > 0 && used + space + word_width <= width {
+                    used = used + space + word_width;
+                } else {
+                    if i > 0 {
+                        lines += 1;
+                    }
+                    used = word_width;
+                }
+            }
+        }
+        lines
+    }
+}
+
+impl<E: genpdfi_extended::Element> genpdfi_extended::Element for KeepLines<E> {
+    fn render(
+        &mut self,
+        context: &genpdfi_extended::Context,
+        mut area: genpdfi_extended::render::Area<'_>,
+        style: genpdfi_extended::style::Style,
+    ) -> Result<genpdfi_extended::RenderResult, genpdfi_extended::error::Error> {
+        if self.placed {
+            return self.element.render(context, area, style);
+        }
+        self.placed = true;
+
+        let text_style = style.clone().with_font_size(self.size);
+        let line_height = text_style.line_height(&context.font_cache);
+        let width = area.size().width - genpdfi_extended::Mm::from(self.inset);
+        let lines = self.measured_lines(context, &text_style, width);
+        let height = area.size().height;
+        let fitting = (1..=lines)
+            .take_while(|&n| line_height * n as f64 <= height)
+            .count();
+        if fitting == 0 || fitting >= lines {
+            return self.element.render(context, area, style);
+        }
+
+        let first = fitting.min(lines.saturating_sub(self.min_top));
+        if first < self.min_bottom {
+            // Rendering nothing with more to come continues on a new page
+            return Ok(genpdfi_extended::RenderResult {
+                has_more: true,
+                ..Default::default()
+            });
+        }
+        if first < fitting {
+            // Half a line of slack so rounding does not push out the last line kept
+            area.set_height(line_height * (first as f64 + 0.5));
+        }
+        self.element.render(context, area, style)
+    }
+}
+
+/// Converts configured margins to genpdf margins.
+fn page_margins(margins: crate::styling::Margins) -> genpdfi_extended::Margins {
+    genpdfi_extended::Margins::trbl(margins.top, margins.right, margins.bottom, margins.left)
 }
 
 /// The main PDF document generator that orchestrates the conversion process from markdown to PDF.
@@ -49,11 +461,78 @@ thread_local! {
 pub struct Pdf {
     input: Vec<Token>,
     style: StyleMatch,
+    /// Document title taken from the leading heading with `[document] title_from_h1`
+    title: Option<String>,
     font_family: FontFamily<FontData>,
     code_font_family: FontFamily<FontData>,
     font_fallback_chain: Option<FontFamily<genpdfi_extended::fonts::FontFallbackChain>>,
     code_font_fallback_chain: Option<FontFamily<genpdfi_extended::fonts::FontFallbackChain>>,
     image_loader: RefCell<Option<crate::images::ImageLoader>>,
+    /// Image from `[image] fallback_image`, shown in place of images that fail to load
+    fallback_image: Option<crate::images::ImageData>,
+    /// Logo images of `[page.header]` and `[page.footer]`
+    header_logo: Option<crate::images::ImageData>,
+    footer_logo: Option<crate::images::ImageData>,
+    abbreviations: Vec<(String, String)>,
+    /// Indices into `abbreviations`, in order of first use
+    used_abbreviations: RefCell<Vec<usize>>,
+    /// Names of the explicit `<a id>` anchors in the document
+    anchors: Vec<String>,
+    /// Distinct terms tagged for the index, sorted alphabetically
+    index_terms: Vec<String>,
+    /// Pages each index term appears on, known once a first rendering pass was laid out
+    index_pages: RefCell<Vec<Vec<usize>>>,
+    /// Fonts loaded for `[fonts.ranges]`, one per distinct font name, followed by the
+    /// fallback chain fonts that cover characters missing from the main font
+    range_font_families: Vec<FontFamily<FontData>>,
+    /// Configured ranges, then single-character ranges for fallback glyphs, paired with an
+    /// index into `range_font_families`
+    font_ranges: Vec<(FontRange, usize)>,
+    /// Characters of `<kbd>` labels the code font cannot render
+    kbd_missing_glyphs: Vec<char>,
+    /// Set once a `<kbd>`, checkbox, highlight or line marker has been rendered
+    decorations: Cell<bool>,
+    /// Orientation of the pages being laid out, switched by `<!-- orientation: ... -->`
+    orientation: Cell<crate::styling::Orientation>,
+    /// Runs of tokens laid out in the same orientation, see [`Pdf::orientation_sections`]
+    orientation_sections: OnceCell<Vec<(crate::styling::Orientation, std::ops::Range<usize>)>>,
+    /// Set once an element other than spacing has been pushed onto the document
+    has_pushed_content: Cell<bool>,
+    /// Spacing held back by `[spacing] trim_edges` until more content follows it
+    pending_spacing: Cell<Option<f32>>,
+    /// Line color while text is being struck through, e.g. in completed task items
+    strike_color: Cell<Option<(u8, u8, u8)>>,
+    /// Line color while `<ins>` text is being underlined
+    underline_color: Cell<Option<(u8, u8, u8)>>,
+    /// Fill color while `<mark>` text is being highlighted
+    highlight_color: Cell<Option<(u8, u8, u8)>>,
+    /// Case transform of the heading being rendered (`[heading.N] text_transform`)
+    text_transform: Cell<TextTransform>,
+    /// Character spacing in points of the heading being rendered
+    /// (`[heading.N] letterspacing`)
+    letter_spacing: Cell<Option<f32>>,
+    /// URIs of anchor and index markers waiting for the next word to attach to
+    pending_markers: RefCell<Vec<String>>,
+    /// Footnote contents in order of reference; a note's number is its index + 1
+    footnotes: RefCell<Vec<Vec<Token>>>,
+    /// Headings seen so far at each level, for `[heading] numbered`
+    heading_counters: RefCell<[usize; 6]>,
+    /// Pages the `[page.footer]` has numbered in the current rendering pass
+    footer_pages: Rc<Cell<usize>>,
+    /// Non-fatal problems encountered while loading fonts and rendering
+    warnings: RefCell<Vec<ValidationWarning>>,
+    /// File name the markdown source is attached under (`[document] attach_source`)
+    source_name: String,
+    /// Markdown source to attach, set through [`Pdf::set_source`]
+    source: Option<String>,
+    /// Compiled `[redact]` patterns, also applied to the attached source
+    redact_patterns: Vec<regex::Regex>,
+    /// Time spent loading fonts while the generator was created
+    font_loading: Duration,
+    /// Time spent highlighting code blocks so far
+    highlighting: Cell<Duration>,
+    /// Time spent loading images so far
+    image_loading: Cell<Duration>,
 }
 
 impl Pdf {
@@ -97,19 +576,74 @@ impl Pdf {
     /// * `font_config` - Optional font configuration with custom paths and font overrides
     /// * `document_path` - Path to the markdown document (for resolving relative image paths)
     pub fn with_document_path(
-        input: Vec<Token>,
+        mut input: Vec<Token>,
         style: StyleMatch,
         font_config: Option<&crate::fonts::FontConfig>,
         document_path: Option<&std::path::Path>,
     ) -> Self {
+        let font_start = Instant::now();
+        if let Some(marks) = style.quote_style.marks() {
+            Token::replace_quotes(&mut input, marks);
+        }
+        let mut warnings = Vec::new();
+        let redact_patterns: Vec<regex::Regex> = style
+            .redact
+            .patterns
+            .iter()
+            .filter_map(|pattern| match regex::Regex::new(pattern) {
+                Ok(regex) => Some(regex),
+                Err(e) => {
+                    warn!("Invalid [redact] pattern '{}': {}", pattern, e);
+                    warnings.push(ValidationWarning::invalid_redact_pattern(
+                        pattern,
+                        &e.to_string(),
+                    ));
+                    None
+                }
+            })
+            .collect();
+        if !redact_patterns.is_empty() {
+            Token::redact(&mut input, &redact_patterns, style.redact.mask);
+        }
+        if style.link_base_url.is_some() || style.image_base_url.is_some() {
+            Token::resolve_urls(
+                &mut input,
+                style.link_base_url.as_deref(),
+                style.image_base_url.as_deref(),
+            );
+        }
+        let title = if style.document.title_from_h1 {
+            Token::take_title(&mut input)
+        } else {
+            None
+        };
+
         let all_text = if font_config.map(|c| c.enable_subsetting).unwrap_or(true) {
             Some(Token::collect_all_text(&input))
         } else {
             None
         };
 
+        // `[text] fontfile` pins the text font to a file, unless a font is set explicitly
+        let font_file_family = style
+            .text_font_file
+            .as_deref()
+            .filter(|_| font_config.and_then(|cfg| cfg.default_font.as_ref()).is_none())
+            .and_then(|path| {
+                crate::fonts::load_font_file(std::path::Path::new(path), all_text.as_deref())
+                    .map_err(|e| {
+                        warnings.push(ValidationWarning::font_fallback(format!(
+                            "Font file '{}' could not be loaded ({}), using the font family instead",
+                            path, e
+                        )));
+                    })
+                    .ok()
+            });
+
         // Try to load fonts with fallback chains
-        let (font_family, font_fallback_chain) = if let Some(family_name) = font_config
+        let (font_family, font_fallback_chain) = if let Some(family) = font_file_family {
+            (family, None)
+        } else if let Some(family_name) = font_config
             .and_then(|cfg| cfg.default_font.as_deref())
             .or(style.text.font_family)
         {
@@ -151,6 +685,10 @@ impl Pdf {
                     (primary_fonts, Some(final_chain))
                 } else {
                     warn!("Fallback chain loading failed, using single best font...");
+                    warnings.push(ValidationWarning::font_fallback(format!(
+                        "Fallback chain for '{}' could not be loaded, using a single font",
+                        family_name
+                    )));
                     let single_font = crate::fonts::load_font_with_fallbacks(
                         family_name,
                         &fallback_fonts,
@@ -208,21 +746,287 @@ impl Pdf {
                         "Warning: could not load code font '{}', falling back to Courier",
                         code_font_name
                     );
+                    warnings.push(ValidationWarning::font_fallback(format!(
+                        "Code font '{}' could not be loaded, using the built-in monospace font",
+                        code_font_name
+                    )));
                     crate::fonts::load_builtin_font_family("space mono")
                         .expect("Failed to load fallback code font family")
                 });
 
+        // Per-script fonts from [fonts.ranges], loaded once per distinct font name. They
+        // take precedence over the main font and its fallbacks for their ranges.
+        let mut range_font_names: Vec<&str> = Vec::new();
+        let mut range_font_families = Vec::new();
+        let mut font_ranges = Vec::new();
+        for range in &style.font_ranges {
+            let idx = match range_font_names.iter().position(|name| *name == range.font) {
+                Some(idx) => idx,
+                None => match crate::fonts::load_font_with_config(
+                    &range.font,
+                    font_config,
+                    all_text.as_deref(),
+                ) {
+                    Ok(family) => {
+                        range_font_names.push(&range.font);
+                        range_font_families.push(family);
+                        range_font_families.len() - 1
+                    }
+                    Err(_) => {
+                        warn!("Could not load range font '{}'", range.font);
+                        warnings.push(ValidationWarning::font_fallback(format!(
+                            "Font '{}' for U+{:04X}-U+{:04X} could not be loaded, using the main font",
+                            range.font, range.start, range.end
+                        )));
+                        continue;
+                    }
+                },
+            };
+            font_ranges.push((range.clone(), idx));
+        }
+
+        // Characters the main font lacks, e.g. emoji, are rendered with the first font of
+        // the fallback chain that has them, through the same per-run font override as
+        // [fonts.ranges]. This covers every text run, including headings and table cells.
+        if let Some(chain) = font_fallback_chain.as_ref() {
+            let text = Token::collect_all_text(&input);
+            let mut missing =
+                crate::fonts::missing_glyphs_for_family(&font_family, &text).unwrap_or_default();
+            for (fallback_idx, family) in crate::fonts::extract_fallback_fonts(chain)
+                .into_iter()
+                .enumerate()
+            {
+                if missing.is_empty() {
+                    break;
+                }
+                let still_missing = crate::fonts::missing_glyphs_for_family(
+                    &family,
+                    &missing.iter().collect::<String>(),
+                )
+                .unwrap_or_else(|_| missing.clone());
+                let mut covered: Vec<u32> = missing
+                    .iter()
+                    .filter(|c| !still_missing.contains(c))
+                    .map(|&c| c as u32)
+                    .collect();
+                if covered.is_empty() {
+                    continue;
+                }
+                range_font_families.push(family);
+                let idx = range_font_families.len() - 1;
+                // Neighbouring code points share a range, so a script the main font lacks
+                // does not cost a range per character when every text run is split
+                covered.sort_unstable();
+                covered.dedup();
+                let mut start = 0;
+                for end in 1..=covered.len() {
+                    if end < covered.len() && covered[end] == covered[end - 1] + 1 {
+                        continue;
+                    }
+                    font_ranges.push((
+                        FontRange {
+                            start: covered[start],
+                            end: covered[end - 1],
+                            font: format!("fallback font {}", fallback_idx + 1),
+                        },
+                        idx,
+                    ));
+                    start = end;
+                }
+                missing = still_missing;
+            }
+            if !missing.is_empty() {
+                warnings.push(ValidationWarning::missing_glyphs(missing));
+            }
+        }
+
+        // Keys such as `<kbd>⌘</kbd>` fall back to the text font when the code font lacks them
+        let mut kbd_text = String::new();
+        collect_kbd_text(&input, &mut kbd_text);
+        let kbd_missing_glyphs = if kbd_text.is_empty() {
+            Vec::new()
+        } else {
+            crate::fonts::missing_glyphs_for_family(&code_font_family, &kbd_text)
+                .unwrap_or_default()
+        };
+
+        // Without a fallback chain, characters the primary font lacks render as boxes
+        if let (Some(text), None) = (all_text.as_deref(), font_fallback_chain.as_ref()) {
+            if let Ok(missing) = crate::fonts::missing_glyphs_for_family(&font_family, text) {
+                if !missing.is_empty() {
+                    warnings.push(ValidationWarning::missing_glyphs(missing));
+                }
+            }
+        }
+
+        let font_loading = font_start.elapsed();
+
+        let abbreviations = Token::collect_abbreviations(&input);
+        let anchors = Token::collect_anchors(&input);
+        let index_terms = if style.index.enabled {
+            Token::collect_index_terms(&input)
+        } else {
+            Vec::new()
+        };
+
+        let mut image_loader = crate::images::ImageLoader::new(document_path);
+        image_loader.set_max_download_size(style.remote_images.max_download_size);
+
+        let image_start = Instant::now();
+        let fallback_image = style.fallback_image.as_deref().and_then(|path| {
+            image_loader
+                .load(path)
+                .map_err(|e| {
+                    warn!("Could not load fallback image '{}': {}", path, e);
+                    warnings.push(ValidationWarning::image_load_failed(format!(
+                        "Fallback image '{}' could not be loaded: {}",
+                        path, e
+                    )));
+                })
+                .ok()
+        });
+        let mut load_logo = |logo: Option<&crate::styling::PageLogo>, section: &str| {
+            let path = &logo?.image;
+            image_loader
+                .load(path)
+                .map_err(|e| {
+                    warn!("Could not load [page.{}] image '{}': {}", section, path, e);
+                    warnings.push(ValidationWarning::image_load_failed(format!(
+                        "[page.{}] image '{}' could not be loaded: {}",
+                        section, path, e
+                    )));
+                })
+                .ok()
+        };
+        let header_logo = load_logo(style.header.logo.as_ref(), "header");
+        let footer_logo = load_logo(style.footer.logo.as_ref(), "footer");
+        let image_loading = image_start.elapsed();
+
         Self {
             input,
             style,
+            title,
             font_family,
             code_font_family,
             font_fallback_chain,
             code_font_fallback_chain: None,
-            image_loader: RefCell::new(Some(crate::images::ImageLoader::new(document_path))),
+            image_loader: RefCell::new(Some(image_loader)),
+            fallback_image,
+            header_logo,
+            footer_logo,
+            abbreviations,
+            used_abbreviations: RefCell::new(Vec::new()),
+            anchors,
+            index_terms,
+            index_pages: RefCell::new(Vec::new()),
+            range_font_families,
+            font_ranges,
+            kbd_missing_glyphs,
+            decorations: Cell::new(false),
+            orientation: Cell::new(style.orientation),
+            orientation_sections: OnceCell::new(),
+            has_pushed_content: Cell::new(false),
+            pending_spacing: Cell::new(None),
+            strike_color: Cell::new(None),
+            underline_color: Cell::new(None),
+            highlight_color: Cell::new(None),
+            text_transform: Cell::new(TextTransform::None),
+            letter_spacing: Cell::new(None),
+            pending_markers: RefCell::new(Vec::new()),
+            footnotes: RefCell::new(Vec::new()),
+            heading_counters: RefCell::new([0; 6]),
+            footer_pages: Rc::new(Cell::new(0)),
+            warnings: RefCell::new(warnings),
+            source_name: document_path
+                .and_then(|path| path.file_name())
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "document.md".to_string()),
+            source: None,
+            redact_patterns,
+            font_loading,
+            highlighting: Cell::new(Duration::ZERO),
+            image_loading: Cell::new(image_loading),
+        }
+    }
+
+    /// Returns the time spent loading fonts, highlighting code and loading images so far.
+    ///
+    /// Lexing and rendering happen outside the generator and are left at zero; the
+    /// conversion functions fill them in for [`crate::ConversionReport`].
+    pub fn timings(&self) -> crate::StageTimings {
+        crate::StageTimings {
+            font_loading: self.font_loading,
+            highlighting: self.highlighting.get(),
+            image_loading: self.image_loading.get(),
+            ..Default::default()
+        }
+    }
+
+    /// Loads an image through `loader`, adding the time spent to the image loading timing.
+    fn load_image(
+        &self,
+        loader: &mut crate::images::ImageLoader,
+        url: &str,
+    ) -> Result<crate::images::ImageData, crate::images::ImageError> {
+        let start = Instant::now();
+        let result = loader.load(url);
+        self.image_loading
+            .set(self.image_loading.get() + start.elapsed());
+        result
+    }
+
+    /// Keeps the markdown source to embed as a file attachment when
+    /// `[document] attach_source` is enabled; otherwise the source is dropped.
+    ///
+    /// Text matching the `[redact]` patterns is masked in the source as well, so the
+    /// attachment does not reveal what the rendered pages hide.
+    pub fn set_source(&mut self, markdown: String) {
+        if self.style.document.attach_source {
+            self.source = Some(if self.redact_patterns.is_empty() {
+                markdown
+            } else {
+                crate::markdown::redact_text(
+                    &markdown,
+                    &self.redact_patterns,
+                    self.style.redact.mask,
+                )
+            });
         }
     }
 
+    /// Returns the file name and content of the markdown source to attach, in which case
+    /// the rendered PDF must be passed through [`Pdf::embed_source`].
+    pub fn source_attachment(&self) -> Option<(&str, &str)> {
+        self.source
+            .as_deref()
+            .map(|markdown| (self.source_name.as_str(), markdown))
+    }
+
+    /// Returns the `[document] lang` language tag, in which case the rendered PDF must be
+    /// passed through [`Pdf::set_language`].
+    pub fn language(&self) -> Option<&str> {
+        self.style.document.lang.as_deref()
+    }
+
+    /// Records `lang` (a BCP 47 tag such as `en-US`) as the natural language of a
+    /// rendered PDF, the catalog's `/Lang` entry read by screen readers.
+    pub fn set_language(pdf: &[u8], lang: &str) -> Result<Vec<u8>, String> {
+        let mut doc = lopdf::Document::load_mem(pdf).map_err(|e| e.to_string())?;
+        let root_id = doc
+            .trailer
+            .get(b"Root")
+            .and_then(|o| o.as_reference())
+            .map_err(|e| e.to_string())?;
+        doc.get_object_mut(root_id)
+            .and_then(|o| o.as_dict_mut())
+            .map_err(|e| e.to_string())?
+            .set("Lang", lopdf::Object::string_literal(lang));
+
+        let mut output = Vec::new();
+        doc.save_to(&mut output).map_err(|e| e.to_string())?;
+        Ok(output)
+    }
+
     /// Finalizes and outputs the processed document to a PDF file at the specified path.
     /// Provides comprehensive error handling to catch and report any issues during the
     /// final rendering phase.
@@ -264,1600 +1068,7488 @@ impl Pdf {
         }
     }
 
-    /// Initializes and returns a new PDF document with configured styling and layout.
+    /// Returns the timestamp to embed when reproducible output is requested.
     ///
-    /// Creates a new document instance with the main font family and configures the page decorator
-    /// with margins from the style settings. The document's base font size is set according to the
-    /// text style configuration.
+    /// The `source_date_epoch` of the document settings, taken from the `SOURCE_DATE_EPOCH`
+    /// environment variable by the conversion entry points, takes precedence and also
+    /// enables reproducible output on its own, following the reproducible-builds
+    /// convention. Otherwise, when `[document] reproducible = true` is set, the Unix epoch
+    /// is used.
+    /// Returns `None` when output does not need to be deterministic.
+    pub fn reproducible_timestamp(&self) -> Option<i64> {
+        reproducible_timestamp(&self.style)
+    }
+
+    /// Formats the date the document is generated on with `format`, in UTC: `%Y`, `%m`,
+    /// `%d`, `%H`, `%M` and `%S` are replaced by the year, month, day, hours, minutes and
+    /// seconds and `%%` by `%`.
     ///
-    /// The function processes all input tokens and renders them into the document structure before
-    /// returning the complete document ready for final output. The document contains all content
-    /// with proper styling, formatting and layout applied according to the style configuration.
+    /// The date is the timestamp of [`Pdf::reproducible_timestamp`] when `style` asks for
+    /// reproducible output, so such builds print and name their output the same on any
+    /// day, and the current time otherwise.
+    pub fn generation_date(style: &StyleMatch, format: &str) -> String {
+        let timestamp = reproducible_timestamp(style).unwrap_or_else(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs() as i64)
+        });
+        format_date(timestamp, format)
+    }
+
+    /// Rewrites rendered PDF bytes so that identical input yields identical output.
     ///
-    /// Through the style configuration, this method controls the overall document appearance including:
-    /// - Page margins and layout
-    /// - Base font size
-    /// - Content processing and rendering
-    pub fn render_into_document(&self) -> Document {
-        let mut doc = genpdfi_extended::Document::new(self.font_family.clone());
-        let mut decorator = genpdfi_extended::SimplePageDecorator::new();
+    /// The creation and modification dates are replaced with `timestamp` (seconds since the
+    /// Unix epoch), the XMP metadata stream (which carries its own dates and a random instance
+    /// ID) is dropped, and the random document ID is replaced by the MD5 digest of the content.
+    ///
+    /// # Arguments
+    /// * `pdf` - The rendered PDF data
+    /// * `timestamp` - The date to embed, in seconds since the Unix epoch
+    ///
+    /// # Returns
+    /// * `Ok(Vec<u8>)` containing the normalized PDF data
+    /// * `Err(String)` if the PDF could not be parsed or written back
+    pub fn make_reproducible(pdf: &[u8], timestamp: i64) -> Result<Vec<u8>, String> {
+        use lopdf::{Object, StringFormat};
+        use md5::{Digest, Md5};
+
+        let mut doc = lopdf::Document::load_mem(pdf).map_err(|e| e.to_string())?;
+        let date = format_pdf_date(timestamp);
+
+        if let Ok(info_id) = doc.trailer.get(b"Info").and_then(|o| o.as_reference()) {
+            if let Ok(info) = doc.get_object_mut(info_id).and_then(|o| o.as_dict_mut()) {
+                info.set("CreationDate", Object::string_literal(date.clone()));
+                info.set("ModDate", Object::string_literal(date));
+            }
+        }
 
-        decorator.set_margins(genpdfi_extended::Margins::trbl(
-            self.style.margins.top,
-            self.style.margins.right,
-            self.style.margins.bottom,
-            self.style.margins.left,
-        ));
+        if let Ok(root_id) = doc.trailer.get(b"Root").and_then(|o| o.as_reference()) {
+            let metadata = doc
+                .get_object_mut(root_id)
+                .and_then(|o| o.as_dict_mut())
+                .ok()
+                .and_then(|root| root.remove(b"Metadata"));
+            if let Some(Object::Reference(metadata_id)) = metadata {
+                doc.objects.remove(&metadata_id);
+            }
+        }
 
-        doc.set_page_decorator(decorator);
-        doc.set_font_size(self.style.text.size);
+        // Derive the document ID from the normalized content
+        doc.trailer.remove(b"ID");
+        let mut unidentified = Vec::new();
+        doc.save_to(&mut unidentified).map_err(|e| e.to_string())?;
+
+        // MD5 of the content, as the PDF specification suggests for the file identifier
+        let id = Md5::digest(&unidentified).to_vec();
+        doc.trailer.set(
+            "ID",
+            Object::Array(vec![
+                Object::String(id.clone(), StringFormat::Hexadecimal),
+                Object::String(id, StringFormat::Hexadecimal),
+            ]),
+        );
+
+        let mut output = Vec::new();
+        doc.save_to(&mut output).map_err(|e| e.to_string())?;
+        Ok(output)
+    }
 
-        // Add code font to the document's font cache for use in code blocks
-        let code_font = doc.add_font_family(self.code_font_family.clone());
+    /// Returns whether `[document] contact_sheet` is set, in which case the rendered PDF
+    /// must be passed through [`Pdf::append_contact_sheet`].
+    pub fn has_contact_sheet(&self) -> bool {
+        self.style.document.contact_sheet
+    }
 
-        // Store it in thread-local storage for access in render_highlighted_line
-        CURRENT_CODE_FONT_OVERRIDE.with(|f| {
-            *f.borrow_mut() = Some(code_font);
-        });
+    /// Appends contact sheet pages to a rendered PDF: every page as a framed thumbnail,
+    /// `CONTACT_SHEET_COLUMNS` to a row, on pages the size of the first one.
+    ///
+    /// The thumbnails are the pages themselves, reused as form XObjects and scaled down,
+    /// so they stay sharp at any zoom and add little to the file size.
+    pub fn append_contact_sheet(pdf: &[u8]) -> Result<Vec<u8>, String> {
+        use lopdf::{dictionary, Object, ObjectId, Stream};
+
+        let mut doc = lopdf::Document::load_mem(pdf).map_err(|e| e.to_string())?;
+        let pages_id = doc
+            .trailer
+            .get(b"Root")
+            .and_then(|root| root.as_reference())
+            .and_then(|root| doc.get_dictionary(root))
+            .and_then(|catalog| catalog.get(b"Pages"))
+            .and_then(|pages| pages.as_reference())
+            .map_err(|e| e.to_string())?;
+
+        // Each page becomes a form showing its content with its resources
+        let page_ids: Vec<ObjectId> = doc.get_pages().into_values().collect();
+        let mut thumbnails = Vec::new();
+        for page_id in page_ids {
+            let Some(media_box) = inherited_page_attribute(&doc, page_id, b"MediaBox") else {
+                continue;
+            };
+            let bounds: Vec<f32> = media_box
+                .as_array()
+                .map_err(|e| e.to_string())?
+                .iter()
+                .filter_map(|v| v.as_float().ok())
+                .collect();
+            if bounds.len() != 4 || bounds[2] <= bounds[0] || bounds[3] <= bounds[1] {
+                continue;
+            }
+            let content = doc.get_page_content(page_id).map_err(|e| e.to_string())?;
+            let mut form = dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Form",
+                "BBox" => media_box,
+            };
+            if let Some(resources) = inherited_page_attribute(&doc, page_id, b"Resources") {
+                form.set("Resources", resources);
+            }
+            let form_id = doc.add_object(Stream::new(form, content));
+            thumbnails.push((form_id, bounds));
+        }
+        let Some((_, first)) = thumbnails.first() else {
+            return Ok(pdf.to_vec());
+        };
 
-        self.process_tokens(&mut doc);
+        let (width, height) = (first[2] - first[0], first[3] - first[1]);
+        let columns = CONTACT_SHEET_COLUMNS;
+        let (margin, gap) = (CONTACT_SHEET_MARGIN_PT, CONTACT_SHEET_GAP_PT);
+        let cell_width = (width - 2.0 * margin - (columns - 1) as f32 * gap) / columns as f32;
+        let cell_height = cell_width * height / width;
+        let rows = (((height - 2.0 * margin + gap) / (cell_height + gap)).floor() as usize).max(1);
+
+        let mut kids = Vec::new();
+        for sheet in thumbnails.chunks(columns * rows) {
+            let mut operations = String::new();
+            let mut forms = lopdf::Dictionary::new();
+            for (i, (form_id, bounds)) in sheet.iter().enumerate() {
+                let (page_width, page_height) = (bounds[2] - bounds[0], bounds[3] - bounds[1]);
+                let scale = (cell_width / page_width).min(cell_height / page_height);
+                let (thumb_width, thumb_height) = (page_width * scale, page_height * scale);
+                let cell_x = margin + (i % columns) as f32 * (cell_width + gap);
+                let cell_top = height - margin - (i / columns) as f32 * (cell_height + gap);
+                let x = cell_x + (cell_width - thumb_width) / 2.0;
+                let y = cell_top - cell_height + (cell_height - thumb_height) / 2.0;
+                let name = format!("P{}", i);
+                operations.push_str(&format!(
+                    "q {s:.4} 0 0 {s:.4} {:.2} {:.2} cm /{} Do Q\n",
+                    x - bounds[0] * scale,
+                    y - bounds[1] * scale,
+                    name,
+                    s = scale,
+                ));
+                operations.push_str(&format!(
+                    "q 0.6 G 0.5 w {:.2} {:.2} {:.2} {:.2} re S Q\n",
+                    x, y, thumb_width, thumb_height
+                ));
+                forms.set(name, Object::Reference(*form_id));
+            }
+            let content_id = doc.add_object(Stream::new(
+                lopdf::Dictionary::new(),
+                operations.into_bytes(),
+            ));
+            let page_id = doc.add_object(dictionary! {
+                "Type" => "Page",
+                "Parent" => pages_id,
+                "MediaBox" => vec![
+                    Object::Integer(0),
+                    Object::Integer(0),
+                    Object::Real(width),
+                    Object::Real(height),
+                ],
+                "Contents" => content_id,
+                "Resources" => dictionary! { "XObject" => forms },
+            });
+            kids.push(Object::Reference(page_id));
+        }
 
-        // Clean up thread-local storage after rendering
-        CURRENT_CODE_FONT_OVERRIDE.with(|f| {
-            *f.borrow_mut() = None;
-        });
+        if let Ok(pages) = doc
+            .get_object_mut(pages_id)
+            .and_then(|pages| pages.as_dict_mut())
+        {
+            let count = pages.get(b"Count").and_then(|c| c.as_i64()).unwrap_or(0);
+            let mut all_kids = pages
+                .get(b"Kids")
+                .and_then(|k| k.as_array())
+                .cloned()
+                .unwrap_or_default();
+            pages.set("Count", Object::Integer(count + kids.len() as i64));
+            all_kids.extend(kids);
+            pages.set("Kids", Object::Array(all_kids));
+        }
 
-        doc
+        let mut output = Vec::new();
+        doc.save_to(&mut output).map_err(|e| e.to_string())?;
+        Ok(output)
     }
 
-    /// Processes and renders tokens directly into the document structure.
-    ///
-    /// This method iterates through all input tokens and renders them into the document,
-    /// handling each token type appropriately according to its semantic meaning. Block-level
-    /// elements like headings, list items, and code blocks trigger the flushing of any
-    /// accumulated inline tokens into paragraphs before being rendered themselves.
-    ///
-    /// The method maintains a buffer of current tokens that gets flushed into paragraphs
-    /// when block-level elements are encountered or when explicit paragraph breaks are
-    /// needed. This ensures proper document flow and maintains correct spacing between
-    /// different content elements while preserving the intended document structure.
+    /// Embeds `markdown` in a rendered PDF as a file attachment named `file_name`.
     ///
-    /// Through careful token processing and rendering, this method builds up the complete
-    /// document content with appropriate styling, formatting and layout applied according
-    /// to the configured style settings.
-    fn process_tokens(&self, doc: &mut Document) {
-        let mut current_tokens = Vec::new();
-        let mut consecutive_images = Vec::new();
+    /// The file is listed in the catalog's `EmbeddedFiles` name tree, so viewers show it
+    /// in their attachments panel, and in the catalog's `AF` array with the `Source`
+    /// relationship.
+    pub fn embed_source(pdf: &[u8], file_name: &str, markdown: &str) -> Result<Vec<u8>, String> {
+        use lopdf::{dictionary, Object, Stream};
+
+        let mut doc = lopdf::Document::load_mem(pdf).map_err(|e| e.to_string())?;
+        let root_id = doc
+            .trailer
+            .get(b"Root")
+            .and_then(|o| o.as_reference())
+            .map_err(|e| e.to_string())?;
+
+        let content = markdown.as_bytes().to_vec();
+        let file_id = doc.add_object(Stream::new(
+            dictionary! {
+                "Type" => "EmbeddedFile",
+                "Subtype" => Object::Name(b"text/markdown".to_vec()),
+                "Params" => dictionary! { "Size" => content.len() as i64 },
+            },
+            content,
+        ));
+        let spec_id = doc.add_object(dictionary! {
+            "Type" => "Filespec",
+            "F" => Object::string_literal(file_name),
+            "UF" => Object::string_literal(file_name),
+            "Desc" => Object::string_literal("Markdown source"),
+            "AFRelationship" => "Source",
+            "EF" => dictionary! { "F" => file_id },
+        });
+        let entry = vec![
+            Object::string_literal(file_name),
+            Object::Reference(spec_id),
+        ];
 
-        for token in &self.input {
-            match token {
-                Token::Heading(content, level) => {
-                    self.flush_paragraph(doc, &current_tokens);
-                    self.flush_consecutive_images(doc, &consecutive_images);
-                    current_tokens.clear();
-                    consecutive_images.clear();
-                    self.render_heading(doc, content, *level);
-                }
-                Token::ListItem {
-                    content,
-                    ordered,
-                    number,
-                } => {
-                    self.flush_paragraph(doc, &current_tokens);
-                    self.flush_consecutive_images(doc, &consecutive_images);
-                    current_tokens.clear();
-                    consecutive_images.clear();
-                    self.render_list_item(doc, content, *ordered, *number, 0);
-                }
-                Token::Code(lang, content) if content.contains('\n') => {
-                    self.flush_paragraph(doc, &current_tokens);
-                    self.flush_consecutive_images(doc, &consecutive_images);
-                    current_tokens.clear();
-                    consecutive_images.clear();
-                    // If the code block language is `mermaid`, render it via genpdfi_extended::elements::Mermaid
-                    if lang.trim().eq_ignore_ascii_case("mermaid") {
-                        self.render_mermaid(doc, content);
-                    } else {
-                        self.render_code_block(doc, lang, content);
-                    }
-                }
-                Token::Math { content, display } if *display => {
-                    // Display math ($$...$$) is a block-level element
-                    self.flush_paragraph(doc, &current_tokens);
-                    self.flush_consecutive_images(doc, &consecutive_images);
-                    current_tokens.clear();
-                    consecutive_images.clear();
-                    self.render_math_block(doc, content);
-                }
-                Token::Math {
-                    content: _,
-                    display: false,
-                } => {
-                    // Inline math ($...$) - treat as inline content, not block
-                    current_tokens.push(token.clone());
-                }
-                Token::HorizontalRule => {
-                    self.flush_paragraph(doc, &current_tokens);
-                    self.flush_consecutive_images(doc, &consecutive_images);
-                    current_tokens.clear();
-                    consecutive_images.clear();
-                    doc.push(genpdfi_extended::elements::Break::new(
-                        self.style.horizontal_rule.after_spacing,
-                    ));
-                }
-                Token::LineBreak => {
-                    // Line breaks are inline - treat as part of paragraph
-                    current_tokens.push(token.clone());
-                }
-                Token::Newline => {
-                    self.flush_paragraph(doc, &current_tokens);
-                    self.flush_consecutive_images(doc, &consecutive_images);
-                    current_tokens.clear();
-                    consecutive_images.clear();
-                }
-                Token::Table {
-                    headers,
-                    aligns,
-                    rows,
-                } => {
-                    self.flush_paragraph(doc, &current_tokens);
-                    self.flush_consecutive_images(doc, &consecutive_images);
-                    current_tokens.clear();
-                    consecutive_images.clear();
-                    self.render_table(doc, headers, aligns, rows)
-                }
-                Token::Image(alt, url) => {
-                    // Collect consecutive images to render together with minimal spacing
-                    consecutive_images.push((alt.clone(), url.clone(), false));
-                }
-                Token::ImageWithLink(alt, image_url, link_url) => {
-                    // Collect consecutive images with links to render together with minimal spacing
-                    consecutive_images.push((
-                        format!("{}||{}", image_url, link_url),
-                        alt.clone(),
-                        true,
-                    ));
-                }
-                Token::Text(content)
-                    if !consecutive_images.is_empty() && content.trim().is_empty() =>
-                {
-                    // Ignore whitespace-only tokens (e.g., from single newlines) when we're collecting images
-                    // This allows images separated by single newlines to be grouped together
-                }
-                _ => {
-                    // If we have accumulated images and encounter non-image content, flush them first
-                    if !consecutive_images.is_empty() {
-                        self.flush_consecutive_images(doc, &consecutive_images);
-                        consecutive_images.clear();
+        let names_ref = doc
+            .get_object(root_id)
+            .and_then(|o| o.as_dict())
+            .and_then(|root| root.get(b"Names"))
+            .and_then(|o| o.as_reference())
+            .ok();
+        let names = match names_ref {
+            Some(id) => doc.get_object_mut(id),
+            None => doc
+                .get_object_mut(root_id)
+                .and_then(|o| o.as_dict_mut())
+                .map(|root| {
+                    if root.get(b"Names").is_err() {
+                        root.set("Names", dictionary! {});
                     }
-                    current_tokens.push(token.clone());
+                    root.get_mut(b"Names").unwrap()
+                }),
+        }
+        .and_then(|o| o.as_dict_mut())
+        .map_err(|e| e.to_string())?;
+        match names
+            .get_mut(b"EmbeddedFiles")
+            .and_then(|o| o.as_dict_mut())
+            .and_then(|tree| tree.get_mut(b"Names"))
+            .and_then(|o| o.as_array_mut())
+        {
+            Ok(existing) => {
+                // Name tree keys must stay in sorted order; a file of the same name is replaced
+                let key = file_name.as_bytes();
+                let position = existing
+                    .chunks(2)
+                    .position(|pair| pair[0].as_str().map_or(false, |name| name >= key))
+                    .map_or(existing.len(), |pair| pair * 2);
+                if existing.get(position).and_then(|o| o.as_str().ok()) == Some(key) {
+                    existing[position + 1] = Object::Reference(spec_id);
+                } else {
+                    existing.splice(position..position, entry);
                 }
             }
+            Err(_) => names.set("EmbeddedFiles", dictionary! { "Names" => entry }),
         }
 
-        // Flush any remaining tokens
-        self.flush_paragraph(doc, &current_tokens);
-        self.flush_consecutive_images(doc, &consecutive_images);
+        let root = doc
+            .get_object_mut(root_id)
+            .and_then(|o| o.as_dict_mut())
+            .map_err(|e| e.to_string())?;
+        match root.get_mut(b"AF").and_then(|o| o.as_array_mut()) {
+            Ok(files) => files.push(Object::Reference(spec_id)),
+            Err(_) => root.set("AF", vec![Object::Reference(spec_id)]),
+        }
+
+        let mut output = Vec::new();
+        doc.save_to(&mut output).map_err(|e| e.to_string())?;
+        Ok(output)
     }
 
-    /// Renders accumulated consecutive images horizontally in a table.
-    /// This allows multiple images to be displayed side-by-side when they are not
-    /// separated by a Newline (paragraph break) in the source markdown.
-    /// According to CommonMark: single newline = whitespace (no line break), so images
-    /// on consecutive lines without double newlines should appear horizontally.
-    fn flush_consecutive_images(&self, doc: &mut Document, images: &[(String, String, bool)]) {
-        if images.is_empty() {
-            return;
-        }
+    /// Returns true if the document defines explicit anchors or, once rendered, footnotes,
+    /// in which case the rendered PDF must be passed through [`Pdf::resolve_anchors`].
+    pub fn has_anchors(&self) -> bool {
+        !self.anchors.is_empty() || !self.footnotes.borrow().is_empty()
+    }
 
-        // Render all consecutive images together in a single container with minimal spacing
-        doc.push(genpdfi_extended::elements::Break::new(
-            self.style.text.before_spacing,
-        ));
+    /// Returns true if the document tags index terms, in which case the index is appended
+    /// to the rendered PDF by [`Pdf::render_pdf`].
+    pub fn has_index(&self) -> bool {
+        !self.index_terms.is_empty()
+    }
 
-        // Render each image without the standard breaks (which would separate them vertically)
-        for (idx, (first_part, second_part, is_link)) in images.iter().enumerate() {
-            if *is_link {
-                // ImageWithLink case: first_part is "url||link_url", second_part is alt
-                let parts: Vec<&str> = first_part.split("||").collect();
-                if parts.len() == 2 {
-                    self.render_image_with_link_no_breaks(doc, second_part, parts[0], parts[1]);
+    /// Appends the index, listing the pages its terms appear on, to the rendered document.
+    ///
+    /// The pages of the index markers are only known once the document is laid out, so
+    /// the index is laid out afterwards as a section of its own, starting on a new page,
+    /// and the document is not laid out a second time. Returns the final PDF, with its
+    /// index markers removed.
+    fn append_index(&self, body: &[u8]) -> Result<Vec<u8>, String> {
+        let (body, markers) = Pdf::index_markers(body)?;
+        {
+            let mut pages = self.index_pages.borrow_mut();
+            *pages = vec![Vec::new(); self.index_terms.len()];
+            for (idx, page) in markers {
+                if let Some(term_pages) = pages.get_mut(idx) {
+                    if !term_pages.contains(&page) {
+                        term_pages.push(page);
+                    }
                 }
-            } else {
-                // Regular Image case: first_part is alt, second_part is url
-                self.render_image_no_breaks(doc, first_part, second_part);
             }
-
-            // Add a small space between consecutive images
-            if idx < images.len() - 1 {
-                doc.push(genpdfi_extended::elements::Break::new(0.05));
+            for term_pages in pages.iter_mut() {
+                term_pages.sort_unstable();
             }
         }
 
-        doc.push(genpdfi_extended::elements::Break::new(
-            self.style.text.after_spacing,
-        ));
+        let orientation = self
+            .orientation_sections()
+            .last()
+            .map_or(self.style.orientation, |(orientation, _)| *orientation);
+        let index = Pdf::render_to_bytes(self.render_index_section(orientation))?;
+        merge_pdfs(&[body, index])
     }
 
-    /// Renders an image without the standard before/after breaks.
-    /// Used for consecutive images that should appear horizontally.
-    fn render_image_no_breaks(&self, doc: &mut Document, alt: &str, url: &str) {
-        let mut loader_opt = self.image_loader.borrow_mut();
-
-        if let Some(ref mut loader) = *loader_opt {
-            match loader.load(url) {
-                Ok(image_data) => {
-                    match image_data.format {
-                        crate::images::ImageFormat::Svg => {
-                            match String::from_utf8(image_data.bytes.clone()) {
-                                Ok(svg_string) => {
-                                    match genpdfi_extended::elements::Image::from_svg_string(
-                                        &svg_string,
-                                    ) {
-                                        Ok(image) => {
-                                            let image = match self.style.svg_config.width {
-                                                crate::styling::SvgWidth::Percentage(percent) => {
-                                                    image.resizing_page_with(percent / 100.0)
-                                                }
-                                                crate::styling::SvgWidth::Pixels(_pixels) => image,
-                                                crate::styling::SvgWidth::Auto => {
-                                                    if self.style.svg_config.scale_factor != 1.0 {
-                                                        image.with_scale(Scale::new(
-                                                            self.style.svg_config.scale_factor,
-                                                            self.style.svg_config.scale_factor,
-                                                        ))
-                                                    } else {
-                                                        image
-                                                    }
-                                                }
-                                            };
-                                            // Render without centered alignment to allow side-by-side display
-                                            doc.push(image);
-                                        }
-                                        Err(e) => {
-                                            warn!("Failed to render SVG: {}", e);
-                                            let mut para =
-                                                genpdfi_extended::elements::Paragraph::default();
-                                            let style = genpdfi_extended::style::Style::new()
-                                                .with_font_size(self.style.text.size)
-                                                .italic();
-                                            para.push_styled(
-                                                format!("[SVG Image: {}]", alt),
-                                                style,
-                                            );
-                                            doc.push(para);
-                                        }
-                                    }
-                                }
-                                Err(e) => {
-                                    warn!("Failed to decode SVG as UTF-8: {}", e);
-                                    let mut para = genpdfi_extended::elements::Paragraph::default();
-                                    let style = genpdfi_extended::style::Style::new()
-                                        .with_font_size(self.style.text.size)
-                                        .italic();
-                                    para.push_styled(
-                                        format!("[Image: {} - decode error]", alt),
-                                        style,
-                                    );
-                                    doc.push(para);
-                                }
-                            }
-                        }
-                        crate::images::ImageFormat::Jpeg
-                        | crate::images::ImageFormat::Png
-                        | crate::images::ImageFormat::WebP
-                        | crate::images::ImageFormat::Gif => {
-                            match genpdfi_extended::elements::Image::from_reader(
-                                std::io::Cursor::new(image_data.bytes.clone()),
-                            ) {
-                                Ok(image) => {
-                                    doc.push(image);
-                                }
-                                Err(e) => {
-                                    warn!("Failed to load image: {}", e);
-                                    let mut para = genpdfi_extended::elements::Paragraph::default();
-                                    let style = genpdfi_extended::style::Style::new()
-                                        .with_font_size(self.style.text.size)
-                                        .italic();
-                                    para.push_styled(format!("[Image: {}]", alt), style);
-                                    doc.push(para);
-                                }
-                            }
-                        }
+    /// Finds the index term markers of a rendered PDF.
+    ///
+    /// Returns the PDF with the markers removed, and for each marker the position of its
+    /// term among the sorted index terms with the 1-based number of the page it is on.
+    pub fn index_markers(pdf: &[u8]) -> Result<(Vec<u8>, Vec<(usize, usize)>), String> {
+        use lopdf::Object;
+
+        let mut doc = lopdf::Document::load_mem(pdf).map_err(|e| e.to_string())?;
+        let mut markers = Vec::new();
+        let mut annotations_to_remove = Vec::new();
+        for (page_number, page_id) in doc.get_pages() {
+            let annotations = match doc
+                .get_dictionary(page_id)
+                .and_then(|page| page.get(b"Annots"))
+                .and_then(|annots| doc.dereference(annots))
+            {
+                Ok((_, Object::Array(items))) => items.clone(),
+                _ => continue,
+            };
+            for annotation in annotations {
+                let Ok(annotation_id) = annotation.as_reference() else {
+                    continue;
+                };
+                let Some(uri) = link_uri(&doc, annotation_id) else {
+                    continue;
+                };
+                if let Some(idx) = uri.strip_prefix(INDEX_URI_PREFIX.as_bytes()) {
+                    if let Some(idx) = std::str::from_utf8(idx)
+                        .ok()
+                        .and_then(|idx| idx.parse::<usize>().ok())
+                    {
+                        markers.push((idx, page_number as usize));
                     }
-                }
-                Err(e) => {
-                    warn!("Failed to load image from {}: {}", url, e);
-                    let mut para = genpdfi_extended::elements::Paragraph::default();
-                    let style = genpdfi_extended::style::Style::new()
-                        .with_font_size(self.style.text.size)
-                        .italic();
-                    para.push_styled(format!("[Image not found: {}]", alt), style);
-                    doc.push(para);
+                    annotations_to_remove.push((page_id, annotation_id));
                 }
             }
         }
+
+        if annotations_to_remove.is_empty() {
+            return Ok((pdf.to_vec(), markers));
+        }
+        for (page_id, annotation_id) in annotations_to_remove {
+            remove_annotation(&mut doc, page_id, annotation_id);
+        }
+        let mut output = Vec::new();
+        doc.save_to(&mut output).map_err(|e| e.to_string())?;
+        Ok((output, markers))
     }
 
-    /// Renders an image with a link without the standard before/after breaks.
-    /// Used for consecutive images that should appear horizontally.
-    fn render_image_with_link_no_breaks(
-        &self,
-        doc: &mut Document,
-        alt: &str,
-        image_url: &str,
-        link_url: &str,
-    ) {
-        let mut loader_opt = self.image_loader.borrow_mut();
+    /// Returns true if `<kbd>` keys, task list checkboxes, boxed inline code, highlighted,
+    /// underlined or struck-through text or tracked headings were rendered, in which case
+    /// the rendered PDF must be passed through [`Pdf::draw_decorations`].
+    pub fn has_decorations(&self) -> bool {
+        self.decorations.get()
+    }
 
-        if let Some(ref mut loader) = *loader_opt {
-            match loader.load(image_url) {
-                Ok(image_data) => {
-                    match image_data.format {
-                        crate::images::ImageFormat::Svg => {
-                            match String::from_utf8(image_data.bytes.clone()) {
-                                Ok(svg_string) => {
-                                    match genpdfi_extended::elements::Image::from_svg_string(
-                                        &svg_string,
-                                    ) {
-                                        Ok(image) => {
-                                            let image = match self.style.svg_config.width {
-                                                crate::styling::SvgWidth::Percentage(percent) => {
-                                                    image.resizing_page_with(percent / 100.0)
-                                                }
-                                                crate::styling::SvgWidth::Pixels(_pixels) => image,
-                                                crate::styling::SvgWidth::Auto => {
-                                                    if self.style.svg_config.scale_factor != 1.0 {
-                                                        image.with_scale(Scale::new(
-                                                            self.style.svg_config.scale_factor,
-                                                            self.style.svg_config.scale_factor,
-                                                        ))
-                                                    } else {
-                                                        image
-                                                    }
-                                                }
-                                            };
-
-                                            let image = image.with_link(link_url.to_string());
-                                            // Render without centered alignment to allow side-by-side display
-                                            doc.push(image);
-                                        }
-                                        Err(e) => {
-                                            warn!("Failed to render SVG with link: {}", e);
-                                            let mut para =
-                                                genpdfi_extended::elements::Paragraph::default();
-                                            let style = genpdfi_extended::style::Style::new()
-                                                .with_font_size(self.style.text.size)
-                                                .italic();
-                                            para.push_styled(
-                                                format!("[SVG Image: {}]", alt),
-                                                style,
-                                            );
-                                            doc.push(para);
-                                        }
-                                    }
-                                }
-                                Err(e) => {
-                                    warn!("Failed to decode SVG as UTF-8: {}", e);
-                                    let mut para = genpdfi_extended::elements::Paragraph::default();
-                                    let style = genpdfi_extended::style::Style::new()
-                                        .with_font_size(self.style.text.size)
-                                        .italic();
-                                    para.push_styled(
-                                        format!("[Image: {} - decode error]", alt),
-                                        style,
-                                    );
-                                    doc.push(para);
-                                }
-                            }
-                        }
-                        crate::images::ImageFormat::Jpeg
-                        | crate::images::ImageFormat::Png
-                        | crate::images::ImageFormat::WebP
-                        | crate::images::ImageFormat::Gif => {
-                            match genpdfi_extended::elements::Image::from_reader(
-                                std::io::Cursor::new(image_data.bytes.clone()),
-                            ) {
-                                Ok(image) => {
-                                    let image = image.with_link(link_url.to_string());
-                                    doc.push(image);
-                                }
-                                Err(e) => {
-                                    warn!("Failed to load image: {}", e);
-                                    let mut para = genpdfi_extended::elements::Paragraph::default();
-                                    let style = genpdfi_extended::style::Style::new()
-                                        .with_font_size(self.style.text.size)
-                                        .italic();
-                                    para.push_styled(format!("[Image: {}]", alt), style);
-                                    doc.push(para);
-                                }
-                            }
-                        }
-                    }
+    /// Draws the rounded boxes behind `<kbd>` keys, task list checkboxes, boxed inline code
+    /// and `<mark>` highlights, and the lines through struck-through and under underlined text.
+    ///
+    /// Every marker is removed and replaced by the matching shape covering the marker's
+    /// area. Boxes are drawn in a content stream placed before the page content so the
+    /// label stays on top; lines go in a stream placed after it. Tracking markers set the
+    /// character spacing of the text they cover instead.
+    ///
+    /// # Arguments
+    /// * `pdf` - The rendered PDF data
+    ///
+    /// # Returns
+    /// * `Ok(Vec<u8>)` containing the PDF with the decorations drawn
+    /// * `Err(String)` if the PDF could not be parsed or written back
+    pub fn draw_decorations(pdf: &[u8]) -> Result<Vec<u8>, String> {
+        use lopdf::{Dictionary, Object, ObjectId, Stream};
+
+        let mut doc = lopdf::Document::load_mem(pdf).map_err(|e| e.to_string())?;
+
+        let mut markers: Vec<(ObjectId, ObjectId)> = Vec::new();
+        // Operators drawn below and above the page content, per page
+        let mut page_operators: Vec<(ObjectId, String, String)> = Vec::new();
+        // Areas of tracked text with their character spacing, per page
+        let mut page_tracking: Vec<(ObjectId, Vec<([f32; 4], f32)>)> = Vec::new();
+
+        for (_, page_id) in doc.get_pages() {
+            let annotations = match doc
+                .get_dictionary(page_id)
+                .and_then(|page| page.get(b"Annots"))
+                .and_then(|annots| doc.dereference(annots))
+            {
+                Ok((_, Object::Array(items))) => items.clone(),
+                _ => continue,
+            };
+
+            let mut underlay = String::new();
+            let mut overlay = String::new();
+            let mut tracking = Vec::new();
+            for annotation in annotations {
+                let annotation_id = match annotation.as_reference() {
+                    Ok(id) => id,
+                    Err(_) => continue,
+                };
+                let uri = match link_uri(&doc, annotation_id) {
+                    Some(uri) => uri,
+                    None => continue,
+                };
+                let (kbd_colors, strike_color, underline_color) = (
+                    uri.strip_prefix(KBD_URI_PREFIX.as_bytes()),
+                    uri.strip_prefix(STRIKE_URI_PREFIX.as_bytes()),
+                    uri.strip_prefix(UNDERLINE_URI_PREFIX.as_bytes()),
+                );
+                let letter_spacing = uri.strip_prefix(TRACKING_URI_PREFIX.as_bytes());
+                if kbd_colors.is_none()
+                    && strike_color.is_none()
+                    && underline_color.is_none()
+                    && letter_spacing.is_none()
+                {
+                    continue;
                 }
-                Err(e) => {
-                    warn!("Failed to load image from {}: {}", image_url, e);
-                    let mut para = genpdfi_extended::elements::Paragraph::default();
-                    let style = genpdfi_extended::style::Style::new()
-                        .with_font_size(self.style.text.size)
-                        .italic();
-                    para.push_styled(format!("[Image not found: {}]", alt), style);
-                    doc.push(para);
+
+                let rect: Vec<f32> = doc
+                    .get_dictionary(annotation_id)
+                    .and_then(|a| a.get(b"Rect"))
+                    .and_then(|r| r.as_array())
+                    .map(|r| r.iter().filter_map(|v| v.as_float().ok()).collect())
+                    .unwrap_or_default();
+                if let [x1, y1, x2, y2] = rect[..] {
+                    if let Some(colors) = kbd_colors {
+                        underlay.push_str(&kbd_box_operators(x1, y1, x2, y2, colors));
+                    }
+                    if let Some(color) = strike_color {
+                        overlay.push_str(&strike_operators(x1, y1, x2, y2, color));
+                    }
+                    if let Some(color) = underline_color {
+                        overlay.push_str(&underline_operators(x1, y1, x2, y2, color));
+                    }
+                    if let Some(spacing) = letter_spacing
+                        .and_then(|s| std::str::from_utf8(s).ok())
+                        .and_then(|s| s.parse::<f32>().ok())
+                    {
+                        tracking.push(([x1, y1, x2, y2], spacing));
+                    }
                 }
+                markers.push((page_id, annotation_id));
+            }
+            if !underlay.is_empty() || !overlay.is_empty() {
+                page_operators.push((page_id, underlay, overlay));
+            }
+            if !tracking.is_empty() {
+                page_tracking.push((page_id, tracking));
             }
         }
-    }
 
-    /// Renders accumulated tokens as a paragraph in the document.
-    ///
-    /// This method takes a document and a slice of tokens, and renders them as a paragraph
-    /// with appropriate styling. If the tokens slice is empty, no paragraph is rendered.
-    /// After rendering the paragraph content, it adds spacing after the paragraph according
-    /// to the configured text style.
-    fn flush_paragraph(&self, doc: &mut Document, tokens: &[Token]) {
-        if tokens.is_empty() {
-            return;
+        if markers.is_empty() {
+            return Ok(pdf.to_vec());
         }
 
-        doc.push(genpdfi_extended::elements::Break::new(
-            self.style.text.before_spacing,
-        ));
-        let mut para = genpdfi_extended::elements::Paragraph::default();
-        self.render_inline_content(&mut para, tokens, doc);
-        doc.push(para);
-        doc.push(genpdfi_extended::elements::Break::new(
-            self.style.text.after_spacing,
-        ));
+        for (page_id, annotation_id) in &markers {
+            remove_annotation(&mut doc, *page_id, *annotation_id);
+        }
+
+        for (page_id, areas) in page_tracking {
+            track_text(&mut doc, page_id, &areas)?;
+        }
+
+        for (page_id, underlay, overlay) in page_operators {
+            let mut contents = Vec::new();
+            if !underlay.is_empty() {
+                let stream_id =
+                    doc.add_object(Stream::new(Dictionary::new(), underlay.into_bytes()));
+                contents.push(Object::Reference(stream_id));
+            }
+            match doc
+                .get_dictionary(page_id)
+                .and_then(|page| page.get(b"Contents"))
+                .cloned()
+            {
+                Ok(Object::Array(items)) => contents.extend(items),
+                Ok(Object::Reference(id)) => match doc.get_object(id) {
+                    Ok(Object::Array(items)) => contents.extend(items.clone()),
+                    _ => contents.push(Object::Reference(id)),
+                },
+                _ => {}
+            }
+            if !overlay.is_empty() {
+                let stream_id =
+                    doc.add_object(Stream::new(Dictionary::new(), overlay.into_bytes()));
+                contents.push(Object::Reference(stream_id));
+            }
+            if let Ok(page) = doc
+                .get_object_mut(page_id)
+                .and_then(|page| page.as_dict_mut())
+            {
+                page.set("Contents", Object::Array(contents));
+            }
+        }
+
+        let mut output = Vec::new();
+        doc.save_to(&mut output).map_err(|e| e.to_string())?;
+        Ok(output)
     }
 
-    /// Renders a heading with the appropriate level styling.
+    /// Turns anchor markers into named destinations and points internal links at them.
     ///
-    /// This method takes a document, heading content tokens, and a level number to render
-    /// a heading with the corresponding style settings. It applies font size, bold/italic effects,
-    /// and text color based on the heading level configuration. After rendering the heading,
-    /// it adds the configured spacing.
-    fn render_heading(&self, doc: &mut Document, content: &[Token], level: usize) {
-        let heading_style = match level {
-            1 => &self.style.heading_1,
-            2 => &self.style.heading_2,
-            3 | _ => &self.style.heading_3,
-        };
-        doc.push(genpdfi_extended::elements::Break::new(
-            heading_style.before_spacing,
-        ));
+    /// Every marker emitted for an `<a id="name">` tag is removed and replaced by an entry
+    /// in the document's `Dests` name tree located at the marker's position. Link
+    /// annotations whose URI is `#name` are then rewritten into `GoTo` actions targeting
+    /// that destination. Links to unknown anchors are left untouched.
+    ///
+    /// Markers of the form `name#target`, emitted for footnote references and notes, stay
+    /// visible: they define the destination `name` and link to `target`.
+    ///
+    /// # Arguments
+    /// * `pdf` - The rendered PDF data
+    ///
+    /// # Returns
+    /// * `Ok(Vec<u8>)` containing the PDF with resolved internal links
+    /// * `Err(String)` if the PDF could not be parsed or written back
+    pub fn resolve_anchors(pdf: &[u8]) -> Result<Vec<u8>, String> {
+        use lopdf::{Dictionary, Object, ObjectId, StringFormat};
+
+        let mut doc = lopdf::Document::load_mem(pdf).map_err(|e| e.to_string())?;
+
+        let mut destinations: Vec<(Vec<u8>, Object)> = Vec::new();
+        let mut markers: Vec<(ObjectId, ObjectId)> = Vec::new();
+        let mut internal_links: Vec<(ObjectId, Vec<u8>)> = Vec::new();
+
+        for (_, page_id) in doc.get_pages() {
+            let annotations = match doc
+                .get_dictionary(page_id)
+                .and_then(|page| page.get(b"Annots"))
+                .and_then(|annots| doc.dereference(annots))
+            {
+                Ok((_, Object::Array(items))) => items.clone(),
+                _ => continue,
+            };
 
-        let mut para = genpdfi_extended::elements::Paragraph::default();
-        let mut style = genpdfi_extended::style::Style::new().with_font_size(heading_style.size);
+            for annotation in annotations {
+                let annotation_id = match annotation.as_reference() {
+                    Ok(id) => id,
+                    Err(_) => continue,
+                };
+                let uri = match link_uri(&doc, annotation_id) {
+                    Some(uri) => uri,
+                    None => continue,
+                };
+
+                if let Some(marker) = uri.strip_prefix(ANCHOR_URI_PREFIX.as_bytes()) {
+                    // `name#target` markers are visible links that are also anchors
+                    let (name, target) = match marker.iter().position(|&b| b == b'#') {
+                        Some(pos) => (&marker[..pos], Some(&marker[pos + 1..])),
+                        None => (marker, None),
+                    };
+                    let rect: Vec<f32> = doc
+                        .get_dictionary(annotation_id)
+                        .and_then(|a| a.get(b"Rect"))
+                        .and_then(|r| r.as_array())
+                        .map(|r| r.iter().filter_map(|v| v.as_float().ok()).collect())
+                        .unwrap_or_default();
+                    let (left, top) = match rect.as_slice() {
+                        [x1, _, _, y2] => (*x1, *y2),
+                        _ => (0.0, 0.0),
+                    };
+                    if !destinations.iter().any(|(existing, _)| existing == name) {
+                        destinations.push((
+                            name.to_vec(),
+                            Object::Array(vec![
+                                Object::Reference(page_id),
+                                Object::Name(b"XYZ".to_vec()),
+                                Object::Real(left),
+                                Object::Real(top),
+                                Object::Null,
+                            ]),
+                        ));
+                    }
+                    match target {
+                        Some(target) => internal_links.push((annotation_id, target.to_vec())),
+                        None => markers.push((page_id, annotation_id)),
+                    }
+                } else if let Some(name) = uri.strip_prefix(b"#") {
+                    internal_links.push((annotation_id, name.to_vec()));
+                }
+            }
+        }
 
-        if heading_style.bold {
-            style = style.bold();
+        if destinations.is_empty() {
+            return Ok(pdf.to_vec());
         }
-        if heading_style.italic {
-            style = style.italic();
+
+        for (page_id, annotation_id) in &markers {
+            remove_annotation(&mut doc, *page_id, *annotation_id);
         }
-        if let Some(color) = heading_style.text_color {
-            style = style.with_color(genpdfi_extended::style::Color::Rgb(
-                color.0, color.1, color.2,
-            ));
+
+        for (annotation_id, name) in internal_links {
+            if !destinations.iter().any(|(existing, _)| *existing == name) {
+                warn!(
+                    "Link target #{} does not match any anchor",
+                    String::from_utf8_lossy(&name)
+                );
+                continue;
+            }
+            if let Ok(annotation) = doc
+                .get_object_mut(annotation_id)
+                .and_then(|a| a.as_dict_mut())
+            {
+                let mut action = Dictionary::new();
+                action.set("S", Object::Name(b"GoTo".to_vec()));
+                action.set("D", Object::String(name, StringFormat::Literal));
+                annotation.set("A", action);
+            }
         }
 
-        self.render_inline_content_with_style_simple(&mut para, content, style);
-        doc.push(para);
-        doc.push(genpdfi_extended::elements::Break::new(
-            heading_style.after_spacing,
-        ));
+        // Name trees require their keys in sorted order
+        destinations.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut names = Vec::with_capacity(destinations.len() * 2);
+        for (name, destination) in destinations {
+            names.push(Object::String(name, StringFormat::Literal));
+            names.push(destination);
+        }
+        let mut dests = Dictionary::new();
+        dests.set("Names", Object::Array(names));
+        let dests_id = doc.add_object(dests);
+
+        let root_id = doc
+            .trailer
+            .get(b"Root")
+            .and_then(|o| o.as_reference())
+            .map_err(|e| e.to_string())?;
+        let root = doc
+            .get_object_mut(root_id)
+            .and_then(|o| o.as_dict_mut())
+            .map_err(|e| e.to_string())?;
+        let has_names = root.get(b"Names").and_then(|n| n.as_dict()).is_ok();
+        if has_names {
+            if let Ok(names) = root.get_mut(b"Names").and_then(|n| n.as_dict_mut()) {
+                names.set("Dests", Object::Reference(dests_id));
+            }
+        } else {
+            let mut names = Dictionary::new();
+            names.set("Dests", Object::Reference(dests_id));
+            root.set("Names", names);
+        }
+
+        let mut output = Vec::new();
+        doc.save_to(&mut output).map_err(|e| e.to_string())?;
+        Ok(output)
     }
 
-    /// Renders inline content with a specified style.
+    /// Estimates the number of pages the document renders to, without rendering it.
     ///
-    /// This method processes a sequence of inline tokens and renders them with the given style.
-    /// It handles various inline elements like plain text, emphasis, strong emphasis, links, and
-    /// inline code, applying appropriate styling modifications for each type while maintaining
-    /// the base style properties.
-    fn render_inline_content_with_style(
-        &self,
-        para: &mut genpdfi_extended::elements::Paragraph,
-        tokens: &[Token],
-        style: genpdfi_extended::style::Style,
-        doc: &mut Document,
-    ) {
+    /// This is a quick heuristic meant for previews such as "~12 pages": characters are
+    /// assumed to be half an em wide on average, every image or diagram takes a fixed
+    /// height and page breaks before headings are honored. The result is an estimate;
+    /// the rendered document can differ, especially with many images, tables or long
+    /// code lines.
+    pub fn estimate_page_count(&self) -> usize {
+        let (page_width, page_height) = self.style.page_dimensions();
+        let margins = self.style.first_page_margins.unwrap_or(self.style.margins);
+        let mut estimate = PageEstimate {
+            width: (page_width - self.style.margins.left - self.style.margins.right).max(1.0),
+            height: (page_height - self.style.margins.top - self.style.margins.bottom).max(1.0),
+            spacing: line_height_mm(self.style.text.size),
+            pages: 1,
+            // A larger first page margin takes room from the first page
+            used: (margins.top + margins.bottom
+                - self.style.margins.top
+                - self.style.margins.bottom)
+                .max(0.0),
+        };
+        self.estimate_tokens(&self.input, 0.0, &mut estimate);
+        estimate.pages
+    }
+
+    /// Adds the estimated height of `tokens`, laid out `indent` millimeters from the left
+    /// margin, to `estimate`.
+    fn estimate_tokens(&self, tokens: &[Token], indent: f32, estimate: &mut PageEstimate) {
+        let mut paragraph = String::new();
+        let mut after_image = false;
+        let flush = |paragraph: &mut String, estimate: &mut PageEstimate| {
+            if !paragraph.trim().is_empty() {
+                estimate.add_text(paragraph, &self.style.text, indent);
+            }
+            paragraph.clear();
+        };
+
         for token in tokens {
+            let is_image = matches!(token, Token::Image(..) | Token::ImageWithLink(..));
             match token {
-                Token::Text(content) => {
-                    para.push_styled(content.clone(), style.clone());
-                }
-                Token::Emphasis { level, content } => {
-                    let mut nested_style = style.clone();
-                    match level {
-                        1 => nested_style = nested_style.italic(),
-                        2 => nested_style = nested_style.bold(),
-                        _ => nested_style = nested_style.bold().italic(),
+                Token::Heading(content, level) => {
+                    flush(&mut paragraph, estimate);
+                    let style = match level {
+                        1 => &self.style.heading_1,
+                        2 => &self.style.heading_2,
+                        _ => &self.style.heading_3,
+                    };
+                    if self.style.page_break_before[(*level).clamp(1, 3) - 1] {
+                        estimate.page_break();
                     }
-                    self.render_inline_content_with_style(para, content, nested_style, doc);
+                    estimate.add_text(&Token::collect_all_text(content), style, indent);
                 }
-                Token::StrongEmphasis(content) => {
-                    let nested_style = style.clone().bold();
-                    self.render_inline_content_with_style(para, content, nested_style, doc);
+                Token::ListItem { content, .. } => {
+                    flush(&mut paragraph, estimate);
+                    let (nested, inline): (Vec<Token>, Vec<Token>) = content
+                        .iter()
+                        .cloned()
+                        .partition(|token| matches!(token, Token::ListItem { .. }));
+                    estimate.add_text(
+                        &Token::collect_all_text(&inline),
+                        &self.style.list_item,
+                        indent + self.style.list_marker_width,
+                    );
+                    self.estimate_tokens(&nested, indent + self.style.list_indent, estimate);
                 }
-                Token::Link(text, url) => {
-                    let mut link_style = style.clone();
-                    if let Some(color) = self.style.link.text_color {
-                        link_style = link_style.with_color(genpdfi_extended::style::Color::Rgb(
-                            color.0, color.1, color.2,
-                        ));
-                    }
-                    para.push_link(text.clone(), url.clone(), link_style);
+                #[cfg(feature = "math-rust")]
+                Token::Code(lang, _) if lang.trim().eq_ignore_ascii_case("asciimath") => {
+                    flush(&mut paragraph, estimate);
+                    estimate.add_lines(2, &self.style.latex);
                 }
-                Token::Code(_, content) => {
-                    let mut code_style = style.clone();
-                    if let Some(color) = self.style.code.text_color {
-                        code_style = code_style.with_color(genpdfi_extended::style::Color::Rgb(
-                            color.0, color.1, color.2,
-                        ));
+                Token::Code(lang, content)
+                    if content.contains('\n') || lang.trim().eq_ignore_ascii_case("qr") =>
+                {
+                    flush(&mut paragraph, estimate);
+                    if lang.trim().eq_ignore_ascii_case("mermaid")
+                        || lang.trim().eq_ignore_ascii_case("svg")
+                        || lang.trim().eq_ignore_ascii_case("qr")
+                    {
+                        estimate.add(ESTIMATED_IMAGE_HEIGHT_MM);
+                    } else {
+                        let (content, hidden) =
+                            truncate_code_lines(content, self.style.code_max_lines);
+                        let lines: usize = content
+                            .lines()
+                            .map(|line| estimate.wrapped_lines(line, self.style.code.size, indent))
+                            .sum();
+                        estimate.add_lines(lines + usize::from(hidden > 0), &self.style.code);
                     }
-                    para.push_styled(content.clone(), code_style);
-                }
-                Token::Math {
-                    content,
-                    display: false,
-                } => {
-                    // Inline math - render to SVG and create image
-                    self.render_inline_math_as_image(doc, content);
                 }
-                Token::LineBreak => {
-                    // Line break within a paragraph - push a newline
-                    para.push_styled("\n".to_string(), style.clone());
-                }
-                Token::Image(_, _) => {
-                    // Images are handled as block-level elements in process_tokens,
-                    // not as inline elements within paragraphs
-                }
-                Token::ImageWithLink(_, _, _) => {
-                    // Images with links are handled as block-level elements in process_tokens,
-                    // not as inline elements within paragraphs
-                }
-                _ => {}
-            }
-        }
-    }
-
-    /// Version without Document - for headings and other places where we can't render images
-    fn render_inline_content_with_style_simple(
-        &self,
-        para: &mut genpdfi_extended::elements::Paragraph,
-        tokens: &[Token],
-        style: genpdfi_extended::style::Style,
-    ) {
-        for token in tokens {
-            match token {
-                Token::Text(content) => {
-                    para.push_styled(content.clone(), style.clone());
-                }
-                Token::Emphasis { level, content } => {
-                    let mut nested_style = style.clone();
-                    match level {
-                        1 => nested_style = nested_style.italic(),
-                        2 => nested_style = nested_style.bold(),
-                        _ => nested_style = nested_style.bold().italic(),
-                    }
-                    self.render_inline_content_with_style_simple(para, content, nested_style);
+                Token::Math { display: true, .. } => {
+                    flush(&mut paragraph, estimate);
+                    estimate.add_lines(2, &self.style.latex);
                 }
-                Token::StrongEmphasis(content) => {
-                    let nested_style = style.clone().bold();
-                    self.render_inline_content_with_style_simple(para, content, nested_style);
+                Token::Table { headers, rows, .. } => {
+                    flush(&mut paragraph, estimate);
+                    let columns = headers.len().max(1) as f32;
+                    let column_indent = estimate.width - (estimate.width - indent) / columns;
+                    let row_lines = |row: &[Vec<Token>], size: u8| {
+                        row.iter()
+                            .map(|cell| {
+                                let text = Token::collect_all_text(cell);
+                                estimate.wrapped_lines(&text, size, column_indent)
+                            })
+                            .max()
+                            .unwrap_or(1)
+                    };
+                    let header_lines = row_lines(&headers[..], self.style.table_header.size);
+                    let cell_lines: usize = rows
+                        .iter()
+                        .map(|row| row_lines(&row[..], self.style.table_cell.size))
+                        .sum();
+                    estimate.add(
+                        header_lines as f32 * line_height_mm(self.style.table_header.size)
+                            + cell_lines as f32 * line_height_mm(self.style.table_cell.size),
+                    );
                 }
-                Token::Link(text, url) => {
-                    let mut link_style = style.clone();
-                    if let Some(color) = self.style.link.text_color {
-                        link_style = link_style.with_color(genpdfi_extended::style::Color::Rgb(
-                            color.0, color.1, color.2,
-                        ));
+                Token::TabGroup(tabs) => {
+                    flush(&mut paragraph, estimate);
+                    for (title, content) in tabs {
+                        estimate.add_text(title, &self.style.text, indent);
+                        self.estimate_tokens(content, indent, estimate);
                     }
-                    para.push_link(text.clone(), url.clone(), link_style);
                 }
-                Token::Code(_, content) => {
-                    let mut code_style = style.clone();
-                    if let Some(color) = self.style.code.text_color {
-                        code_style = code_style.with_color(genpdfi_extended::style::Color::Rgb(
-                            color.0, color.1, color.2,
-                        ));
-                    }
-                    para.push_styled(content.clone(), code_style);
+                Token::Admonition { kind, content } => {
+                    flush(&mut paragraph, estimate);
+                    estimate.add_text(kind, &self.style.text, indent);
+                    self.estimate_tokens(content, indent, estimate);
                 }
-                Token::Math {
-                    content,
-                    display: false,
-                } => {
-                    // Inline math - render as styled text
-                    self.render_inline_math(para, content, style.clone());
+                Token::HorizontalRule => {
+                    flush(&mut paragraph, estimate);
+                    estimate.add(self.style.horizontal_rule.after_spacing * estimate.spacing);
                 }
-                Token::LineBreak => {
-                    // Line break within a paragraph - push a newline
-                    para.push_styled("\n".to_string(), style.clone());
+                Token::BlockQuote(text) => {
+                    flush(&mut paragraph, estimate);
+                    estimate.add_text(text, &self.style.block_quote, indent);
                 }
-                Token::Image(_, _) => {
-                    // Images are handled as block-level elements in process_tokens,
-                    // not as inline elements
+                Token::Newline => flush(&mut paragraph, estimate),
+                Token::SoftBreak | Token::LineBreak => paragraph.push(' '),
+                // Consecutive images share a row, e.g. badges
+                Token::Image(..) | Token::ImageWithLink(..) if !after_image => {
+                    flush(&mut paragraph, estimate);
+                    estimate.add(ESTIMATED_IMAGE_HEIGHT_MM);
                 }
-                _ => {}
+                Token::Image(..) | Token::ImageWithLink(..) => {}
+                other => paragraph.push_str(&Token::collect_all_text(std::slice::from_ref(other))),
             }
+            after_image = is_image;
         }
+        flush(&mut paragraph, estimate);
     }
 
-    fn render_inline_content(
-        &self,
-        para: &mut genpdfi_extended::elements::Paragraph,
-        tokens: &[Token],
-        doc: &mut Document,
-    ) {
-        let style = genpdfi_extended::style::Style::new().with_font_size(self.style.text.size);
-        self.render_inline_content_with_style(para, tokens, style, doc);
+    /// Initializes and returns a new PDF document with configured styling and layout.
+    ///
+    /// Creates a new document instance with the main font family and configures the page decorator
+    /// with margins from the style settings. The document's base font size is set according to the
+    /// text style configuration.
+    ///
+    /// The function processes all input tokens and renders them into the document structure before
+    /// returning the complete document ready for final output. The document contains all content
+    /// with proper styling, formatting and layout applied according to the style configuration.
+    ///
+    /// Through the style configuration, this method controls the overall document appearance including:
+    /// - Page margins and layout
+    /// - Base font size
+    /// - Content processing and rendering
+    ///
+    /// A genpdfi document has a single paper size, so all pages are laid out in the
+    /// configured orientation, the index lists its terms without page numbers, and the
+    /// post-processing of the rendered PDF (anchors, drawn decorations...) is left to the
+    /// caller. [`Pdf::render_pdf`] does all of it; a warning is recorded when the document
+    /// changes orientation, which only [`Pdf::render_pdf`] honors.
+    pub fn render_into_document(&self) -> Document {
+        if self.has_orientation_changes() {
+            self.render_warning(
+                "Orientation changes are ignored when the document is laid out as a single \
+                 genpdfi document; render it with Pdf::render_pdf instead"
+                    .to_string(),
+            );
+        }
+        let mut doc = self.begin_section(self.style.orientation, true);
+        self.process_tokens(&mut doc, &self.input);
+        self.render_index(&mut doc);
+        self.pending_spacing.set(None);
+        self.end_section();
+        doc
     }
 
-    /// Renders inline content with a specified style.
+    /// Returns true if the rendered PDF needs work that [`Pdf::render`] and
+    /// [`Pdf::render_to_bytes`] do not do: a layout in orientation sections, an index,
+    /// anchor resolution, drawn decorations, a contact sheet, the source attachment, the
+    /// document language or reproducible output. Decorations are only known once the
+    /// document was laid out.
+    pub fn needs_post_processing(&self) -> bool {
+        self.reproducible_timestamp().is_some()
+            || self.has_anchors()
+            || self.has_index()
+            || self.has_decorations()
+            || self.source_attachment().is_some()
+            || self.language().is_some()
+            || self.has_orientation_changes()
+            || self.has_contact_sheet()
+    }
 
-    /// Renders a code block with appropriate styling.
+    /// Lays the document out and renders it to PDF bytes, one section per page orientation
+    /// when it changes orientation, then applies the post-processing the document needs
+    /// (the index, anchor resolution, key boxes and other drawn decorations,
+    /// contact sheet, source attachment, document language, reproducible output).
     ///
-    /// This method handles multi-line code blocks, rendering each line as a separate
-    /// paragraph with the configured code style. It applies the code font size and
-    /// text color settings, and adds the configured spacing after the block.
-    fn render_code_block(&self, doc: &mut Document, lang: &str, content: &str) {
-        doc.push(genpdfi_extended::elements::Break::new(
-            self.style.code.before_spacing,
-        ));
+    /// # Returns
+    /// * `Ok(Vec<u8>)` containing the final PDF data
+    /// * `Err(String)` if rendering or post-processing failed
+    pub fn render_pdf(&self) -> Result<Vec<u8>, String> {
+        let bytes = if self.has_orientation_changes() {
+            self.render_sections()?
+        } else {
+            Pdf::render_to_bytes(self.render_section(
+                &self.input,
+                self.style.orientation,
+                true,
+                true,
+            ))?
+        };
+        self.finish_pdf(bytes)
+    }
 
-        // Get syntax highlighted tokens
-        let highlighted_tokens = highlighting::highlight_code(content, lang);
+    /// Renders the document to a file like [`Pdf::render_pdf`]. A document that needs no
+    /// post-processing is written straight to the file. Returns an error message on
+    /// failure.
+    pub fn render_pdf_to_file(&self, path: &str) -> Option<String> {
+        let bytes = if self.has_orientation_changes() {
+            self.render_pdf()
+        } else {
+            let document = self.render_section(&self.input, self.style.orientation, true, true);
+            if !self.needs_post_processing() {
+                return Pdf::render(document, path);
+            }
+            Pdf::render_to_bytes(document).and_then(|bytes| self.finish_pdf(bytes))
+        };
+        match bytes {
+            Ok(bytes) => std::fs::write(path, bytes).err().map(|e| e.to_string()),
+            Err(err) => Some(err),
+        }
+    }
 
-        let indent = "    "; // TODO: make this configurable from style match.
-        let mut current_line = String::new();
-        let mut line_tokens = Vec::new();
+    /// Applies the post-processing of [`Pdf::render_pdf`] to the PDF the document was
+    /// rendered to.
+    fn finish_pdf(&self, mut bytes: Vec<u8>) -> Result<Vec<u8>, String> {
+        if self.has_index() {
+            bytes = self.append_index(&bytes)?;
+        }
+        if self.has_anchors() {
+            bytes = Pdf::resolve_anchors(&bytes)?;
+        }
+        if self.has_decorations() {
+            bytes = Pdf::draw_decorations(&bytes)?;
+        }
+        if self.has_contact_sheet() {
+            bytes = Pdf::append_contact_sheet(&bytes)?;
+        }
+        if let Some((name, markdown)) = self.source_attachment() {
+            bytes = Pdf::embed_source(&bytes, name, markdown)?;
+        }
+        if let Some(lang) = self.language() {
+            bytes = Pdf::set_language(&bytes, lang)?;
+        }
+        match self.reproducible_timestamp() {
+            Some(timestamp) => Pdf::make_reproducible(&bytes, timestamp),
+            None => Ok(bytes),
+        }
+    }
 
-        for token in highlighted_tokens {
-            // Check if we need to flush current line
-            if token.text.contains('\n') {
-                // Add remaining text before newline
-                let parts: Vec<&str> = token.text.split('\n').collect();
-                for (i, part) in parts.iter().enumerate() {
-                    if i > 0 {
-                        // Render previous line and start new one
-                        self.render_highlighted_line(doc, indent, &line_tokens);
-                        line_tokens.clear();
-                        current_line.clear();
-                    }
-                    if !part.is_empty() {
-                        line_tokens.push((part.to_string(), token.color, token.bold, token.italic));
-                        current_line.push_str(part);
-                    }
-                }
-            } else {
-                line_tokens.push((token.text.clone(), token.color, token.bold, token.italic));
-                current_line.push_str(&token.text);
+    /// Lays out `tokens` on pages of the given orientation.
+    ///
+    /// The first section of a rendering pass resets the footnote and abbreviation state
+    /// and gets the first page margins; the last one ends with the footnotes and glossary.
+    fn render_section(
+        &self,
+        tokens: &[Token],
+        orientation: crate::styling::Orientation,
+        first: bool,
+        last: bool,
+    ) -> Document {
+        let mut doc = self.begin_section(orientation, first);
+        if last {
+            self.process_tokens(&mut doc, tokens);
+            // Spacing after the document's last element is trimmed
+            self.pending_spacing.set(None);
+        } else {
+            self.render_tokens(&mut doc, tokens);
+            if let Some(lines) = self.pending_spacing.take() {
+                doc.push(genpdfi_extended::elements::Break::new(lines));
             }
         }
+        self.end_section();
+        doc
+    }
 
-        // Render final line if there's any content
-        if !line_tokens.is_empty() {
-            self.render_highlighted_line(doc, indent, &line_tokens);
+    /// Lays out the index on pages of the given orientation, as the section following the
+    /// rest of the document.
+    fn render_index_section(&self, orientation: crate::styling::Orientation) -> Document {
+        let mut doc = self.begin_section(orientation, false);
+        // The index starts a page, so no spacing goes before its heading
+        self.has_pushed_content.set(false);
+        self.pending_spacing.set(None);
+        self.render_index(&mut doc);
+        self.pending_spacing.set(None);
+        self.end_section();
+        doc
+    }
+
+    /// Creates the document a section is laid out in, with its page decorator, paper
+    /// size and fonts, and makes the code and range fonts available while it is rendered.
+    fn begin_section(&self, orientation: crate::styling::Orientation, first: bool) -> Document {
+        if first {
+            // Footnotes and headings are numbered and abbreviations first used per
+            // rendering pass
+            self.footnotes.borrow_mut().clear();
+            *self.heading_counters.borrow_mut() = [0; 6];
+            self.footer_pages.set(0);
+            self.used_abbreviations.borrow_mut().clear();
+            self.has_pushed_content.set(false);
+            self.pending_spacing.set(None);
         }
 
-        doc.push(genpdfi_extended::elements::Break::new(
-            self.style.code.after_spacing,
-        ));
-    }
+        let mut doc = genpdfi_extended::Document::new(self.font_family.clone());
+        self.orientation.set(orientation);
+        let footer = self.page_footer();
+        let logos = self.page_logos();
+        let first_margins = self.style.first_page_margins.filter(|_| first);
+        let decorated = footer.is_some() || !logos.is_empty();
+        match first_margins.or(decorated.then_some(self.style.margins)) {
+            Some(first) => doc.set_page_decorator(FirstPageDecorator {
+                first,
+                rest: self.style.margins,
+                page: 0,
+                footer,
+                logos,
+            }),
+            None => {
+                let mut decorator = genpdfi_extended::SimplePageDecorator::new();
+                decorator.set_margins(page_margins(self.style.margins));
+                doc.set_page_decorator(decorator);
+            }
+        }
+        let (width, height) = self.page_dimensions();
+        doc.set_paper_size(genpdfi_extended::Size::new(width, height));
+        doc.set_font_size(self.style.text.size);
+        if let Some(title) = &self.title {
+            doc.set_title(title.clone());
+        }
 
-    /// Renders a single line of highlighted code
-    fn render_highlighted_line(
-        &self,
-        doc: &mut Document,
-        indent: &str,
-        tokens: &[(String, highlighting::HighlightColor, bool, bool)],
-    ) {
-        let mut para = genpdfi_extended::elements::Paragraph::default();
+        // Add code font to the document's font cache for use in code blocks
+        let code_font = doc.add_font_family(self.code_font_family.clone());
 
-        // Create base code style with font override
-        let mut code_style =
-            genpdfi_extended::style::Style::new().with_font_size(self.style.code.size);
+        // Store it in thread-local storage for access in render_highlighted_line
+        CURRENT_CODE_FONT_OVERRIDE.with(|f| {
+            *f.borrow_mut() = Some(code_font);
+        });
 
-        // Apply code font override if available
+        // Same for the [fonts.ranges] fonts, used by push_text_with_range_fonts
+        let range_fonts = self
+            .range_font_families
+            .iter()
+            .map(|family| doc.add_font_family(family.clone()))
+            .collect();
+        CURRENT_RANGE_FONTS.with(|f| {
+            *f.borrow_mut() = range_fonts;
+        });
+
+        doc
+    }
+
+    /// Clears the fonts [`Pdf::begin_section`] made available once a section is laid out.
+    fn end_section(&self) {
         CURRENT_CODE_FONT_OVERRIDE.with(|f| {
-            if let Some(code_font) = f.borrow().as_ref() {
-                code_style = code_style.with_font_override(*code_font);
-            }
+            *f.borrow_mut() = None;
         });
+        CURRENT_RANGE_FONTS.with(|f| f.borrow_mut().clear());
+        self.orientation.set(self.style.orientation);
+    }
 
-        // Add indentation
-        let mut style = code_style;
-        if let Some(color) = self.style.code.text_color {
-            style = style.with_color(genpdfi_extended::style::Color::Rgb(
-                color.0, color.1, color.2,
-            ));
+    /// Returns the `[page.footer]` line for the pages being laid out, or `None` when the
+    /// footer is turned off.
+    ///
+    /// The date is the reproducible timestamp when one is set, so the footer does not
+    /// break byte-identical output, and the current time otherwise.
+    fn page_footer(&self) -> Option<PageFooter> {
+        let footer = &self.style.footer;
+        if !footer.is_enabled() {
+            return None;
         }
-        para.push_styled(indent.to_string(), style);
+        let date = footer
+            .show_date
+            .then(|| Pdf::generation_date(&self.style, &footer.date_format));
+        Some(PageFooter {
+            date,
+            page_numbers: footer.page_numbers,
+            size: footer.size,
+            page_size: self.page_dimensions(),
+            pages: Rc::clone(&self.footer_pages),
+        })
+    }
 
-        // Add colored tokens
-        for (text, color, _bold, _italic) in tokens {
-            let mut token_style = code_style;
-            let (r, g, b) = color.as_rgb_u8();
-            token_style = token_style.with_color(genpdfi_extended::style::Color::Rgb(r, g, b));
+    /// Returns the `[page.header]` and `[page.footer]` logos for the pages being laid out.
+    /// Logos whose image cannot be decoded are left out with a warning.
+    fn page_logos(&self) -> Vec<MarginLogo> {
+        let logos = [
+            (self.style.header.logo.as_ref(), &self.header_logo, true),
+            (self.style.footer.logo.as_ref(), &self.footer_logo, false),
+        ];
+        logos
+            .into_iter()
+            .filter_map(|(config, data, header)| {
+                let (config, data) = (config?, data.as_ref()?);
+                match decode_image_data(data) {
+                    Ok(image) => Some(MarginLogo {
+                        image: image.resizing_page_with(1.0),
+                        aspect_ratio: image_aspect_ratio(data).unwrap_or(1.0),
+                        height: config.height,
+                        alignment: config.alignment,
+                        header,
+                        page_size: self.page_dimensions(),
+                    }),
+                    Err(e) => {
+                        self.image_warning(format!(
+                            "Failed to decode page logo '{}': {}",
+                            config.image, e
+                        ));
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
 
-            // Note: genpdfi doesn't support bold/italic in its current version,
-            // so we only apply the color for now
-            para.push_styled(text.clone(), token_style);
+    /// Returns the page width and height in millimeters of the pages being laid out,
+    /// following `<!-- orientation: ... -->` directives.
+    fn page_dimensions(&self) -> (f32, f32) {
+        let (width, height) = self.style.page_dimensions();
+        if self.orientation.get() == self.style.orientation {
+            (width, height)
+        } else {
+            (height, width)
         }
-
-        doc.push(para);
     }
 
-    /// Renders a list item with appropriate styling and formatting.
-    ///
-    /// This method handles both ordered and unordered list items, with support for nested lists.
-    /// For ordered lists, it includes the item number prefixed with a period (like "1."), while
-    /// unordered lists use a bullet point dash character. The content is rendered with the
-    /// configured list item style settings from the document style configuration.
-    ///
-    /// The method processes both the direct content of the list item as well as any nested list
-    /// items recursively. Each nested level increases the indentation by 4 spaces to create a
-    /// visual hierarchy. The method filters the content to separate inline elements from nested
-    /// list items, rendering the inline content first before processing any nested items.
+    /// Splits the document at `<!-- orientation: landscape -->` and
+    /// `<!-- orientation: portrait -->` directives into runs of tokens laid out in the same
+    /// orientation. A table turned by `[table] rotate_if_oversized` is a section of its
+    /// own. Sections without visible content are left out.
     ///
-    /// After rendering each list item's content, appropriate spacing is added based on the
-    /// configured after_spacing value. The method maintains consistent styling throughout the
-    /// list hierarchy while allowing for proper nesting and indentation of complex list structures.
-    fn render_list_item(
-        &self,
-        doc: &mut Document,
-        content: &[Token],
-        ordered: bool,
-        number: Option<usize>,
-        nesting_level: usize,
-    ) {
-        doc.push(genpdfi_extended::elements::Break::new(
-            self.style.list_item.before_spacing,
-        ));
-        let mut para = genpdfi_extended::elements::Paragraph::default();
-        let style = genpdfi_extended::style::Style::new().with_font_size(self.style.list_item.size);
+    /// The sections are computed once, since measuring tables is not free, and shared by
+    /// the layout and the post-processing steps.
+    fn orientation_sections(&self) -> &[(crate::styling::Orientation, std::ops::Range<usize>)] {
+        self.orientation_sections
+            .get_or_init(|| self.split_orientation_sections())
+    }
 
-        let indent = "    ".repeat(nesting_level);
-        if !ordered {
-            para.push_styled(format!("{}- ", indent), style.clone());
-        } else if let Some(n) = number {
-            para.push_styled(format!("{}{}. ", indent, n), style.clone());
-        }
-
-        let inline_content: Vec<Token> = content
-            .iter()
-            .filter(|token| !matches!(token, Token::ListItem { .. }))
-            .cloned()
-            .collect();
-        self.render_inline_content_with_style_simple(&mut para, &inline_content, style);
-        doc.push(para);
-        doc.push(genpdfi_extended::elements::Break::new(
-            self.style.list_item.after_spacing,
-        ));
-
-        for token in content {
-            if let Token::ListItem {
-                content: nested_content,
-                ordered: nested_ordered,
-                number: nested_number,
-            } = token
-            {
-                self.render_list_item(
-                    doc,
-                    nested_content,
-                    *nested_ordered,
-                    *nested_number,
-                    nesting_level + 1,
-                );
+    /// Computes the [`Pdf::orientation_sections`] of the document.
+    fn split_orientation_sections(
+        &self,
+    ) -> Vec<(crate::styling::Orientation, std::ops::Range<usize>)> {
+        let mut sections = Vec::new();
+        let mut orientation = self.style.orientation;
+        let mut start = 0;
+        for (index, token) in self.input.iter().enumerate() {
+            match orientation_directive(token) {
+                Some(next) if next != orientation => {
+                    sections.push((orientation, start..index));
+                    orientation = next;
+                    start = index + 1;
+                }
+                _ => {
+                    if let Some(turned) = self.oversized_table_orientation(token, orientation) {
+                        sections.push((orientation, start..index));
+                        sections.push((turned, index..index + 1));
+                        start = index + 1;
+                    }
+                }
             }
         }
+        sections.push((orientation, start..self.input.len()));
+
+        let visible = |range: &std::ops::Range<usize>| {
+            self.input[range.clone()].iter().any(|token| {
+                !matches!(
+                    token,
+                    Token::Newline
+                        | Token::SoftBreak
+                        | Token::HtmlComment(_)
+                        | Token::AbbreviationDefinition(..)
+                )
+            })
+        };
+        if sections.iter().filter(|(_, range)| visible(range)).count() > 0 {
+            sections.retain(|(_, range)| visible(range));
+        }
+        sections
     }
 
-    /// Renders a table with headers, alignment information, and rows.
-    ///
-    /// Each row is a vector of cells.
-    ///
-    /// The table is rendered using genpdfi's TableLayout with proper column weights
-    /// and cell borders. Each cell content is processed as inline tokens to handle
-    /// formatting within table them.
-    fn render_table(
+    /// With `[table] rotate_if_oversized`, returns the turned orientation for a table that
+    /// does not fit pages of `orientation` even at [`TABLE_MIN_FONT_SIZE`], when it fits
+    /// the wider turned pages at that size. Returns `None` for other tokens, tables that
+    /// fit and tables too wide for either orientation, which are laid out following
+    /// `[table] overflow` instead.
+    fn oversized_table_orientation(
         &self,
-        doc: &mut Document,
-        headers: &Vec<Vec<Token>>,
-        aligns: &Vec<Alignment>,
-        rows: &Vec<Vec<Vec<Token>>>,
-    ) {
-        doc.push(genpdfi_extended::elements::Break::new(
-            self.style.text.before_spacing,
-        ));
-
-        let column_count = headers.len();
-        let column_weights = vec![1; column_count];
-
-        let mut table = genpdfi_extended::elements::TableLayout::new(column_weights);
-        table.set_cell_decorator(genpdfi_extended::elements::FrameCellDecorator::new(
-            true, true, false,
-        ));
-
-        // Render header row
-        let mut header_row = table.row();
-        for (i, header_cell) in headers.iter().enumerate() {
-            let mut para = genpdfi_extended::elements::Paragraph::default();
-            let style =
-                genpdfi_extended::style::Style::new().with_font_size(self.style.table_header.size);
-
-            if let Some(align) = aligns.get(i) {
-                para.set_alignment(*align);
-            }
+        token: &Token,
+        orientation: crate::styling::Orientation,
+    ) -> Option<crate::styling::Orientation> {
+        use crate::styling::Orientation;
 
-            self.render_inline_content_with_style_simple(&mut para, header_cell, style);
-            header_row.push_element(para);
+        let Token::Table { headers, rows, .. } = token else {
+            return None;
+        };
+        if !self.style.table_rotate_if_oversized {
+            return None;
         }
-
-        if let Err(_) = header_row.push() {
-            warn!("Failed rendering a table");
-            return; // Skip the entire table if header fails
+        let turned = match orientation {
+            Orientation::Portrait => Orientation::Landscape,
+            Orientation::Landscape => Orientation::Portrait,
+        };
+        let (width, height) = self.style.page_dimensions();
+        let (width, turned_width) = if orientation == self.style.orientation {
+            (width, height)
+        } else {
+            (height, width)
+        };
+        if turned_width <= width {
+            return None;
         }
 
-        // Render data rows
-        for (row_idx, row) in rows.iter().enumerate() {
-            let mut table_row = table.row();
-
-            for (i, cell_tokens) in row.iter().enumerate() {
-                let mut para = genpdfi_extended::elements::Paragraph::default();
-                let style = genpdfi_extended::style::Style::new()
-                    .with_font_size(self.style.table_cell.size);
+        let margins = self.style.margins.left + self.style.margins.right;
+        let (natural, minimum) = self.measure_table_columns(headers, rows);
+        let smallest = self.style.table_header.size.min(self.style.table_cell.size);
+        let fits = |page: f32| match fit_table_columns(&natural, &minimum, page - margins) {
+            Some((_, scale)) => smallest as f32 * scale >= TABLE_MIN_FONT_SIZE as f32,
+            None => true,
+        };
+        (!fits(width) && fits(turned_width)).then_some(turned)
+    }
 
-                if let Some(align) = aligns.get(i) {
-                    para.set_alignment(*align);
-                }
+    /// Returns true if `<!-- orientation: ... -->` directives or oversized tables change
+    /// the page orientation of the document, in which case it must be rendered with
+    /// [`Pdf::render_sections`].
+    pub fn has_orientation_changes(&self) -> bool {
+        let sections = self.orientation_sections();
+        sections.len() > 1 || sections[0].0 != self.style.orientation
+    }
 
-                self.render_inline_content_with_style_simple(&mut para, cell_tokens, style);
-                table_row.push_element(para);
-            }
+    /// Renders the document one orientation section at a time and joins the pages into
+    /// one PDF. Each section starts on a new page, with the paper size turned for
+    /// landscape sections. Fonts and images identical in several sections are embedded
+    /// once.
+    pub fn render_sections(&self) -> Result<Vec<u8>, String> {
+        let sections = self.orientation_sections();
+        let mut parts = Vec::new();
+        for (i, (orientation, range)) in sections.iter().enumerate() {
+            let document = self.render_section(
+                &self.input[range.clone()],
+                *orientation,
+                i == 0,
+                i + 1 == sections.len(),
+            );
+            parts.push(Pdf::render_to_bytes(document));
+        }
+        merge_pdfs(&parts.into_iter().collect::<Result<Vec<_>, _>>()?)
+    }
 
-            if let Err(_) = table_row.push() {
-                warn!("Failed to push row {} in a table", row_idx);
-                continue; // Continue with next row
-            }
+    /// Pushes vertical spacing of `lines` lines onto the document.
+    ///
+    /// With `[spacing] trim_edges`, spacing before the first element is dropped and
+    /// spacing is held back until [`Pdf::push_block`] adds more content, so the spacing
+    /// after the last element never reaches the document.
+    fn push_spacing(&self, doc: &mut Document, lines: f32) {
+        if !self.style.spacing.trim_edges {
+            doc.push(genpdfi_extended::elements::Break::new(lines));
+        } else if self.has_pushed_content.get() {
+            let pending = self.pending_spacing.get().unwrap_or(0.0);
+            self.pending_spacing.set(Some(pending + lines));
         }
+    }
 
-        doc.push(table);
-        doc.push(genpdfi_extended::elements::Break::new(
-            self.style.text.after_spacing,
-        ));
+    /// Pushes an element onto the document, after any spacing held back for it.
+    fn push_block<E: genpdfi_extended::Element + 'static>(&self, doc: &mut Document, element: E) {
+        if let Some(lines) = self.pending_spacing.take() {
+            doc.push(genpdfi_extended::elements::Break::new(lines));
+        }
+        self.has_pushed_content.set(true);
+        doc.push(element);
     }
 
-    /// Renders an image token as a block-level element in the document.
-    ///
-    /// Attempts to load the image from the configured ImageLoader and embed it
-    /// into the PDF. For SVG images, uses native SVG rendering with configurable sizing.
-    /// For raster formats, uses Image::from_reader(). If loading fails or no loader is
-    /// configured, renders the alt text.
-    ///
-    /// # SVG Sizing Configuration
-    ///
-    /// SVG images can be sized using the `[image.svg]` configuration section:
-    ///
-    /// - `width`: Percentage of page width (e.g., "50%")
-    ///   - When specified, completely overrides `scale_factor`
-    ///   - Example: `width = "50%"` renders at 50% of page width
-    ///
-    /// - `scale_factor`: Multiplier of original SVG dimensions (default: 1.0)
-    ///   - Only used when `width` is not specified
-    ///   - Scales the intrinsic SVG dimensions (from width/height attributes)
-    ///   - Example: `scale_factor = 2.0` renders at 200% of original size
-    ///   - Example: `scale_factor = 0.5` renders at 50% of original size
-    ///
-    /// # Priority
-    ///
-    /// If both `width` and `scale_factor` are specified, `width` takes priority
-    /// and `scale_factor` is completely ignored.
-    ///
-    /// # Examples
+    /// Processes and renders tokens directly into the document structure.
     ///
-    /// With `markdown2pdfrc.toml`:
-    /// ```toml
-    /// [image.svg]
-    /// width = "50%"     # SVG will be 50% of page width
-    /// ```
+    /// This method iterates through all input tokens and renders them into the document,
+    /// handling each token type appropriately according to its semantic meaning. Block-level
+    /// elements like headings, list items, and code blocks trigger the flushing of any
+    /// accumulated inline tokens into paragraphs before being rendered themselves.
     ///
-    /// Or with scale_factor only:
-    /// ```toml
-    /// [image.svg]
-    /// scale_factor = 0.5  # SVG will be 50% of its original size
-    /// ```
+    /// The method maintains a buffer of current tokens that gets flushed into paragraphs
+    /// when block-level elements are encountered or when explicit paragraph breaks are
+    /// needed. This ensures proper document flow and maintains correct spacing between
+    /// different content elements while preserving the intended document structure.
     ///
-    /// Or with scale_factor = 2.0:
-    /// ```toml
-    /// [image.svg]
-    /// scale_factor = 2.0  # SVG will be 200% of its original size
-    /// ```
-    fn render_image(&self, doc: &mut Document, alt: &str, url: &str) {
-        doc.push(genpdfi_extended::elements::Break::new(0.5));
+    /// Through careful token processing and rendering, this method builds up the complete
+    /// document content with appropriate styling, formatting and layout applied according
+    /// to the configured style settings.
+    fn process_tokens(&self, doc: &mut Document, tokens: &[Token]) {
+        self.render_tokens(doc, tokens);
 
-        let mut loader_opt = self.image_loader.borrow_mut();
+        self.render_footnotes(doc);
 
-        if let Some(ref mut loader) = *loader_opt {
-            match loader.load(url) {
-                Ok(image_data) => {
-                    // Try to load the image based on its format
-                    match image_data.format {
-                        crate::images::ImageFormat::Svg => {
-                            // For SVG, use native SVG rendering with configuration
-                            match String::from_utf8(image_data.bytes.clone()) {
-                                Ok(svg_string) => {
-                                    match genpdfi_extended::elements::Image::from_svg_string(
-                                        &svg_string,
-                                    ) {
-                                        Ok(image) => {
-                                            // Apply width and scale_factor configuration
-                                            // Priority: width > scale_factor (width surcharges scale_factor)
-                                            //
-                                            // width: percentage of page width (e.g., "50%")
-                                            //        When specified, scale_factor is completely ignored
-                                            // scale_factor: multiplier of original SVG dimensions (1.0 = original, 0.5 = 50%, 2.0 = 200%)
-                                            //              Only used when width is not specified
-                                            //              Scales the intrinsic SVG dimensions by this factor
-                                            let image = match self.style.svg_config.width {
-                                                SvgWidth::Percentage(percent) => {
-                                                    // width surcharges scale_factor
-                                                    image.resizing_page_with(percent / 100.0)
-                                                }
-                                                SvgWidth::Pixels(_pixels) => {
-                                                    // For pixel-based sizing, genpdfi only supports page-fraction sizing
-                                                    // So we can't properly implement this yet
-                                                    image
-                                                }
-                                                SvgWidth::Auto => {
-                                                    // When width is not specified, use scale_factor
-                                                    // scale_factor multiplies the intrinsic SVG dimensions
-                                                    if self.style.svg_config.scale_factor != 1.0 {
-                                                        image.with_scale(Scale::new(
-                                                            self.style.svg_config.scale_factor,
-                                                            self.style.svg_config.scale_factor,
-                                                        ))
-                                                    } else {
-                                                        image
-                                                    }
-                                                }
-                                            };
-
-                                            let resized_image =
-                                                image.with_alignment(Alignment::Center);
-                                            doc.push(resized_image);
-                                        }
-                                        Err(e) => {
-                                            warn!("Failed to render SVG: {}", e);
-                                            let mut para =
-                                                genpdfi_extended::elements::Paragraph::default();
-                                            let style = genpdfi_extended::style::Style::new()
-                                                .with_font_size(self.style.text.size)
-                                                .italic();
-                                            para.push_styled(
-                                                format!("[SVG Image: {}]", alt),
-                                                style,
-                                            );
-                                            doc.push(para);
-                                        }
-                                    }
-                                }
-                                Err(e) => {
-                                    warn!("Failed to decode SVG as UTF-8: {}", e);
-                                    let mut para = genpdfi_extended::elements::Paragraph::default();
-                                    let style = genpdfi_extended::style::Style::new()
-                                        .with_font_size(self.style.text.size)
-                                        .italic();
-                                    para.push_styled(format!("[SVG Image: {}]", alt), style);
-                                    doc.push(para);
-                                }
-                            }
-                        }
-                        _ => {
-                            // For raster formats (JPEG, PNG, WebP, GIF), use from_reader
-                            match genpdfi_extended::elements::Image::from_reader(
-                                std::io::Cursor::new(image_data.bytes),
-                            ) {
-                                Ok(image) => {
-                                    let resized_image = image
-                                        .resizing_page_with(0.8)
-                                        .with_alignment(Alignment::Center);
-                                    doc.push(resized_image);
-                                }
-                                Err(e) => {
-                                    warn!("Failed to create image from data: {}", e);
-                                    let mut para = genpdfi_extended::elements::Paragraph::default();
-                                    let style = genpdfi_extended::style::Style::new()
-                                        .with_font_size(self.style.text.size)
-                                        .italic();
-                                    para.push_styled(format!("[Image: {}]", alt), style);
-                                    doc.push(para);
-                                }
-                            }
+        if self.style.abbreviations.mode == AbbreviationMode::Glossary {
+            self.render_abbreviation_glossary(doc);
+        }
+    }
+
+    /// Renders a sequence of block and inline tokens, grouping inline tokens into paragraphs.
+    /// Used for the document body and for block content nested in it, such as tabs.
+    fn render_tokens(&self, doc: &mut Document, tokens: &[Token]) {
+        let mut current_tokens = Vec::new();
+        let mut consecutive_images = Vec::new();
+        // Whether anything visible precedes the current token
+        let mut has_content = false;
+        // Completed and total task items of the list being rendered
+        let mut task_progress = (0, 0);
+        let list_numbers = list_numbers(tokens);
+
+        for (index, token) in tokens.iter().enumerate() {
+            if !matches!(
+                token,
+                Token::ListItem { .. } | Token::Newline | Token::SoftBreak
+            ) {
+                self.render_task_progress(doc, &mut task_progress);
+            }
+
+            let at_document_start = !has_content;
+            has_content |= !matches!(
+                token,
+                Token::Newline
+                    | Token::SoftBreak
+                    | Token::HtmlComment(_)
+                    | Token::AbbreviationDefinition(..)
+                    | Token::Anchor(_)
+            );
+
+            match token {
+                Token::Heading(content, level) => {
+                    self.flush_paragraph(doc, &current_tokens);
+                    self.flush_consecutive_images(doc, &consecutive_images);
+                    current_tokens.clear();
+                    consecutive_images.clear();
+                    // A break before the document's first content would leave a blank page
+                    let level_index = (*level).clamp(1, 3) - 1;
+                    if self.style.page_break_before[level_index] && !at_document_start {
+                        self.push_block(doc, genpdfi_extended::elements::PageBreak::new());
+                    }
+                    let number = self
+                        .style
+                        .heading_numbering
+                        .next_number(&mut self.heading_counters.borrow_mut(), *level);
+                    match number {
+                        Some(number) => {
+                            let mut numbered = vec![Token::Text(format!("{} ", number))];
+                            numbered.extend(content.iter().cloned());
+                            self.render_heading(doc, &numbered, *level);
                         }
+                        None => self.render_heading(doc, content, *level),
                     }
                 }
-                Err(e) => {
-                    warn!("Failed to load image {}: {}", url, e);
-                    let mut para = genpdfi_extended::elements::Paragraph::default();
-                    let style = genpdfi_extended::style::Style::new()
-                        .with_font_size(self.style.text.size)
-                        .italic();
-                    para.push_styled(format!("[Image not found: {}]", alt), style);
-                    doc.push(para);
+                Token::ListItem {
+                    content,
+                    ordered,
+                    number,
+                } => {
+                    self.flush_paragraph(doc, &current_tokens);
+                    self.flush_consecutive_images(doc, &consecutive_images);
+                    current_tokens.clear();
+                    consecutive_images.clear();
+                    let (done, total) = count_tasks(std::slice::from_ref(token));
+                    task_progress = (task_progress.0 + done, task_progress.1 + total);
+                    let number = list_numbers[index].or(*number);
+                    self.render_list_item(doc, content, *ordered, number, 0);
+                }
+                Token::Code(lang, content) if lang.trim().eq_ignore_ascii_case("qr") => {
+                    self.flush_paragraph(doc, &current_tokens);
+                    self.flush_consecutive_images(doc, &consecutive_images);
+                    current_tokens.clear();
+                    consecutive_images.clear();
+                    self.render_qr_code(doc, lang, content);
+                }
+                #[cfg(feature = "math-rust")]
+                Token::Code(lang, content) if lang.trim().eq_ignore_ascii_case("asciimath") => {
+                    self.flush_paragraph(doc, &current_tokens);
+                    self.flush_consecutive_images(doc, &consecutive_images);
+                    current_tokens.clear();
+                    consecutive_images.clear();
+                    self.render_unicode_math_block(doc, crate::math::asciimath_to_unicode(content));
+                }
+                Token::Code(lang, content) if content.contains('\n') => {
+                    self.flush_paragraph(doc, &current_tokens);
+                    self.flush_consecutive_images(doc, &consecutive_images);
+                    current_tokens.clear();
+                    consecutive_images.clear();
+                    // If the code block language is `mermaid`, render it via genpdfi_extended::elements::Mermaid
+                    if lang.trim().eq_ignore_ascii_case("mermaid") {
+                        self.render_mermaid(doc, content);
+                    } else if lang.trim().eq_ignore_ascii_case("svg") {
+                        self.render_inline_svg(doc, lang, content);
+                    } else if lang.trim().eq_ignore_ascii_case("shortcuts") {
+                        self.render_shortcuts(doc, lang, content);
+                    } else {
+                        self.render_code_block(doc, lang, content);
+                    }
+                }
+                Token::Math { content, display } if *display => {
+                    // Display math ($$...$$) is a block-level element
+                    self.flush_paragraph(doc, &current_tokens);
+                    self.flush_consecutive_images(doc, &consecutive_images);
+                    current_tokens.clear();
+                    consecutive_images.clear();
+                    self.render_math_block(doc, content);
+                }
+                Token::Math {
+                    content: _,
+                    display: false,
+                } => {
+                    // Inline math ($...$) - treat as inline content, not block
+                    current_tokens.push(token.clone());
+                }
+                Token::BlockQuote(text) => {
+                    self.flush_paragraph(doc, &current_tokens);
+                    self.flush_consecutive_images(doc, &consecutive_images);
+                    current_tokens.clear();
+                    consecutive_images.clear();
+                    let first = !is_block_quote(tokens[..index].iter().rev());
+                    let last = !is_block_quote(tokens[index + 1..].iter());
+                    self.render_block_quote_line(doc, text, first, last);
+                }
+                Token::SoftBreak | Token::LineBreak
+                    if index > 0 && matches!(tokens[index - 1], Token::BlockQuote(_)) =>
+                {
+                    // Quote lines are rendered as their own paragraphs
+                }
+                Token::HorizontalRule => {
+                    self.flush_paragraph(doc, &current_tokens);
+                    self.flush_consecutive_images(doc, &consecutive_images);
+                    current_tokens.clear();
+                    consecutive_images.clear();
+                    self.push_spacing(doc, self.style.horizontal_rule.after_spacing);
+                }
+                Token::LineBreak => {
+                    // Line breaks are inline - treat as part of paragraph
+                    current_tokens.push(token.clone());
+                }
+                Token::Newline => {
+                    self.flush_paragraph(doc, &current_tokens);
+                    self.flush_consecutive_images(doc, &consecutive_images);
+                    current_tokens.clear();
+                    consecutive_images.clear();
+                }
+                Token::Table {
+                    headers,
+                    aligns,
+                    rows,
+                } => {
+                    self.flush_paragraph(doc, &current_tokens);
+                    self.flush_consecutive_images(doc, &consecutive_images);
+                    current_tokens.clear();
+                    consecutive_images.clear();
+                    self.render_table(doc, headers, aligns, rows)
+                }
+                Token::TabGroup(tabs) => {
+                    self.flush_paragraph(doc, &current_tokens);
+                    self.flush_consecutive_images(doc, &consecutive_images);
+                    current_tokens.clear();
+                    consecutive_images.clear();
+                    self.render_tab_group(doc, tabs);
+                }
+                Token::Admonition { kind, content } => {
+                    self.flush_paragraph(doc, &current_tokens);
+                    self.flush_consecutive_images(doc, &consecutive_images);
+                    current_tokens.clear();
+                    consecutive_images.clear();
+                    self.render_admonition(doc, kind, content);
+                }
+                Token::Spoiler(content) => {
+                    self.flush_paragraph(doc, &current_tokens);
+                    self.flush_consecutive_images(doc, &consecutive_images);
+                    current_tokens.clear();
+                    consecutive_images.clear();
+                    self.render_spoiler(doc, content);
+                }
+                Token::Image(alt, url) => {
+                    // Collect consecutive images to render together with minimal spacing
+                    consecutive_images.push((alt.clone(), url.clone(), false));
+                }
+                Token::ImageWithLink(alt, image_url, link_url) => {
+                    // Collect consecutive images with links to render together with minimal spacing
+                    consecutive_images.push((
+                        format!("{}||{}", image_url, link_url),
+                        alt.clone(),
+                        true,
+                    ));
+                }
+                Token::AbbreviationDefinition(_, _) => {
+                    // Definitions are collected up front and produce no output of their own
+                }
+                Token::Text(content)
+                    if !consecutive_images.is_empty() && content.trim().is_empty() =>
+                {
+                    // Ignore whitespace-only tokens (e.g., from single newlines) when we're collecting images
+                    // This allows images separated by single newlines to be grouped together
+                }
+                Token::SoftBreak if !consecutive_images.is_empty() => {
+                    // Images separated by single newlines are grouped together
+                }
+                Token::SoftBreak
+                    if current_tokens.is_empty()
+                        && self.style.soft_break == SoftBreakMode::Newline =>
+                {
+                    // A soft break right after a block element would start the paragraph
+                    // with an empty line
+                }
+                _ => {
+                    // If we have accumulated images and encounter non-image content, flush them first
+                    if !consecutive_images.is_empty() {
+                        self.flush_consecutive_images(doc, &consecutive_images);
+                        consecutive_images.clear();
+                    }
+                    current_tokens.push(token.clone());
                 }
             }
-        } else {
-            // No loader configured, just show alt text
-            let mut para = genpdfi_extended::elements::Paragraph::default();
-            let style = genpdfi_extended::style::Style::new()
-                .with_font_size(self.style.text.size)
-                .italic();
-            para.push_styled(format!("[Image: {}]", alt), style);
-            doc.push(para);
         }
 
-        doc.push(genpdfi_extended::elements::Break::new(0.5));
+        // Flush any remaining tokens
+        self.flush_paragraph(doc, &current_tokens);
+        self.flush_consecutive_images(doc, &consecutive_images);
+        self.render_task_progress(doc, &mut task_progress);
+    }
+
+    /// Renders the `[task_list] show_progress` summary of the list that just ended and
+    /// resets the counts. Lists without task items get no summary.
+    fn render_task_progress(&self, doc: &mut Document, progress: &mut (usize, usize)) {
+        let (done, total) = std::mem::take(progress);
+        if !self.style.task_list.show_progress || total == 0 {
+            return;
+        }
+        let mut style =
+            genpdfi_extended::style::Style::new().with_font_size(self.style.list_item.size);
+        if let Some((r, g, b)) = self.style.list_item.text_color {
+            style = style.with_color(genpdfi_extended::style::Color::Rgb(r, g, b));
+        }
+        self.push_spacing(doc, self.style.list_item.before_spacing);
+        let mut para = genpdfi_extended::elements::Paragraph::default();
+        para.push_styled(
+            format!("Progress: {}/{} completed", done, total),
+            style.italic(),
+        );
+        self.push_block(doc, para);
+        self.push_spacing(doc, self.style.list_item.after_spacing);
+    }
+
+    /// Renders a `:::tabs` group with the tabs stacked vertically, each introduced by its
+    /// title as a small bold header.
+    fn render_tab_group(&self, doc: &mut Document, tabs: &[(String, Vec<Token>)]) {
+        let title_style = genpdfi_extended::style::Style::new()
+            .with_font_size(self.style.text.size)
+            .bold();
+        for (title, content) in tabs {
+            if !title.is_empty() {
+                self.push_spacing(doc, self.style.text.before_spacing);
+                let mut para = genpdfi_extended::elements::Paragraph::default();
+                self.push_text_with_abbreviations(&mut para, title, title_style.clone());
+                self.push_block(doc, para);
+            }
+            self.render_tokens(doc, content);
+        }
+        self.push_spacing(doc, self.style.text.after_spacing);
+    }
+
+    /// Renders a reStructuredText admonition as a callout: its kind as a bold title in the
+    /// kind's color, followed by its content.
+    fn render_admonition(&self, doc: &mut Document, kind: &str, content: &[Token]) {
+        let (r, g, b) = admonition_color(kind);
+        let title_style = genpdfi_extended::style::Style::new()
+            .with_font_size(self.style.text.size)
+            .with_color(genpdfi_extended::style::Color::Rgb(r, g, b))
+            .bold();
+        let mut title: String = kind.chars().take(1).flat_map(char::to_uppercase).collect();
+        title.extend(kind.chars().skip(1));
+
+        self.push_spacing(doc, self.style.text.before_spacing);
+        let mut para = genpdfi_extended::elements::Paragraph::default();
+        para.push_styled(title, title_style);
+        self.push_block(doc, para);
+        self.render_tokens(doc, content);
+        self.push_spacing(doc, self.style.text.after_spacing);
+    }
+
+    /// Renders a spoiler revealed: its content in a bordered box, under the `[spoiler]`
+    /// label in bold.
+    fn render_spoiler(&self, doc: &mut Document, content: &[Token]) {
+        let spoiler = &self.style.spoiler;
+        let mut style = genpdfi_extended::style::Style::new().with_font_size(spoiler.style.size);
+        if spoiler.style.italic {
+            style = style.italic();
+        }
+        if let Some((r, g, b)) = spoiler.style.text_color {
+            style = style.with_color(genpdfi_extended::style::Color::Rgb(r, g, b));
+        }
+        let padding = genpdfi_extended::Margins::trbl(1.0, 2.0, 1.0, 2.0);
+
+        self.push_spacing(doc, spoiler.style.before_spacing);
+        let mut block = genpdfi_extended::elements::LinearLayout::vertical();
+        let mut label = genpdfi_extended::elements::Paragraph::default();
+        label.push_styled(spoiler.label.clone(), style.clone().bold());
+        block.push(label.padded(padding));
+        let mut para = genpdfi_extended::elements::Paragraph::default();
+        if spoiler.style.bold {
+            style = style.bold();
+        }
+        self.render_inline_content_with_style_simple(&mut para, content, style);
+        block.push(para.padded(padding));
+
+        let mut table = genpdfi_extended::elements::TableLayout::new(vec![1]);
+        table.set_cell_decorator(genpdfi_extended::elements::FrameCellDecorator::new(
+            true, true, false,
+        ));
+        let mut row = table.row();
+        row.push_element(block);
+        if row.push().is_err() {
+            self.render_warning("Failed rendering a spoiler".to_string());
+            return;
+        }
+        self.push_block(doc, table);
+        self.push_spacing(doc, spoiler.style.after_spacing);
+    }
+
+    /// Renders the notes referenced in the document, numbered in order of reference.
+    ///
+    /// Notes may themselves contain footnotes; those are appended to the collection while
+    /// rendering and listed after the others. Each note number links back to its reference
+    /// marker, as GFM footnotes do in HTML.
+    fn render_footnotes(&self, doc: &mut Document) {
+        if self.footnotes.borrow().is_empty() {
+            return;
+        }
+
+        self.render_heading(doc, &[Token::Text("Notes".to_string())], 2);
+
+        let style = genpdfi_extended::style::Style::new().with_font_size(self.style.text.size);
+        let mut idx = 0;
+        loop {
+            let Some(content) = self.footnotes.borrow().get(idx).cloned() else {
+                break;
+            };
+            let mut para = genpdfi_extended::elements::Paragraph::default();
+            para.push_link(
+                format!("{}.", idx + 1),
+                footnote_link_uri(&format!("fn-{}", idx + 1), &format!("fnref-{}", idx + 1)),
+                style.clone(),
+            );
+            para.push_styled(" ".to_string(), style.clone());
+            self.render_inline_content_with_style(&mut para, &content, style.clone(), doc);
+            self.push_block(doc, para);
+            idx += 1;
+        }
+        self.push_spacing(doc, self.style.text.after_spacing);
+    }
+
+    /// Records a footnote and pushes its `[n]` reference marker, linked to the note.
+    fn push_footnote_marker(
+        &self,
+        para: &mut genpdfi_extended::elements::Paragraph,
+        content: &[Token],
+        style: genpdfi_extended::style::Style,
+    ) {
+        let number = {
+            let mut footnotes = self.footnotes.borrow_mut();
+            footnotes.push(content.to_vec());
+            footnotes.len()
+        };
+        para.push_link(
+            format!("[{}]", number),
+            footnote_link_uri(&format!("fnref-{}", number), &format!("fn-{}", number)),
+            style,
+        );
+    }
+
+    /// Renders the index of tagged terms, alphabetically and grouped by initial, each with
+    /// the pages it appears on. Pages are only known once the rest of the document is
+    /// laid out (see [`Pdf::render_pdf`]); until then the terms are listed without them.
+    fn render_index(&self, doc: &mut Document) {
+        if self.index_terms.is_empty() {
+            return;
+        }
+
+        self.render_heading(doc, &[Token::Text(self.style.index.title.clone())], 2);
+
+        let style = genpdfi_extended::style::Style::new().with_font_size(self.style.text.size);
+        let pages = self.index_pages.borrow();
+        let mut initial = None;
+        for (idx, term) in self.index_terms.iter().enumerate() {
+            let term_initial = term.chars().next().map(|c| c.to_uppercase().to_string());
+            if term_initial != initial {
+                self.push_spacing(doc, self.style.text.before_spacing);
+                let mut para = genpdfi_extended::elements::Paragraph::default();
+                para.push_styled(
+                    term_initial.clone().unwrap_or_default(),
+                    style.clone().bold(),
+                );
+                self.push_block(doc, para);
+                initial = term_initial;
+            }
+
+            let mut para = genpdfi_extended::elements::Paragraph::default();
+            self.push_text_with_range_fonts(&mut para, term.clone(), style.clone());
+            if let Some(term_pages) = pages.get(idx).filter(|p| !p.is_empty()) {
+                let list: Vec<String> = term_pages.iter().map(|p| p.to_string()).collect();
+                para.push_styled(format!(", {}", list.join(", ")), style.clone());
+            }
+            self.push_block(doc, para);
+        }
+        self.push_spacing(doc, self.style.text.after_spacing);
+    }
+
+    /// Renders the glossary of abbreviations used in the document.
+    ///
+    /// Only abbreviations that actually appear in the text are listed, in order of first use.
+    /// Nothing is rendered when no defined abbreviation was used.
+    fn render_abbreviation_glossary(&self, doc: &mut Document) {
+        let used = self.used_abbreviations.borrow().clone();
+        if used.is_empty() {
+            return;
+        }
+
+        self.render_heading(doc, &[Token::Text("Abbreviations".to_string())], 2);
+
+        let style = genpdfi_extended::style::Style::new().with_font_size(self.style.text.size);
+        for idx in used {
+            let (abbr, expansion) = &self.abbreviations[idx];
+            let mut para = genpdfi_extended::elements::Paragraph::default();
+            para.push_styled(abbr.clone(), style.clone().bold());
+            para.push_styled(format!(": {}", expansion), style.clone());
+            self.push_block(doc, para);
+        }
+        self.push_spacing(doc, self.style.text.after_spacing);
+    }
+
+    /// Pushes plain text into a paragraph, styling occurrences of defined abbreviations.
+    ///
+    /// In `first_use` mode the expansion is appended in parentheses the first time an
+    /// abbreviation is encountered; in `glossary` mode the abbreviation is recorded so it
+    /// can be listed at the end of the document.
+    fn push_text_with_abbreviations(
+        &self,
+        para: &mut genpdfi_extended::elements::Paragraph,
+        text: &str,
+        style: genpdfi_extended::style::Style,
+    ) {
+        if self.abbreviations.is_empty() {
+            self.push_text_with_range_fonts(para, text.to_string(), style);
+            return;
+        }
+
+        let abbr_config = &self.style.abbreviations;
+        for (segment, abbr_idx) in split_abbreviations(text, &self.abbreviations) {
+            let Some(idx) = abbr_idx else {
+                self.push_text_with_range_fonts(para, segment, style.clone());
+                continue;
+            };
+
+            let mut abbr_style = style.clone();
+            if abbr_config.style.bold {
+                abbr_style = abbr_style.bold();
+            }
+            if abbr_config.style.italic {
+                abbr_style = abbr_style.italic();
+            }
+            if let Some(color) = abbr_config.style.text_color {
+                abbr_style = abbr_style.with_color(genpdfi_extended::style::Color::Rgb(
+                    color.0, color.1, color.2,
+                ));
+            }
+            if abbr_config.style.size != self.style.text.size {
+                // The size is relative to the body text, so abbreviations in headings
+                // scale with the heading
+                let size = style.font_size() as f32 * abbr_config.style.size as f32
+                    / self.style.text.size.max(1) as f32;
+                abbr_style = abbr_style.with_font_size((size.round() as u8).max(1));
+            }
+            // An abbreviation inside `<ins>` or `<del>` keeps that line
+            let underline = abbr_config.style.underline
+                && self.strike_color.get().is_none()
+                && self.underline_color.get().is_none();
+            if underline {
+                self.underline_color.set(Some(
+                    abbr_config
+                        .style
+                        .text_color
+                        .or(self.style.text.text_color)
+                        .unwrap_or((0, 0, 0)),
+                ));
+            }
+            self.push_text_with_range_fonts(para, segment, abbr_style);
+            if underline {
+                self.underline_color.set(None);
+            }
+
+            let first_use = !self.used_abbreviations.borrow().contains(&idx);
+            if first_use {
+                self.used_abbreviations.borrow_mut().push(idx);
+                if abbr_config.mode == AbbreviationMode::FirstUse {
+                    self.push_text_with_range_fonts(
+                        para,
+                        format!(" ({})", self.abbreviations[idx].1),
+                        style.clone(),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Pushes the text of a link. Text that is itself a URL is pushed in pieces ending after
+    /// `/`, `.`, `?` or `&`, giving the line wrapper break opportunities inside long URLs.
+    ///
+    /// With `[link] style = "footnote"`, external links whose text differs from the URL are
+    /// pushed as plain text followed by a footnote marker, and the URL becomes the note.
+    /// With `[link] style = "parenthetical"`, they are followed by the URL in parentheses.
+    fn push_link_text(
+        &self,
+        para: &mut genpdfi_extended::elements::Paragraph,
+        text: &str,
+        url: &str,
+        style: genpdfi_extended::style::Style,
+    ) {
+        if self.style.link_style == LinkStyle::Footnote && text != url && !url.starts_with('#') {
+            self.push_text_with_range_fonts(para, text.to_string(), style.clone());
+            self.push_footnote_marker(
+                para,
+                &[Token::Link(url.to_string(), url.to_string())],
+                style,
+            );
+            return;
+        }
+        if self.style.link_style == LinkStyle::Parenthetical && text != url && !url.starts_with('#')
+        {
+            self.push_link_segments(para, text, url, style.clone());
+            self.push_text_with_range_fonts(para, " (".to_string(), style.clone());
+            self.push_link_segments(para, url, url, style.clone());
+            self.push_text_with_range_fonts(para, ")".to_string(), style);
+            return;
+        }
+        self.push_link_segments(para, text, url, style);
+    }
+
+    /// Pushes `text` as a link to `url`, split into break-friendly pieces when it is a URL.
+    fn push_link_segments(
+        &self,
+        para: &mut genpdfi_extended::elements::Paragraph,
+        text: &str,
+        url: &str,
+        style: genpdfi_extended::style::Style,
+    ) {
+        let looks_like_url = (text.contains("://") || text.starts_with("www."))
+            && !text.contains(char::is_whitespace);
+        if !looks_like_url {
+            para.push_link(text.to_string(), url.to_string(), style);
+            return;
+        }
+        for segment in url_break_segments(text) {
+            para.push_link(segment.to_string(), url.to_string(), style.clone());
+        }
+    }
+
+    /// Pushes text, rendering characters covered by `[fonts.ranges]` with their mapped font.
+    /// While a strike-through, underline or highlight color is set, the text is wrapped in
+    /// the matching markers. A run carries a single marker: strike-through takes precedence
+    /// over underline, and underline over highlight. Otherwise pending anchor and index
+    /// markers are attached to the first words of the text. While a heading
+    /// `text_transform` is set, the text is uppercased or set in small caps first.
+    fn push_text_with_range_fonts(
+        &self,
+        para: &mut genpdfi_extended::elements::Paragraph,
+        text: String,
+        style: genpdfi_extended::style::Style,
+    ) {
+        match self.text_transform.get() {
+            TextTransform::None => {}
+            TextTransform::Uppercase => {
+                self.text_transform.set(TextTransform::None);
+                self.push_text_with_range_fonts(para, text.to_uppercase(), style);
+                self.text_transform.set(TextTransform::Uppercase);
+                return;
+            }
+            TextTransform::SmallCaps => {
+                self.text_transform.set(TextTransform::None);
+                for (run, run_style) in small_caps_runs(&text, style) {
+                    self.push_text_with_range_fonts(para, run, run_style);
+                }
+                self.text_transform.set(TextTransform::SmallCaps);
+                return;
+            }
+        }
+        let marker_uri = if let Some(color) = self.strike_color.get() {
+            Some(format!(
+                "{}{}",
+                STRIKE_URI_PREFIX,
+                marker_color(Some(color))
+            ))
+        } else if let Some(color) = self.underline_color.get() {
+            Some(format!(
+                "{}{}",
+                UNDERLINE_URI_PREFIX,
+                marker_color(Some(color))
+            ))
+        } else if let Some(color) = self.highlight_color.get() {
+            Some(format!(
+                "{}{}:{}",
+                KBD_URI_PREFIX,
+                marker_color(Some(color)),
+                marker_color(None)
+            ))
+        } else {
+            self.letter_spacing
+                .get()
+                .map(|spacing| format!("{}{}", TRACKING_URI_PREFIX, spacing))
+        };
+        let mut push = |mut run: String, style: genpdfi_extended::style::Style| {
+            if marker_uri.is_none() {
+                let mut pending = self.pending_markers.borrow_mut();
+                while !pending.is_empty() {
+                    let Some(end) = first_word_end(&run) else {
+                        break;
+                    };
+                    let rest = run.split_off(end);
+                    para.push_link(run, pending.remove(0), style.clone());
+                    run = rest;
+                }
+            }
+            if run.is_empty() {
+                return;
+            }
+            match &marker_uri {
+                Some(uri) => {
+                    para.push_link(run, uri.clone(), style);
+                    self.decorations.set(true);
+                }
+                None => para.push_styled(run, style),
+            }
+        };
+
+        if self.font_ranges.is_empty() {
+            push(text, style);
+            return;
+        }
+
+        CURRENT_RANGE_FONTS.with(|fonts| {
+            let fonts = fonts.borrow();
+            for (run, font_idx) in split_font_ranges(&text, &self.font_ranges) {
+                match font_idx.and_then(|idx| fonts.get(idx)) {
+                    Some(font) => push(run, style.clone().with_font_override(*font)),
+                    None => push(run, style.clone()),
+                }
+            }
+        });
+    }
+
+    /// Returns the warnings collected so far while loading fonts and rendering.
+    ///
+    /// Warnings are also written to the log as they occur; this accessor lets callers
+    /// inspect them programmatically, e.g. to fail a CI job on missing images.
+    pub fn warnings(&self) -> Vec<ValidationWarning> {
+        self.warnings.borrow().clone()
+    }
+
+    /// Logs and records a warning about an image that could not be loaded or rendered.
+    fn image_warning(&self, message: String) {
+        warn!("{}", message);
+        self.warnings
+            .borrow_mut()
+            .push(ValidationWarning::image_load_failed(message));
+    }
+
+    /// Logs and records a warning about an element that could not be rendered.
+    fn render_warning(&self, message: String) {
+        warn!("{}", message);
+        self.warnings
+            .borrow_mut()
+            .push(ValidationWarning::render_failure(message));
+    }
+
+    /// Returns the text a soft break renders as, following `[text] soft_break`.
+    fn soft_break_text(&self) -> String {
+        match self.style.soft_break {
+            SoftBreakMode::Space => " ".to_string(),
+            SoftBreakMode::Newline => "\n".to_string(),
+        }
+    }
+
+    /// Queues the invisible marker for an explicit anchor.
+    ///
+    /// The marker is a link over the next word whose URI carries the anchor name; its
+    /// position on the page becomes the target of `[text](#name)` links once
+    /// [`Pdf::resolve_anchors`] post-processes the rendered PDF.
+    fn push_anchor(&self, name: &str) {
+        self.pending_markers
+            .borrow_mut()
+            .push(format!("{}{}", ANCHOR_URI_PREFIX, name));
+    }
+
+    /// Pushes the markers queued since the first `pending` ones that no word took, at the
+    /// end of the inline content they were queued in. With no text left to attach to,
+    /// each marker is a one-space link, which ends the line and takes no visible room.
+    fn flush_markers(
+        &self,
+        para: &mut genpdfi_extended::elements::Paragraph,
+        pending: usize,
+        style: genpdfi_extended::style::Style,
+    ) {
+        let mut markers = self.pending_markers.borrow_mut();
+        if markers.len() > pending {
+            for uri in markers.split_off(pending) {
+                para.push_link(" ".to_string(), uri, style.clone());
+            }
+        }
+    }
+
+    /// Reports a token the inline renderers cannot render, so content does not disappear
+    /// silently: a render warning names the token, and debug builds also show it in the
+    /// text as `[Variant]`.
+    fn push_unrendered_token(
+        &self,
+        para: &mut genpdfi_extended::elements::Paragraph,
+        token: &Token,
+        style: genpdfi_extended::style::Style,
+    ) {
+        let name = token_variant_name(token);
+        self.render_warning(format!(
+            "{} token cannot be rendered inline and was dropped",
+            name
+        ));
+        if cfg!(debug_assertions) {
+            para.push_styled(format!("[{}]", name), style.italic());
+        }
+    }
+
+    /// Queues the invisible marker recording the position of an index term, whose page
+    /// [`Pdf::index_markers`] reads from the rendered PDF. Like anchors, the marker covers
+    /// the next word.
+    fn push_index_marker(&self, term: &str) {
+        if let Some(idx) = self.index_terms.iter().position(|t| t == term) {
+            self.pending_markers
+                .borrow_mut()
+                .push(format!("{}{}", INDEX_URI_PREFIX, idx));
+        }
+    }
+
+    /// Returns `style` with the `[mark]` text color, bold and italic applied, for the
+    /// content of a `<mark>` element.
+    fn mark_style(
+        &self,
+        mut style: genpdfi_extended::style::Style,
+    ) -> genpdfi_extended::style::Style {
+        let mark = &self.style.mark;
+        if mark.bold {
+            style = style.bold();
+        }
+        if mark.italic {
+            style = style.italic();
+        }
+        if let Some((r, g, b)) = mark.text_color {
+            style = style.with_color(genpdfi_extended::style::Color::Rgb(r, g, b));
+        }
+        style
+    }
+
+    /// Returns the line color state an `<ins>` (underline) or `<del>` (strike-through)
+    /// token sets while its content is rendered.
+    fn line_color_cell(&self, token: &Token) -> &Cell<Option<(u8, u8, u8)>> {
+        match token {
+            Token::Ins(_) => &self.underline_color,
+            _ => &self.strike_color,
+        }
+    }
+
+    /// Returns the style of the content of a CriticMarkup change, with the line or
+    /// highlight state it sets while that content is rendered: additions are green and
+    /// underlined, deletions red and struck through, and highlights look like `<mark>`.
+    fn critic_style(
+        &self,
+        change: CriticMarkup,
+        style: genpdfi_extended::style::Style,
+    ) -> (genpdfi_extended::style::Style, Option<ColorState<'_>>) {
+        match change {
+            CriticMarkup::Addition => {
+                let (r, g, b) = CRITIC_ADDITION_COLOR;
+                (
+                    style.with_color(genpdfi_extended::style::Color::Rgb(r, g, b)),
+                    Some((&self.underline_color, Some(CRITIC_ADDITION_COLOR))),
+                )
+            }
+            CriticMarkup::Deletion => {
+                let (r, g, b) = CRITIC_DELETION_COLOR;
+                (
+                    style.with_color(genpdfi_extended::style::Color::Rgb(r, g, b)),
+                    Some((&self.strike_color, Some(CRITIC_DELETION_COLOR))),
+                )
+            }
+            CriticMarkup::Highlight => (
+                self.mark_style(style),
+                Some((&self.highlight_color, self.style.mark.background_color)),
+            ),
+            CriticMarkup::Substitution => (style, None),
+        }
+    }
+
+    /// Pushes an inline code span in the `[code]` text color. With `[code] inline_background`
+    /// it is wrapped in a `<kbd>`-style marker so that [`Pdf::draw_decorations`] draws a
+    /// rounded box behind it, filled with the `[code]` background color or a light gray.
+    fn push_inline_code(
+        &self,
+        para: &mut genpdfi_extended::elements::Paragraph,
+        content: &str,
+        style: genpdfi_extended::style::Style,
+    ) {
+        let mut code_style = style;
+        if let Some(color) = self.style.code.text_color {
+            code_style = code_style.with_color(genpdfi_extended::style::Color::Rgb(
+                color.0, color.1, color.2,
+            ));
+        }
+
+        if !self.style.code_inline_background || content.is_empty() {
+            para.push_styled(content.to_string(), code_style);
+            return;
+        }
+        let fill = self
+            .style
+            .code
+            .background_color
+            .unwrap_or(INLINE_CODE_BACKGROUND);
+        let uri = format!("{}{}:-", KBD_URI_PREFIX, marker_color(Some(fill)));
+        para.push_link(format!(" {} ", content), uri, code_style);
+        self.decorations.set(true);
+    }
+
+    /// Pushes a `<kbd>` keyboard key.
+    ///
+    /// The label is rendered with the `[kbd]` style in the code font, or in the text font
+    /// (or its `[fonts.ranges]` font) when the code font lacks one of its glyphs. It is
+    /// wrapped in a marker link so that [`Pdf::draw_decorations`] can draw the key box.
+    fn push_kbd(
+        &self,
+        para: &mut genpdfi_extended::elements::Paragraph,
+        key: &str,
+        style: genpdfi_extended::style::Style,
+    ) {
+        let kbd = &self.style.kbd;
+        let mut kbd_style = style.with_font_size(kbd.style.size);
+        if kbd.style.bold {
+            kbd_style = kbd_style.bold();
+        }
+        if kbd.style.italic {
+            kbd_style = kbd_style.italic();
+        }
+        if let Some(color) = kbd.style.text_color {
+            kbd_style = kbd_style.with_color(genpdfi_extended::style::Color::Rgb(
+                color.0, color.1, color.2,
+            ));
+        }
+
+        if key.chars().all(|c| !self.kbd_missing_glyphs.contains(&c)) {
+            CURRENT_CODE_FONT_OVERRIDE.with(|f| {
+                if let Some(code_font) = f.borrow().as_ref() {
+                    kbd_style = kbd_style.clone().with_font_override(*code_font);
+                }
+            });
+        } else if let Some((_, idx)) = key
+            .chars()
+            .find_map(|c| self.font_ranges.iter().find(|(range, _)| range.contains(c)))
+        {
+            CURRENT_RANGE_FONTS.with(|fonts| {
+                if let Some(font) = fonts.borrow().get(*idx) {
+                    kbd_style = kbd_style.clone().with_font_override(*font);
+                }
+            });
+        }
+
+        let uri = format!(
+            "{}{}:{}",
+            KBD_URI_PREFIX,
+            marker_color(kbd.style.background_color),
+            marker_color(kbd.border_color)
+        );
+        para.push_link(format!(" {} ", key), uri, kbd_style);
+        self.decorations.set(true);
+    }
+
+    /// Pushes a task list checkbox: an outlined box in the code font, holding an `x` when
+    /// checked, drawn through a `<kbd>`-style marker in the `[task_list]` colors.
+    fn push_task_checkbox(
+        &self,
+        para: &mut genpdfi_extended::elements::Paragraph,
+        checked: bool,
+        style: genpdfi_extended::style::Style,
+    ) {
+        let config = &self.style.task_list;
+        let color = if checked {
+            config.checked_color
+        } else {
+            config.unchecked_color
+        };
+
+        let mut box_style = style.clone().bold();
+        if let Some((r, g, b)) = color {
+            box_style = box_style.with_color(genpdfi_extended::style::Color::Rgb(r, g, b));
+        }
+        // A monospace font keeps checked and unchecked boxes the same width
+        CURRENT_CODE_FONT_OVERRIDE.with(|f| {
+            if let Some(code_font) = f.borrow().as_ref() {
+                box_style = box_style.clone().with_font_override(*code_font);
+            }
+        });
+
+        let mark = if checked { " x " } else { "   " };
+        let uri = format!("{}-:{}", KBD_URI_PREFIX, marker_color(color));
+        para.push_link(mark.to_string(), uri, box_style);
+        para.push_styled(" ".to_string(), style);
+        self.decorations.set(true);
+    }
+
+    /// Renders accumulated consecutive images horizontally in a table.
+    /// This allows multiple images to be displayed side-by-side when they are not
+    /// separated by a Newline (paragraph break) in the source markdown.
+    /// According to CommonMark: single newline = whitespace (no line break), so images
+    /// on consecutive lines without double newlines should appear horizontally.
+    fn flush_consecutive_images(&self, doc: &mut Document, images: &[(String, String, bool)]) {
+        if images.is_empty() {
+            return;
+        }
+        if self.style.image_gallery.columns > 0 && images.len() > 1 {
+            self.render_image_gallery(doc, images);
+            return;
+        }
+
+        // Render all consecutive images together in a single container with minimal spacing
+        self.push_spacing(doc, self.style.text.before_spacing);
+
+        // Render each image without the standard breaks (which would separate them vertically)
+        for (idx, (first_part, second_part, is_link)) in images.iter().enumerate() {
+            if *is_link {
+                // ImageWithLink case: first_part is "url||link_url", second_part is alt
+                let parts: Vec<&str> = first_part.split("||").collect();
+                if parts.len() == 2 {
+                    self.push_image(doc, second_part, parts[0], Some(parts[1]));
+                }
+            } else {
+                // Regular Image case: first_part is alt, second_part is url
+                self.push_image(doc, first_part, second_part, None);
+            }
+
+            // Add a small space between consecutive images
+            if idx < images.len() - 1 {
+                self.push_spacing(doc, 0.05);
+            }
+        }
+
+        self.push_spacing(doc, self.style.text.after_spacing);
+    }
+
+    /// Lays consecutive images out in a grid of `[image.gallery] columns`, each image scaled
+    /// to the width of its column less the gutter, which also separates the rows.
+    fn render_image_gallery(&self, doc: &mut Document, images: &[(String, String, bool)]) {
+        let columns = self.style.image_gallery.columns;
+        let gutter = self.style.image_gallery.gutter;
+        let text_width =
+            self.page_dimensions().0 - self.style.margins.left - self.style.margins.right;
+        let width = if text_width > 0.0 {
+            ((text_width / columns as f32 - gutter) / text_width).clamp(0.01, 1.0)
+        } else {
+            1.0 / columns as f32
+        };
+
+        self.push_spacing(doc, self.style.text.before_spacing);
+        let mut table = genpdfi_extended::elements::TableLayout::new(vec![1; columns]);
+        let rows = images.chunks(columns).count();
+        for (row_idx, row_images) in images.chunks(columns).enumerate() {
+            let bottom = if row_idx + 1 < rows { gutter } else { 0.0 };
+            let padding = genpdfi_extended::Margins::trbl(0.0, gutter / 2.0, bottom, gutter / 2.0);
+            let mut row = table.row();
+            for idx in 0..columns {
+                let Some((first_part, second_part, is_link)) = row_images.get(idx) else {
+                    row.push_element(genpdfi_extended::elements::Paragraph::default());
+                    continue;
+                };
+                // Same encoding as `flush_consecutive_images`
+                let (alt, url, link) = if *is_link {
+                    let (url, link) = first_part
+                        .split_once("||")
+                        .unwrap_or((first_part.as_str(), ""));
+                    (second_part, url, Some(link))
+                } else {
+                    (first_part, second_part.as_str(), None)
+                };
+                let image = self.load_image_element(alt, url, |image, _| {
+                    image
+                        .resizing_page_with(width)
+                        .with_alignment(Alignment::Center)
+                });
+                match image {
+                    Ok(image) => {
+                        let image = match link {
+                            Some(link) if !link.is_empty() => image.with_link(link.to_string()),
+                            _ => image,
+                        };
+                        row.push_element(image.padded(padding));
+                    }
+                    Err(placeholder) => {
+                        let mut para = genpdfi_extended::elements::Paragraph::default();
+                        let style = genpdfi_extended::style::Style::new()
+                            .with_font_size(self.style.text.size)
+                            .italic();
+                        para.push_styled(placeholder, style);
+                        row.push_element(para.padded(padding));
+                    }
+                }
+            }
+            if row.push().is_err() {
+                self.render_warning(format!(
+                    "Failed to push row {} of an image gallery",
+                    row_idx
+                ));
+            }
+        }
+        self.push_block(doc, table);
+        self.push_spacing(doc, self.style.text.after_spacing);
+    }
+
+    /// Renders an image, linked to `link` if given and sized with [`Pdf::size_image`]. A
+    /// text placeholder takes its place when neither it nor the fallback image can be shown.
+    fn push_image(&self, doc: &mut Document, alt: &str, url: &str, link: Option<&str>) {
+        match self.load_image_element(alt, url, |image, data| self.size_image(image, data)) {
+            Ok(image) => {
+                let image = match link {
+                    Some(link) if !link.is_empty() => image.with_link(link.to_string()),
+                    _ => image,
+                };
+                self.push_block(doc, image);
+            }
+            Err(placeholder) => {
+                let mut para = genpdfi_extended::elements::Paragraph::default();
+                let style = genpdfi_extended::style::Style::new()
+                    .with_font_size(self.style.text.size)
+                    .italic();
+                para.push_styled(placeholder, style);
+                self.push_block(doc, para);
+            }
+        }
+    }
+
+    /// Loads the image at `url`, decodes it with [`decode_image_data`] and sizes it with
+    /// `size`. An image that cannot be loaded is replaced by `[image] fallback_image`,
+    /// sized the same way. Problems are recorded as image warnings; the error is the text
+    /// placeholder to render instead.
+    fn load_image_element(
+        &self,
+        alt: &str,
+        url: &str,
+        size: impl Fn(
+            genpdfi_extended::elements::Image,
+            &crate::images::ImageData,
+        ) -> genpdfi_extended::elements::Image,
+    ) -> Result<genpdfi_extended::elements::Image, String> {
+        let mut loader_opt = self.image_loader.borrow_mut();
+        let Some(loader) = loader_opt.as_mut() else {
+            return Err(format!("[Image: {}]", alt));
+        };
+        match self.load_image(loader, url) {
+            Ok(data) => match decode_image_data(&data) {
+                Ok(image) => Ok(size(image, &data)),
+                Err(e) => {
+                    self.image_warning(format!("Failed to render image {}: {}", url, e));
+                    Err(format!("[Image: {}]", alt))
+                }
+            },
+            Err(e) => {
+                self.image_warning(format!("Failed to load image from {}: {}", url, e));
+                self.fallback_image
+                    .as_ref()
+                    .and_then(|data| Some(size(decode_image_data(data).ok()?, data)))
+                    .ok_or_else(|| format!("[Image not found: {}]", alt))
+            }
+        }
+    }
+
+    /// Sizes a decoded image for the document flow.
+    ///
+    /// SVG images follow `[image.svg]`: `width` is a percentage of the page width and
+    /// takes priority over `scale_factor`, a multiplier of the SVG's intrinsic size. Pixel
+    /// widths keep the intrinsic size, as genpdfi only sizes images as a fraction of the
+    /// page. Raster images are rendered at [`Pdf::raster_image_width`].
+    fn size_image(
+        &self,
+        image: genpdfi_extended::elements::Image,
+        data: &crate::images::ImageData,
+    ) -> genpdfi_extended::elements::Image {
+        let svg = &self.style.svg_config;
+        match data.format {
+            crate::images::ImageFormat::Svg => match svg.width {
+                SvgWidth::Percentage(percent) => image.resizing_page_with(percent / 100.0),
+                SvgWidth::Auto if svg.scale_factor != 1.0 => {
+                    image.with_scale(Scale::new(svg.scale_factor, svg.scale_factor))
+                }
+                SvgWidth::Pixels(_) | SvgWidth::Auto => image,
+            },
+            _ => image.resizing_page_with(self.raster_image_width(data)),
+        }
+    }
+
+    /// Renders accumulated tokens as a paragraph in the document.
+    ///
+    /// This method takes a document and a slice of tokens, and renders them as a paragraph
+    /// with appropriate styling. If the tokens slice is empty, no paragraph is rendered.
+    /// After rendering the paragraph content, it adds spacing after the paragraph according
+    /// to the configured text style.
+    fn flush_paragraph(&self, doc: &mut Document, tokens: &[Token]) {
+        let mut tokens = tokens;
+        if self.style.soft_break == SoftBreakMode::Newline {
+            // Trailing soft breaks would add empty lines at the end of the paragraph
+            while let [rest @ .., Token::SoftBreak] = tokens {
+                tokens = rest;
+            }
+        }
+        if tokens.is_empty() {
+            return;
+        }
+
+        self.push_spacing(doc, self.style.text.before_spacing);
+        let mut para = genpdfi_extended::elements::Paragraph::default();
+        self.render_inline_content(&mut para, tokens, doc);
+        let text = Token::collect_all_text(tokens);
+        let padding = self.text_measure_padding();
+        if padding > 0.0 {
+            self.push_body_paragraph(
+                doc,
+                para.padded(genpdfi_extended::Margins::trbl(0.0, padding, 0.0, padding)),
+                &text,
+                2.0 * padding,
+            );
+        } else {
+            self.push_body_paragraph(doc, para, &text, 0.0);
+        }
+        self.push_spacing(doc, self.style.text.after_spacing);
+    }
+
+    /// Pushes a body paragraph of `text`, `inset` millimeters narrower than the text width.
+    /// With `[page] min_lines_top` or `min_lines_bottom` above 1, a paragraph split across
+    /// pages keeps at least that many lines on each side of the break.
+    fn push_body_paragraph<E: genpdfi_extended::Element + 'static>(
+        &self,
+        doc: &mut Document,
+        element: E,
+        text: &str,
+        inset: f32,
+    ) {
+        let (min_top, min_bottom) = (self.style.min_lines_top, self.style.min_lines_bottom);
+        if min_top <= 1 && min_bottom <= 1 {
+            self.push_block(doc, element);
+            return;
+        }
+
+        self.push_block(
+            doc,
+            KeepLines {
+                element,
+                text: text.to_string(),
+                size: self.style.text.size,
+                inset,
+                min_top,
+                min_bottom,
+                placed: false,
+            },
+        );
+    }
+
+    /// Returns the padding in millimeters on each side of body paragraphs that narrows
+    /// them to the centered column of `[text] max_width`, or 0 when the limit is unset or
+    /// wider than the text width.
+    fn text_measure_padding(&self) -> f32 {
+        let Some(max_width) = self.style.text_max_width else {
+            return 0.0;
+        };
+        let text_width =
+            self.page_dimensions().0 - self.style.margins.left - self.style.margins.right;
+        let measure = match max_width {
+            MaxWidth::Points(points) => points * PT_TO_MM,
+            MaxWidth::Characters(chars) => {
+                chars as f32 * self.style.text.size.max(1) as f32 * PT_TO_MM * 0.5
+            }
+        };
+        ((text_width - measure) / 2.0).max(0.0)
+    }
+
+    /// Renders one line of a block quote in the `[block_quote]` style, indented from the
+    /// text. `first` and `last` tell whether the line starts or ends its quote, which adds
+    /// the quote's spacing. With `[block_quote.attribution]`, a last line starting with an
+    /// em dash (`— Author`) is rendered in that style, right-aligned by default.
+    fn render_block_quote_line(&self, doc: &mut Document, text: &str, first: bool, last: bool) {
+        let attribution = self
+            .style
+            .block_quote_attribution
+            .filter(|_| last && text.trim_start().starts_with('—'));
+        let quote = attribution.unwrap_or(self.style.block_quote);
+
+        if first {
+            self.push_spacing(doc, self.style.block_quote.before_spacing);
+        }
+        let mut para = genpdfi_extended::elements::Paragraph::default();
+        para.set_alignment(match quote.alignment {
+            Some(crate::styling::TextAlignment::Center) => Alignment::Center,
+            Some(crate::styling::TextAlignment::Right) => Alignment::Right,
+            None if attribution.is_some() => Alignment::Right,
+            _ => Alignment::Left,
+        });
+
+        let mut style = genpdfi_extended::style::Style::new().with_font_size(quote.size);
+        if quote.bold {
+            style = style.bold();
+        }
+        if quote.italic {
+            style = style.italic();
+        }
+        if let Some((r, g, b)) = quote.text_color {
+            style = style.with_color(genpdfi_extended::style::Color::Rgb(r, g, b));
+        }
+
+        let mut content = crate::markdown::Lexer::new(text.to_string())
+            .parse_with_context(crate::markdown::ParseContext::BlockQuote)
+            .unwrap_or_else(|_| vec![Token::Text(text.to_string())]);
+        if let Some(marks) = self.style.quote_style.marks() {
+            Token::replace_quotes(&mut content, marks);
+        }
+        self.render_inline_content_with_style_simple(&mut para, &content, style);
+        self.push_block(
+            doc,
+            para.padded(genpdfi_extended::Margins::trbl(
+                0.0,
+                0.0,
+                0.0,
+                BLOCK_QUOTE_INDENT_MM,
+            )),
+        );
+        if last {
+            self.push_spacing(doc, self.style.block_quote.after_spacing);
+        }
+    }
+
+    /// Renders a heading with the appropriate level styling.
+    ///
+    /// This method takes a document, heading content tokens, and a level number to render
+    /// a heading with the corresponding style settings. It applies font size, bold/italic effects,
+    /// and text color based on the heading level configuration. After rendering the heading,
+    /// it adds the configured spacing.
+    fn render_heading(&self, doc: &mut Document, content: &[Token], level: usize) {
+        let heading_style = match level {
+            1 => &self.style.heading_1,
+            2 => &self.style.heading_2,
+            3 | _ => &self.style.heading_3,
+        };
+        self.push_spacing(doc, heading_style.before_spacing);
+
+        let mut para = genpdfi_extended::elements::Paragraph::default();
+        let mut style = genpdfi_extended::style::Style::new().with_font_size(heading_style.size);
+
+        if heading_style.bold {
+            style = style.bold();
+        }
+        if heading_style.italic {
+            style = style.italic();
+        }
+        if let Some(color) = heading_style.text_color {
+            style = style.with_color(genpdfi_extended::style::Color::Rgb(
+                color.0, color.1, color.2,
+            ));
+        }
+
+        let previous = self.text_transform.replace(heading_style.text_transform);
+        self.letter_spacing
+            .set(Some(heading_style.letter_spacing).filter(|&spacing| spacing != 0.0));
+        self.render_inline_content_with_style_simple(&mut para, content, style);
+        self.letter_spacing.set(None);
+        self.text_transform.set(previous);
+
+        // The spacing widens the text after it is laid out, so the lines wrap in a
+        // column narrowed by the share of the width the spacing adds
+        let tracked_width = self.tracked_width(content, heading_style);
+        if tracked_width > 1.0 {
+            let text_width =
+                self.page_dimensions().0 - self.style.margins.left - self.style.margins.right;
+            let right = text_width * (1.0 - 1.0 / tracked_width);
+            self.push_block(
+                doc,
+                para.padded(genpdfi_extended::Margins::trbl(0.0, right, 0.0, 0.0)),
+            );
+        } else {
+            self.push_block(doc, para);
+        }
+        self.push_spacing(doc, heading_style.after_spacing);
+    }
+
+    /// Returns how much wider `[heading.N] letterspacing` makes the text of a heading: the
+    /// ratio of its tracked width to its natural width, 1 when the spacing adds no width.
+    fn tracked_width(
+        &self,
+        content: &[Token],
+        heading_style: &crate::styling::BasicTextStyle,
+    ) -> f32 {
+        if heading_style.letter_spacing <= 0.0 {
+            return 1.0;
+        }
+        let text = Token::plain_text(content);
+        let text = match heading_style.text_transform {
+            TextTransform::Uppercase => text.to_uppercase(),
+            TextTransform::None | TextTransform::SmallCaps => text,
+        };
+        let natural = crate::fonts::TextMeasure::new(&self.font_family)
+            .width(&text, heading_style.size as f32);
+        if natural <= 0.0 {
+            return 1.0;
+        }
+        let spacing = heading_style.letter_spacing * PT_TO_MM * text.chars().count() as f32;
+        (natural + spacing) / natural
+    }
+
+    /// Renders inline content with a specified style.
+    ///
+    /// This method processes a sequence of inline tokens and renders them with the given style.
+    /// It handles various inline elements like plain text, emphasis, strong emphasis, links, and
+    /// inline code, applying appropriate styling modifications for each type while maintaining
+    /// the base style properties.
+    fn render_inline_content_with_style(
+        &self,
+        para: &mut genpdfi_extended::elements::Paragraph,
+        tokens: &[Token],
+        style: genpdfi_extended::style::Style,
+        doc: &mut Document,
+    ) {
+        let pending = self.pending_markers.borrow().len();
+        for token in tokens {
+            match token {
+                Token::Text(content) => {
+                    self.push_text_with_abbreviations(para, content, style.clone());
+                }
+                Token::Emphasis { level, content } => {
+                    let mut nested_style = style.clone();
+                    match level {
+                        1 => nested_style = nested_style.italic(),
+                        2 => nested_style = nested_style.bold(),
+                        _ => nested_style = nested_style.bold().italic(),
+                    }
+                    self.render_inline_content_with_style(para, content, nested_style, doc);
+                }
+                Token::StrongEmphasis(content) => {
+                    let nested_style = style.clone().bold();
+                    self.render_inline_content_with_style(para, content, nested_style, doc);
+                }
+                Token::Link(text, url) => {
+                    let mut link_style = style.clone();
+                    if let Some(color) = self.style.link.text_color {
+                        link_style = link_style.with_color(genpdfi_extended::style::Color::Rgb(
+                            color.0, color.1, color.2,
+                        ));
+                    }
+                    self.push_link_text(para, text, url, link_style);
+                }
+                Token::Code(_, content) => {
+                    self.push_inline_code(para, content, style.clone());
+                }
+                Token::Anchor(name) => self.push_anchor(name),
+                Token::HtmlComment(_) => {
+                    if let Some(term) = token.index_term() {
+                        self.push_index_marker(term);
+                    }
+                }
+                Token::Kbd(key) => {
+                    self.push_kbd(para, key, style.clone());
+                }
+                Token::FootnoteInline(content) => {
+                    self.push_footnote_marker(para, content, style.clone());
+                }
+                Token::Spoiler(content) => {
+                    self.render_inline_content_with_style(para, content, style.clone(), doc);
+                }
+                Token::Mark(content) => {
+                    let previous = self
+                        .highlight_color
+                        .replace(self.style.mark.background_color);
+                    self.render_inline_content_with_style(
+                        para,
+                        content,
+                        self.mark_style(style.clone()),
+                        doc,
+                    );
+                    self.highlight_color.set(previous);
+                }
+                Token::Ins(content) | Token::Del(content) => {
+                    let line = self.line_color_cell(token);
+                    let previous =
+                        line.replace(Some(self.style.text.text_color.unwrap_or((0, 0, 0))));
+                    self.render_inline_content_with_style(para, content, style.clone(), doc);
+                    line.set(previous);
+                }
+                Token::Critic(change, content) => {
+                    let (critic_style, state) = self.critic_style(*change, style.clone());
+                    let previous = state.map(|(cell, value)| (cell, cell.replace(value)));
+                    self.render_inline_content_with_style(para, content, critic_style, doc);
+                    if let Some((cell, value)) = previous {
+                        cell.set(value);
+                    }
+                }
+                Token::Math {
+                    content,
+                    display: false,
+                } => {
+                    // Inline math - render to SVG and create image, or set it as text with
+                    // the pure-Rust backend
+                    #[cfg(all(feature = "math-rust", not(feature = "latex")))]
+                    self.render_inline_math(para, content, style.clone());
+                    #[cfg(not(all(feature = "math-rust", not(feature = "latex"))))]
+                    self.render_inline_math_as_image(doc, content);
+                }
+                Token::LineBreak => {
+                    // Line break within a paragraph - push a newline
+                    para.push_styled("\n".to_string(), style.clone());
+                }
+                Token::SoftBreak => {
+                    para.push_styled(self.soft_break_text(), style.clone());
+                }
+                Token::Image(_, _) => {
+                    // Images are handled as block-level elements in process_tokens,
+                    // not as inline elements within paragraphs
+                }
+                Token::ImageWithLink(_, _, _) => {
+                    // Images with links are handled as block-level elements in process_tokens,
+                    // not as inline elements within paragraphs
+                }
+                Token::Unknown(text) => {
+                    self.push_text_with_range_fonts(para, text.clone(), style.clone());
+                }
+                // Invisible by design, or drawn by the enclosing list item
+                Token::Newline
+                | Token::TableAlignment(_)
+                | Token::AbbreviationDefinition(..)
+                | Token::TaskCheckbox(_) => {}
+                _ => self.push_unrendered_token(para, token, style.clone()),
+            }
+        }
+        self.flush_markers(para, pending, style);
+    }
+
+    /// Version without Document - for headings and other places where we can't render images
+    fn render_inline_content_with_style_simple(
+        &self,
+        para: &mut genpdfi_extended::elements::Paragraph,
+        tokens: &[Token],
+        style: genpdfi_extended::style::Style,
+    ) {
+        let pending = self.pending_markers.borrow().len();
+        for token in tokens {
+            match token {
+                Token::Text(content) => {
+                    self.push_text_with_abbreviations(para, content, style.clone());
+                }
+                Token::Emphasis { level, content } => {
+                    let mut nested_style = style.clone();
+                    match level {
+                        1 => nested_style = nested_style.italic(),
+                        2 => nested_style = nested_style.bold(),
+                        _ => nested_style = nested_style.bold().italic(),
+                    }
+                    self.render_inline_content_with_style_simple(para, content, nested_style);
+                }
+                Token::StrongEmphasis(content) => {
+                    let nested_style = style.clone().bold();
+                    self.render_inline_content_with_style_simple(para, content, nested_style);
+                }
+                Token::Link(text, url) => {
+                    let mut link_style = style.clone();
+                    if let Some(color) = self.style.link.text_color {
+                        link_style = link_style.with_color(genpdfi_extended::style::Color::Rgb(
+                            color.0, color.1, color.2,
+                        ));
+                    }
+                    self.push_link_text(para, text, url, link_style);
+                }
+                Token::Code(_, content) => {
+                    self.push_inline_code(para, content, style.clone());
+                }
+                Token::Anchor(name) => self.push_anchor(name),
+                Token::HtmlComment(_) => {
+                    if let Some(term) = token.index_term() {
+                        self.push_index_marker(term);
+                    }
+                }
+                Token::Kbd(key) => {
+                    self.push_kbd(para, key, style.clone());
+                }
+                Token::FootnoteInline(content) => {
+                    self.push_footnote_marker(para, content, style.clone());
+                }
+                Token::Spoiler(content) => {
+                    self.render_inline_content_with_style_simple(para, content, style.clone());
+                }
+                Token::Mark(content) => {
+                    let previous = self
+                        .highlight_color
+                        .replace(self.style.mark.background_color);
+                    self.render_inline_content_with_style_simple(
+                        para,
+                        content,
+                        self.mark_style(style.clone()),
+                    );
+                    self.highlight_color.set(previous);
+                }
+                Token::Ins(content) | Token::Del(content) => {
+                    let line = self.line_color_cell(token);
+                    let previous =
+                        line.replace(Some(self.style.text.text_color.unwrap_or((0, 0, 0))));
+                    self.render_inline_content_with_style_simple(para, content, style.clone());
+                    line.set(previous);
+                }
+                Token::Critic(change, content) => {
+                    let (critic_style, state) = self.critic_style(*change, style.clone());
+                    let previous = state.map(|(cell, value)| (cell, cell.replace(value)));
+                    self.render_inline_content_with_style_simple(para, content, critic_style);
+                    if let Some((cell, value)) = previous {
+                        cell.set(value);
+                    }
+                }
+                Token::Math { content, .. } => {
+                    // Math in table cells and list items - render as styled text. Display
+                    // math has no block of its own there and is set inline as well
+                    self.render_inline_math(para, content, style.clone());
+                }
+                Token::LineBreak => {
+                    // Line break within a paragraph - push a newline
+                    para.push_styled("\n".to_string(), style.clone());
+                }
+                Token::SoftBreak => {
+                    para.push_styled(self.soft_break_text(), style.clone());
+                }
+                Token::Image(_, _) => {
+                    // Images are handled as block-level elements in process_tokens,
+                    // not as inline elements
+                }
+                Token::Unknown(text) => {
+                    self.push_text_with_range_fonts(para, text.clone(), style.clone());
+                }
+                // Invisible by design, or drawn by the enclosing list item
+                Token::Newline
+                | Token::TableAlignment(_)
+                | Token::AbbreviationDefinition(..)
+                | Token::TaskCheckbox(_) => {}
+                _ => self.push_unrendered_token(para, token, style.clone()),
+            }
+        }
+        self.flush_markers(para, pending, style);
+    }
+
+    fn render_inline_content(
+        &self,
+        para: &mut genpdfi_extended::elements::Paragraph,
+        tokens: &[Token],
+        doc: &mut Document,
+    ) {
+        let style = genpdfi_extended::style::Style::new().with_font_size(self.style.text.size);
+        self.render_inline_content_with_style(para, tokens, style, doc);
+    }
+
+    /// Renders inline content with a specified style.
+
+    /// Renders a code block with appropriate styling.
+    ///
+    /// This method handles multi-line code blocks, rendering each line as a separate
+    /// paragraph with the configured code style. It applies the code font size and
+    /// text color settings, and adds the configured spacing after the block. With
+    /// `[code] keep_together`, a block that would split but fits on a page starts on the
+    /// next page instead.
+    fn render_code_block(&self, doc: &mut Document, lang: &str, content: &str) {
+        self.push_spacing(doc, self.style.code.before_spacing);
+
+        let (content, hidden_lines) = truncate_code_lines(content, self.style.code_max_lines);
+        if hidden_lines > 0 {
+            self.render_warning(format!(
+                "Code block truncated after {} lines, {} more lines omitted ([code] max_lines)",
+                self.style.code_max_lines, hidden_lines
+            ));
+        }
+
+        let mut block = genpdfi_extended::elements::LinearLayout::vertical();
+
+        // Get syntax highlighted tokens
+        let start = Instant::now();
+        let highlighted_tokens = highlighting::highlight_code(content, lang);
+        self.highlighting
+            .set(self.highlighting.get() + start.elapsed());
+
+        let indent = "    "; // TODO: make this configurable from style match.
+        let size = self.code_block_font_size(content, indent);
+        let mut current_line = String::new();
+        let mut line_tokens = Vec::new();
+
+        for token in highlighted_tokens {
+            // Check if we need to flush current line
+            if token.text.contains('\n') {
+                // Add remaining text before newline
+                let parts: Vec<&str> = token.text.split('\n').collect();
+                for (i, part) in parts.iter().enumerate() {
+                    if i > 0 {
+                        // Render previous line and start new one
+                        self.render_highlighted_line(&mut block, indent, size, &line_tokens);
+                        line_tokens.clear();
+                        current_line.clear();
+                    }
+                    if !part.is_empty() {
+                        line_tokens.push((part.to_string(), token.color, token.bold, token.italic));
+                        current_line.push_str(part);
+                    }
+                }
+            } else {
+                line_tokens.push((token.text.clone(), token.color, token.bold, token.italic));
+                current_line.push_str(&token.text);
+            }
+        }
+
+        // Render final line if there's any content
+        if !line_tokens.is_empty() {
+            self.render_highlighted_line(&mut block, indent, size, &line_tokens);
+        }
+        if hidden_lines > 0 {
+            let indicator = (
+                format!("... ({} more lines)", hidden_lines),
+                highlighting::HighlightColor::from_rgb(128, 128, 128),
+                false,
+                true,
+            );
+            self.render_highlighted_line(&mut block, indent, size, &[indicator]);
+        }
+        let block = CornerLabel {
+            element: block,
+            label: (self.style.show_code_language && !lang.trim().is_empty())
+                .then(|| self.code_language_label(lang.trim())),
+        };
+
+        if self.style.code_keep_together {
+            let (page_width, page_height) = self.page_dimensions();
+            let margins = self.style.margins;
+            let estimate = PageEstimate {
+                width: (page_width - margins.left - margins.right).max(1.0),
+                height: (page_height - margins.top - margins.bottom).max(1.0),
+                spacing: line_height_mm(self.style.text.size),
+                pages: 1,
+                used: 0.0,
+            };
+            let lines: usize = content
+                .lines()
+                .map(|line| estimate.wrapped_lines(&format!("{}{}", indent, line), size, 0.0))
+                .sum();
+            let lines = lines + usize::from(hidden_lines > 0);
+            self.push_block(
+                doc,
+                KeepTogether {
+                    element: block,
+                    height: lines as f32 * line_height_mm(size),
+                    page_height: estimate.height,
+                    placed: false,
+                },
+            );
+        } else {
+            self.push_block(doc, block);
+        }
+
+        self.push_spacing(doc, self.style.code.after_spacing);
+    }
+
+    /// Returns the font size of a code block: the `[code]` size, or with `fit = "shrink"`
+    /// the size at which the widest line fits the text width, no smaller than
+    /// `CODE_MIN_FONT_SIZE`. Lines still too wide at that size wrap.
+    fn code_block_font_size(&self, content: &str, indent: &str) -> u8 {
+        let size = self.style.code.size;
+        if self.style.code_fit != CodeFit::Shrink {
+            return size;
+        }
+        let measure = crate::fonts::TextMeasure::new(&self.code_font_family);
+        let widest = content
+            .lines()
+            .map(|line| measure.width(&format!("{}{}", indent, line), size as f32))
+            .fold(0.0f32, f32::max);
+        let available =
+            self.page_dimensions().0 - self.style.margins.left - self.style.margins.right;
+        if widest <= available {
+            return size;
+        }
+        ((size as f32 * available / widest).floor() as u8).clamp(CODE_MIN_FONT_SIZE.min(size), size)
+    }
+
+    /// Returns the fence language of a code block as a small badge styled by
+    /// `[code.label]`, aligned to the right corner of the first line by default.
+    fn code_language_label(&self, lang: &str) -> genpdfi_extended::elements::Paragraph {
+        let label = &self.style.code_label;
+        let mut para = genpdfi_extended::elements::Paragraph::default();
+        para.set_alignment(match label.alignment {
+            Some(crate::styling::TextAlignment::Left) => Alignment::Left,
+            Some(crate::styling::TextAlignment::Center) => Alignment::Center,
+            _ => Alignment::Right,
+        });
+
+        let mut style = genpdfi_extended::style::Style::new().with_font_size(label.size);
+        if label.bold {
+            style = style.bold();
+        }
+        if label.italic {
+            style = style.italic();
+        }
+        if let Some(color) = label.text_color {
+            style = style.with_color(genpdfi_extended::style::Color::Rgb(
+                color.0, color.1, color.2,
+            ));
+        }
+        CURRENT_CODE_FONT_OVERRIDE.with(|f| {
+            if let Some(code_font) = f.borrow().as_ref() {
+                style = style.with_font_override(*code_font);
+            }
+        });
+
+        if label.background_color.is_some() {
+            let uri = format!(
+                "{}{}:{}",
+                KBD_URI_PREFIX,
+                marker_color(label.background_color),
+                marker_color(None)
+            );
+            para.push_link(format!(" {} ", lang), uri, style);
+            self.decorations.set(true);
+        } else {
+            para.push_styled(lang.to_string(), style);
+        }
+        para
+    }
+
+    /// Renders a single line of highlighted code
+    fn render_highlighted_line(
+        &self,
+        block: &mut genpdfi_extended::elements::LinearLayout,
+        indent: &str,
+        size: u8,
+        tokens: &[(String, highlighting::HighlightColor, bool, bool)],
+    ) {
+        let mut para = genpdfi_extended::elements::Paragraph::default();
+
+        // Create base code style with font override
+        let mut code_style = genpdfi_extended::style::Style::new().with_font_size(size);
+
+        // Apply code font override if available
+        CURRENT_CODE_FONT_OVERRIDE.with(|f| {
+            if let Some(code_font) = f.borrow().as_ref() {
+                code_style = code_style.with_font_override(*code_font);
+            }
+        });
+
+        // Add indentation
+        let mut style = code_style;
+        if let Some(color) = self.style.code.text_color {
+            style = style.with_color(genpdfi_extended::style::Color::Rgb(
+                color.0, color.1, color.2,
+            ));
+        }
+        para.push_styled(indent.to_string(), style);
+
+        // Add colored tokens, with the theme's bold and italic tokens
+        for (text, color, bold, italic) in tokens {
+            let token_style = self.highlighted_token_style(code_style, *color, *bold, *italic);
+            para.push_styled(text.clone(), token_style);
+        }
+
+        block.push(para);
+    }
+
+    /// Returns the style of a highlighted code token: the theme color (as a gray with
+    /// `[code] style = "grayscale"`) and the bold and italic variants of the code font
+    /// where the theme marks the token bold or italic.
+    fn highlighted_token_style(
+        &self,
+        code_style: genpdfi_extended::style::Style,
+        color: highlighting::HighlightColor,
+        bold: bool,
+        italic: bool,
+    ) -> genpdfi_extended::style::Style {
+        let (r, g, b) = match self.style.code_highlight_style {
+            CodeHighlightStyle::Color => color.as_rgb_u8(),
+            CodeHighlightStyle::Grayscale => grayscale(color.as_rgb_u8()),
+        };
+        let mut token_style = code_style.with_color(genpdfi_extended::style::Color::Rgb(r, g, b));
+        if bold {
+            token_style = token_style.bold();
+        }
+        if italic {
+            token_style = token_style.italic();
+        }
+        token_style
+    }
+
+    /// Renders a list item with appropriate styling and formatting.
+    ///
+    /// This method handles both ordered and unordered list items, with support for nested lists.
+    /// For ordered lists, it includes the item number prefixed with a period (like "1."), while
+    /// unordered lists use a bullet point dash character. The content is rendered with the
+    /// configured list item style settings from the document style configuration.
+    ///
+    /// The method processes both the direct content of the list item as well as any nested list
+    /// items recursively. The marker is right-aligned in a column of `[list_item] marker_width`
+    /// so that `9.` and `10.` end at the same position and wrapped lines align with the item
+    /// text rather than the bullet, and each nested level shifts the item right by
+    /// `[list_item] indent`. The method filters the content to separate inline elements from
+    /// nested list items, rendering the inline content first before processing any nested items.
+    ///
+    /// After rendering each list item's content, appropriate spacing is added based on the
+    /// configured after_spacing value. The method maintains consistent styling throughout the
+    /// list hierarchy while allowing for proper nesting and indentation of complex list structures.
+    fn render_list_item(
+        &self,
+        doc: &mut Document,
+        content: &[Token],
+        ordered: bool,
+        number: Option<usize>,
+        nesting_level: usize,
+    ) {
+        self.push_spacing(doc, self.style.list_item.before_spacing);
+        let mut para = genpdfi_extended::elements::Paragraph::default();
+        let mut style =
+            genpdfi_extended::style::Style::new().with_font_size(self.style.list_item.size);
+
+        let inline_content: Vec<Token> = content
+            .iter()
+            .filter(|token| !matches!(token, Token::ListItem { .. }))
+            .cloned()
+            .collect();
+        // Paragraphs after the first, separated by blank lines in the source, hang under
+        // the item text
+        let mut paragraphs = inline_content.split(|token| matches!(token, Token::Newline));
+        let first_paragraph = paragraphs.next().unwrap_or(&[]);
+
+        // A task item's checkbox replaces its bullet; completed items get the done styling
+        let task = match first_paragraph.first() {
+            Some(Token::TaskCheckbox(checked)) => Some(*checked),
+            _ => None,
+        };
+        if let Some(checked) = task {
+            self.push_task_checkbox(&mut para, checked, style.clone());
+            let config = &self.style.task_list;
+            if checked {
+                if let Some((r, g, b)) = config.done_color {
+                    style = style.with_color(genpdfi_extended::style::Color::Rgb(r, g, b));
+                }
+                if config.strikethrough_done {
+                    let color = config
+                        .done_color
+                        .or(self.style.list_item.text_color)
+                        .unwrap_or((0, 0, 0));
+                    self.strike_color.set(Some(color));
+                }
+            }
+        }
+        self.render_inline_content_with_style_simple(&mut para, first_paragraph, style.clone());
+        let continuation: Vec<_> = paragraphs
+            .map(|tokens| {
+                let mut para = genpdfi_extended::elements::Paragraph::default();
+                self.render_inline_content_with_style_simple(&mut para, tokens, style.clone());
+                para
+            })
+            .collect();
+        self.strike_color.set(None);
+
+        // The bullet sits in the left indent so wrapped lines align with the item text
+        let bullet = match (ordered, number) {
+            _ if task.is_some() => String::new(),
+            (true, Some(n)) => format!("{}.", n),
+            (true, None) => String::new(),
+            (false, _) => "-".to_string(),
+        };
+        let indent = self.style.list_indent * nesting_level as f32;
+        let item = genpdfi_extended::elements::BulletPoint::new(para)
+            .with_bullet(bullet)
+            .with_indent(self.style.list_marker_width);
+        self.push_block(
+            doc,
+            item.padded(genpdfi_extended::Margins::trbl(0.0, 0.0, 0.0, indent)),
+        );
+        self.push_spacing(doc, self.style.list_item.after_spacing);
+        for para in continuation {
+            self.push_spacing(doc, self.style.list_item.before_spacing);
+            self.push_block(
+                doc,
+                para.padded(genpdfi_extended::Margins::trbl(
+                    0.0,
+                    0.0,
+                    0.0,
+                    indent + self.style.list_marker_width,
+                )),
+            );
+            self.push_spacing(doc, self.style.list_item.after_spacing);
+        }
+
+        // Past `[list_item] max_nesting` levels, deeper items are rendered at this level
+        // instead of recursing further, so crafted input cannot exhaust the stack
+        if nesting_level + 1 >= self.style.list_max_nesting
+            && content
+                .iter()
+                .any(|token| matches!(token, Token::ListItem { .. }))
+        {
+            self.render_warning(format!(
+                "List nested deeper than {} levels; deeper items are flattened",
+                self.style.list_max_nesting
+            ));
+            for (ordered, number, item_content) in flatten_list_items(content) {
+                self.render_list_item(doc, &item_content, ordered, number, nesting_level);
+            }
+            return;
+        }
+
+        // Nested items are numbered on their own, leaving this list's sequence untouched
+        let nested_numbers = list_numbers(content);
+        for (token, number) in content.iter().zip(nested_numbers) {
+            if let Token::ListItem {
+                content: nested_content,
+                ordered: nested_ordered,
+                number: nested_number,
+            } = token
+            {
+                self.render_list_item(
+                    doc,
+                    nested_content,
+                    *nested_ordered,
+                    number.or(*nested_number),
+                    nesting_level + 1,
+                );
+            }
+        }
+    }
+
+    /// Renders a table with headers, alignment information, and rows.
+    ///
+    /// Each row is a vector of cells.
+    ///
+    /// The table is rendered using genpdfi's TableLayout with proper column weights
+    /// and cell borders. Each cell content is processed as inline tokens to handle
+    /// formatting within table them.
+    fn render_table(
+        &self,
+        doc: &mut Document,
+        headers: &Vec<Vec<Token>>,
+        aligns: &Vec<Alignment>,
+        rows: &Vec<Vec<Vec<Token>>>,
+    ) {
+        self.push_spacing(doc, self.style.text.before_spacing);
+
+        let column_count = headers.len();
+        let mut column_weights = vec![1; column_count];
+        let mut header_size = self.style.table_header.size;
+        let mut cell_size = self.style.table_cell.size;
+
+        if self.style.table_overflow == TableOverflow::Shrink {
+            let (natural, minimum) = self.measure_table_columns(headers, rows);
+            let available =
+                self.page_dimensions().0 - self.style.margins.left - self.style.margins.right;
+            if let Some((weights, scale)) = fit_table_columns(&natural, &minimum, available) {
+                column_weights = weights;
+                if scale < 1.0 {
+                    let shrink = |size: u8| {
+                        ((size as f32 * scale).floor() as u8)
+                            .max(TABLE_MIN_FONT_SIZE)
+                            .min(size)
+                    };
+                    header_size = shrink(header_size);
+                    cell_size = shrink(cell_size);
+                }
+            }
+        }
+
+        let mut table = genpdfi_extended::elements::TableLayout::new(column_weights);
+        table.set_cell_decorator(genpdfi_extended::elements::FrameCellDecorator::new(
+            true, true, false,
+        ));
+
+        // Render header row
+        let mut header_row = table.row();
+        for (i, header_cell) in headers.iter().enumerate() {
+            self.push_table_cell(&mut header_row, header_cell, aligns.get(i), header_size);
+        }
+
+        if let Err(_) = header_row.push() {
+            self.render_warning("Failed rendering a table".to_string());
+            return; // Skip the entire table if header fails
+        }
+
+        // Render data rows
+        for (row_idx, row) in rows.iter().enumerate() {
+            let mut table_row = table.row();
+
+            // Ragged rows are padded with empty cells and cut to the header width.
+            for i in 0..column_count {
+                let cell_tokens = row.get(i).map(Vec::as_slice).unwrap_or(&[]);
+                self.push_table_cell(&mut table_row, cell_tokens, aligns.get(i), cell_size);
+            }
+
+            if let Err(_) = table_row.push() {
+                self.render_warning(format!("Failed to push row {} in a table", row_idx));
+                continue; // Continue with next row
+            }
+        }
+
+        self.push_block(doc, table);
+        self.push_spacing(doc, self.style.text.after_spacing);
+    }
+
+    /// Pushes one table cell onto a row: a paragraph of its inline tokens, or a vertical
+    /// stack of paragraphs and tables when the cell holds nested tables.
+    fn push_table_cell(
+        &self,
+        row: &mut genpdfi_extended::elements::TableLayoutRow<'_>,
+        tokens: &[Token],
+        align: Option<&Alignment>,
+        size: u8,
+    ) {
+        let paragraph = |tokens: &[Token]| {
+            let mut para = genpdfi_extended::elements::Paragraph::default();
+            if let Some(align) = align {
+                para.set_alignment(*align);
+            }
+            let style = genpdfi_extended::style::Style::new().with_font_size(size);
+            self.render_inline_content_with_style_simple(&mut para, tokens, style);
+            para
+        };
+
+        if !tokens
+            .iter()
+            .any(|token| matches!(token, Token::Table { .. }))
+        {
+            row.push_element(paragraph(tokens));
+            return;
+        }
+
+        let mut cell = genpdfi_extended::elements::LinearLayout::vertical();
+        let mut text_start = 0;
+        for (i, token) in tokens.iter().enumerate() {
+            if let Token::Table {
+                headers,
+                aligns,
+                rows,
+            } = token
+            {
+                if i > text_start {
+                    cell.push(paragraph(&tokens[text_start..i]));
+                }
+                cell.push(self.nested_table(headers, aligns, rows, size));
+                text_start = i + 1;
+            }
+        }
+        if text_start < tokens.len() {
+            cell.push(paragraph(&tokens[text_start..]));
+        }
+        row.push_element(cell);
+    }
+
+    /// Builds a table nested in a table cell, with equal columns and the font size of the
+    /// cell around it.
+    fn nested_table(
+        &self,
+        headers: &[Vec<Token>],
+        aligns: &[Alignment],
+        rows: &[Vec<Vec<Token>>],
+        size: u8,
+    ) -> genpdfi_extended::elements::TableLayout {
+        let mut table = genpdfi_extended::elements::TableLayout::new(vec![1; headers.len()]);
+        table.set_cell_decorator(genpdfi_extended::elements::FrameCellDecorator::new(
+            true, true, false,
+        ));
+        for (row_idx, cells) in std::iter::once(headers)
+            .chain(rows.iter().map(Vec::as_slice))
+            .enumerate()
+        {
+            let mut table_row = table.row();
+            for i in 0..headers.len() {
+                let cell_tokens = cells.get(i).map(Vec::as_slice).unwrap_or(&[]);
+                self.push_table_cell(&mut table_row, cell_tokens, aligns.get(i), size);
+            }
+            if table_row.push().is_err() {
+                self.render_warning(format!("Failed to push row {} in a nested table", row_idx));
+            }
+        }
+        table
+    }
+
+    /// Measures each table column at the configured header and cell sizes.
+    ///
+    /// Returns the natural widths (widest cell on a single line) and the minimum widths
+    /// (longest unbreakable word) of the columns, in millimeters and without padding.
+    fn measure_table_columns(
+        &self,
+        headers: &[Vec<Token>],
+        rows: &[Vec<Vec<Token>>],
+    ) -> (Vec<f32>, Vec<f32>) {
+        let measure = crate::fonts::TextMeasure::new(&self.font_family);
+        let mut natural = vec![0.0f32; headers.len()];
+        let mut minimum = vec![0.0f32; headers.len()];
+
+        let header_row = (headers, self.style.table_header.size);
+        let data_rows = rows
+            .iter()
+            .map(|row| (row.as_slice(), self.style.table_cell.size));
+        for (cells, size) in std::iter::once(header_row).chain(data_rows) {
+            for (i, cell) in cells.iter().enumerate().take(headers.len()) {
+                // A `<br>` in the cell starts a new line, measured on its own
+                for line in cell.split(|token| matches!(token, Token::LineBreak)) {
+                    let text = Token::collect_all_text(line);
+                    let size = size as f32;
+                    natural[i] = natural[i].max(measure.width(text.trim(), size));
+                    for word in text.split_whitespace() {
+                        minimum[i] = minimum[i].max(measure.width(word, size));
+                    }
+                }
+            }
+        }
+        (natural, minimum)
+    }
+
+    /// Returns the fraction of the text width a raster image is rendered at.
+    ///
+    /// Images are at most [`RASTER_IMAGE_MAX_WIDTH`] of the text width. Narrower images
+    /// keep their natural size (see [`crate::images::ImageData::natural_size_mm`]) unless
+    /// `[image] upscale` is set; images whose size cannot be read fill the maximum width.
+    ///
+    /// With `[image] target_dpi`, the width instead follows from the image's pixels at that
+    /// resolution, up to the full text width.
+    fn raster_image_width(&self, image: &crate::images::ImageData) -> f32 {
+        let text_width =
+            self.page_dimensions().0 - self.style.margins.left - self.style.margins.right;
+        if let Some(dpi) = self.style.image_target_dpi {
+            if let Some((pixels, _)) = image.pixel_size().filter(|_| text_width > 0.0) {
+                return (pixels as f32 * 25.4 / dpi / text_width).min(1.0);
+            }
+        }
+        if self.style.image_upscale {
+            return RASTER_IMAGE_MAX_WIDTH;
+        }
+        match image.natural_size_mm() {
+            Some((width, _)) if text_width > 0.0 => {
+                (width / text_width).min(RASTER_IMAGE_MAX_WIDTH)
+            }
+            _ => RASTER_IMAGE_MAX_WIDTH,
+        }
+    }
+
+    /// Renders a display math block ($$...$$).
+    ///
+    /// This method converts LaTeX mathematical expressions to SVG and embeds them
+    /// as centered images in the PDF. Display math is rendered as a block-level
+    /// element with appropriate spacing. The rendering uses dimensional metrics to
+    /// ensure consistent sizing with the surrounding text.
+    #[cfg(feature = "latex")]
+    fn render_math_block(&self, doc: &mut Document, latex_content: &str) {
+        // Add spacing before the math block based on latex style
+        self.push_spacing(doc, self.style.latex.before_spacing);
+
+        // Use genpdfi_extended's native Latex element when the feature is enabled.
+        // Size in points is taken from the latex style.
+        let size_pt = self.style.latex.size as f32;
+        let latex_elem = genpdfi_extended::elements::Latex::new(latex_content.to_string(), size_pt);
+
+        // Apply configured alignment
+        let align = match self.style.latex.alignment {
+            Some(crate::styling::TextAlignment::Left) => Alignment::Left,
+            Some(crate::styling::TextAlignment::Right) => Alignment::Right,
+            Some(crate::styling::TextAlignment::Center) => Alignment::Center,
+            Some(crate::styling::TextAlignment::Justify) => Alignment::Left,
+            None => Alignment::Center,
+        };
+        let latex_elem = latex_elem.with_alignment(align);
+        self.push_block(doc, latex_elem);
+
+        // Add spacing after the math block
+        self.push_spacing(doc, self.style.latex.after_spacing);
+    }
+
+    /// Renders a math block with the pure-Rust backend, as Unicode text in the latex style.
+    #[cfg(all(feature = "math-rust", not(feature = "latex")))]
+    fn render_math_block(&self, doc: &mut Document, latex_content: &str) {
+        self.render_unicode_math_block(doc, crate::math::to_unicode(latex_content));
+    }
+
+    /// Renders math already set as Unicode text by the pure-Rust backend as a block in
+    /// the latex style. Also used for `asciimath` code blocks, whatever the LaTeX backend.
+    #[cfg(feature = "math-rust")]
+    fn render_unicode_math_block(&self, doc: &mut Document, text: String) {
+        self.push_spacing(doc, self.style.latex.before_spacing);
+
+        let mut para = genpdfi_extended::elements::Paragraph::default();
+        para.set_alignment(match self.style.latex.alignment {
+            Some(crate::styling::TextAlignment::Left) => Alignment::Left,
+            Some(crate::styling::TextAlignment::Right) => Alignment::Right,
+            Some(crate::styling::TextAlignment::Center) => Alignment::Center,
+            Some(crate::styling::TextAlignment::Justify) => Alignment::Left,
+            None => Alignment::Center,
+        });
+        let mut style = genpdfi_extended::style::Style::new().with_font_size(self.style.latex.size);
+        if let Some((r, g, b)) = self.style.latex.text_color {
+            style = style.with_color(genpdfi_extended::style::Color::Rgb(r, g, b));
+        }
+        self.push_text_with_range_fonts(&mut para, text, style);
+        self.push_block(doc, para);
+
+        self.push_spacing(doc, self.style.latex.after_spacing);
+    }
+
+    #[cfg(not(any(feature = "latex", feature = "math-rust")))]
+    fn render_math_block(&self, doc: &mut Document, _latex_content: &str) {
+        // Feature disabled: show an informative message instead of rendering
+        let mut para = genpdfi_extended::elements::Paragraph::default();
+        let mut style = genpdfi_extended::style::Style::new().with_font_size(self.style.latex.size);
+        // apply latex text color if present
+        if let Some((r, g, b)) = self.style.latex.text_color {
+            style = style.with_color(genpdfi_extended::style::Color::Rgb(r, g, b));
+        }
+        para.push_styled("need LaTeX feature".to_string(), style);
+        self.push_block(doc, para);
+    }
+
+    /// Renders inline math ($...$).
+    ///
+    /// This method converts inline LaTeX expressions to SVG and embeds them
+    /// as inline images in the paragraph. The rendering uses dimensional metrics
+    /// to ensure consistent sizing with the surrounding text.
+    #[cfg(feature = "latex")]
+    fn render_inline_math_as_image(&self, doc: &mut Document, latex_content: &str) {
+        // Use genpdfi_extended's Latex element in inline mode and size it slightly smaller than configured latex size
+        let size_pt = (self.style.latex.size as f32) * 0.9;
+        let latex_elem = genpdfi_extended::elements::Latex::new(latex_content.to_string(), size_pt);
+        self.push_block(doc, latex_elem);
+    }
+
+    #[cfg(not(any(feature = "latex", feature = "math-rust")))]
+    fn render_inline_math_as_image(&self, doc: &mut Document, _latex_content: &str) {
+        // Feature disabled: show a small placeholder using latex style
+        let mut para = genpdfi_extended::elements::Paragraph::default();
+        let mut style = genpdfi_extended::style::Style::new().with_font_size(self.style.latex.size);
+        if let Some((r, g, b)) = self.style.latex.text_color {
+            style = style.with_color(genpdfi_extended::style::Color::Rgb(r, g, b));
+        }
+        para.push_styled("need LaTeX feature".to_string(), style);
+        self.push_block(doc, para);
+    }
+
+    /// Renders a fenced `shortcuts` code block of `Key | Action` lines as a two-column table,
+    /// each key of a combination such as `Ctrl+Shift+P` drawn as a `<kbd>` box. A block
+    /// with a line that is not a shortcut is shown as a code block.
+    fn render_shortcuts(&self, doc: &mut Document, lang: &str, content: &str) {
+        let Some(shortcuts) = parse_shortcuts(content) else {
+            self.render_warning(
+                "Shortcuts block lines must read `Key | Action`, rendering it as code".to_string(),
+            );
+            self.render_code_block(doc, lang, content);
+            return;
+        };
+
+        self.push_spacing(doc, self.style.text.before_spacing);
+        let mut table = genpdfi_extended::elements::TableLayout::new(vec![1, 2]);
+        let style = genpdfi_extended::style::Style::new().with_font_size(self.style.text.size);
+        for (keys, action) in &shortcuts {
+            let mut key_para = genpdfi_extended::elements::Paragraph::default();
+            for (i, key) in keys.iter().enumerate() {
+                if i > 0 {
+                    key_para.push_styled("+".to_string(), style.clone());
+                }
+                self.push_kbd(&mut key_para, key, style.clone());
+            }
+
+            let action_tokens = crate::markdown::Lexer::new(action.clone())
+                .parse_with_context(crate::markdown::ParseContext::Inline)
+                .unwrap_or_else(|_| vec![Token::Text(action.clone())]);
+            let mut action_para = genpdfi_extended::elements::Paragraph::default();
+            self.render_inline_content_with_style_simple(
+                &mut action_para,
+                &action_tokens,
+                style.clone(),
+            );
+
+            let padding = genpdfi_extended::Margins::trbl(0.5, 1.0, 0.5, 0.0);
+            let mut row = table.row();
+            row.push_element(key_para.padded(padding));
+            row.push_element(action_para.padded(padding));
+            if row.push().is_err() {
+                self.render_warning("Failed to push a row of a shortcuts block".to_string());
+            }
+        }
+        self.push_block(doc, table);
+        self.push_spacing(doc, self.style.text.after_spacing);
+    }
+
+    /// Renders a fenced `svg` code block as an image, sized by `[image.svg]` like an SVG
+    /// file. Markup that cannot be rendered is reported and shown as a code block.
+    fn render_inline_svg(&self, doc: &mut Document, lang: &str, content: &str) {
+        let image = match decode_svg_image(content) {
+            Ok(image) => image,
+            Err(e) => {
+                self.image_warning(format!("Failed to render inline SVG: {}", e));
+                self.render_code_block(doc, lang, content);
+                return;
+            }
+        };
+
+        self.push_spacing(doc, 0.5);
+        // width takes precedence over scale_factor, as for SVG files
+        let image = match self.style.svg_config.width {
+            SvgWidth::Percentage(percent) => image.resizing_page_with(percent / 100.0),
+            SvgWidth::Pixels(_) => image,
+            SvgWidth::Auto if self.style.svg_config.scale_factor != 1.0 => {
+                image.with_scale(Scale::new(
+                    self.style.svg_config.scale_factor,
+                    self.style.svg_config.scale_factor,
+                ))
+            }
+            SvgWidth::Auto => image,
+        };
+        self.push_block(doc, image.with_alignment(Alignment::Center));
+        self.push_spacing(doc, 0.5);
+    }
+
+    /// Renders a ```` ```qr ```` block as a QR code of its text, `[qr] width` percent of the
+    /// text width wide. Text that cannot be encoded is rendered as a code block instead.
+    #[cfg(feature = "qr")]
+    fn render_qr_code(&self, doc: &mut Document, lang: &str, content: &str) {
+        let svg = match qrcode::QrCode::new(content.trim().as_bytes()) {
+            Ok(code) => code
+                .render::<qrcode::render::svg::Color>()
+                .min_dimensions(256, 256)
+                .build(),
+            Err(e) => {
+                self.image_warning(format!("Failed to encode QR code: {}", e));
+                self.render_code_block(doc, lang, content);
+                return;
+            }
+        };
+        let image = match decode_svg_image(&svg) {
+            Ok(image) => image,
+            Err(e) => {
+                self.image_warning(format!("Failed to render QR code: {}", e));
+                self.render_code_block(doc, lang, content);
+                return;
+            }
+        };
+
+        self.push_spacing(doc, 0.5);
+        let image = image.resizing_page_with(self.style.qr.width / 100.0);
+        self.push_block(doc, image.with_alignment(Alignment::Center));
+        self.push_spacing(doc, 0.5);
+    }
+
+    #[cfg(not(feature = "qr"))]
+    fn render_qr_code(&self, doc: &mut Document, lang: &str, content: &str) {
+        // Feature disabled: keep the encoded text readable
+        self.render_warning("QR codes need the `qr` feature, showing the text instead".into());
+        self.render_code_block(doc, lang, content);
+    }
+
+    // Render a Mermaid diagram (fenced code block with language `mermaid`)
+    #[cfg(feature = "mermaid")]
+    fn render_mermaid(&self, doc: &mut Document, content: &str) {
+        // Add spacing before the mermaid block
+        self.push_spacing(doc, self.style.code.before_spacing);
+
+        // Use genpdfi_extended's Mermaid element (may use headless_chrome internally)
+        let mer = genpdfi_extended::elements::Mermaid::new(content.to_string());
+        let auto_scale = self.style.mermaid.auto_scale;
+        let mut max_ratio = self.style.mermaid.max_ratio;
+        if max_ratio > 1.0 {
+            max_ratio = 1.0;
+        }
+        let mer = mer
+            .with_alignment(Alignment::Center)
+            .with_auto_scale(auto_scale, max_ratio);
+        self.push_block(doc, mer);
+
+        // Add spacing after the mermaid block
+        self.push_spacing(doc, self.style.code.after_spacing);
+    }
+
+    #[cfg(not(feature = "mermaid"))]
+    fn render_mermaid(&self, doc: &mut Document, _content: &str) {
+        // Feature disabled: render a placeholder telling the user the feature is required
+        let mut para = genpdfi_extended::elements::Paragraph::default();
+        let mut style = genpdfi_extended::style::Style::new().with_font_size(self.style.code.size);
+        if let Some(color) = self.style.code.text_color {
+            style = style.with_color(genpdfi_extended::style::Color::Rgb(
+                color.0, color.1, color.2,
+            ));
+        }
+        para.push_styled("need Mermaid feature".to_string(), style);
+        self.push_block(doc, para);
+    }
+
+    fn render_inline_math(
+        &self,
+        para: &mut genpdfi_extended::elements::Paragraph,
+        latex_content: &str,
+        style: genpdfi_extended::style::Style,
+    ) {
+        // For inline math in paragraphs, we render the LaTeX code as styled text
+        // This ensures consistent rendering with the surrounding text and proper baseline alignment
+        let mut math_style = style.clone();
+
+        // Apply styling for code/math content
+        if let Some(color) = self.style.code.text_color {
+            math_style = math_style.with_color(genpdfi_extended::style::Color::Rgb(
+                color.0, color.1, color.2,
+            ));
+        }
+
+        // Render the LaTeX source as styled text, converted to Unicode math when the
+        // pure-Rust backend is enabled
+        #[cfg(feature = "math-rust")]
+        let text = crate::math::to_unicode(latex_content);
+        #[cfg(not(feature = "math-rust"))]
+        let text = latex_content.to_string();
+        para.push_styled(text, math_style);
+    }
+}
+
+/// Decodes a raster image. A panic inside the decoder, as a malformed file can cause, is
+/// caught and reported as an error, so the image degrades to a placeholder instead of
+/// aborting the conversion.
+fn decode_raster_image(
+    bytes: Vec<u8>,
+) -> Result<genpdfi_extended::elements::Image, crate::images::ImageError> {
+    std::panic::catch_unwind(move || {
+        genpdfi_extended::elements::Image::from_reader(std::io::Cursor::new(bytes))
+    })
+    .map_err(|payload| crate::images::ImageError::DecodeError(panic_message(payload)))?
+    .map_err(|e| crate::images::ImageError::DecodeError(e.to_string()))
+}
+
+/// Decodes an SVG image, catching panics inside the SVG parser like
+/// [`decode_raster_image`].
+fn decode_svg_image(
+    svg: &str,
+) -> Result<genpdfi_extended::elements::Image, crate::images::ImageError> {
+    std::panic::catch_unwind(|| genpdfi_extended::elements::Image::from_svg_string(svg))
+        .map_err(|payload| crate::images::ImageError::DecodeError(panic_message(payload)))?
+        .map_err(|e| crate::images::ImageError::DecodeError(e.to_string()))
+}
+
+/// Decodes loaded image data, SVG or raster, with [`decode_svg_image`] or
+/// [`decode_raster_image`].
+fn decode_image_data(
+    data: &crate::images::ImageData,
+) -> Result<genpdfi_extended::elements::Image, crate::images::ImageError> {
+    match data.format {
+        crate::images::ImageFormat::Svg => std::str::from_utf8(&data.bytes)
+            .map_err(|e| crate::images::ImageError::DecodeError(e.to_string()))
+            .and_then(decode_svg_image),
+        _ => decode_raster_image(data.bytes.clone()),
+    }
+}
+
+/// Returns the width divided by the height of an image: from the pixel size of raster
+/// images, and from the `viewBox`, or else the `width` and `height`, of SVG images.
+fn image_aspect_ratio(data: &crate::images::ImageData) -> Option<f32> {
+    let (width, height) = match data.format {
+        crate::images::ImageFormat::Svg => {
+            let svg = std::str::from_utf8(&data.bytes).ok()?;
+            let view_box = regex::Regex::new(
+                r#"viewBox\s*=\s*["']\s*[-\d.]+[\s,]+[-\d.]+[\s,]+([\d.]+)[\s,]+([\d.]+)"#,
+            )
+            .ok()?;
+            let attribute = |name: &str| {
+                regex::Regex::new(&format!(r#"<svg[^>]*?\s{}\s*=\s*["']([\d.]+)"#, name))
+                    .ok()?
+                    .captures(svg)?[1]
+                    .parse::<f32>()
+                    .ok()
+            };
+            match view_box.captures(svg) {
+                Some(caps) => (caps[1].parse().ok()?, caps[2].parse().ok()?),
+                None => (attribute("width")?, attribute("height")?),
+            }
+        }
+        _ => {
+            let (width, height) = data.pixel_size()?;
+            (width as f32, height as f32)
+        }
+    };
+    (width > 0.0 && height > 0.0).then_some(width / height)
+}
+
+/// Returns the message of a caught panic, or a generic one when it carries none.
+fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => format!("decoder panicked: {}", message),
+        Err(payload) => match payload.downcast::<&str>() {
+            Ok(message) => format!("decoder panicked: {}", message),
+            Err(_) => "decoder panicked".to_string(),
+        },
+    }
+}
+
+/// Returns the URI of a marker that defines the anchor `name` and links to the anchor
+/// `target`; [`Pdf::resolve_anchors`] turns it into a destination and a `GoTo` link.
+fn footnote_link_uri(name: &str, target: &str) -> String {
+    format!("{}{}#{}", ANCHOR_URI_PREFIX, name, target)
+}
+
+/// Returns the URI of a link annotation with a `URI` action, if any.
+fn link_uri(doc: &lopdf::Document, annotation_id: lopdf::ObjectId) -> Option<Vec<u8>> {
+    let annotation = doc.get_dictionary(annotation_id).ok()?;
+    let (_, action) = doc.dereference(annotation.get(b"A").ok()?).ok()?;
+    let action = action.as_dict().ok()?;
+    if action.get(b"S").and_then(|s| s.as_name()).ok()? != &b"URI"[..] {
+        return None;
+    }
+    action
+        .get(b"URI")
+        .and_then(|uri| uri.as_str())
+        .ok()
+        .map(|uri| uri.to_vec())
+}
+
+/// Removes a link annotation from its page and from the document.
+fn remove_annotation(
+    doc: &mut lopdf::Document,
+    page_id: lopdf::ObjectId,
+    annotation_id: lopdf::ObjectId,
+) {
+    use lopdf::Object;
+
+    let annots_id = doc
+        .get_dictionary(page_id)
+        .and_then(|page| page.get(b"Annots"))
+        .and_then(|annots| annots.as_reference())
+        .ok();
+    let annots = match annots_id {
+        Some(id) => doc.get_object_mut(id).ok(),
+        None => doc
+            .get_object_mut(page_id)
+            .and_then(|page| page.as_dict_mut())
+            .and_then(|page| page.get_mut(b"Annots"))
+            .ok(),
+    };
+    if let Some(Object::Array(items)) = annots {
+        items.retain(|item| item.as_reference().ok() != Some(annotation_id));
+    }
+    doc.objects.remove(&annotation_id);
+}
+
+/// Returns the name of the variant of `token`, such as `TabGroup`.
+fn token_variant_name(token: &Token) -> String {
+    format!("{:?}", token)
+        .chars()
+        .take_while(|c| c.is_alphanumeric())
+        .collect()
+}
+
+/// Appends the text of every `<kbd>` key in `tokens`, including nested ones, to `result`.
+fn collect_kbd_text(tokens: &[Token], result: &mut String) {
+    for token in tokens {
+        match token {
+            Token::Kbd(key) => result.push_str(key),
+            Token::Heading(content, _)
+            | Token::StrongEmphasis(content)
+            | Token::Emphasis { content, .. }
+            | Token::ListItem { content, .. }
+            | Token::FootnoteInline(content)
+            | Token::Spoiler(content) => collect_kbd_text(content, result),
+            Token::Table { headers, rows, .. } => {
+                for cell in headers.iter().chain(rows.iter().flatten()) {
+                    collect_kbd_text(cell, result);
+                }
+            }
+            Token::TabGroup(tabs) => {
+                for (_, content) in tabs {
+                    collect_kbd_text(content, result);
+                }
+            }
+            Token::Admonition { content, .. } => collect_kbd_text(content, result),
+            _ => {}
+        }
+    }
+}
+
+/// Computes column weights and a font scale fitting a table into `available` millimeters.
+///
+/// `natural` and `minimum` are the measured column widths without padding. Returns `None`
+/// when the table already fits. Otherwise each column keeps its minimum width and the
+/// remaining room is shared in proportion to how much more it needs; when even the minimum
+/// widths do not fit, the returned scale (below 1.0) is the factor the font size must be
+/// reduced by.
+fn fit_table_columns(
+    natural: &[f32],
+    minimum: &[f32],
+    available: f32,
+) -> Option<(Vec<usize>, f32)> {
+    let padding = TABLE_CELL_PADDING_MM * natural.len() as f32;
+    let natural_total: f32 = natural.iter().sum();
+    if natural.is_empty() || natural_total + padding <= available {
+        return None;
+    }
+
+    let minimum_total: f32 = minimum.iter().sum();
+    let (widths, scale): (Vec<f32>, f32) = if minimum_total + padding <= available {
+        let share = if natural_total > minimum_total {
+            (available - padding - minimum_total) / (natural_total - minimum_total)
+        } else {
+            0.0
+        };
+        let widths = natural
+            .iter()
+            .zip(minimum)
+            .map(|(n, m)| m + (n - m) * share + TABLE_CELL_PADDING_MM)
+            .collect();
+        (widths, 1.0)
+    } else {
+        let scale = ((available - padding) / minimum_total).max(0.0);
+        let widths = minimum
+            .iter()
+            .map(|m| m * scale + TABLE_CELL_PADDING_MM)
+            .collect();
+        (widths, scale)
+    };
+
+    let weights = widths
+        .iter()
+        .map(|w| ((w * 10.0).round() as usize).max(1))
+        .collect();
+    Some((weights, scale))
+}
+
+/// Counts the completed and total task items among `tokens`, including nested items.
+fn count_tasks(tokens: &[Token]) -> (usize, usize) {
+    let mut progress = (0, 0);
+    for token in tokens {
+        if let Token::ListItem { content, .. } = token {
+            if let Some(Token::TaskCheckbox(checked)) = content.first() {
+                progress.0 += usize::from(*checked);
+                progress.1 += 1;
+            }
+            let (done, total) = count_tasks(content);
+            progress = (progress.0 + done, progress.1 + total);
+        }
+    }
+    progress
+}
+
+/// Collects the list items among `tokens` and all their nested items, in document order,
+/// as (ordered, number, content without nested items). Walks the tree with an explicit
+/// stack rather than recursion, whatever its depth.
+fn flatten_list_items(tokens: &[Token]) -> Vec<(bool, Option<usize>, Vec<Token>)> {
+    let mut items = Vec::new();
+    let mut stack = vec![tokens.iter()];
+    while let Some(siblings) = stack.last_mut() {
+        match siblings.next() {
+            Some(Token::ListItem {
+                content,
+                ordered,
+                number,
+            }) => {
+                let own_content = content
+                    .iter()
+                    .filter(|token| !matches!(token, Token::ListItem { .. }))
+                    .cloned()
+                    .collect();
+                items.push((*ordered, *number, own_content));
+                stack.push(content.iter());
+            }
+            Some(_) => {}
+            None => {
+                stack.pop();
+            }
+        }
+    }
+    items
+}
+
+/// Returns the number shown for each of `tokens` that is an ordered list item.
+///
+/// A run of sibling ordered items starts at the first item's number and counts up from
+/// there, so items after a nested sub-list continue the sequence. Any other block token,
+/// including an unordered item, ends the run.
+fn list_numbers(tokens: &[Token]) -> Vec<Option<usize>> {
+    let mut previous: Option<usize> = None;
+    tokens
+        .iter()
+        .map(|token| match token {
+            Token::ListItem {
+                ordered: true,
+                number,
+                ..
+            } => {
+                let current = previous.map_or(number.unwrap_or(1), |n| n + 1);
+                previous = Some(current);
+                Some(current)
+            }
+            Token::Newline | Token::SoftBreak => None,
+            _ => {
+                previous = None;
+                None
+            }
+        })
+        .collect()
+}
+
+/// Converts a highlighting color to a gray of the same luminance, darkened so that even
+/// light colors stay legible when printed.
+fn grayscale((r, g, b): (u8, u8, u8)) -> (u8, u8, u8) {
+    let luminance = 0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32;
+    let gray = (luminance * 0.6).round() as u8;
+    (gray, gray, gray)
+}
+
+/// Returns the title color of an admonition kind, grouping the docutils kinds by severity.
+fn admonition_color(kind: &str) -> (u8, u8, u8) {
+    match kind {
+        "tip" | "hint" => (26, 127, 55),
+        "important" => (130, 80, 223),
+        "warning" | "caution" | "attention" => (154, 103, 0),
+        "danger" | "error" => (207, 34, 46),
+        _ => (9, 105, 218),
+    }
+}
+
+/// Returns the height in millimeters of a line of text set at `size` points.
+fn line_height_mm(size: u8) -> f32 {
+    size as f32 * PT_TO_MM * 1.2
+}
+
+/// Running state of [`Pdf::estimate_page_count`]: the printable area in millimeters and
+/// how much of the current page is filled.
+struct PageEstimate {
+    width: f32,
+    height: f32,
+    /// Height of one body text line, the unit of the styles' spacing
+    spacing: f32,
+    pages: usize,
+    used: f32,
+}
+
+impl PageEstimate {
+    /// Adds a block of `height` millimeters, starting a new page when it does not fit.
+    /// Blocks taller than a page continue on the following pages.
+    fn add(&mut self, height: f32) {
+        if self.used > 0.0 && self.used + height > self.height {
+            self.pages += 1;
+            self.used = 0.0;
+        }
+        self.used += height;
+        while self.used > self.height {
+            self.pages += 1;
+            self.used -= self.height;
+        }
+    }
+
+    /// Starts a new page unless the current one is still empty.
+    fn page_break(&mut self) {
+        if self.used > 0.0 {
+            self.pages += 1;
+            self.used = 0.0;
+        }
+    }
+
+    /// Returns the number of lines `text` wraps to at `size` points, `indent` millimeters
+    /// from the left margin.
+    fn wrapped_lines(&self, text: &str, size: u8, indent: f32) -> usize {
+        let char_width = size.max(1) as f32 * PT_TO_MM * 0.5;
+        let per_line = ((self.width - indent).max(char_width) / char_width) as usize;
+        text.chars().count().div_ceil(per_line.max(1)).max(1)
+    }
+
+    /// Adds `lines` lines set in `style`, with its spacing before and after.
+    fn add_lines(&mut self, lines: usize, style: &crate::styling::BasicTextStyle) {
+        self.add(
+            (style.before_spacing + style.after_spacing) * self.spacing
+                + lines as f32 * line_height_mm(style.size),
+        );
+    }
+
+    /// Adds a paragraph of `text` set in `style`.
+    fn add_text(&mut self, text: &str, style: &crate::styling::BasicTextStyle, indent: f32) {
+        let lines = self.wrapped_lines(text.trim(), style.size, indent);
+        self.add_lines(lines, style);
+    }
+}
+
+/// Parses the `Key | Action` lines of a `shortcuts` block into the keys of each combination
+/// and the action. Blank lines are skipped; returns `None` if another line has no `|`, an
+/// empty side, or if there are no shortcuts at all.
+fn parse_shortcuts(content: &str) -> Option<Vec<(Vec<String>, String)>> {
+    let mut shortcuts = Vec::new();
+    for line in content.lines().filter(|line| !line.trim().is_empty()) {
+        let (keys, action) = line.split_once('|')?;
+        let (keys, action) = (keys.trim(), action.trim());
+        if keys.is_empty() || action.is_empty() {
+            return None;
+        }
+        // `Ctrl++` presses the plus key itself
+        let mut parts: Vec<String> = keys
+            .split('+')
+            .map(str::trim)
+            .filter(|key| !key.is_empty())
+            .map(str::to_string)
+            .collect();
+        if keys.ends_with('+') {
+            parts.push("+".to_string());
+        }
+        shortcuts.push((parts, action.to_string()));
+    }
+    (!shortcuts.is_empty()).then_some(shortcuts)
+}
+
+/// Sets the character spacing of the text lines of a page that start inside one of the
+/// `areas`, replacing the page content with the rewritten operations.
+///
+/// Lines are laid out before the spacing is known, so a tracked line is wider than the
+/// layout measured.
+fn track_text(
+    doc: &mut lopdf::Document,
+    page_id: lopdf::ObjectId,
+    areas: &[([f32; 4], f32)],
+) -> Result<(), String> {
+    use lopdf::content::{Content, Operation};
+    use lopdf::{Dictionary, Object, Stream};
+
+    let content = doc.get_page_content(page_id).map_err(|e| e.to_string())?;
+    let content = Content::decode(&content).map_err(|e| e.to_string())?;
+
+    let mut operations = Vec::with_capacity(content.operations.len());
+    let mut tracking = false;
+    for operation in content.operations {
+        let position = match operation.operator.as_str() {
+            "Td" | "TD" => Some((0, 1)),
+            "Tm" => Some((4, 5)),
+            _ => None,
+        }
+        .and_then(|(x, y)| {
+            let x = operation.operands.get(x)?.as_float().ok()?;
+            let y = operation.operands.get(y)?.as_float().ok()?;
+            Some((x, y))
+        });
+        if operation.operator == "ET" && tracking {
+            operations.push(Operation::new("Tc", vec![Object::Integer(0)]));
+            tracking = false;
+        }
+        operations.push(operation);
+
+        if let Some((x, y)) = position {
+            let spacing = areas
+                .iter()
+                .find(|([x1, y1, x2, y2], _)| {
+                    x >= x1 - 0.5 && x <= *x2 && y >= y1 - 0.5 && y <= y2 + 0.5
+                })
+                .map(|(_, spacing)| *spacing);
+            match spacing {
+                Some(spacing) => {
+                    operations.push(Operation::new("Tc", vec![Object::Real(spacing)]));
+                    tracking = true;
+                }
+                None if tracking => {
+                    operations.push(Operation::new("Tc", vec![Object::Integer(0)]));
+                    tracking = false;
+                }
+                None => {}
+            }
+        }
+    }
+
+    let content = Content { operations }.encode().map_err(|e| e.to_string())?;
+    let stream_id = doc.add_object(Stream::new(Dictionary::new(), content));
+    if let Ok(page) = doc
+        .get_object_mut(page_id)
+        .and_then(|page| page.as_dict_mut())
+    {
+        page.set("Contents", Object::Reference(stream_id));
+    }
+    Ok(())
+}
+
+/// Reads an `<!-- orientation: landscape -->` or `<!-- orientation: portrait -->`
+/// directive.
+fn orientation_directive(token: &Token) -> Option<crate::styling::Orientation> {
+    match token {
+        Token::HtmlComment(comment) => comment
+            .trim()
+            .strip_prefix("orientation:")
+            .and_then(|name| crate::styling::Orientation::from_name(name.trim())),
+        _ => None,
+    }
+}
+
+/// Appends the pages of the other PDFs to those of the first one, for documents
+/// rendered in sections. Fonts and images embedded identically by several parts are
+/// shared; subset fonts hold the glyphs of their own part and stay separate.
+fn merge_pdfs(parts: &[Vec<u8>]) -> Result<Vec<u8>, String> {
+    use lopdf::{Object, ObjectId};
+
+    let (first, rest) = parts
+        .split_first()
+        .ok_or_else(|| "No PDF to merge".to_string())?;
+    let mut merged = lopdf::Document::load_mem(first).map_err(|e| e.to_string())?;
+    let pages_id = merged
+        .trailer
+        .get(b"Root")
+        .and_then(|root| root.as_reference())
+        .and_then(|root| merged.get_dictionary(root))
+        .and_then(|catalog| catalog.get(b"Pages"))
+        .and_then(|pages| pages.as_reference())
+        .map_err(|e| e.to_string())?;
+
+    let mut kids = Vec::new();
+    for part in rest {
+        let mut doc = lopdf::Document::load_mem(part).map_err(|e| e.to_string())?;
+        doc.renumber_objects_with(merged.max_id + 1);
+        merged.max_id = merged.max_id.max(doc.max_id);
+
+        let page_ids: Vec<ObjectId> = doc.get_pages().into_values().collect();
+        for page_id in page_ids {
+            // The page leaves its page tree, so it takes the attributes it inherits from it
+            let inherited: Vec<(&[u8], Object)> = match doc
+                .get_dictionary(page_id)
+                .and_then(|page| page.get(b"Parent"))
+                .and_then(|parent| parent.as_reference())
+                .and_then(|parent| doc.get_dictionary(parent))
+            {
+                Ok(parent) => [&b"MediaBox"[..], b"CropBox", b"Resources", b"Rotate"]
+                    .into_iter()
+                    .filter_map(|key| parent.get(key).ok().map(|value| (key, value.clone())))
+                    .collect(),
+                Err(_) => Vec::new(),
+            };
+            if let Ok(page) = doc
+                .get_object_mut(page_id)
+                .and_then(|page| page.as_dict_mut())
+            {
+                for (key, value) in inherited {
+                    if !page.has(key) {
+                        page.set(key, value);
+                    }
+                }
+                page.set("Parent", Object::Reference(pages_id));
+            }
+            kids.push(Object::Reference(page_id));
+        }
+        merged.objects.extend(doc.objects);
+    }
+    share_identical_streams(&mut merged);
+
+    if let Ok(pages) = merged
+        .get_object_mut(pages_id)
+        .and_then(|pages| pages.as_dict_mut())
+    {
+        let count = pages.get(b"Count").and_then(|c| c.as_i64()).unwrap_or(0);
+        let mut all_kids = pages
+            .get(b"Kids")
+            .and_then(|k| k.as_array())
+            .cloned()
+            .unwrap_or_default();
+        pages.set("Count", Object::Integer(count + kids.len() as i64));
+        all_kids.extend(kids);
+        pages.set("Kids", Object::Array(all_kids));
+    }
+    // Drops the catalogs and page trees of the appended parts
+    merged.prune_objects();
+
+    let mut output = Vec::new();
+    merged.save_to(&mut output).map_err(|e| e.to_string())?;
+    Ok(output)
+}
+
+/// Points every reference to a stream at the first stream with the same dictionary and
+/// content, such as a font program or an image each part of a merged document embeds,
+/// so that `prune_objects` drops the copies. Page content streams are left alone, as
+/// post-processing rewrites them page by page.
+fn share_identical_streams(doc: &mut lopdf::Document) {
+    use lopdf::{Object, ObjectId};
+    use md5::{Digest, Md5};
+    use std::collections::{hash_map::Entry, HashMap, HashSet};
+
+    fn redirect(object: &mut Object, replacements: &HashMap<ObjectId, ObjectId>) {
+        match object {
+            Object::Reference(id) => {
+                if let Some(target) = replacements.get(id) {
+                    *id = *target;
+                }
+            }
+            Object::Array(items) => items
+                .iter_mut()
+                .for_each(|item| redirect(item, replacements)),
+            Object::Dictionary(dict) => dict
+                .iter_mut()
+                .for_each(|(_, value)| redirect(value, replacements)),
+            Object::Stream(stream) => stream
+                .dict
+                .iter_mut()
+                .for_each(|(_, value)| redirect(value, replacements)),
+            _ => {}
+        }
+    }
+
+    let contents: HashSet<ObjectId> = doc
+        .get_pages()
+        .into_values()
+        .flat_map(|page_id| doc.get_page_contents(page_id))
+        .collect();
+    let mut first = HashMap::new();
+    let mut replacements = HashMap::new();
+    for (id, object) in &doc.objects {
+        let Object::Stream(stream) = object else {
+            continue;
+        };
+        if contents.contains(id) {
+            continue;
+        }
+        let key = (
+            format!("{:?}", stream.dict),
+            Md5::digest(&stream.content).to_vec(),
+        );
+        match first.entry(key) {
+            Entry::Occupied(entry) => {
+                replacements.insert(*id, *entry.get());
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(*id);
+            }
+        }
+    }
+    if replacements.is_empty() {
+        return;
+    }
+    for object in doc.objects.values_mut() {
+        redirect(object, &replacements);
+    }
+}
+
+/// Looks up a page attribute such as `MediaBox` or `Resources`, which the page may
+/// inherit from its ancestors in the page tree.
+fn inherited_page_attribute(
+    doc: &lopdf::Document,
+    page_id: lopdf::ObjectId,
+    key: &[u8],
+) -> Option<lopdf::Object> {
+    let mut node = doc.get_dictionary(page_id).ok()?;
+    // The depth bound guards against a cyclic page tree
+    for _ in 0..32 {
+        if let Ok(value) = node.get(key) {
+            return Some(value.clone());
+        }
+        let parent = node.get(b"Parent").and_then(|p| p.as_reference()).ok()?;
+        node = doc.get_dictionary(parent).ok()?;
+    }
+    None
+}
+
+/// Cuts `content` after `max_lines` lines for `[code] max_lines`, returning the kept part
+/// and the number of lines left out. A limit of 0 keeps every line.
+fn truncate_code_lines(content: &str, max_lines: usize) -> (&str, usize) {
+    let total = content.lines().count();
+    if max_lines == 0 || total <= max_lines {
+        return (content, 0);
+    }
+    let end = content
+        .match_indices('\n')
+        .nth(max_lines - 1)
+        .map_or(content.len(), |(i, _)| i);
+    (&content[..end], total - max_lines)
+}
+
+/// Returns true if the first token other than a line break is a block quote line, which
+/// tells whether a quote line continues the quote next to it.
+fn is_block_quote<'a>(mut tokens: impl Iterator<Item = &'a Token>) -> bool {
+    matches!(
+        tokens.find(|token| !matches!(token, Token::SoftBreak | Token::LineBreak)),
+        Some(Token::BlockQuote(_))
+    )
+}
+
+/// Formats a color for a marker URI payload: `RRGGBB` hex, or `-` for no color.
+fn marker_color(color: Option<(u8, u8, u8)>) -> String {
+    match color {
+        Some((r, g, b)) => format!("{:02X}{:02X}{:02X}", r, g, b),
+        None => "-".to_string(),
+    }
+}
+
+/// Builds the content stream operators drawing a horizontal line through the text in the
+/// given rectangle. `color` is the marker payload, an `RRGGBB` hex color.
+fn strike_operators(x1: f32, y1: f32, x2: f32, y2: f32, color: &[u8]) -> String {
+    // The marker spans the line box; the middle of lowercase letters sits a bit below its center
+    line_operators(x1, y1, x2, y2, color, 0.4)
+}
+
+/// Builds the content stream operators drawing a horizontal line just below the baseline of
+/// the text in the given rectangle. `color` is the marker payload, an `RRGGBB` hex color.
+fn underline_operators(x1: f32, y1: f32, x2: f32, y2: f32, color: &[u8]) -> String {
+    line_operators(x1, y1, x2, y2, color, 0.18)
+}
+
+/// Builds the content stream operators drawing a horizontal line across the given rectangle,
+/// `height` of the way up from its bottom.
+fn line_operators(x1: f32, y1: f32, x2: f32, y2: f32, color: &[u8], height: f32) -> String {
+    let Some(value) = std::str::from_utf8(color)
+        .ok()
+        .filter(|hex| hex.len() == 6)
+        .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+    else {
+        return String::new();
+    };
+    let channel = |shift: u32| ((value >> shift) & 0xFF) as f32 / 255.0;
+
+    let (left, right) = (x1.min(x2), x1.max(x2));
+    let (bottom, top) = (y1.min(y2), y1.max(y2));
+    let y = bottom + (top - bottom) * height;
+    let width = ((top - bottom) * 0.06).max(0.4);
+    format!(
+        "q\n{:.3} {:.3} {:.3} RG\n{:.2} w\n{:.2} {:.2} m\n{:.2} {:.2} l\nS\nQ\n",
+        channel(16),
+        channel(8),
+        channel(0),
+        width,
+        left,
+        y,
+        right,
+        y
+    )
+}
+
+/// Builds the content stream operators drawing a `<kbd>` box over the given rectangle.
+///
+/// `colors` is the marker payload `FILL:BORDER`, each either an `RRGGBB` hex color or
+/// `-` when that part is not drawn.
+fn kbd_box_operators(x1: f32, y1: f32, x2: f32, y2: f32, colors: &[u8]) -> String {
+    let color = |hex: &[u8]| -> Option<(f32, f32, f32)> {
+        let hex = std::str::from_utf8(hex).ok()?;
+        let value = u32::from_str_radix(hex, 16)
+            .ok()
+            .filter(|_| hex.len() == 6)?;
+        let channel = |shift: u32| ((value >> shift) & 0xFF) as f32 / 255.0;
+        Some((channel(16), channel(8), channel(0)))
+    };
+    let mut parts = colors.split(|&b| b == b':');
+    let fill = parts.next().and_then(color);
+    let border = parts.next().and_then(color);
+
+    let paint = match (fill, border) {
+        (Some(_), Some(_)) => "B",
+        (Some(_), None) => "f",
+        (None, Some(_)) => "S",
+        (None, None) => return String::new(),
+    };
+
+    let (left, right) = (x1.min(x2), x1.max(x2));
+    let (bottom, top) = (y1.min(y2), y1.max(y2));
+    let r = 2.0_f32.min((top - bottom) / 4.0).min((right - left) / 4.0);
+    // Control point distance approximating a quarter circle with a cubic Bezier curve
+    let k = r * 0.552_284_8;
+
+    let mut ops = String::from("q\n");
+    if let Some((red, green, blue)) = fill {
+        ops.push_str(&format!("{:.3} {:.3} {:.3} rg\n", red, green, blue));
+    }
+    if let Some((red, green, blue)) = border {
+        ops.push_str(&format!("{:.3} {:.3} {:.3} RG\n0.5 w\n", red, green, blue));
+    }
+    ops.push_str(&format!("{:.2} {:.2} m\n", left + r, bottom));
+    ops.push_str(&format!("{:.2} {:.2} l\n", right - r, bottom));
+    ops.push_str(&format!(
+        "{:.2} {:.2} {:.2} {:.2} {:.2} {:.2} c\n",
+        right - r + k,
+        bottom,
+        right,
+        bottom + r - k,
+        right,
+        bottom + r
+    ));
+    ops.push_str(&format!("{:.2} {:.2} l\n", right, top - r));
+    ops.push_str(&format!(
+        "{:.2} {:.2} {:.2} {:.2} {:.2} {:.2} c\n",
+        right,
+        top - r + k,
+        right - r + k,
+        top,
+        right - r,
+        top
+    ));
+    ops.push_str(&format!("{:.2} {:.2} l\n", left + r, top));
+    ops.push_str(&format!(
+        "{:.2} {:.2} {:.2} {:.2} {:.2} {:.2} c\n",
+        left + r - k,
+        top,
+        left,
+        top - r + k,
+        left,
+        top - r
+    ));
+    ops.push_str(&format!("{:.2} {:.2} l\n", left, bottom + r));
+    ops.push_str(&format!(
+        "{:.2} {:.2} {:.2} {:.2} {:.2} {:.2} c\n",
+        left,
+        bottom + r - k,
+        left + r - k,
+        bottom,
+        left + r,
+        bottom
+    ));
+    ops.push_str(&format!("h {}\nQ\n", paint));
+    ops
+}
+
+/// Returns the timestamp `style` asks to embed for reproducible output: its
+/// `source_date_epoch` when set, the Unix epoch with `[document] reproducible`, and `None`
+/// otherwise.
+fn reproducible_timestamp(style: &StyleMatch) -> Option<i64> {
+    if let Some(epoch) = style.document.source_date_epoch {
+        return Some(epoch);
+    }
+    if style.document.reproducible {
+        Some(0)
+    } else {
+        None
+    }
+}
+
+/// Formats a Unix timestamp as a PDF date string in UTC (e.g. `D:19700101000000+00'00'`).
+fn format_pdf_date(timestamp: i64) -> String {
+    format_date(timestamp, "D:%Y%m%d%H%M%S+00'00'")
+}
+
+/// Formats `timestamp` (seconds since the Unix epoch) in UTC, replacing `%Y`, `%m`, `%d`,
+/// `%H`, `%M` and `%S` in `format` by the year, month, day, hours, minutes and seconds and
+/// `%%` by `%`. Other characters, including unknown `%` sequences, are kept as they are.
+fn format_date(timestamp: i64, format: &str) -> String {
+    // Civil-from-days conversion (proleptic Gregorian calendar)
+    let days = timestamp.div_euclid(86_400);
+    let secs = timestamp.rem_euclid(86_400);
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    let mut formatted = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            formatted.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => formatted.push_str(&format!("{:04}", year)),
+            Some('m') => formatted.push_str(&format!("{:02}", month)),
+            Some('d') => formatted.push_str(&format!("{:02}", day)),
+            Some('H') => formatted.push_str(&format!("{:02}", secs / 3600)),
+            Some('M') => formatted.push_str(&format!("{:02}", secs % 3600 / 60)),
+            Some('S') => formatted.push_str(&format!("{:02}", secs % 60)),
+            Some('%') => formatted.push('%'),
+            Some(other) => {
+                formatted.push('%');
+                formatted.push(other);
+            }
+            None => formatted.push('%'),
+        }
+    }
+    formatted
+}
+
+/// Relative size of the capitals standing in for lowercase letters in small caps.
+const SMALL_CAPS_SCALE: f32 = 0.8;
+
+/// Splits `text` into runs set in faux small caps: lowercase letters become capitals in
+/// `style` at a reduced size, everything else keeps `style`.
+fn small_caps_runs(
+    text: &str,
+    style: genpdfi_extended::style::Style,
+) -> Vec<(String, genpdfi_extended::style::Style)> {
+    let small = style
+        .clone()
+        .with_font_size(((style.font_size() as f32 * SMALL_CAPS_SCALE).round() as u8).max(1));
+    let mut runs: Vec<(String, bool)> = Vec::new();
+    for c in text.chars() {
+        let lowercase = c.is_lowercase();
+        match runs.last_mut() {
+            Some((run, run_lowercase)) if *run_lowercase == lowercase => run.push(c),
+            _ => runs.push((c.to_string(), lowercase)),
+        }
+    }
+    runs.into_iter()
+        .map(|(run, lowercase)| {
+            if lowercase {
+                (run.to_uppercase(), small.clone())
+            } else {
+                (run, style.clone())
+            }
+        })
+        .collect()
+}
+
+/// Returns the byte offset where the first word of `text` ends, including the whitespace
+/// before it, or `None` when `text` is blank.
+fn first_word_end(text: &str) -> Option<usize> {
+    let start = text.find(|c: char| !c.is_whitespace())?;
+    Some(
+        text[start..]
+            .find(char::is_whitespace)
+            .map_or(text.len(), |end| start + end),
+    )
+}
+
+/// Splits a URL after each run of `/`, `.`, `?` and `&`, so `https://example.com/a?b&c`
+/// becomes `https://`, `example.`, `com/`, `a?`, `b&`, `c`.
+fn url_break_segments(url: &str) -> Vec<&str> {
+    let is_break = |c: char| matches!(c, '/' | '.' | '?' | '&');
+    let mut segments = Vec::new();
+    let mut start = 0;
+    let mut chars = url.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let next_breaks = chars.peek().map_or(false, |&(_, next)| is_break(next));
+        if is_break(c) && !next_breaks {
+            let end = i + c.len_utf8();
+            if end < url.len() {
+                segments.push(&url[start..end]);
+                start = end;
+            }
+        }
+    }
+    segments.push(&url[start..]);
+    segments
+}
+
+/// Splits text into runs that share a font: `Some(idx)` for characters inside a configured
+/// range (the first matching range wins), `None` for the main font.
+///
+/// Whitespace stays in the current run so that a phrase in one script is not broken into
+/// a run per word.
+fn split_font_ranges(text: &str, ranges: &[(FontRange, usize)]) -> Vec<(String, Option<usize>)> {
+    let mut runs: Vec<(String, Option<usize>)> = Vec::new();
+    for c in text.chars() {
+        let font_idx = match runs.last() {
+            Some((_, current)) if c.is_whitespace() => *current,
+            _ => ranges
+                .iter()
+                .find(|(range, _)| range.contains(c))
+                .map(|(_, idx)| *idx),
+        };
+        match runs.last_mut() {
+            Some((run, current)) if *current == font_idx => run.push(c),
+            _ => runs.push((c.to_string(), font_idx)),
+        }
+    }
+    runs
+}
+
+/// Splits text into segments, tagging occurrences of abbreviations with their index.
+///
+/// An abbreviation only matches on word boundaries, so "HTML" matches in "HTML5 and HTML"
+/// only for the second occurrence. When several abbreviations match at the same position
+/// the longest one wins.
+fn split_abbreviations(
+    text: &str,
+    abbreviations: &[(String, String)],
+) -> Vec<(String, Option<usize>)> {
+    let mut segments = Vec::new();
+    let mut plain_start = 0;
+    let mut i = 0;
+
+    while i < text.len() {
+        let at_boundary = text[..i]
+            .chars()
+            .next_back()
+            .map_or(true, |c| !c.is_alphanumeric());
+
+        let matched = if at_boundary {
+            abbreviations
+                .iter()
+                .enumerate()
+                .filter(|(_, (abbr, _))| {
+                    !abbr.is_empty()
+                        && text[i..].starts_with(abbr.as_str())
+                        && text[i + abbr.len()..]
+                            .chars()
+                            .next()
+                            .map_or(true, |c| !c.is_alphanumeric())
+                })
+                .max_by_key(|(_, (abbr, _))| abbr.len())
+                .map(|(idx, _)| idx)
+        } else {
+            None
+        };
+
+        if let Some(idx) = matched {
+            if plain_start < i {
+                segments.push((text[plain_start..i].to_string(), None));
+            }
+            let end = i + abbreviations[idx].0.len();
+            segments.push((text[i..end].to_string(), Some(idx)));
+            i = end;
+            plain_start = i;
+        } else {
+            i += text[i..].chars().next().map_or(1, |c| c.len_utf8());
+        }
+    }
+
+    if plain_start < text.len() {
+        segments.push((text[plain_start..].to_string(), None));
+    }
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::styling::StyleMatch;
+
+    // Helper function to create a basic PDF instance for testing
+    fn create_test_pdf(tokens: Vec<Token>) -> Pdf {
+        Pdf::new(tokens, StyleMatch::default(), None)
+    }
+
+    // Helper function returning the `(x, y)` positions of the text on the first page
+    fn text_positions(bytes: &[u8]) -> Vec<(f32, f32)> {
+        let doc = lopdf::Document::load_mem(bytes).unwrap();
+        let (_, page_id) = doc.get_pages().into_iter().next().unwrap();
+        let content = doc.get_page_content(page_id).unwrap();
+        lopdf::content::Content::decode(&content)
+            .unwrap()
+            .operations
+            .iter()
+            .filter(|op| op.operator == "Td" || op.operator == "Tm")
+            .map(|op| {
+                let y = op.operands.len() - 1;
+                (
+                    op.operands[y - 1].as_float().unwrap(),
+                    op.operands[y].as_float().unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_pdf_creation() {
+        let pdf = create_test_pdf(vec![]);
+        assert!(pdf.input.is_empty());
+
+        // Test that both font families exist
+        let _font_family = &pdf.font_family;
+        let _code_font_family = &pdf.code_font_family;
+
+        // Since FontData's fields are private and it doesn't implement comparison traits,
+        // we can only verify that the PDF was created successfully with these fonts
+        let doc = pdf.render_into_document();
+        assert!(Pdf::render(doc, "/dev/null").is_none());
+    }
+
+    #[cfg(feature = "qr")]
+    #[test]
+    fn test_qr_code_block() {
+        let pdf = create_test_pdf(vec![Token::Code(
+            "qr".to_string(),
+            "https://example.com".to_string(),
+        )]);
+        let bytes = Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+        assert!(pdf.warnings().is_empty());
+        // The code is drawn as an image, not set as text
+        let doc = lopdf::Document::load_mem(&bytes).unwrap();
+        let subtypes: Vec<&[u8]> = doc
+            .objects
+            .values()
+            .filter_map(|object| {
+                let stream = object.as_stream().ok()?;
+                stream.dict.get(b"Subtype").ok()?.as_name().ok()
+            })
+            .collect();
+        assert!(subtypes
+            .iter()
+            .any(|&subtype| subtype == b"Image" || subtype == b"Form"));
+        let page_id = *doc.get_pages().values().next().unwrap();
+        let operations = lopdf::content::Content::decode(&doc.get_page_content(page_id).unwrap())
+            .unwrap()
+            .operations;
+        assert_eq!(
+            operations.iter().filter(|op| op.operator == "Do").count(),
+            1
+        );
+    }
+
+    #[cfg(not(feature = "qr"))]
+    #[test]
+    fn test_qr_code_block_without_feature() {
+        let pdf = create_test_pdf(vec![Token::Code(
+            "qr".to_string(),
+            "https://example.com".to_string(),
+        )]);
+        assert!(Pdf::render_to_bytes(pdf.render_into_document()).is_ok());
+        // The text is shown as a code block, with a warning that says why
+        let warnings = pdf.warnings();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(
+            warnings[0].kind,
+            crate::validation::WarningKind::RenderFailure
+        );
+        assert!(warnings[0].message.contains("`qr` feature"));
+    }
+
+    #[cfg(not(feature = "mermaid"))]
+    #[test]
+    fn test_mermaid_placeholder() {
+        // If the mermaid feature is not enabled, render_mermaid should emit a placeholder
+        let tokens = vec![Token::Code(
+            "mermaid".to_string(),
+            "graph LR\nA-->B".to_string(),
+        )];
+
+        let pdf = create_test_pdf(tokens);
+        let doc = pdf.render_into_document();
+        assert!(Pdf::render(doc, "/dev/null").is_none());
+    }
+
+    #[cfg(feature = "mermaid")]
+    #[test]
+    #[ignore]
+    fn test_mermaid_rendering_ignored() {
+        // This test is ignored by default because Mermaid rendering can be slow and may require
+        // headless Chrome to be downloaded on first run. Run manually when needed.
+        let tokens = vec![Token::Code(
+            "mermaid".to_string(),
+            "graph LR\nA-->B".to_string(),
+        )];
+
+        let pdf = create_test_pdf(tokens);
+        let doc = pdf.render_into_document();
+        // We don't assert anything about content; the purpose is to ensure rendering doesn't panic
+        assert!(Pdf::render(doc, "/dev/null").is_none());
+    }
+
+    #[cfg(feature = "mermaid")]
+    #[test]
+    #[ignore]
+    fn test_mermaid_rendering_with_custom_config_ignored() {
+        // Ensure custom mermaid config values don't panic during rendering
+        let tokens = vec![Token::Code(
+            "mermaid".to_string(),
+            "graph LR\nA-->B".to_string(),
+        )];
+
+        let mut pdf = create_test_pdf(tokens);
+        pdf.style.mermaid.auto_scale = 3.5;
+        pdf.style.mermaid.max_ratio = 0.8;
+        let doc = pdf.render_into_document();
+        assert!(Pdf::render(doc, "/dev/null").is_none());
+    }
+
+    #[cfg(feature = "mermaid")]
+    #[test]
+    #[ignore]
+    fn test_mermaid_rendering_with_max_ratio_clamped_ignored() {
+        // If max_ratio is > 1 it should be clamped and not cause errors
+        let tokens = vec![Token::Code(
+            "mermaid".to_string(),
+            "graph LR\nA-->B".to_string(),
+        )];
+
+        let mut pdf = create_test_pdf(tokens);
+        pdf.style.mermaid.auto_scale = 2.0;
+        pdf.style.mermaid.max_ratio = 2.0; // will be clamped by render_mermaid
+        let doc = pdf.render_into_document();
+        assert!(Pdf::render(doc, "/dev/null").is_none());
+    }
+
+    #[test]
+    fn test_render_heading() {
+        let tokens = vec![
+            Token::Heading(vec![Token::Text("Test Heading".to_string())], 1),
+            Token::Heading(vec![Token::Text("Subheading".to_string())], 2),
+            Token::Heading(vec![Token::Text("Sub-subheading".to_string())], 3),
+        ];
+        let pdf = create_test_pdf(tokens);
+        let doc = pdf.render_into_document();
+        // Document should be created successfully
+        assert!(Pdf::render(doc, "/dev/null").is_none());
+    }
+
+    #[test]
+    fn test_render_paragraphs() {
+        let tokens = vec![
+            Token::Text("First paragraph".to_string()),
+            Token::Newline,
+            Token::Text("Second paragraph".to_string()),
+        ];
+        let pdf = create_test_pdf(tokens);
+        let doc = pdf.render_into_document();
+        assert!(Pdf::render(doc, "/dev/null").is_none());
+    }
+
+    #[test]
+    fn test_render_list_items() {
+        let tokens = vec![
+            Token::ListItem {
+                content: vec![Token::Text("First item".to_string())],
+                ordered: false,
+                number: None,
+            },
+            Token::ListItem {
+                content: vec![Token::Text("Second item".to_string())],
+                ordered: true,
+                number: Some(1),
+            },
+        ];
+        let pdf = create_test_pdf(tokens);
+        let doc = pdf.render_into_document();
+        assert!(Pdf::render(doc, "/dev/null").is_none());
+    }
+
+    #[test]
+    fn test_render_nested_list_items() {
+        let tokens = vec![Token::ListItem {
+            content: vec![
+                Token::Text("Parent item".to_string()),
+                Token::ListItem {
+                    content: vec![Token::Text("Child item".to_string())],
+                    ordered: false,
+                    number: None,
+                },
+            ],
+            ordered: false,
+            number: None,
+        }];
+        let pdf = create_test_pdf(tokens);
+        let doc = pdf.render_into_document();
+        assert!(Pdf::render(doc, "/dev/null").is_none());
+    }
+
+    #[test]
+    fn test_render_code_blocks() {
+        let tokens = vec![Token::Code(
+            "rust".to_string(),
+            "fn main() {\n    println!(\"Hello\");\n}".to_string(),
+        )];
+        let pdf = create_test_pdf(tokens);
+        let doc = pdf.render_into_document();
+        assert!(Pdf::render(doc, "/dev/null").is_none());
+    }
+
+    #[test]
+    fn test_render_inline_formatting() {
+        let tokens = vec![
+            Token::Text("Normal ".to_string()),
+            Token::Emphasis {
+                level: 1,
+                content: vec![Token::Text("italic".to_string())],
+            },
+            Token::Text(" and ".to_string()),
+            Token::StrongEmphasis(vec![Token::Text("bold".to_string())]),
+            Token::Text(" text".to_string()),
+        ];
+        let pdf = create_test_pdf(tokens);
+        let doc = pdf.render_into_document();
+        assert!(Pdf::render(doc, "/dev/null").is_none());
+    }
+
+    #[test]
+    fn test_render_links() {
+        let tokens = vec![
+            Token::Text("Here is a ".to_string()),
+            Token::Link("link".to_string(), "https://example.com".to_string()),
+            Token::Text(" to click".to_string()),
+        ];
+        let pdf = create_test_pdf(tokens);
+        let doc = pdf.render_into_document();
+        assert!(Pdf::render(doc, "/dev/null").is_none());
+    }
+
+    #[test]
+    fn test_render_horizontal_rule() {
+        let tokens = vec![
+            Token::Text("Before rule".to_string()),
+            Token::HorizontalRule,
+            Token::Text("After rule".to_string()),
+        ];
+        let pdf = create_test_pdf(tokens);
+        let doc = pdf.render_into_document();
+        assert!(Pdf::render(doc, "/dev/null").is_none());
+    }
+
+    #[test]
+    fn test_render_mixed_content() {
+        let tokens = vec![
+            Token::Heading(vec![Token::Text("Title".to_string())], 1),
+            Token::Text("Some text ".to_string()),
+            Token::Link("with link".to_string(), "https://example.com".to_string()),
+            Token::Newline,
+            Token::ListItem {
+                content: vec![Token::Text("List item".to_string())],
+                ordered: false,
+                number: None,
+            },
+            Token::Code("rust".to_string(), "let x = 42;".to_string()),
+        ];
+        let pdf = create_test_pdf(tokens);
+        let doc = pdf.render_into_document();
+        assert!(Pdf::render(doc, "/dev/null").is_none());
+    }
+
+    #[test]
+    fn test_render_empty_content() {
+        let pdf = create_test_pdf(vec![]);
+        let doc = pdf.render_into_document();
+        assert!(Pdf::render(doc, "/dev/null").is_none());
+    }
+
+    #[test]
+    fn test_render_invalid_path() {
+        let pdf = create_test_pdf(vec![Token::Text("Test".to_string())]);
+        let doc = pdf.render_into_document();
+        let result = Pdf::render(doc, "/nonexistent/path/file.pdf");
+        assert!(result.is_some()); // Should return an error message
+    }
+
+    #[test]
+    fn test_render_to_bytes() {
+        let tokens = vec![
+            Token::Heading(vec![Token::Text("Test Document".to_string())], 1),
+            Token::Text("This is a test paragraph.".to_string()),
+        ];
+        let pdf = create_test_pdf(tokens);
+        let doc = pdf.render_into_document();
+        let result = Pdf::render_to_bytes(doc);
+
+        assert!(result.is_ok());
+        let pdf_bytes = result.unwrap();
+        assert!(!pdf_bytes.is_empty());
+        // PDF files should start with "%PDF-"
+        assert!(pdf_bytes.starts_with(b"%PDF-"));
+    }
+
+    #[test]
+    fn test_render_to_bytes_empty_document() {
+        let pdf = create_test_pdf(vec![]);
+        let doc = pdf.render_into_document();
+        let result = Pdf::render_to_bytes(doc);
+
+        assert!(result.is_ok());
+        let pdf_bytes = result.unwrap();
+        assert!(!pdf_bytes.is_empty());
+        assert!(pdf_bytes.starts_with(b"%PDF-"));
+    }
+
+    #[test]
+    fn test_render_to_bytes_complex_content() {
+        let tokens = vec![
+            Token::Heading(vec![Token::Text("Main Title".to_string())], 1),
+            Token::Text("Introduction paragraph.".to_string()),
+            Token::Heading(vec![Token::Text("Section 1".to_string())], 2),
+            Token::ListItem {
+                content: vec![Token::Text("First item".to_string())],
+                ordered: false,
+                number: None,
+            },
+            Token::ListItem {
+                content: vec![Token::Text("Second item".to_string())],
+                ordered: false,
+                number: None,
+            },
+            Token::Code(
+                "rust".to_string(),
+                "fn main() {\n    println!(\"Hello\");\n}".to_string(),
+            ),
+            Token::Link(
+                "Example Link".to_string(),
+                "https://example.com".to_string(),
+            ),
+        ];
+        let pdf = create_test_pdf(tokens);
+        let doc = pdf.render_into_document();
+        let result = Pdf::render_to_bytes(doc);
+
+        assert!(result.is_ok());
+        let pdf_bytes = result.unwrap();
+        assert!(!pdf_bytes.is_empty());
+        assert!(pdf_bytes.starts_with(b"%PDF-"));
+    }
+
+    #[test]
+    fn test_split_abbreviations_respects_word_boundaries() {
+        let abbreviations = vec![
+            ("HTML".to_string(), "HyperText Markup Language".to_string()),
+            ("W3C".to_string(), "World Wide Web Consortium".to_string()),
+        ];
+        let segments = split_abbreviations("HTML5 and HTML by the W3C.", &abbreviations);
+        assert_eq!(
+            segments,
+            vec![
+                ("HTML5 and ".to_string(), None),
+                ("HTML".to_string(), Some(0)),
+                (" by the ".to_string(), None),
+                ("W3C".to_string(), Some(1)),
+                (".".to_string(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_font_ranges() {
+        let cjk = FontRange {
+            start: 0x4E00,
+            end: 0x9FFF,
+            font: "Noto Sans CJK SC".to_string(),
+        };
+        let runs = split_font_ranges("Hello 中文 世界 and more", &[(cjk, 0)]);
+        assert_eq!(
+            runs,
+            vec![
+                ("Hello ".to_string(), None),
+                ("中文 世界 ".to_string(), Some(0)),
+                ("and more".to_string(), None),
+            ]
+        );
+        assert!(split_font_ranges("", &[]).is_empty());
+    }
+
+    #[test]
+    fn test_render_with_font_ranges() {
+        let mut style = StyleMatch::default();
+        style.font_ranges = vec![FontRange {
+            start: 0x41,
+            end: 0x5A,
+            font: "space mono".to_string(),
+        }];
+        let pdf = Pdf::new(
+            vec![Token::Text("UPPER and lower".to_string())],
+            style,
+            None,
+        );
+        assert_eq!(pdf.font_ranges.len(), 1);
+        assert!(Pdf::render(pdf.render_into_document(), "/dev/null").is_none());
+    }
+
+    #[test]
+    fn test_render_inline_footnotes() {
+        let tokens = vec![
+            Token::Text("First".to_string()),
+            Token::FootnoteInline(vec![Token::Text("One".to_string())]),
+            Token::Text(" and second".to_string()),
+            Token::FootnoteInline(vec![
+                Token::Text("Two".to_string()),
+                Token::FootnoteInline(vec![Token::Text("Nested".to_string())]),
+            ]),
+        ];
+        let pdf = create_test_pdf(tokens);
+        let doc = pdf.render_into_document();
+        let footnotes = pdf.footnotes.borrow().clone();
+        assert_eq!(footnotes.len(), 3);
+        assert_eq!(footnotes[0], vec![Token::Text("One".to_string())]);
+        assert_eq!(footnotes[2], vec![Token::Text("Nested".to_string())]);
+        assert!(Pdf::render(doc, "/dev/null").is_none());
+    }
+
+    #[test]
+    fn test_fallback_image_replaces_missing_images() {
+        let tokens = vec![
+            Token::Image("gone".to_string(), "does-not-exist.png".to_string()),
+            Token::Newline,
+            Token::ImageWithLink(
+                "gone".to_string(),
+                "does-not-exist.png".to_string(),
+                "https://example.com".to_string(),
+            ),
+        ];
+        let mut style = StyleMatch::default();
+        style.fallback_image =
+            Some(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/images/test_image.jpg").to_string());
+        let pdf = Pdf::new(tokens, style, None);
+        assert!(pdf.fallback_image.is_some());
+        assert!(Pdf::render_to_bytes(pdf.render_into_document()).is_ok());
+        // The original load failures are still reported
+        let missing = |pdf: &Pdf| {
+            pdf.warnings()
+                .iter()
+                .filter(|w| w.kind == crate::validation::WarningKind::MissingImage)
+                .count()
+        };
+        assert_eq!(missing(&pdf), 2);
+
+        // The fallback is sized like the image it replaces
+        let image_scales = |url: &str| {
+            let mut style = StyleMatch::default();
+            style.image_target_dpi = Some(600.0);
+            style.fallback_image = Some(
+                concat!(env!("CARGO_MANIFEST_DIR"), "/tests/images/test_image.jpg").to_string(),
+            );
+            let pdf = Pdf::new(
+                vec![Token::Image("alt".to_string(), url.to_string())],
+                style,
+                None,
+            );
+            let bytes = Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+            let doc = lopdf::Document::load_mem(&bytes).unwrap();
+            let page_id = *doc.get_pages().values().next().unwrap();
+            lopdf::content::Content::decode(&doc.get_page_content(page_id).unwrap())
+                .unwrap()
+                .operations
+                .into_iter()
+                .filter(|op| op.operator == "cm")
+                .map(|op| op.operands[0].as_float().unwrap())
+                .collect::<Vec<f32>>()
+        };
+        let direct = image_scales(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/images/test_image.jpg"
+        ));
+        assert!(!direct.is_empty());
+        assert_eq!(image_scales("does-not-exist.png"), direct);
+
+        // An unusable fallback is reported and the text placeholder is kept
+        let mut style = StyleMatch::default();
+        style.fallback_image = Some("missing-fallback.png".to_string());
+        let pdf = Pdf::new(vec![], style, None);
+        assert!(pdf.fallback_image.is_none());
+        assert_eq!(missing(&pdf), 1);
+    }
+
+    #[test]
+    fn test_title_from_h1() {
+        let tokens = vec![
+            Token::Heading(vec![Token::Text("User Guide".to_string())], 1),
+            Token::Newline,
+            Token::Text("Body".to_string()),
+        ];
+
+        let pdf = Pdf::new(tokens.clone(), StyleMatch::default(), None);
+        assert_eq!(pdf.title, None);
+        assert_eq!(pdf.input, tokens);
+
+        let mut style = StyleMatch::default();
+        style.document.title_from_h1 = true;
+        let pdf = Pdf::new(tokens, style, None);
+        assert_eq!(pdf.title.as_deref(), Some("User Guide"));
+        assert!(!pdf.input.iter().any(|t| matches!(t, Token::Heading(..))));
+        assert!(Pdf::render_to_bytes(pdf.render_into_document()).is_ok());
+    }
+
+    #[test]
+    fn test_render_tab_group() {
+        let tokens = vec![
+            Token::Text("Intro".to_string()),
+            Token::TabGroup(vec![
+                (
+                    "Rust".to_string(),
+                    vec![Token::Code(
+                        "rust".to_string(),
+                        "fn main() {\n}".to_string(),
+                    )],
+                ),
+                (
+                    "Shell".to_string(),
+                    vec![
+                        Token::Text("Press ".to_string()),
+                        Token::Kbd("Enter".to_string()),
+                    ],
+                ),
+            ]),
+        ];
+        let pdf = create_test_pdf(tokens);
+        let bytes = Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+        assert!(bytes.starts_with(b"%PDF"));
+        // Inline content inside a tab is rendered like top-level content
+        assert!(pdf.has_decorations());
+    }
+
+    #[test]
+    fn test_render_page_break_before_headings() {
+        let tokens = vec![
+            Token::Heading(vec![Token::Text("Chapter 1".to_string())], 1),
+            Token::Text("Body".to_string()),
+            Token::Newline,
+            Token::Heading(vec![Token::Text("Chapter 2".to_string())], 1),
+            Token::Text("Body".to_string()),
+        ];
+        let mut style = StyleMatch::default();
+        style.page_break_before[0] = true;
+        let pdf = Pdf::new(tokens, style, None);
+        let bytes = Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+
+        // No blank page before the first chapter, a new page for the second one
+        let doc = lopdf::Document::load_mem(&bytes).unwrap();
+        assert_eq!(doc.get_pages().len(), 2);
+    }
+
+    #[test]
+    fn test_render_abbreviations() {
+        let tokens = vec![
+            Token::AbbreviationDefinition(
+                "HTML".to_string(),
+                "HyperText Markup Language".to_string(),
+            ),
+            Token::Text("HTML is used twice: HTML.".to_string()),
+        ];
+        let pdf = create_test_pdf(tokens);
+        let doc = pdf.render_into_document();
+        assert_eq!(*pdf.used_abbreviations.borrow(), vec![0]);
+        assert!(Pdf::render(doc, "/dev/null").is_none());
+    }
+
+    #[test]
+    fn test_render_abbreviation_size_and_underline() {
+        let tokens = vec![
+            Token::AbbreviationDefinition("CPU".to_string(), "Central Processing Unit".to_string()),
+            Token::Text("The CPU runs.".to_string()),
+        ];
+        let mut style = StyleMatch::default();
+        style.abbreviations.style.size = style.text.size * 2;
+        let pdf = Pdf::new(tokens.clone(), style.clone(), None);
+        let bytes = Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+        // Underlined by default, which draws a line under the occurrence
+        assert!(pdf.has_decorations());
+
+        let doc = lopdf::Document::load_mem(&bytes).unwrap();
+        let (_, page_id) = doc.get_pages().into_iter().next().unwrap();
+        let operations = lopdf::content::Content::decode(&doc.get_page_content(page_id).unwrap())
+            .unwrap()
+            .operations;
+        let sizes: Vec<f32> = operations
+            .iter()
+            .filter(|op| op.operator == "Tf")
+            .map(|op| op.operands[1].as_float().unwrap())
+            .collect();
+        assert!(sizes.contains(&(style.text.size as f32)));
+        assert!(sizes.contains(&(style.abbreviations.style.size as f32)));
+
+        style.abbreviations.style.underline = false;
+        let pdf = Pdf::new(tokens, style, None);
+        pdf.render_into_document();
+        assert!(!pdf.has_decorations());
+    }
+
+    #[test]
+    fn test_format_pdf_date() {
+        assert_eq!(format_pdf_date(0), "D:19700101000000+00'00'");
+        assert_eq!(format_pdf_date(951_782_400), "D:20000229000000+00'00'");
+        assert_eq!(format_pdf_date(1_700_000_000), "D:20231114221320+00'00'");
+    }
+
+    #[test]
+    fn test_make_reproducible_is_deterministic() {
+        let render = || {
+            let pdf = create_test_pdf(vec![Token::Text("Same input".to_string())]);
+            let bytes = Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+            Pdf::make_reproducible(&bytes, 1_700_000_000).unwrap()
+        };
+        let first = render();
+        let second = render();
+        assert_eq!(first, second);
+
+        let doc = lopdf::Document::load_mem(&first).unwrap();
+        let id = doc.trailer.get(b"ID").unwrap().as_array().unwrap();
+        assert_eq!(id.len(), 2);
+        assert_eq!(id[0].as_str().unwrap().len(), 16);
+        assert_eq!(id[0].as_str().unwrap(), id[1].as_str().unwrap());
+        let info_id = doc.trailer.get(b"Info").unwrap().as_reference().unwrap();
+        let info = doc.get_object(info_id).unwrap().as_dict().unwrap();
+        assert_eq!(
+            info.get(b"CreationDate").unwrap().as_str().unwrap(),
+            b"D:20231114221320+00'00'"
+        );
+    }
+
+    #[test]
+    fn test_set_language() {
+        let pdf = create_test_pdf(vec![Token::Text("Grüezi".to_string())]);
+        assert_eq!(pdf.language(), None);
+
+        let mut style = StyleMatch::default();
+        style.document.lang = Some("de-CH".to_string());
+        let pdf = Pdf::new(vec![Token::Text("Grüezi".to_string())], style, None);
+        assert_eq!(pdf.language(), Some("de-CH"));
+
+        let bytes = Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+        let bytes = Pdf::set_language(&bytes, pdf.language().unwrap()).unwrap();
+        let doc = lopdf::Document::load_mem(&bytes).unwrap();
+        let root_id = doc.trailer.get(b"Root").unwrap().as_reference().unwrap();
+        let root = doc.get_dictionary(root_id).unwrap();
+        assert_eq!(root.get(b"Lang").unwrap().as_str().unwrap(), b"de-CH");
+    }
+
+    #[test]
+    fn test_embed_source_attaches_markdown() {
+        let markdown = "# Notes\nSee <a id=\"here\"></a>[here](#here)\n";
+        let mut style = StyleMatch::default();
+        style.document.attach_source = true;
+        let tokens = crate::markdown::Lexer::new(markdown.to_string())
+            .parse()
+            .unwrap();
+        let mut pdf = Pdf::with_document_path(
+            tokens,
+            style,
+            None,
+            Some(std::path::Path::new("docs/notes.md")),
+        );
+        pdf.set_source(markdown.to_string());
+        let (name, source) = pdf.source_attachment().unwrap();
+        assert_eq!((name, source), ("notes.md", markdown));
+
+        let bytes = Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+        let bytes = Pdf::resolve_anchors(&bytes).unwrap();
+        let attached = Pdf::embed_source(&bytes, name, source).unwrap();
+
+        let doc = lopdf::Document::load_mem(&attached).unwrap();
+        let root_id = doc.trailer.get(b"Root").unwrap().as_reference().unwrap();
+        let root = doc.get_dictionary(root_id).unwrap();
+        let names = root.get(b"Names").unwrap().as_dict().unwrap();
+        // Existing name trees are kept next to the attachment
+        assert!(names.get(b"Dests").is_ok());
+        let files = names.get(b"EmbeddedFiles").unwrap().as_dict().unwrap();
+        let entries = files.get(b"Names").unwrap().as_array().unwrap();
+        assert_eq!(entries[0].as_str().unwrap(), b"notes.md");
+
+        let spec_id = entries[1].as_reference().unwrap();
+        let spec = doc.get_dictionary(spec_id).unwrap();
+        let file_id = spec
+            .get(b"EF")
+            .and_then(|ef| ef.as_dict())
+            .and_then(|ef| ef.get(b"F"))
+            .and_then(|f| f.as_reference())
+            .unwrap();
+        let stream = doc.get_object(file_id).unwrap().as_stream().unwrap();
+        assert_eq!(stream.content, markdown.as_bytes());
+        assert_eq!(root.get(b"AF").unwrap().as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_embed_source_keeps_names_sorted() {
+        let pdf = create_test_pdf(vec![Token::Text("Hello".to_string())]);
+        let bytes = Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+        let bytes = Pdf::embed_source(&bytes, "b.md", "b").unwrap();
+        let bytes = Pdf::embed_source(&bytes, "c.md", "c").unwrap();
+        let bytes = Pdf::embed_source(&bytes, "a.md", "a").unwrap();
+        let bytes = Pdf::embed_source(&bytes, "bb.md", "bb").unwrap();
+
+        let doc = lopdf::Document::load_mem(&bytes).unwrap();
+        let root_id = doc.trailer.get(b"Root").unwrap().as_reference().unwrap();
+        let root = doc.get_dictionary(root_id).unwrap();
+        let names = root.get(b"Names").unwrap().as_dict().unwrap();
+        let files = names.get(b"EmbeddedFiles").unwrap().as_dict().unwrap();
+        let entries = files.get(b"Names").unwrap().as_array().unwrap();
+        let keys: Vec<&[u8]> = entries
+            .chunks(2)
+            .map(|pair| pair[0].as_str().unwrap())
+            .collect();
+        assert_eq!(keys, vec![&b"a.md"[..], b"b.md", b"bb.md", b"c.md"]);
+    }
+
+    #[test]
+    fn test_set_source_requires_attach_source() {
+        let mut pdf = create_test_pdf(vec![Token::Text("Hello".to_string())]);
+        pdf.set_source("Hello".to_string());
+        assert!(pdf.source_attachment().is_none());
+    }
+
+    #[test]
+    fn test_resolve_anchors_creates_named_destination() {
+        let pdf = create_test_pdf(vec![
+            Token::Link("jump".to_string(), "#target".to_string()),
+            Token::Newline,
+            Token::Text("Somewhere ".to_string()),
+            Token::Anchor("target".to_string()),
+            Token::Text("else".to_string()),
+        ]);
+        assert!(pdf.has_anchors());
+
+        let bytes = Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+        let resolved = Pdf::resolve_anchors(&bytes).unwrap();
+
+        let doc = lopdf::Document::load_mem(&resolved).unwrap();
+        let root_id = doc.trailer.get(b"Root").unwrap().as_reference().unwrap();
+        let root = doc.get_dictionary(root_id).unwrap();
+        let names = root.get(b"Names").unwrap().as_dict().unwrap();
+        let dests_id = names.get(b"Dests").unwrap().as_reference().unwrap();
+        let dests = doc.get_dictionary(dests_id).unwrap();
+        let entries = dests.get(b"Names").unwrap().as_array().unwrap();
+        assert_eq!(entries[0].as_str().unwrap(), b"target");
+    }
+
+    #[test]
+    fn test_markers_cover_the_next_word() {
+        let pdf = create_test_pdf(vec![
+            Token::Text("Somewhere ".to_string()),
+            Token::Anchor("target".to_string()),
+            Token::Text("else entirely".to_string()),
+            Token::Anchor("end".to_string()),
+        ]);
+        let bytes = Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+        assert!(pdf.pending_markers.borrow().is_empty());
+
+        let doc = lopdf::Document::load_mem(&bytes).unwrap();
+        let widths: std::collections::HashMap<Vec<u8>, f32> = doc
+            .objects
+            .keys()
+            .filter_map(|&id| {
+                let uri = link_uri(&doc, id)?;
+                let rect = doc
+                    .get_dictionary(id)
+                    .ok()?
+                    .get(b"Rect")
+                    .ok()?
+                    .as_array()
+                    .ok()?;
+                let width = rect[2].as_float().ok()? - rect[0].as_float().ok()?;
+                Some((uri, width))
+            })
+            .collect();
+        // The anchor covers "else" instead of adding a space before it; with no word
+        // left, the last one falls back to a space at the end of the line
+        let anchor = |name: &str| widths[format!("{}{}", ANCHOR_URI_PREFIX, name).as_bytes()];
+        assert!(anchor("target") > 2.0 * anchor("end"));
+    }
+
+    #[test]
+    fn test_resolve_anchors_links_footnotes_both_ways() {
+        let pdf = create_test_pdf(vec![
+            Token::Text("Claim".to_string()),
+            Token::FootnoteInline(vec![Token::Text("Source".to_string())]),
+        ]);
+        let bytes = Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+        assert!(pdf.has_anchors());
+        let resolved = Pdf::resolve_anchors(&bytes).unwrap();
+
+        let doc = lopdf::Document::load_mem(&resolved).unwrap();
+        let root_id = doc.trailer.get(b"Root").unwrap().as_reference().unwrap();
+        let root = doc.get_dictionary(root_id).unwrap();
+        let names = root.get(b"Names").unwrap().as_dict().unwrap();
+        let dests_id = names.get(b"Dests").unwrap().as_reference().unwrap();
+        let dests = doc.get_dictionary(dests_id).unwrap();
+        let entries = dests.get(b"Names").unwrap().as_array().unwrap();
+        assert_eq!(entries[0].as_str().unwrap(), b"fn-1");
+        assert_eq!(entries[2].as_str().unwrap(), b"fnref-1");
+
+        let targets: Vec<Vec<u8>> = doc
+            .objects
+            .values()
+            .filter_map(|o| o.as_dict().ok())
+            .filter_map(|d| d.get(b"A").ok()?.as_dict().ok())
+            .filter(|a| a.get(b"S").and_then(|s| s.as_name()).ok() == Some(&b"GoTo"[..]))
+            .filter_map(|a| {
+                a.get(b"D")
+                    .and_then(|d| d.as_str())
+                    .ok()
+                    .map(|d| d.to_vec())
+            })
+            .collect();
+        assert!(targets.contains(&b"fn-1".to_vec()));
+        assert!(targets.contains(&b"fnref-1".to_vec()));
+    }
+
+    #[test]
+    fn test_draw_decorations() {
+        let pdf = create_test_pdf(vec![
+            Token::Text("Press ".to_string()),
+            Token::Kbd("Ctrl".to_string()),
+            Token::Text("+".to_string()),
+            Token::Kbd("⌘".to_string()),
+        ]);
+        let bytes = Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+        assert!(pdf.has_decorations());
+
+        let drawn = Pdf::draw_decorations(&bytes).unwrap();
+        let doc = lopdf::Document::load_mem(&drawn).unwrap();
+        let (_, page_id) = doc.get_pages().into_iter().next().unwrap();
+        // The markers are gone and the box stream comes first
+        let page = doc.get_dictionary(page_id).unwrap();
+        let annotations = match page.get(b"Annots") {
+            Ok(annots) => doc.dereference(annots).unwrap().1.as_array().unwrap().len(),
+            Err(_) => 0,
+        };
+        assert_eq!(annotations, 0);
+        let contents = page.get(b"Contents").unwrap().as_array().unwrap();
+        let stream_id = contents[0].as_reference().unwrap();
+        let stream = doc.get_object(stream_id).unwrap().as_stream().unwrap();
+        assert!(String::from_utf8_lossy(&stream.content).contains(" B\n"));
+    }
+
+    #[test]
+    fn test_render_block_quote_attribution() {
+        let tokens = vec![
+            Token::BlockQuote("Simplicity is prerequisite for reliability.".to_string()),
+            Token::SoftBreak,
+            Token::BlockQuote("— Edsger W. Dijkstra".to_string()),
+        ];
+        assert!(is_block_quote(tokens[1..].iter()));
+        assert!(!is_block_quote(tokens[..0].iter()));
+
+        let page_content = |style: StyleMatch| {
+            let pdf = Pdf::new(tokens.clone(), style, None);
+            let bytes = Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+            let doc = lopdf::Document::load_mem(&bytes).unwrap();
+            let (_, page_id) = doc.get_pages().into_iter().next().unwrap();
+            doc.get_page_content(page_id).unwrap()
+        };
+
+        let plain = page_content(StyleMatch::default());
+        let mut style = StyleMatch::default();
+        style.block_quote_attribution = Some(style.block_quote);
+        // Right-aligned, the attribution moves away from the quote indent
+        assert_ne!(page_content(style), plain);
+    }
+
+    #[test]
+    fn test_render_trim_edges() {
+        let text_lines = |trim_edges: bool| {
+            let mut style = StyleMatch::default();
+            style.heading_1.before_spacing = 3.0;
+            style.heading_1.after_spacing = 1.0;
+            style.spacing.trim_edges = trim_edges;
+            let pdf = Pdf::new(
+                vec![
+                    Token::Heading(vec![Token::Text("Title".to_string())], 1),
+                    Token::Text("Body text".to_string()),
+                ],
+                style,
+                None,
+            );
+            let bytes = Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+            let doc = lopdf::Document::load_mem(&bytes).unwrap();
+            let (_, page_id) = doc.get_pages().into_iter().next().unwrap();
+            let content = doc.get_page_content(page_id).unwrap();
+            lopdf::content::Content::decode(&content)
+                .unwrap()
+                .operations
+                .iter()
+                .filter(|op| op.operator == "Td" || op.operator == "Tm")
+                .map(|op| op.operands.last().unwrap().as_float().unwrap())
+                .collect::<Vec<f32>>()
+        };
+
+        let spaced = text_lines(false);
+        let trimmed = text_lines(true);
+        assert_eq!(spaced.len(), trimmed.len());
+        // The heading moves up to the top margin, the spacing after it is kept
+        assert!(trimmed[0] > spaced[0]);
+        assert!((spaced[0] - spaced[1] - (trimmed[0] - trimmed[1])).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_render_text_max_width() {
+        let first_x = |max_width: Option<MaxWidth>| {
+            let mut style = StyleMatch::default();
+            style.text_max_width = max_width;
+            let pdf = Pdf::new(vec![Token::Text("Body text".to_string())], style, None);
+            let bytes = Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+            let doc = lopdf::Document::load_mem(&bytes).unwrap();
+            let (_, page_id) = doc.get_pages().into_iter().next().unwrap();
+            let content = doc.get_page_content(page_id).unwrap();
+            lopdf::content::Content::decode(&content)
+                .unwrap()
+                .operations
+                .iter()
+                .find(|op| op.operator == "Td" || op.operator == "Tm")
+                .map(|op| op.operands[op.operands.len() - 2].as_float().unwrap())
+                .unwrap()
+        };
+
+        let full = first_x(None);
+        // A narrow column is centered, so the text starts further right
+        assert!(first_x(Some(MaxWidth::Points(200.0))) > full + 1.0);
+        assert!(first_x(Some(MaxWidth::Characters(40))) > full + 1.0);
+        // A limit wider than the page keeps the full width
+        assert_eq!(first_x(Some(MaxWidth::Points(5000.0))), full);
+    }
+
+    #[test]
+    fn test_render_table_multi_line_cell() {
+        let headers = vec![vec![Token::Text("Notes".to_string())]];
+        let one_line = vec![vec![vec![Token::Text("a longer first line".to_string())]]];
+        let two_lines = vec![vec![vec![
+            Token::Text("a longer first line".to_string()),
+            Token::LineBreak,
+            Token::Text("second".to_string()),
+        ]]];
+
+        let pdf = create_test_pdf(vec![Token::Table {
+            headers: headers.clone(),
+            aligns: vec![Alignment::Left],
+            rows: two_lines.clone(),
+        }]);
+        // Lines are measured separately, so the second line does not widen the column
+        assert_eq!(
+            pdf.measure_table_columns(&headers, &two_lines),
+            pdf.measure_table_columns(&headers, &one_line)
+        );
+        let bytes = Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+        assert!(!pdf.warnings().iter().any(|w| w.message.contains("table")));
+
+        // The text after the `<br>` starts a line of its own below the first one,
+        // aligned with it
+        let positions = text_positions(&bytes);
+        let first = positions.len() - 2;
+        let (first_x, first_y) = positions[first];
+        let (second_x, second_y) = positions[first + 1];
+        assert!(second_y < first_y);
+        assert!((second_x - first_x).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_render_nested_table() {
+        let text = |s: &str| vec![Token::Text(s.to_string())];
+        let nested = Token::Table {
+            headers: vec![text("x"), text("1")],
+            aligns: vec![Alignment::Left, Alignment::Right],
+            rows: vec![vec![text("y"), text("2")]],
+        };
+        let pdf = create_test_pdf(vec![Token::Table {
+            headers: vec![text("Key"), text("Values")],
+            aligns: vec![Alignment::Left, Alignment::Left],
+            rows: vec![
+                vec![
+                    text("a"),
+                    vec![
+                        Token::Text("Pairs:".to_string()),
+                        nested,
+                        Token::Text("end".to_string()),
+                    ],
+                ],
+                vec![text("b"), text("none")],
+            ],
+        }]);
+        Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+        assert!(!pdf.warnings().iter().any(|w| w.message.contains("table")));
+    }
+
+    #[test]
+    fn test_render_math_in_tables_and_lists() {
+        let markdown =
+            "| $x^2$ | value |\n|---|---|\n| $$\\sqrt{y}$$ | $a+b$ |\n\n- Square $x^2$\n";
+        let tokens = crate::markdown::Lexer::new(markdown.to_string())
+            .parse()
+            .unwrap();
+        let is_math = |tokens: &[Token]| matches!(tokens, [Token::Math { .. }]);
+        match &tokens[0] {
+            Token::Table { headers, rows, .. } => {
+                assert!(is_math(&headers[0]));
+                assert!(is_math(&rows[0][0]) && is_math(&rows[0][1]));
+            }
+            other => panic!("expected a table, got {:?}", other),
+        }
+        assert!(tokens.iter().any(|token| matches!(
+            token,
+            Token::ListItem { content, .. }
+                if content.iter().any(|t| matches!(t, Token::Math { .. }))
+        )));
+
+        let pdf = create_test_pdf(tokens);
+        let bytes = Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+        assert!(!pdf.warnings().iter().any(|w| w.message.contains("table")));
+        // Table cells set their math as text, converted to Unicode with `math-rust`
+        #[cfg(feature = "math-rust")]
+        let math = crate::math::to_unicode("x^2");
+        #[cfg(not(feature = "math-rust"))]
+        let math = "x^2".to_string();
+        let text = lopdf::Document::load_mem(&bytes)
+            .unwrap()
+            .extract_text(&[1])
+            .unwrap();
+        assert!(text.contains(&math), "{:?} not found in {:?}", math, text);
+    }
+
+    #[test]
+    fn test_render_ragged_table() {
+        let text = |s: &str| vec![Token::Text(s.to_string())];
+        let pdf = create_test_pdf(vec![Token::Table {
+            headers: vec![text("A"), text("B"), text("C")],
+            aligns: vec![Alignment::Left; 3],
+            rows: vec![
+                vec![text("1")],
+                vec![text("2"), text("3"), text("4"), text("5")],
+                vec![],
+            ],
+        }]);
+        Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+        // Short rows are padded and long rows cut, so no row is skipped
+        assert!(!pdf.warnings().iter().any(|w| w.message.contains("table")));
+    }
+
+    #[test]
+    fn test_render_shortcuts() {
+        let keys = |keys: &[&str]| keys.iter().map(|k| k.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            parse_shortcuts("Ctrl+Shift+P | Open the *command* palette\n\nCtrl++ | Zoom in\n"),
+            Some(vec![
+                (
+                    keys(&["Ctrl", "Shift", "P"]),
+                    "Open the *command* palette".to_string()
+                ),
+                (keys(&["Ctrl", "+"]), "Zoom in".to_string()),
+            ])
+        );
+        assert_eq!(parse_shortcuts("Ctrl+S saves"), None);
+        assert_eq!(parse_shortcuts(" | Nothing"), None);
+        assert_eq!(parse_shortcuts("\n"), None);
+
+        let pdf = create_test_pdf(vec![Token::Code(
+            "shortcuts".to_string(),
+            "Ctrl+S | Save\nF5 | Run".to_string(),
+        )]);
+        Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+        assert!(pdf.has_decorations());
+
+        let pdf = create_test_pdf(vec![Token::Code(
+            "shortcuts".to_string(),
+            "Ctrl+S saves\nF5 | Run".to_string(),
+        )]);
+        Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+        assert!(!pdf.has_decorations());
+        assert!(pdf
+            .warnings()
+            .iter()
+            .any(|w| w.message.contains("Shortcuts block")));
+    }
+
+    #[test]
+    fn test_heading_text_transform() {
+        let style = genpdfi_extended::style::Style::new().with_font_size(20);
+        let runs: Vec<(String, u8)> = small_caps_runs("Hello, World", style)
+            .into_iter()
+            .map(|(run, style)| (run, style.font_size()))
+            .collect();
+        assert_eq!(
+            runs,
+            [("H", 20), ("ELLO", 16), (", W", 20), ("ORLD", 16)]
+                .map(|(run, size)| (run.to_string(), size))
+        );
+
+        let mut style = StyleMatch::default();
+        style.heading_1.text_transform = TextTransform::Uppercase;
+        style.heading_2.text_transform = TextTransform::SmallCaps;
+        let pdf = Pdf::new(
+            vec![
+                Token::Heading(vec![Token::Text("Chapter".to_string())], 1),
+                Token::Heading(vec![Token::Text("Section".to_string())], 2),
+                Token::Text("Body text".to_string()),
+            ],
+            style,
+            None,
+        );
+        assert!(Pdf::render_to_bytes(pdf.render_into_document()).is_ok());
+        // The transform only lasts for the heading
+        assert_eq!(pdf.text_transform.get(), TextTransform::None);
+    }
+
+    #[test]
+    fn test_render_heading_letter_spacing() {
+        let mut style = StyleMatch::default();
+        style.heading_1.letter_spacing = 1.5;
+        // Runs of the heading keep their own styling and are tracked one by one
+        let heading = vec![
+            Token::Text("CHAPTER ".to_string()),
+            Token::Emphasis {
+                level: 1,
+                content: vec![Token::Text("ONE".to_string())],
+            },
+        ];
+        let pdf = Pdf::new(
+            vec![
+                Token::Heading(heading.clone(), 1),
+                Token::Text("Body text".to_string()),
+            ],
+            style.clone(),
+            None,
+        );
+        let bytes = Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+        assert!(pdf.has_decorations());
+        // The lines are laid out narrower by the width the spacing adds
+        assert!(pdf.tracked_width(&heading, &style.heading_1) > 1.0);
+        assert_eq!(pdf.tracked_width(&heading, &style.heading_2), 1.0);
+
+        let drawn = Pdf::draw_decorations(&bytes).unwrap();
+        let doc = lopdf::Document::load_mem(&drawn).unwrap();
+        let (_, page_id) = doc.get_pages().into_iter().next().unwrap();
+        let content = doc.get_page_content(page_id).unwrap();
+        let operations = lopdf::content::Content::decode(&content)
+            .unwrap()
+            .operations;
+        let spacings: Vec<f32> = operations
+            .iter()
+            .filter(|op| op.operator == "Tc")
+            .map(|op| op.operands[0].as_float().unwrap())
+            .collect();
+        // Both heading runs are tracked and the spacing is reset before the body text
+        let tracked = spacings.iter().filter(|&&spacing| spacing == 1.5);
+        assert_eq!(tracked.count(), 2);
+        assert!(spacings.iter().all(|&s| s == 1.5 || s == 0.0));
+        assert_eq!(spacings.last(), Some(&0.0));
+        let page = doc.get_dictionary(page_id).unwrap();
+        let annotations = match page.get(b"Annots") {
+            Ok(annots) => doc.dereference(annots).unwrap().1.as_array().unwrap().len(),
+            Err(_) => 0,
+        };
+        assert_eq!(annotations, 0);
+    }
+
+    #[test]
+    fn test_append_contact_sheet() {
+        let tokens = (0..3)
+            .map(|i| Token::Heading(vec![Token::Text(format!("Part {}", i))], 1))
+            .collect();
+        let mut style = StyleMatch::default();
+        style.page_break_before[0] = true;
+        style.document.contact_sheet = true;
+        let pdf = Pdf::new(tokens, style, None);
+        assert!(pdf.has_contact_sheet());
+        let bytes = Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+        let page_count = lopdf::Document::load_mem(&bytes).unwrap().get_pages().len();
+        assert_eq!(page_count, 3);
+
+        let sheet = Pdf::append_contact_sheet(&bytes).unwrap();
+        let doc = lopdf::Document::load_mem(&sheet).unwrap();
+        let pages = doc.get_pages();
+        // All thumbnails fit on one sheet
+        assert_eq!(pages.len(), page_count + 1);
+        let last = doc.get_dictionary(pages[&(page_count as u32 + 1)]).unwrap();
+        let resources = last.get(b"Resources").unwrap().as_dict().unwrap();
+        let forms = resources.get(b"XObject").unwrap().as_dict().unwrap();
+        assert_eq!(forms.len(), page_count);
+        let content = doc
+            .get_page_content(pages[&(page_count as u32 + 1)])
+            .unwrap();
+        let operations = lopdf::content::Content::decode(&content)
+            .unwrap()
+            .operations;
+        assert_eq!(
+            operations.iter().filter(|op| op.operator == "Do").count(),
+            page_count
+        );
+    }
+
+    #[test]
+    fn test_render_orientation_sections() {
+        let comment = |text: &str| Token::HtmlComment(text.to_string());
+        let tokens = vec![
+            Token::Text("Portrait".to_string()),
+            Token::Newline,
+            comment(" orientation: landscape "),
+            Token::Text("Wide".to_string()),
+            Token::Newline,
+            comment(" orientation: Portrait "),
+            Token::Text("Back".to_string()),
+        ];
+        assert!(
+            !create_test_pdf(vec![comment(" orientation: portrait ")]).has_orientation_changes()
+        );
+        // A directive opening the document turns it as a whole
+        let opening = create_test_pdf(tokens[2..5].to_vec());
+        assert!(opening.has_orientation_changes());
+        let bytes = opening.render_sections().unwrap();
+        assert_eq!(
+            lopdf::Document::load_mem(&bytes).unwrap().get_pages().len(),
+            1
+        );
+
+        let pdf = create_test_pdf(tokens);
+        assert!(pdf.has_orientation_changes());
+        let bytes = pdf.render_sections().unwrap();
+        let doc = lopdf::Document::load_mem(&bytes).unwrap();
+        let pages = doc.get_pages();
+        assert_eq!(pages.len(), 3);
+
+        let media_box = |page_id: lopdf::ObjectId| {
+            let page = doc.get_dictionary(page_id).unwrap();
+            let media_box = page.get(b"MediaBox").cloned().unwrap_or_else(|_| {
+                let parent = page.get(b"Parent").unwrap().as_reference().unwrap();
+                doc.get_dictionary(parent)
+                    .unwrap()
+                    .get(b"MediaBox")
+                    .unwrap()
+                    .clone()
+            });
+            let values: Vec<f32> = media_box
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|v| v.as_float().unwrap())
+                .collect();
+            (values[2] - values[0], values[3] - values[1])
+        };
+        let (width, height) = media_box(pages[&1]);
+        assert!(width < height);
+        let (width, height) = media_box(pages[&2]);
+        assert!(width > height);
+        let (width, height) = media_box(pages[&3]);
+        assert!(width < height);
+
+        // The sections embed the same fonts, which the merged document holds once
+        let font_files = |doc: &lopdf::Document| -> std::collections::HashSet<lopdf::ObjectId> {
+            doc.objects
+                .values()
+                .filter_map(|object| object.as_dict().ok())
+                .filter(|dict| dict.has_type(b"FontDescriptor"))
+                .flat_map(|dict| {
+                    [&b"FontFile"[..], b"FontFile2", b"FontFile3"]
+                        .into_iter()
+                        .filter_map(|key| dict.get(key).and_then(|file| file.as_reference()).ok())
+                        .collect::<Vec<_>>()
+                })
+                .collect()
+        };
+        let single = Pdf::render_to_bytes(pdf.render_section(
+            &pdf.input[..2],
+            pdf.style.orientation,
+            true,
+            true,
+        ))
+        .unwrap();
+        let single = font_files(&lopdf::Document::load_mem(&single).unwrap());
+        assert!(!single.is_empty());
+        assert_eq!(font_files(&doc).len(), single.len());
+
+        // Laid out as a single genpdfi document, the directives cannot be honored
+        assert!(pdf.warnings().is_empty());
+        pdf.render_into_document();
+        assert_eq!(pdf.warnings().len(), 1);
+        assert_eq!(
+            pdf.warnings()[0].kind,
+            crate::validation::WarningKind::RenderFailure
+        );
+        assert_eq!(
+            lopdf::Document::load_mem(&pdf.render_pdf().unwrap())
+                .unwrap()
+                .get_pages()
+                .len(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_code_max_lines() {
+        assert_eq!(truncate_code_lines("a\nb\nc", 0), ("a\nb\nc", 0));
+        assert_eq!(truncate_code_lines("a\nb\nc", 3), ("a\nb\nc", 0));
+        assert_eq!(truncate_code_lines("a\nb\nc\n", 2), ("a\nb", 1));
+
+        let code: String = (0..50).map(|i| format!("let x{} = {};\n", i, i)).collect();
+        let tokens = vec![Token::Code("rust".to_string(), code)];
+        let truncated = |max_lines: usize| {
+            let mut style = StyleMatch::default();
+            style.code_max_lines = max_lines;
+            let pdf = Pdf::new(tokens.clone(), style, None);
+            Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+            pdf.warnings()
+                .iter()
+                .any(|w| w.message.contains("[code] max_lines"))
+        };
+        assert!(truncated(10));
+        assert!(!truncated(50));
+        assert!(!truncated(0));
     }
 
-    /// Renders an image with a hyperlink ([![alt](image)](url))
-    fn render_image_with_link(
-        &self,
-        doc: &mut Document,
-        alt: &str,
-        image_url: &str,
-        link_url: &str,
-    ) {
-        doc.push(genpdfi_extended::elements::Break::new(0.5));
+    #[test]
+    fn test_code_fit_shrink() {
+        let wide = format!("// {}\nshort", "+----".repeat(60));
+        let mut style = StyleMatch::default();
+        let pdf = Pdf::new(Vec::new(), style.clone(), None);
+        assert_eq!(pdf.code_block_font_size(&wide, "    "), style.code.size);
+
+        style.code_fit = CodeFit::Shrink;
+        let pdf = Pdf::new(Vec::new(), style.clone(), None);
+        assert_eq!(pdf.code_block_font_size("short", "    "), style.code.size);
+        let size = pdf.code_block_font_size(&format!("// {}", "+--".repeat(47)), "    ");
+        assert!(size < style.code.size && size > CODE_MIN_FONT_SIZE);
+        // Lines no size can fit stop at the floor
+        assert_eq!(pdf.code_block_font_size(&wide, "    "), CODE_MIN_FONT_SIZE);
+
+        let pdf = Pdf::new(vec![Token::Code("text".to_string(), wide)], style, None);
+        Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+    }
 
-        let mut loader_opt = self.image_loader.borrow_mut();
+    #[test]
+    fn test_code_inline_background() {
+        let tokens = vec![
+            Token::Text("Run ".to_string()),
+            Token::Code(String::new(), "cargo test".to_string()),
+        ];
+        let pdf = create_test_pdf(tokens.clone());
+        Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+        assert!(!pdf.has_decorations());
+
+        let mut style = StyleMatch::default();
+        style.code_inline_background = true;
+        let pdf = Pdf::new(tokens, style, None);
+        let bytes = Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+        assert!(pdf.has_decorations());
+
+        let drawn = Pdf::draw_decorations(&bytes).unwrap();
+        let doc = lopdf::Document::load_mem(&drawn).unwrap();
+        let (_, page_id) = doc.get_pages().into_iter().next().unwrap();
+        let page = doc.get_dictionary(page_id).unwrap();
+        let contents = page.get(b"Contents").unwrap().as_array().unwrap();
+        let stream_id = contents[0].as_reference().unwrap();
+        let stream = doc.get_object(stream_id).unwrap().as_stream().unwrap();
+        // Filled without a border
+        assert!(String::from_utf8_lossy(&stream.content).contains(" f\n"));
+    }
 
-        if let Some(ref mut loader) = *loader_opt {
-            match loader.load(image_url) {
-                Ok(image_data) => {
-                    // Try to load the image based on its format
-                    match image_data.format {
-                        crate::images::ImageFormat::Svg => {
-                            // For SVG, use native SVG rendering with configuration
-                            match String::from_utf8(image_data.bytes.clone()) {
-                                Ok(svg_string) => {
-                                    match genpdfi_extended::elements::Image::from_svg_string(
-                                        &svg_string,
-                                    ) {
-                                        Ok(image) => {
-                                            // Apply width and scale_factor configuration
-                                            let image = match self.style.svg_config.width {
-                                                SvgWidth::Percentage(percent) => {
-                                                    image.resizing_page_with(percent / 100.0)
-                                                }
-                                                SvgWidth::Pixels(_pixels) => image,
-                                                SvgWidth::Auto => {
-                                                    if self.style.svg_config.scale_factor != 1.0 {
-                                                        image.with_scale(Scale::new(
-                                                            self.style.svg_config.scale_factor,
-                                                            self.style.svg_config.scale_factor,
-                                                        ))
-                                                    } else {
-                                                        image
-                                                    }
-                                                }
-                                            };
-
-                                            let resized_image = image
-                                                .with_link(link_url.to_string())
-                                                .with_alignment(Alignment::Center);
-                                            doc.push(resized_image);
-                                        }
-                                        Err(e) => {
-                                            warn!("Failed to render SVG with link: {}", e);
-                                            let mut para =
-                                                genpdfi_extended::elements::Paragraph::default();
-                                            let style = genpdfi_extended::style::Style::new()
-                                                .with_font_size(self.style.text.size)
-                                                .italic();
-                                            para.push_styled(
-                                                format!("[SVG Image: {}]", alt),
-                                                style,
-                                            );
-                                            doc.push(para);
-                                        }
-                                    }
-                                }
-                                Err(e) => {
-                                    warn!("Failed to decode SVG as UTF-8: {}", e);
-                                    let mut para = genpdfi_extended::elements::Paragraph::default();
-                                    let style = genpdfi_extended::style::Style::new()
-                                        .with_font_size(self.style.text.size)
-                                        .italic();
-                                    para.push_styled(format!("[SVG Image: {}]", alt), style);
-                                    doc.push(para);
-                                }
-                            }
-                        }
-                        _ => {
-                            // For raster formats (JPEG, PNG, WebP, GIF), use from_reader
-                            match genpdfi_extended::elements::Image::from_reader(
-                                std::io::Cursor::new(image_data.bytes),
-                            ) {
-                                Ok(image) => {
-                                    let resized_image = image
-                                        .resizing_page_with(0.8)
-                                        .with_link(link_url.to_string())
-                                        .with_alignment(Alignment::Center);
-                                    doc.push(resized_image);
-                                }
-                                Err(e) => {
-                                    warn!("Failed to create image with link from data: {}", e);
-                                    let mut para = genpdfi_extended::elements::Paragraph::default();
-                                    let style = genpdfi_extended::style::Style::new()
-                                        .with_font_size(self.style.text.size)
-                                        .italic();
-                                    para.push_styled(format!("[Image: {}]", alt), style);
-                                    doc.push(para);
-                                }
-                            }
-                        }
-                    }
-                }
-                Err(e) => {
-                    warn!("Failed to load image {}: {}", image_url, e);
-                    let mut para = genpdfi_extended::elements::Paragraph::default();
-                    let style = genpdfi_extended::style::Style::new()
-                        .with_font_size(self.style.text.size)
-                        .italic();
-                    para.push_styled(format!("[Image not found: {}]", alt), style);
-                    doc.push(para);
-                }
-            }
-        } else {
-            // No loader configured, just show alt text
-            let mut para = genpdfi_extended::elements::Paragraph::default();
-            let style = genpdfi_extended::style::Style::new()
-                .with_font_size(self.style.text.size)
-                .italic();
-            para.push_styled(format!("[Image: {}]", alt), style);
-            doc.push(para);
-        }
+    #[test]
+    fn test_render_task_list() {
+        let item = |checked: bool, text: &str| Token::ListItem {
+            content: vec![Token::TaskCheckbox(checked), Token::Text(text.to_string())],
+            ordered: false,
+            number: None,
+        };
+        let mut style = StyleMatch::default();
+        style.task_list.strikethrough_done = true;
+        style.task_list.done_color = Some((128, 128, 128));
+        let pdf = Pdf::new(vec![item(true, "Done"), item(false, "Todo")], style, None);
+        let bytes = Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+        assert!(pdf.has_decorations());
+
+        let drawn = Pdf::draw_decorations(&bytes).unwrap();
+        let doc = lopdf::Document::load_mem(&drawn).unwrap();
+        let (_, page_id) = doc.get_pages().into_iter().next().unwrap();
+        let page = doc.get_dictionary(page_id).unwrap();
+        let contents = page.get(b"Contents").unwrap().as_array().unwrap();
+        let stream_content = |object: &lopdf::Object| {
+            let id = object.as_reference().unwrap();
+            let stream = doc.get_object(id).unwrap().as_stream().unwrap();
+            String::from_utf8_lossy(&stream.content).to_string()
+        };
+        // Checkbox outlines are drawn below the text, the strike-through line above it
+        assert!(stream_content(&contents[0]).contains(" S\n"));
+        let last = stream_content(contents.last().unwrap());
+        assert!(last.contains("0.502 0.502 0.502 RG"));
+        assert!(last.ends_with(" l\nS\nQ\n"));
+    }
 
-        doc.push(genpdfi_extended::elements::Break::new(0.5));
+    #[test]
+    fn test_fit_table_columns() {
+        // Fits already: layout is left alone
+        assert!(fit_table_columns(&[20.0, 30.0], &[10.0, 10.0], 100.0).is_none());
+
+        // Minimum widths fit: columns share the remaining room by how much more they need
+        let (weights, scale) = fit_table_columns(&[100.0, 10.0], &[20.0, 10.0], 64.0).unwrap();
+        assert_eq!(scale, 1.0);
+        assert_eq!(weights, vec![520, 120]);
+
+        // Even the longest words overflow: the font has to shrink
+        let (weights, scale) = fit_table_columns(&[80.0, 80.0], &[60.0, 60.0], 64.0).unwrap();
+        assert!((scale - 0.5).abs() < 1e-6);
+        assert_eq!(weights, vec![320, 320]);
     }
 
-    /// Renders a display math block ($$...$$).
-    ///
-    /// This method converts LaTeX mathematical expressions to SVG and embeds them
-    /// as centered images in the PDF. Display math is rendered as a block-level
-    /// element with appropriate spacing. The rendering uses dimensional metrics to
-    /// ensure consistent sizing with the surrounding text.
-    #[cfg(feature = "latex")]
-    fn render_math_block(&self, doc: &mut Document, latex_content: &str) {
-        // Add spacing before the math block based on latex style
-        doc.push(genpdfi_extended::elements::Break::new(
-            self.style.latex.before_spacing,
-        ));
+    #[test]
+    fn test_render_wide_table() {
+        let cell = |text: &str| vec![Token::Text(text.to_string())];
+        let long = "a fairly long cell value that needs room";
+        let headers: Vec<_> = (0..8).map(|i| cell(&format!("Column {}", i))).collect();
+        let rows = vec![(0..8).map(|_| cell(long)).collect::<Vec<_>>()];
+        let tokens = vec![Token::Table {
+            aligns: vec![Alignment::Left; 8],
+            headers: headers.clone(),
+            rows: rows.clone(),
+        }];
 
-        // Use genpdfi_extended's native Latex element when the feature is enabled.
-        // Size in points is taken from the latex style.
-        let size_pt = self.style.latex.size as f32;
-        let latex_elem = genpdfi_extended::elements::Latex::new(latex_content.to_string(), size_pt);
+        let pdf = create_test_pdf(tokens.clone());
+        let (natural, _) = pdf.measure_table_columns(&headers, &rows);
+        assert!(natural.iter().sum::<f32>() > StyleMatch::default().page_dimensions().0);
+        assert!(Pdf::render_to_bytes(pdf.render_into_document()).is_ok());
 
-        // Apply configured alignment
-        let align = match self.style.latex.alignment {
-            Some(crate::styling::TextAlignment::Left) => Alignment::Left,
-            Some(crate::styling::TextAlignment::Right) => Alignment::Right,
-            Some(crate::styling::TextAlignment::Center) => Alignment::Center,
-            Some(crate::styling::TextAlignment::Justify) => Alignment::Left,
-            None => Alignment::Center,
+        let mut style = StyleMatch::default();
+        style.table_overflow = TableOverflow::Scroll;
+        let pdf = Pdf::new(tokens, style, None);
+        assert!(Pdf::render_to_bytes(pdf.render_into_document()).is_ok());
+    }
+
+    #[test]
+    fn test_rotate_oversized_table() {
+        use crate::styling::Orientation;
+
+        let cell = |text: &str| vec![Token::Text(text.to_string())];
+        let table = |columns: usize| Token::Table {
+            aligns: vec![Alignment::Left; columns],
+            headers: (0..columns).map(|i| cell(&format!("Field{}", i))).collect(),
+            rows: vec![(0..columns).map(|_| cell("Supercalifragilistic")).collect()],
         };
-        let latex_elem = latex_elem.with_alignment(align);
-        doc.push(latex_elem);
+        // Two columns fit the page, 16 only fit it turned and 40 not even turned
+        let tokens = vec![
+            Token::Text("Before".to_string()),
+            table(2),
+            table(16),
+            table(40),
+            Token::Text("After".to_string()),
+        ];
 
-        // Add spacing after the math block
-        doc.push(genpdfi_extended::elements::Break::new(
-            self.style.latex.after_spacing,
+        // Off by default: the table is shrunk on the page like any other
+        assert!(!create_test_pdf(tokens.clone()).has_orientation_changes());
+
+        let mut style = StyleMatch::default();
+        style.table_rotate_if_oversized = true;
+        let pdf = Pdf::new(tokens, style, None);
+        assert!(pdf.has_orientation_changes());
+        assert_eq!(
+            pdf.orientation_sections(),
+            vec![
+                (Orientation::Portrait, 0..2),
+                (Orientation::Landscape, 2..3),
+                (Orientation::Portrait, 3..5),
+            ]
+        );
+        // The sections are measured once
+        assert!(std::ptr::eq(
+            pdf.orientation_sections(),
+            pdf.orientation_sections()
         ));
+        let bytes = pdf.render_sections().unwrap();
+        assert_eq!(
+            lopdf::Document::load_mem(&bytes).unwrap().get_pages().len(),
+            3
+        );
     }
 
-    #[cfg(not(feature = "latex"))]
-    fn render_math_block(&self, doc: &mut Document, _latex_content: &str) {
-        // Feature disabled: show an informative message instead of rendering
-        let mut para = genpdfi_extended::elements::Paragraph::default();
-        let mut style = genpdfi_extended::style::Style::new().with_font_size(self.style.latex.size);
-        // apply latex text color if present
-        if let Some((r, g, b)) = self.style.latex.text_color {
-            style = style.with_color(genpdfi_extended::style::Color::Rgb(r, g, b));
-        }
-        para.push_styled("need LaTeX feature".to_string(), style);
-        doc.push(para);
+    #[test]
+    fn test_url_break_segments() {
+        assert_eq!(
+            url_break_segments("https://example.com/a?b&c"),
+            vec!["https://", "example.", "com/", "a?", "b&", "c"]
+        );
+        assert_eq!(
+            url_break_segments("www.example.org/"),
+            vec!["www.", "example.", "org/"]
+        );
+        assert_eq!(url_break_segments("plain"), vec!["plain"]);
+        assert_eq!(
+            url_break_segments("https://x.io/ü/é").concat(),
+            "https://x.io/ü/é"
+        );
     }
 
-    /// Renders inline math ($...$).
-    ///
-    /// This method converts inline LaTeX expressions to SVG and embeds them
-    /// as inline images in the paragraph. The rendering uses dimensional metrics
-    /// to ensure consistent sizing with the surrounding text.
-    #[cfg(feature = "latex")]
-    fn render_inline_math_as_image(&self, doc: &mut Document, latex_content: &str) {
-        // Use genpdfi_extended's Latex element in inline mode and size it slightly smaller than configured latex size
-        let size_pt = (self.style.latex.size as f32) * 0.9;
-        let latex_elem = genpdfi_extended::elements::Latex::new(latex_content.to_string(), size_pt);
-        doc.push(latex_elem);
+    #[test]
+    fn test_render_long_url_link() {
+        let url = "https://example.com/a/very/long/path/that/would/not/fit/on/one/line/of/the/page/index.html?query=value&other=thing";
+        let tokens = vec![
+            Token::Text("See ".to_string()),
+            Token::Link(url.to_string(), url.to_string()),
+        ];
+        let pdf = create_test_pdf(tokens);
+        assert!(Pdf::render_to_bytes(pdf.render_into_document()).is_ok());
     }
 
-    #[cfg(not(feature = "latex"))]
-    fn render_inline_math_as_image(&self, doc: &mut Document, _latex_content: &str) {
-        // Feature disabled: show a small placeholder using latex style
-        let mut para = genpdfi_extended::elements::Paragraph::default();
-        let mut style = genpdfi_extended::style::Style::new().with_font_size(self.style.latex.size);
-        if let Some((r, g, b)) = self.style.latex.text_color {
-            style = style.with_color(genpdfi_extended::style::Color::Rgb(r, g, b));
-        }
-        para.push_styled("need LaTeX feature".to_string(), style);
-        doc.push(para);
+    #[test]
+    fn test_render_image_gallery() {
+        let image = |name: &str| {
+            Token::Image(
+                name.to_string(),
+                format!("{}/tests/images/{}", env!("CARGO_MANIFEST_DIR"), name),
+            )
+        };
+        let tokens = vec![
+            image("formule_1.svg"),
+            image("formule_2.svg"),
+            image("triangle-161210_1280.png"),
+            image("missing.png"),
+            Token::ImageWithLink(
+                "linked".to_string(),
+                format!("{}/tests/images/formule_3.svg", env!("CARGO_MANIFEST_DIR")),
+                "https://example.com".to_string(),
+            ),
+        ];
+        let failures = |pdf: &Pdf| {
+            pdf.warnings()
+                .into_iter()
+                .filter(|w| w.message.contains("Failed to load image"))
+                .count()
+        };
+
+        let mut style = StyleMatch::default();
+        style.image_gallery.columns = 2;
+        let pdf = Pdf::new(tokens.clone(), style, None);
+        assert!(Pdf::render_to_bytes(pdf.render_into_document()).is_ok());
+        // The missing image leaves a placeholder in its cell
+        assert_eq!(failures(&pdf), 1);
+
+        // More columns than images leaves the rest of the row empty
+        let mut style = StyleMatch::default();
+        style.image_gallery.columns = 8;
+        style.image_gallery.gutter = 0.0;
+        let pdf = Pdf::new(tokens.clone(), style, None);
+        assert!(Pdf::render_to_bytes(pdf.render_into_document()).is_ok());
+
+        // `[image] fallback_image` takes the place of the missing image
+        let drawn_images = |fallback_image: Option<&str>| {
+            let mut style = StyleMatch::default();
+            style.image_gallery.columns = 8;
+            style.fallback_image = fallback_image.map(str::to_string);
+            let pdf = Pdf::new(tokens.clone(), style, None);
+            let bytes = Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+            assert_eq!(failures(&pdf), 1);
+            let doc = lopdf::Document::load_mem(&bytes).unwrap();
+            let page_id = *doc.get_pages().values().next().unwrap();
+            lopdf::content::Content::decode(&doc.get_page_content(page_id).unwrap())
+                .unwrap()
+                .operations
+                .iter()
+                .filter(|op| op.operator == "Do")
+                .count()
+        };
+        assert_eq!(
+            drawn_images(Some(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/images/test_image.jpg"
+            ))),
+            drawn_images(None) + 1
+        );
     }
 
-    // Render a Mermaid diagram (fenced code block with language `mermaid`)
-    #[cfg(feature = "mermaid")]
-    fn render_mermaid(&self, doc: &mut Document, content: &str) {
-        // Add spacing before the mermaid block
-        doc.push(genpdfi_extended::elements::Break::new(
-            self.style.code.before_spacing,
-        ));
+    #[test]
+    fn test_unrendered_inline_tokens() {
+        assert_eq!(token_variant_name(&Token::HorizontalRule), "HorizontalRule");
+        assert_eq!(
+            token_variant_name(&Token::TabGroup(vec![(String::new(), Vec::new())])),
+            "TabGroup"
+        );
+        assert_eq!(
+            token_variant_name(&Token::Heading(Vec::new(), 1)),
+            "Heading"
+        );
+
+        // Block tokens nested in inline content no longer vanish without a trace
+        let tokens = vec![Token::Emphasis {
+            level: 1,
+            content: vec![
+                Token::Text("before".to_string()),
+                Token::HorizontalRule,
+                Token::Unknown("raw".to_string()),
+            ],
+        }];
+        let pdf = create_test_pdf(tokens);
+        assert!(Pdf::render_to_bytes(pdf.render_into_document()).is_ok());
+        let warnings = pdf.warnings();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(
+            warnings[0].kind,
+            crate::validation::WarningKind::RenderFailure
+        );
+        assert!(warnings[0].message.contains("HorizontalRule"));
+    }
 
-        // Use genpdfi_extended's Mermaid element (may use headless_chrome internally)
-        let mer = genpdfi_extended::elements::Mermaid::new(content.to_string());
-        let auto_scale = self.style.mermaid.auto_scale;
-        let mut max_ratio = self.style.mermaid.max_ratio;
-        if max_ratio > 1.0 {
-            max_ratio = 1.0;
-        }
-        let mer = mer
-            .with_alignment(Alignment::Center)
-            .with_auto_scale(auto_scale, max_ratio);
-        doc.push(mer);
+    #[test]
+    fn test_render_inline_svg() {
+        let svg = "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"40\" height=\"20\">\n<rect width=\"40\" height=\"20\" fill=\"red\"/>\n</svg>\n";
+        let svg_warnings = |pdf: &Pdf| {
+            pdf.warnings()
+                .into_iter()
+                .filter(|w| w.message.contains("inline SVG"))
+                .count()
+        };
 
-        // Add spacing after the mermaid block
-        doc.push(genpdfi_extended::elements::Break::new(
-            self.style.code.after_spacing,
-        ));
+        let pdf = create_test_pdf(vec![Token::Code("svg".to_string(), svg.to_string())]);
+        assert!(Pdf::render_to_bytes(pdf.render_into_document()).is_ok());
+        assert_eq!(svg_warnings(&pdf), 0);
+
+        // Invalid markup falls back to a code block with a warning
+        let pdf = create_test_pdf(vec![Token::Code(
+            "svg".to_string(),
+            "<svg\nnot markup\n".to_string(),
+        )]);
+        assert!(Pdf::render_to_bytes(pdf.render_into_document()).is_ok());
+        assert_eq!(svg_warnings(&pdf), 1);
     }
 
-    #[cfg(not(feature = "mermaid"))]
-    fn render_mermaid(&self, doc: &mut Document, _content: &str) {
-        // Feature disabled: render a placeholder telling the user the feature is required
-        let mut para = genpdfi_extended::elements::Paragraph::default();
-        let mut style = genpdfi_extended::style::Style::new().with_font_size(self.style.code.size);
-        if let Some(color) = self.style.code.text_color {
-            style = style.with_color(genpdfi_extended::style::Color::Rgb(
-                color.0, color.1, color.2,
-            ));
-        }
-        para.push_styled("need Mermaid feature".to_string(), style);
-        doc.push(para);
-    }
+    #[test]
+    fn test_code_keep_together() {
+        let code = (1..=20)
+            .map(|i| format!("let line_{} = {};", i, i))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let render = |paragraphs: usize, keep_together: bool| {
+            let mut tokens: Vec<Token> = (0..paragraphs)
+                .flat_map(|i| [Token::Text(format!("Paragraph {}", i)), Token::Newline])
+                .collect();
+            tokens.push(Token::Code("rust".to_string(), code.clone()));
+            let mut style = StyleMatch::default();
+            style.code_keep_together = keep_together;
+            let pdf = Pdf::new(tokens, style, None);
+            let bytes = Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+            // Text positioning operations per page
+            let doc = lopdf::Document::load_mem(&bytes).unwrap();
+            doc.get_pages()
+                .values()
+                .map(|&page_id| {
+                    lopdf::content::Content::decode(&doc.get_page_content(page_id).unwrap())
+                        .unwrap()
+                        .operations
+                        .iter()
+                        .filter(|op| op.operator == "Td" || op.operator == "Tm")
+                        .count()
+                })
+                .collect::<Vec<usize>>()
+        };
 
-    fn render_inline_math(
-        &self,
-        para: &mut genpdfi_extended::elements::Paragraph,
-        latex_content: &str,
-        style: genpdfi_extended::style::Style,
-    ) {
-        // For inline math in paragraphs, we render the LaTeX code as styled text
-        // This ensures consistent rendering with the surrounding text and proper baseline alignment
-        let mut math_style = style.clone();
+        let block = render(0, true);
+        assert_eq!(block.len(), 1);
+        // Enough paragraphs before the block that it splits across two pages
+        let paragraphs = (5..400)
+            .step_by(5)
+            .find(|&n| {
+                let pages = render(n, false);
+                pages.len() == 2 && pages[1] > 0 && pages[1] < block[0]
+            })
+            .unwrap();
+
+        // Kept together, the whole block moves to the second page
+        let pages = render(paragraphs, true);
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages[1], block[0]);
+
+        // A block taller than a page splits
+        let long_code = vec!["x"; 400].join("\n");
+        let mut style = StyleMatch::default();
+        style.code_keep_together = true;
+        let pdf = Pdf::new(vec![Token::Code(String::new(), long_code)], style, None);
+        let bytes = Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+        assert!(lopdf::Document::load_mem(&bytes).unwrap().get_pages().len() > 1);
+    }
 
-        // Apply styling for code/math content
-        if let Some(color) = self.style.code.text_color {
-            math_style = math_style.with_color(genpdfi_extended::style::Color::Rgb(
-                color.0, color.1, color.2,
-            ));
-        }
+    #[test]
+    fn test_format_date() {
+        assert_eq!(format_date(0, "%Y-%m-%d"), "1970-01-01");
+        assert_eq!(
+            format_date(1_700_000_000, "Generated on %d/%m/%Y at %H:%M:%S"),
+            "Generated on 14/11/2023 at 22:13:20"
+        );
+        assert_eq!(format_date(0, "100%% %Q %"), "100% %Q %");
+    }
 
-        // Render the LaTeX source as styled text
-        para.push_styled(latex_content.to_string(), math_style);
+    #[test]
+    fn test_generation_date() {
+        let mut style = StyleMatch::default();
+        style.document.reproducible = true;
+        assert_eq!(Pdf::generation_date(&style, "%Y-%m-%d"), "1970-01-01");
+
+        // The source date takes precedence and enables reproducible output on its own
+        style.document.source_date_epoch = Some(1_700_000_000);
+        assert_eq!(Pdf::generation_date(&style, "%Y-%m-%d"), "2023-11-14");
+        style.document.reproducible = false;
+        assert_eq!(Pdf::generation_date(&style, "%Y-%m-%d"), "2023-11-14");
+
+        // Without reproducible output the date is today's
+        style.document.source_date_epoch = None;
+        assert_ne!(Pdf::generation_date(&style, "%Y"), "1970");
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::styling::StyleMatch;
+    #[test]
+    fn test_page_footer() {
+        let mut style = StyleMatch::default();
+        style.document.reproducible = true;
+        let pdf = Pdf::new(vec![Token::Text("Report".to_string())], style.clone(), None);
+        assert!(pdf.page_footer().is_none());
+
+        style.footer.show_date = true;
+        style.footer.page_numbers = true;
+        let pdf = Pdf::new(vec![Token::Text("Report".to_string())], style, None);
+        let footer = pdf.page_footer().unwrap();
+        assert_eq!(footer.date.as_deref(), Some("1970-01-01"));
+        assert!(Pdf::render_to_bytes(pdf.render_into_document()).is_ok());
+    }
 
-    // Helper function to create a basic PDF instance for testing
-    fn create_test_pdf(tokens: Vec<Token>) -> Pdf {
-        Pdf::new(tokens, StyleMatch::default(), None)
+    #[test]
+    fn test_footer_logo_beside_date() {
+        use crate::styling::TextAlignment;
+
+        // Positions in millimeters of the date and the page number on the first page
+        let footer_line = |alignment: Option<TextAlignment>| {
+            let mut style = StyleMatch::default();
+            style.document.reproducible = true;
+            style.footer.show_date = true;
+            style.footer.page_numbers = true;
+            style.footer.logo = alignment.map(|alignment| crate::styling::PageLogo {
+                image: format!("{}/tests/images/formule_1.svg", env!("CARGO_MANIFEST_DIR")),
+                alignment,
+                height: 6.0,
+            });
+            let pdf = Pdf::new(vec![Token::Text("Report".to_string())], style, None);
+            let bytes = Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+            let mut positions = text_positions(&bytes);
+            let bottom = positions.iter().map(|&(_, y)| y).fold(f32::MAX, f32::min);
+            positions.retain(|&(_, y)| y == bottom);
+            positions
+                .iter()
+                .map(|&(x, _)| x * PT_TO_MM)
+                .collect::<Vec<f32>>()
+        };
+        let plain = footer_line(None);
+        assert_eq!(plain.len(), 2);
+        let margin = StyleMatch::default().margins.left;
+        let logo_width = 6.0 * 183.0 / 67.0;
+
+        // A centered logo, the default, leaves room for both
+        assert_eq!(footer_line(Some(TextAlignment::Center)), plain);
+        // A logo on the left moves the date past it, one on the right the page number
+        let left = footer_line(Some(TextAlignment::Left));
+        assert!(left[0] > margin + logo_width + FOOTER_LOGO_GAP_MM - 0.1);
+        assert_eq!(left[1], plain[1]);
+        let right = footer_line(Some(TextAlignment::Right));
+        assert_eq!(right[0], plain[0]);
+        let page_width = StyleMatch::default().page_dimensions().0;
+        assert!(right[1] < page_width - margin - logo_width - FOOTER_LOGO_GAP_MM + 0.1);
     }
 
     #[test]
-    fn test_pdf_creation() {
-        let pdf = create_test_pdf(vec![]);
-        assert!(pdf.input.is_empty());
+    fn test_page_logos() {
+        let image = |name: &str| format!("{}/tests/images/{}", env!("CARGO_MANIFEST_DIR"), name);
+        let mut style = StyleMatch::default();
+        style.margins.top = 15.0;
+        style.header.logo = Some(crate::styling::PageLogo {
+            image: image("triangle-161210_1280.png"),
+            alignment: crate::styling::TextAlignment::Right,
+            height: 10.0,
+        });
+        style.footer.logo = Some(crate::styling::PageLogo {
+            image: image("formule_1.svg"),
+            alignment: crate::styling::TextAlignment::Center,
+            height: 20.0,
+        });
+        let pdf = Pdf::new(vec![Token::Text("Report".to_string())], style, None);
+        let logos = pdf.page_logos();
+        assert_eq!(logos.len(), 2);
+        assert!(logos[0].header && !logos[1].header);
+        assert_eq!(logos[0].aspect_ratio, 2.0);
+        assert_eq!(logos[1].aspect_ratio, 183.0 / 67.0);
+        assert!(Pdf::render_to_bytes(pdf.render_into_document()).is_ok());
+
+        // A logo that cannot be loaded is reported and left out
+        let mut style = StyleMatch::default();
+        style.header.logo = Some(crate::styling::PageLogo {
+            image: "missing-logo.png".to_string(),
+            alignment: crate::styling::TextAlignment::Left,
+            height: 6.0,
+        });
+        let pdf = Pdf::new(vec![], style, None);
+        assert!(pdf.page_logos().is_empty());
+        assert!(pdf
+            .warnings()
+            .iter()
+            .any(|w| w.message.contains("[page.header] image 'missing-logo.png'")));
+    }
 
-        // Test that both font families exist
-        let _font_family = &pdf.font_family;
-        let _code_font_family = &pdf.code_font_family;
+    #[test]
+    fn test_redact_patterns() {
+        let mut style = StyleMatch::default();
+        style.redact.patterns = vec!["ACME-\\d+".to_string(), "(unclosed".to_string()];
+        let pdf = Pdf::new(
+            vec![Token::Text("Ticket ACME-1234".to_string())],
+            style,
+            None,
+        );
+        assert_eq!(pdf.input, vec![Token::Text("Ticket █████████".to_string())]);
 
-        // Since FontData's fields are private and it doesn't implement comparison traits,
-        // we can only verify that the PDF was created successfully with these fonts
-        let doc = pdf.render_into_document();
-        assert!(Pdf::render(doc, "/dev/null").is_none());
+        // A pattern that does not compile is reported, as its text stays visible
+        let warnings = pdf.warnings();
+        assert!(warnings
+            .iter()
+            .any(|w| w.kind == crate::validation::WarningKind::InvalidRedactPattern));
     }
 
-    #[cfg(not(feature = "mermaid"))]
     #[test]
-    fn test_mermaid_placeholder() {
-        // If the mermaid feature is not enabled, render_mermaid should emit a placeholder
-        let tokens = vec![Token::Code(
-            "mermaid".to_string(),
-            "graph LR\nA-->B".to_string(),
-        )];
+    fn test_redact_index_glossary_and_source() {
+        let mut style = StyleMatch::default();
+        style.redact.patterns = vec!["ACME-\\d+".to_string()];
+        style.index.enabled = true;
+        style.document.attach_source = true;
+        let markdown = "Ticket ACME-1234 <!-- index: ACME-1234 -->\n\n*[TKT]: ACME-99 ticket\n";
+        let tokens = vec![
+            Token::Text("Ticket ACME-1234 ".to_string()),
+            Token::HtmlComment("index: ACME-1234".to_string()),
+            Token::AbbreviationDefinition("TKT".to_string(), "ACME-99 ticket".to_string()),
+        ];
+        let mut pdf = Pdf::new(tokens, style, None);
+        assert_eq!(pdf.index_terms, vec!["█████████".to_string()]);
+        assert_eq!(
+            pdf.abbreviations,
+            vec![("TKT".to_string(), "███████ ticket".to_string())]
+        );
+
+        // The attached source hides the same text as the pages
+        pdf.set_source(markdown.to_string());
+        let (_, source) = pdf.source_attachment().unwrap();
+        assert!(!source.contains("ACME-"));
+        assert!(source.starts_with("Ticket █████████ <!-- index: █████████ -->"));
+    }
 
-        let pdf = create_test_pdf(tokens);
-        let doc = pdf.render_into_document();
-        assert!(Pdf::render(doc, "/dev/null").is_none());
+    #[test]
+    fn test_base_urls() {
+        let mut style = StyleMatch::default();
+        style.link_base_url = Some("https://site.example/docs/".to_string());
+        let pdf = Pdf::new(
+            vec![
+                Token::Link("Setup".to_string(), "setup".to_string()),
+                Token::Image("Logo".to_string(), "logo.png".to_string()),
+            ],
+            style,
+            None,
+        );
+        assert_eq!(
+            pdf.input,
+            vec![
+                Token::Link(
+                    "Setup".to_string(),
+                    "https://site.example/docs/setup".to_string()
+                ),
+                // Images keep their path without an `[image] base_url`
+                Token::Image("Logo".to_string(), "logo.png".to_string()),
+            ]
+        );
     }
 
-    #[cfg(feature = "mermaid")]
     #[test]
-    #[ignore]
-    fn test_mermaid_rendering_ignored() {
-        // This test is ignored by default because Mermaid rendering can be slow and may require
-        // headless Chrome to be downloaded on first run. Run manually when needed.
-        let tokens = vec![Token::Code(
-            "mermaid".to_string(),
-            "graph LR\nA-->B".to_string(),
-        )];
+    fn test_decode_corrupt_images() {
+        let truncated = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+        assert!(matches!(
+            decode_raster_image(truncated),
+            Err(crate::images::ImageError::DecodeError(_))
+        ));
+        assert!(matches!(
+            decode_svg_image("<svg"),
+            Err(crate::images::ImageError::DecodeError(_))
+        ));
 
-        let pdf = create_test_pdf(tokens);
-        let doc = pdf.render_into_document();
-        // We don't assert anything about content; the purpose is to ensure rendering doesn't panic
-        assert!(Pdf::render(doc, "/dev/null").is_none());
+        let payload = std::panic::catch_unwind(|| panic!("bad chunk")).unwrap_err();
+        assert_eq!(panic_message(payload), "decoder panicked: bad chunk");
     }
 
-    #[cfg(feature = "mermaid")]
     #[test]
-    #[ignore]
-    fn test_mermaid_rendering_with_custom_config_ignored() {
-        // Ensure custom mermaid config values don't panic during rendering
-        let tokens = vec![Token::Code(
-            "mermaid".to_string(),
-            "graph LR\nA-->B".to_string(),
-        )];
+    fn test_min_lines_at_page_breaks() {
+        let long = vec!["word"; 400].join(" ");
+        // Text lines of the long paragraph on the first and the second page after `fillers`
+        // one-line paragraphs, or None once the fillers no longer fit on the first page
+        let split = |fillers: usize, min_lines: usize| {
+            let mut tokens: Vec<Token> = (0..fillers)
+                .flat_map(|i| {
+                    [
+                        Token::Text(format!("Filler {}", i)),
+                        Token::Newline,
+                        Token::Newline,
+                    ]
+                })
+                .collect();
+            tokens.push(Token::Text(long.clone()));
+            let mut style = StyleMatch::default();
+            style.min_lines_top = min_lines;
+            style.min_lines_bottom = min_lines;
+            let pdf = Pdf::new(tokens, style, None);
+            let bytes = Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+            let doc = lopdf::Document::load_mem(&bytes).unwrap();
+            let lines: Vec<usize> = doc
+                .get_pages()
+                .values()
+                .map(|&page_id| {
+                    let content = doc.get_page_content(page_id).unwrap();
+                    lopdf::content::Content::decode(&content)
+                        .unwrap()
+                        .operations
+                        .iter()
+                        .filter(|op| op.operator == "Td" || op.operator == "Tm")
+                        .count()
+                })
+                .collect();
+            match lines[..] {
+                [first] => Some((first.checked_sub(fillers)?, 0)),
+                [first, second] => Some((first.checked_sub(fillers)?, second)),
+                _ => None,
+            }
+        };
+        // Page breaks that leave a single line of the paragraph on either page
+        let stranded = |min_lines: usize| {
+            (0..80)
+                .map_while(|fillers| split(fillers, min_lines))
+                .filter(|&(first, second)| second > 0 && (first == 1 || second == 1))
+                .count()
+        };
 
-        let mut pdf = create_test_pdf(tokens);
-        pdf.style.mermaid.auto_scale = 3.5;
-        pdf.style.mermaid.max_ratio = 0.8;
-        let doc = pdf.render_into_document();
-        assert!(Pdf::render(doc, "/dev/null").is_none());
+        // Moving the paragraph start down the page eventually strands a line
+        let unguarded = stranded(1);
+        assert!(unguarded > 0);
+        assert_eq!(stranded(3), 0);
     }
 
-    #[cfg(feature = "mermaid")]
     #[test]
-    #[ignore]
-    fn test_mermaid_rendering_with_max_ratio_clamped_ignored() {
-        // If max_ratio is > 1 it should be clamped and not cause errors
+    fn test_render_code_language_label() {
+        let tokens = vec![Token::Code("rust".to_string(), "fn main() {}".to_string())];
+
+        let pdf = create_test_pdf(tokens.clone());
+        assert!(Pdf::render_to_bytes(pdf.render_into_document()).is_ok());
+        // Off by default: nothing is decorated
+        assert!(!pdf.has_decorations());
+
+        let mut style = StyleMatch::default();
+        style.show_code_language = true;
+        let pdf = Pdf::new(tokens.clone(), style, None);
+        assert!(Pdf::render_to_bytes(pdf.render_into_document()).is_ok());
+        // The label is drawn as a badge behind the language name
+        assert!(pdf.has_decorations());
+
+        let text_lines = |show_code_language: bool| {
+            let mut style = StyleMatch::default();
+            style.show_code_language = show_code_language;
+            let pdf = Pdf::new(tokens.clone(), style, None);
+            let bytes = Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+            let doc = lopdf::Document::load_mem(&bytes).unwrap();
+            let (_, page_id) = doc.get_pages().into_iter().next().unwrap();
+            let content = doc.get_page_content(page_id).unwrap();
+            lopdf::content::Content::decode(&content)
+                .unwrap()
+                .operations
+                .iter()
+                .filter(|op| op.operator == "Td" || op.operator == "Tm")
+                .map(|op| op.operands.last().unwrap().as_float().unwrap())
+                .collect::<Vec<f32>>()
+        };
+        let plain = text_lines(false);
+        let labeled = text_lines(true);
+        // The label is printed in the corner and does not push the code down
+        assert!(labeled.len() > plain.len());
+        let lowest = |lines: &[f32]| lines.iter().copied().fold(f32::MAX, f32::min);
+        assert!((lowest(&labeled) - lowest(&plain)).abs() < 0.01);
+    }
+
+    #[test]
+    #[cfg(all(feature = "math-rust", not(feature = "latex")))]
+    fn test_render_math_with_rust_backend() {
+        let tokens = vec![
+            Token::Math {
+                content: r"\frac{1}{2} \leq \sqrt{x}".to_string(),
+                display: true,
+            },
+            Token::Text("Inline ".to_string()),
+            Token::Math {
+                content: r"\alpha^2".to_string(),
+                display: false,
+            },
+        ];
+
+        let pdf = create_test_pdf(tokens);
+        assert!(Pdf::render_to_bytes(pdf.render_into_document()).is_ok());
+    }
+
+    #[test]
+    #[cfg(feature = "math-rust")]
+    fn test_render_asciimath_block() {
         let tokens = vec![Token::Code(
-            "mermaid".to_string(),
-            "graph LR\nA-->B".to_string(),
+            "asciimath".to_string(),
+            "sum_(i=1)^n i^2\n".to_string(),
         )];
+        let mut style = StyleMatch::default();
+        style.latex.size = 17;
+        let pdf = Pdf::new(tokens, style, None);
+        let bytes = Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+
+        // The block is set as math in the latex style, not as a code block
+        let doc = lopdf::Document::load_mem(&bytes).unwrap();
+        let (_, page_id) = doc.get_pages().into_iter().next().unwrap();
+        let operations = lopdf::content::Content::decode(&doc.get_page_content(page_id).unwrap())
+            .unwrap()
+            .operations;
+        assert!(operations
+            .iter()
+            .any(|op| op.operator == "Tf" && op.operands[1].as_float().unwrap() == 17.0));
+    }
 
-        let mut pdf = create_test_pdf(tokens);
-        pdf.style.mermaid.auto_scale = 2.0;
-        pdf.style.mermaid.max_ratio = 2.0; // will be clamped by render_mermaid
-        let doc = pdf.render_into_document();
-        assert!(Pdf::render(doc, "/dev/null").is_none());
+    #[test]
+    fn test_heading_uses_fallback_fonts() {
+        let font_config = crate::fonts::FontConfig {
+            custom_paths: vec![std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fonts")],
+            default_font: Some("CMU Typewriter Text".to_string()),
+            fallback_fonts: vec!["DejaVu Sans".to_string()],
+            ..Default::default()
+        };
+        let tokens = vec![Token::Heading(
+            vec![Token::Text("✓✔ Launch".to_string())],
+            2,
+        )];
+        let pdf = Pdf::new(tokens, StyleMatch::default(), Some(&font_config));
+
+        // DejaVu Sans ships in the fonts directory, so the chain always loads
+        assert!(pdf.font_fallback_chain.is_some());
+        // The check mark is missing from the typewriter font and routed to the fallback
+        assert!(pdf.font_ranges.iter().any(|(range, _)| range.contains('✓')));
+        // Consecutive code points are coalesced into one range
+        assert!(pdf
+            .font_ranges
+            .iter()
+            .any(|(range, _)| range.contains('✓') && range.contains('✔')));
+        assert!(!pdf.font_ranges.iter().any(|(range, _)| range.contains('L')));
+        assert!(Pdf::render_to_bytes(pdf.render_into_document()).is_ok());
     }
 
     #[test]
-    fn test_render_heading() {
+    fn test_render_links_as_footnotes() {
         let tokens = vec![
-            Token::Heading(vec![Token::Text("Test Heading".to_string())], 1),
-            Token::Heading(vec![Token::Text("Subheading".to_string())], 2),
-            Token::Heading(vec![Token::Text("Sub-subheading".to_string())], 3),
+            Token::Text("See ".to_string()),
+            Token::Link(
+                "the docs".to_string(),
+                "https://example.com/docs".to_string(),
+            ),
+            Token::Text(", ".to_string()),
+            Token::Link(
+                "https://example.com".to_string(),
+                "https://example.com".to_string(),
+            ),
+            Token::Text(" and ".to_string()),
+            Token::Link("below".to_string(), "#ref".to_string()),
         ];
-        let pdf = create_test_pdf(tokens);
-        let doc = pdf.render_into_document();
-        // Document should be created successfully
-        assert!(Pdf::render(doc, "/dev/null").is_none());
+
+        let pdf = create_test_pdf(tokens.clone());
+        assert!(Pdf::render_to_bytes(pdf.render_into_document()).is_ok());
+        assert!(pdf.footnotes.borrow().is_empty());
+
+        let mut style = StyleMatch::default();
+        style.link_style = LinkStyle::Footnote;
+        let pdf = Pdf::new(tokens, style, None);
+        assert!(Pdf::render_to_bytes(pdf.render_into_document()).is_ok());
+        // Only the external link with its own text gets a note; bare URLs and internal
+        // links stay inline
+        assert_eq!(
+            *pdf.footnotes.borrow(),
+            vec![vec![Token::Link(
+                "https://example.com/docs".to_string(),
+                "https://example.com/docs".to_string()
+            )]]
+        );
     }
 
     #[test]
-    fn test_render_paragraphs() {
+    fn test_render_links_parenthetical() {
         let tokens = vec![
-            Token::Text("First paragraph".to_string()),
-            Token::Newline,
-            Token::Text("Second paragraph".to_string()),
+            Token::Text("See ".to_string()),
+            Token::Link(
+                "the docs".to_string(),
+                "https://example.com/docs".to_string(),
+            ),
+            Token::Text(" and ".to_string()),
+            Token::Link("below".to_string(), "#ref".to_string()),
         ];
-        let pdf = create_test_pdf(tokens);
-        let doc = pdf.render_into_document();
-        assert!(Pdf::render(doc, "/dev/null").is_none());
+        let count_links = |style: StyleMatch| {
+            let pdf = Pdf::new(tokens.clone(), style, None);
+            let bytes = Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+            assert!(pdf.footnotes.borrow().is_empty());
+            let doc = lopdf::Document::load_mem(&bytes).unwrap();
+            doc.objects
+                .values()
+                .filter_map(|object| object.as_dict().ok())
+                .filter(|dict| {
+                    dict.get(b"Subtype")
+                        .and_then(|subtype| subtype.as_name())
+                        .is_ok_and(|name| name == b"Link")
+                })
+                .count()
+        };
+
+        let inline = count_links(StyleMatch::default());
+        let mut style = StyleMatch::default();
+        style.link_style = LinkStyle::Parenthetical;
+        // The URL after the external link text is clickable too; the internal link is
+        // left as it is
+        assert!(count_links(style) > inline);
     }
 
     #[test]
-    fn test_render_list_items() {
-        let tokens = vec![
-            Token::ListItem {
-                content: vec![Token::Text("First item".to_string())],
-                ordered: false,
-                number: None,
-            },
-            Token::ListItem {
-                content: vec![Token::Text("Second item".to_string())],
-                ordered: true,
-                number: Some(1),
-            },
-        ];
-        let pdf = create_test_pdf(tokens);
-        let doc = pdf.render_into_document();
-        assert!(Pdf::render(doc, "/dev/null").is_none());
+    fn test_page_size_and_orientation() {
+        let style = StyleMatch::default().with_page_size(
+            crate::styling::PageSize::Letter,
+            crate::styling::Orientation::Landscape,
+        );
+        let pdf = Pdf::new(vec![Token::Text("Wide".to_string())], style, None);
+        let bytes = Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+
+        let doc = lopdf::Document::load_mem(&bytes).unwrap();
+        let (_, page_id) = doc.get_pages().into_iter().next().unwrap();
+        let media_box = doc
+            .get_dictionary(page_id)
+            .unwrap()
+            .get(b"MediaBox")
+            .unwrap()
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| v.as_float().unwrap())
+            .collect::<Vec<_>>();
+        // 11 x 8.5 inches in points
+        assert!((media_box[2] - media_box[0] - 792.0).abs() < 1.0);
+        assert!((media_box[3] - media_box[1] - 612.0).abs() < 1.0);
     }
 
     #[test]
-    fn test_render_nested_list_items() {
-        let tokens = vec![Token::ListItem {
-            content: vec![
-                Token::Text("Parent item".to_string()),
-                Token::ListItem {
-                    content: vec![Token::Text("Child item".to_string())],
-                    ordered: false,
-                    number: None,
-                },
-            ],
+    fn test_task_list_progress() {
+        let item = |checked: bool, content: Vec<Token>| Token::ListItem {
+            content: [vec![Token::TaskCheckbox(checked)], content].concat(),
             ordered: false,
             number: None,
-        }];
-        let pdf = create_test_pdf(tokens);
-        let doc = pdf.render_into_document();
-        assert!(Pdf::render(doc, "/dev/null").is_none());
+        };
+        let nested = item(
+            false,
+            vec![item(true, vec![]), Token::Text("Child".to_string())],
+        );
+        let plain = Token::ListItem {
+            content: vec![Token::Text("Not a task".to_string())],
+            ordered: false,
+            number: None,
+        };
+        let tokens = vec![item(true, vec![]), nested, plain];
+        assert_eq!(count_tasks(&tokens), (2, 3));
+
+        let mut style = StyleMatch::default();
+        style.task_list.show_progress = true;
+        let mut document = tokens.clone();
+        document.push(Token::Text("After the list".to_string()));
+        let pdf = Pdf::new(document, style, None);
+        assert!(Pdf::render_to_bytes(pdf.render_into_document()).is_ok());
     }
 
     #[test]
-    fn test_render_code_blocks() {
+    fn test_highlighted_token_style() {
+        let pdf = Pdf::new(vec![], StyleMatch::default(), None);
+        let code_style = genpdfi_extended::style::Style::new().with_font_size(8);
+        let tokens = highlighting::highlight_code("// entry point\nfn main() { return; }", "rust");
+        let style_of = |token: &highlighting::HighlightedToken| {
+            pdf.highlighted_token_style(code_style, token.color, token.bold, token.italic)
+        };
+
+        // InspiredGitHub sets comments in italic and keywords in bold
+        let comment = tokens
+            .iter()
+            .find(|t| t.text.contains("entry point"))
+            .unwrap();
+        assert!(style_of(comment).is_italic());
+        let keyword = tokens.iter().find(|t| t.text == "fn").unwrap();
+        assert!(style_of(keyword).is_bold());
+        let punctuation = tokens.iter().find(|t| t.text.contains(';')).unwrap();
+        assert!(!style_of(punctuation).is_bold() && !style_of(punctuation).is_italic());
+
         let tokens = vec![Token::Code(
             "rust".to_string(),
-            "fn main() {\n    println!(\"Hello\");\n}".to_string(),
+            "// entry point\nfn main() { return; }".to_string(),
         )];
-        let pdf = create_test_pdf(tokens);
-        let doc = pdf.render_into_document();
-        assert!(Pdf::render(doc, "/dev/null").is_none());
+        let pdf = Pdf::new(tokens, StyleMatch::default(), None);
+        assert!(Pdf::render_to_bytes(pdf.render_into_document()).is_ok());
     }
 
     #[test]
-    fn test_render_inline_formatting() {
+    fn test_grayscale_code_highlighting() {
+        assert_eq!(grayscale((0, 0, 0)), (0, 0, 0));
+        assert_eq!(grayscale((255, 255, 255)), (153, 153, 153));
+        let (r, g, b) = grayscale((167, 29, 93));
+        assert!(r == g && g == b);
+        // Brighter colors stay lighter grays
+        assert!(grayscale((0, 160, 0)).0 > grayscale((0, 0, 160)).0);
+
+        let mut style = StyleMatch::default();
+        style.code_highlight_style = CodeHighlightStyle::Grayscale;
+        let tokens = vec![Token::Code(
+            "rust".to_string(),
+            "// comment\nfn main() {\n    let x = 1;\n}".to_string(),
+        )];
+        let pdf = Pdf::new(tokens, style, None);
+        assert!(Pdf::render_to_bytes(pdf.render_into_document()).is_ok());
+    }
+
+    #[test]
+    fn test_render_spoiler() {
+        let mut style = StyleMatch::default();
+        style.spoiler.label = "Plot twist".to_string();
+        let pdf = Pdf::new(
+            vec![
+                Token::Text("The killer is".to_string()),
+                Token::Spoiler(vec![
+                    Token::Text("the ".to_string()),
+                    Token::Kbd("butler".to_string()),
+                ]),
+                Token::Text("after all.".to_string()),
+            ],
+            style,
+            None,
+        );
+        let bytes = Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+        assert!(!pdf.warnings().iter().any(|w| w.message.contains("spoiler")));
+        let doc = lopdf::Document::load_mem(&bytes).unwrap();
+        let (_, page_id) = doc.get_pages().into_iter().next().unwrap();
+        let operations = lopdf::content::Content::decode(&doc.get_page_content(page_id).unwrap())
+            .unwrap()
+            .operations;
+        // The frame of the box is drawn as lines
+        assert!(operations.iter().any(|op| op.operator == "l"));
+    }
+
+    #[test]
+    fn test_render_admonition() {
+        assert_eq!(admonition_color("hint"), admonition_color("tip"));
+        assert_ne!(admonition_color("danger"), admonition_color("note"));
+
         let tokens = vec![
-            Token::Text("Normal ".to_string()),
-            Token::Emphasis {
-                level: 1,
-                content: vec![Token::Text("italic".to_string())],
+            Token::Admonition {
+                kind: "warning".to_string(),
+                content: vec![
+                    Token::Text("Back up first".to_string()),
+                    Token::ListItem {
+                        content: vec![Token::Text("Step".to_string())],
+                        ordered: true,
+                        number: Some(1),
+                    },
+                ],
             },
-            Token::Text(" and ".to_string()),
-            Token::StrongEmphasis(vec![Token::Text("bold".to_string())]),
-            Token::Text(" text".to_string()),
+            Token::Text("After".to_string()),
         ];
         let pdf = create_test_pdf(tokens);
-        let doc = pdf.render_into_document();
-        assert!(Pdf::render(doc, "/dev/null").is_none());
+        assert!(Pdf::render_to_bytes(pdf.render_into_document()).is_ok());
     }
 
     #[test]
-    fn test_render_links() {
-        let tokens = vec![
-            Token::Text("Here is a ".to_string()),
-            Token::Link("link".to_string(), "https://example.com".to_string()),
-            Token::Text(" to click".to_string()),
-        ];
-        let pdf = create_test_pdf(tokens);
-        let doc = pdf.render_into_document();
-        assert!(Pdf::render(doc, "/dev/null").is_none());
+    fn test_list_nesting_limit() {
+        fn nested_list(depth: usize) -> Token {
+            let mut item = Token::ListItem {
+                content: vec![Token::Text(format!("Level {}", depth))],
+                ordered: false,
+                number: None,
+            };
+            for level in (1..depth).rev() {
+                item = Token::ListItem {
+                    content: vec![Token::Text(format!("Level {}", level)), item],
+                    ordered: level % 2 == 0,
+                    number: Some(1),
+                };
+            }
+            item
+        }
+
+        let flattened = flatten_list_items(&[nested_list(4)]);
+        let texts: Vec<String> = flattened
+            .iter()
+            .map(|(_, _, content)| Token::collect_all_text(content))
+            .collect();
+        assert_eq!(texts, ["Level 1", "Level 2", "Level 3", "Level 4"]);
+        assert!(flattened[1].0 && !flattened[3].0);
+
+        let nesting_warnings = |pdf: &Pdf| {
+            pdf.warnings()
+                .into_iter()
+                .filter(|w| w.message.contains("nested deeper"))
+                .collect::<Vec<_>>()
+        };
+        let pdf = create_test_pdf(vec![nested_list(10)]);
+        let _ = pdf.render_into_document();
+        assert!(nesting_warnings(&pdf).is_empty());
+
+        let mut style = StyleMatch::default();
+        style.list_max_nesting = 3;
+        let pdf = Pdf::new(vec![nested_list(10)], style, None);
+        assert!(Pdf::render_to_bytes(pdf.render_into_document()).is_ok());
+        let warnings = nesting_warnings(&pdf);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].message.contains("deeper than 3 levels"));
     }
 
     #[test]
-    fn test_render_horizontal_rule() {
+    fn test_render_index() {
+        let tag = |term: &str| Token::HtmlComment(format!(" index: {} ", term));
         let tokens = vec![
-            Token::Text("Before rule".to_string()),
+            Token::Text("Ownership".to_string()),
+            tag("ownership"),
+            Token::Newline,
             Token::HorizontalRule,
-            Token::Text("After rule".to_string()),
+            Token::Text("Borrowing".to_string()),
+            tag("borrow checker"),
+            tag("ownership"),
+            Token::Newline,
         ];
-        let pdf = create_test_pdf(tokens);
-        let doc = pdf.render_into_document();
-        assert!(Pdf::render(doc, "/dev/null").is_none());
+        let pdf = create_test_pdf(tokens.clone());
+        assert!(pdf.has_index());
+
+        let first_pass = Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+        let (stripped, mut markers) = Pdf::index_markers(&first_pass).unwrap();
+        markers.sort_unstable();
+        assert_eq!(markers, [(0, 1), (1, 1), (1, 1)]);
+        assert!(Pdf::index_markers(&stripped).unwrap().1.is_empty());
+
+        // The document is laid out once, followed by the index on a page of its own
+        let output = pdf.render_pdf().unwrap();
+        assert!(Pdf::index_markers(&output).unwrap().1.is_empty());
+        assert_eq!(*pdf.index_pages.borrow(), [vec![1], vec![1]]);
+        let doc = lopdf::Document::load_mem(&output).unwrap();
+        assert_eq!(doc.get_pages().len(), 2);
+
+        let mut style = StyleMatch::default();
+        style.index.enabled = false;
+        assert!(!Pdf::new(tokens, style, None).has_index());
     }
 
     #[test]
-    fn test_render_mixed_content() {
+    fn test_ordered_list_numbering_after_nesting() {
+        let markdown = "1. First\n   1. Sub one\n   2. Sub two\n1. Second\n";
+        let tokens = crate::markdown::Lexer::new(markdown.to_string())
+            .parse()
+            .unwrap();
+        let top: Vec<_> = list_numbers(&tokens).into_iter().flatten().collect();
+        assert_eq!(top, vec![1, 2]);
+
+        let Some(Token::ListItem { content, .. }) = tokens.first() else {
+            panic!("expected a list item, got {:?}", tokens);
+        };
+        let nested: Vec<_> = list_numbers(content).into_iter().flatten().collect();
+        assert_eq!(nested, vec![1, 2]);
+
+        // A list starting at 3 keeps its start, and an unordered item ends the run
+        let item = |ordered: bool, number: Option<usize>| Token::ListItem {
+            content: vec![Token::Text("Item".to_string())],
+            ordered,
+            number,
+        };
         let tokens = vec![
-            Token::Heading(vec![Token::Text("Title".to_string())], 1),
-            Token::Text("Some text ".to_string()),
-            Token::Link("with link".to_string(), "https://example.com".to_string()),
+            item(true, Some(3)),
             Token::Newline,
-            Token::ListItem {
-                content: vec![Token::Text("List item".to_string())],
-                ordered: false,
-                number: None,
-            },
-            Token::Code("rust".to_string(), "let x = 42;".to_string()),
+            item(true, Some(3)),
+            item(false, None),
+            item(true, Some(1)),
         ];
+        assert_eq!(
+            list_numbers(&tokens),
+            vec![Some(3), None, Some(4), None, Some(1)]
+        );
+
         let pdf = create_test_pdf(tokens);
-        let doc = pdf.render_into_document();
-        assert!(Pdf::render(doc, "/dev/null").is_none());
+        assert!(Pdf::render_to_bytes(pdf.render_into_document()).is_ok());
     }
 
     #[test]
-    fn test_render_empty_content() {
-        let pdf = create_test_pdf(vec![]);
-        let doc = pdf.render_into_document();
-        assert!(Pdf::render(doc, "/dev/null").is_none());
+    fn test_raster_image_width() {
+        let image = |path: &str| crate::images::ImageData {
+            bytes: std::fs::read(path).unwrap(),
+            format: crate::images::ImageFormat::Png,
+            source: path.to_string(),
+        };
+        let small = image("tests/images/triangle-ruler-1016726_640.png");
+        let large = image("tests/images/triangle-161210_1280.png");
+
+        let mut style = StyleMatch::default().with_margins(10.0, 55.0, 10.0, 55.0);
+        let pdf = Pdf::new(vec![], style.clone(), None);
+        // 640 px at 96 DPI are about 169 mm, on a 100 mm wide text area
+        assert_eq!(pdf.raster_image_width(&large), RASTER_IMAGE_MAX_WIDTH);
+        assert_eq!(pdf.raster_image_width(&small), RASTER_IMAGE_MAX_WIDTH);
+
+        style = StyleMatch::default().with_page_size(
+            crate::styling::PageSize::A3,
+            crate::styling::Orientation::Landscape,
+        );
+        let pdf = Pdf::new(vec![], style.clone(), None);
+        let text_width = style.page_dimensions().0 - style.margins.left - style.margins.right;
+        let (natural_width, _) = small.natural_size_mm().unwrap();
+        assert!((pdf.raster_image_width(&small) - natural_width / text_width).abs() < 0.001);
+        assert_eq!(pdf.raster_image_width(&large), RASTER_IMAGE_MAX_WIDTH);
+
+        style.image_upscale = true;
+        let pdf = Pdf::new(vec![], style.clone(), None);
+        assert_eq!(pdf.raster_image_width(&small), RASTER_IMAGE_MAX_WIDTH);
+
+        // 1280 px at 300 DPI are about 108 mm; wider than the text area they fill its width
+        style.image_target_dpi = Some(300.0);
+        let pdf = Pdf::new(vec![], style.clone(), None);
+        let expected = 1280.0 * 25.4 / 300.0 / text_width;
+        assert!((pdf.raster_image_width(&large) - expected).abs() < 0.001);
+        let style = style.with_margins(10.0, 180.0, 10.0, 180.0);
+        let pdf = Pdf::new(vec![], style, None);
+        assert_eq!(pdf.raster_image_width(&large), 1.0);
+
+        // Images on consecutive lines are sized like a lone image
+        let image_scales = |count: usize, upscale: bool| {
+            let mut style = StyleMatch::default().with_page_size(
+                crate::styling::PageSize::A3,
+                crate::styling::Orientation::Landscape,
+            );
+            style.image_upscale = upscale;
+            let image = Token::Image(
+                "alt".to_string(),
+                concat!(
+                    env!("CARGO_MANIFEST_DIR"),
+                    "/tests/images/triangle-ruler-1016726_640.png"
+                )
+                .to_string(),
+            );
+            let pdf = Pdf::new(vec![image; count], style, None);
+            let bytes = Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+            let doc = lopdf::Document::load_mem(&bytes).unwrap();
+            let page_id = *doc.get_pages().values().next().unwrap();
+            lopdf::content::Content::decode(&doc.get_page_content(page_id).unwrap())
+                .unwrap()
+                .operations
+                .into_iter()
+                .filter(|op| op.operator == "cm")
+                .map(|op| op.operands[0].as_float().unwrap())
+                .collect::<Vec<f32>>()
+        };
+        let natural = image_scales(1, false);
+        assert_eq!(natural.len(), 1);
+        assert_eq!(image_scales(2, false), vec![natural[0]; 2]);
+        assert!(image_scales(2, true)
+            .iter()
+            .all(|&scale| scale > natural[0]));
     }
 
     #[test]
-    fn test_render_invalid_path() {
-        let pdf = create_test_pdf(vec![Token::Text("Test".to_string())]);
-        let doc = pdf.render_into_document();
-        let result = Pdf::render(doc, "/nonexistent/path/file.pdf");
-        assert!(result.is_some()); // Should return an error message
+    fn test_estimate_page_count() {
+        let short = Pdf::new(
+            vec![Token::Text("A single short paragraph".to_string())],
+            StyleMatch::default(),
+            None,
+        );
+        assert_eq!(short.estimate_page_count(), 1);
+
+        let paragraph = "Lorem ipsum dolor sit amet, consectetur adipiscing elit. ".repeat(12);
+        let mut markdown = String::new();
+        for section in 1..=12 {
+            markdown.push_str(&format!(
+                "## Section {section}\n\n{paragraph}\n\n{paragraph}\n\n"
+            ));
+        }
+        let tokens = crate::markdown::Lexer::new(markdown).parse().unwrap();
+        let long = Pdf::new(tokens, StyleMatch::default(), None);
+        let estimate = long.estimate_page_count();
+        let bytes = Pdf::render_to_bytes(long.render_into_document()).unwrap();
+        let actual = lopdf::Document::load_mem(&bytes).unwrap().get_pages().len();
+        assert!(actual > 1);
+        assert!(
+            estimate * 2 >= actual && estimate <= actual * 2,
+            "estimated {estimate} pages, rendered {actual}"
+        );
     }
 
     #[test]
-    fn test_render_to_bytes() {
-        let tokens = vec![
-            Token::Heading(vec![Token::Text("Test Document".to_string())], 1),
-            Token::Text("This is a test paragraph.".to_string()),
-        ];
+    fn test_render_multi_paragraph_list_item() {
+        let tokens = crate::markdown::Lexer::new(
+            "- First paragraph of the item\n\n  Second paragraph, indented under the text\n- Next"
+                .to_string(),
+        )
+        .parse()
+        .unwrap();
         let pdf = create_test_pdf(tokens);
-        let doc = pdf.render_into_document();
-        let result = Pdf::render_to_bytes(doc);
+        let bytes = Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+        let positions = text_positions(&bytes);
+
+        let line = |text_y: f32| {
+            positions
+                .iter()
+                .filter(|(_, y)| (y - text_y).abs() < 0.01)
+                .map(|(x, _)| *x)
+                .collect::<Vec<f32>>()
+        };
+        let mut rows: Vec<f32> = positions.iter().map(|(_, y)| *y).collect();
+        rows.sort_by(|a, b| b.partial_cmp(a).unwrap());
+        rows.dedup_by(|a, b| (*a - *b).abs() < 0.01);
+        // The bullet line, the second paragraph and the next item, top to bottom
+        assert_eq!(rows.len(), 3);
+        let first = line(rows[0]);
+        let bullet = first.iter().copied().fold(f32::MAX, f32::min);
+        let text = first.iter().copied().fold(f32::MIN, f32::max);
+        assert!(text > bullet);
+        // The second paragraph hangs under the item text, not under the bullet
+        let second = line(rows[1]);
+        assert!(second.iter().all(|x| (x - text).abs() < 0.01));
+    }
 
-        assert!(result.is_ok());
-        let pdf_bytes = result.unwrap();
-        assert!(!pdf_bytes.is_empty());
-        // PDF files should start with "%PDF-"
-        assert!(pdf_bytes.starts_with(b"%PDF-"));
+    #[test]
+    fn test_render_list_marker_column() {
+        let item = |n: usize| Token::ListItem {
+            content: vec![Token::Text(format!("Item {}", n))],
+            ordered: true,
+            number: Some(n),
+        };
+        let tokens = vec![Token::ListItem {
+            content: vec![Token::Text("Parent".to_string()), item(9), item(10)],
+            ordered: true,
+            number: Some(1),
+        }];
+
+        let mut style = StyleMatch::default();
+        style.list_marker_width = 6.0;
+        style.list_indent = 4.0;
+        let pdf = Pdf::new(tokens, style, None);
+        assert!(Pdf::render_to_bytes(pdf.render_into_document()).is_ok());
     }
 
     #[test]
-    fn test_render_to_bytes_empty_document() {
-        let pdf = create_test_pdf(vec![]);
-        let doc = pdf.render_into_document();
-        let result = Pdf::render_to_bytes(doc);
+    fn test_first_page_margins() {
+        // A long document spans several pages with the first one using its own margins
+        let tokens: Vec<Token> = (0..120)
+            .flat_map(|i| {
+                vec![
+                    Token::Text(format!("Paragraph {} with some text.", i)),
+                    Token::Newline,
+                    Token::Newline,
+                ]
+            })
+            .collect();
+        let top_lines = |first_top: Option<f32>| {
+            let mut style = StyleMatch::default();
+            style.first_page_margins = first_top.map(|top| crate::styling::Margins {
+                top,
+                ..style.margins
+            });
+            let pdf = Pdf::new(tokens.clone(), style, None);
+            let bytes = Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+            let doc = lopdf::Document::load_mem(&bytes).unwrap();
+            let pages = doc.get_pages();
+            assert!(pages.len() > 1);
+            // Baseline of the first text line on the first and second page
+            let top_line = |page: u32| {
+                let content = doc.get_page_content(pages[&page]).unwrap();
+                lopdf::content::Content::decode(&content)
+                    .unwrap()
+                    .operations
+                    .iter()
+                    .find(|op| op.operator == "Td" || op.operator == "Tm")
+                    .map(|op| op.operands.last().unwrap().as_float().unwrap())
+                    .unwrap()
+            };
+            (top_line(1), top_line(2))
+        };
 
-        assert!(result.is_ok());
-        let pdf_bytes = result.unwrap();
-        assert!(!pdf_bytes.is_empty());
-        assert!(pdf_bytes.starts_with(b"%PDF-"));
+        let (first, second) = top_lines(None);
+        assert!((first - second).abs() < 0.01);
+
+        // The first page starts 60 mm lower, the following pages keep the regular margin
+        let top = StyleMatch::default().margins.top;
+        let (first_wide, second_wide) = top_lines(Some(top + 60.0));
+        assert!((second_wide - second).abs() < 0.01);
+        assert!((first - first_wide - 60.0 / PT_TO_MM).abs() < 1.0);
     }
 
     #[test]
-    fn test_render_to_bytes_complex_content() {
+    fn test_strike_operators() {
+        let ops = strike_operators(10.0, 20.0, 40.0, 30.0, b"FF0000");
+        assert_eq!(
+            ops,
+            "q\n1.000 0.000 0.000 RG\n0.60 w\n10.00 24.00 m\n40.00 24.00 l\nS\nQ\n"
+        );
+        assert!(strike_operators(0.0, 0.0, 1.0, 1.0, b"-").is_empty());
+    }
+
+    #[test]
+    fn test_underline_operators() {
+        let ops = underline_operators(10.0, 20.0, 40.0, 30.0, b"0000FF");
+        assert!(ops.starts_with("q\n0.000 0.000 1.000 RG\n"));
+        assert!(ops.contains("10.00 21.80 m\n40.00 21.80 l\n"));
+        assert!(underline_operators(0.0, 0.0, 1.0, 1.0, b"-").is_empty());
+    }
+
+    #[test]
+    fn test_render_inline_html_tags() {
+        let tokens = crate::markdown::Lexer::new(
+            "Keep <mark>this</mark>, <ins>add</ins> and <del>drop</del>".to_string(),
+        )
+        .parse()
+        .unwrap();
+        let pdf = Pdf::new(tokens, StyleMatch::default(), None);
+        let bytes = Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+        assert!(pdf.has_decorations());
+        // The decoration state is restored once the elements end
+        assert_eq!(pdf.strike_color.get(), None);
+        assert_eq!(pdf.underline_color.get(), None);
+        assert_eq!(pdf.highlight_color.get(), None);
+
+        let drawn = Pdf::draw_decorations(&bytes).unwrap();
+        let doc = lopdf::Document::load_mem(&drawn).unwrap();
+        let (_, page_id) = doc.get_pages().into_iter().next().unwrap();
+        let page = doc.get_dictionary(page_id).unwrap();
+        let contents = page.get(b"Contents").unwrap().as_array().unwrap();
+        let stream_content = |object: &lopdf::Object| {
+            let id = object.as_reference().unwrap();
+            let stream = doc.get_object(id).unwrap().as_stream().unwrap();
+            String::from_utf8_lossy(&stream.content).to_string()
+        };
+        // The highlight is filled below the text, the two lines are drawn above it
+        assert!(stream_content(&contents[0]).contains("1.000 0.945 0.463 rg"));
+        let last = stream_content(contents.last().unwrap());
+        assert_eq!(last.matches(" l\nS\nQ\n").count(), 2);
+    }
+
+    #[test]
+    fn test_render_critic_markup() {
         let tokens = vec![
-            Token::Heading(vec![Token::Text("Main Title".to_string())], 1),
-            Token::Text("Introduction paragraph.".to_string()),
-            Token::Heading(vec![Token::Text("Section 1".to_string())], 2),
-            Token::ListItem {
-                content: vec![Token::Text("First item".to_string())],
-                ordered: false,
-                number: None,
-            },
-            Token::ListItem {
-                content: vec![Token::Text("Second item".to_string())],
-                ordered: false,
-                number: None,
-            },
-            Token::Code(
-                "rust".to_string(),
-                "fn main() {\n    println!(\"Hello\");\n}".to_string(),
+            Token::Text("Keep ".to_string()),
+            Token::Critic(
+                CriticMarkup::Addition,
+                vec![Token::Text("added".to_string())],
             ),
-            Token::Link(
-                "Example Link".to_string(),
-                "https://example.com".to_string(),
+            Token::Text(" and ".to_string()),
+            Token::Critic(
+                CriticMarkup::Deletion,
+                vec![Token::Text("removed".to_string())],
             ),
         ];
+        let pdf = Pdf::new(tokens, StyleMatch::default(), None);
+        let bytes = Pdf::render_to_bytes(pdf.render_into_document()).unwrap();
+        assert_eq!(pdf.strike_color.get(), None);
+        assert_eq!(pdf.underline_color.get(), None);
+
+        let drawn = Pdf::draw_decorations(&bytes).unwrap();
+        let doc = lopdf::Document::load_mem(&drawn).unwrap();
+        let (_, page_id) = doc.get_pages().into_iter().next().unwrap();
+        let content = doc.get_page_content(page_id).unwrap();
+        let operations = lopdf::content::Content::decode(&content)
+            .unwrap()
+            .operations;
+        // The inserted text is set in green and the deleted text in red
+        let fill_colors: Vec<Vec<f32>> = operations
+            .iter()
+            .filter(|op| op.operator == "rg")
+            .map(|op| op.operands.iter().map(|o| o.as_float().unwrap()).collect())
+            .collect();
+        for (r, g, b) in [CRITIC_ADDITION_COLOR, CRITIC_DELETION_COLOR] {
+            let rgb = [r, g, b].map(|c| c as f32 / 255.0);
+            assert!(fill_colors
+                .iter()
+                .any(|color| color.iter().zip(rgb).all(|(a, b)| (a - b).abs() < 0.01)));
+        }
+        // ... underlined and struck through in the same colors
+        let page = String::from_utf8_lossy(&content).to_string();
+        assert!(page.contains("0.000 0.502 0.000 RG"));
+        assert!(page.contains("0.753 0.000 0.000 RG"));
+        assert_eq!(page.matches(" l\nS\nQ\n").count(), 2);
+    }
+
+    #[test]
+    fn test_kbd_box_operators() {
+        let ops = kbd_box_operators(10.0, 20.0, 40.0, 32.0, b"F0F0F0:A0A0A0");
+        assert!(ops.starts_with("q\n0.941 0.941 0.941 rg\n0.627 0.627 0.627 RG\n"));
+        assert!(ops.ends_with("h B\nQ\n"));
+        assert!(kbd_box_operators(0.0, 0.0, 10.0, 10.0, b"F0F0F0:-").ends_with("h f\nQ\n"));
+        assert!(kbd_box_operators(0.0, 0.0, 10.0, 10.0, b"-:-").is_empty());
+    }
+
+    #[test]
+    fn test_render_wrapped_list_item() {
+        let long_text = "This list item is long enough to wrap onto several lines, ".repeat(6);
+        let tokens = vec![Token::ListItem {
+            content: vec![
+                Token::Text(long_text.clone()),
+                Token::ListItem {
+                    content: vec![Token::Text(long_text)],
+                    ordered: true,
+                    number: Some(1),
+                },
+            ],
+            ordered: false,
+            number: None,
+        }];
         let pdf = create_test_pdf(tokens);
         let doc = pdf.render_into_document();
-        let result = Pdf::render_to_bytes(doc);
+        assert!(Pdf::render(doc, "/dev/null").is_none());
+    }
 
-        assert!(result.is_ok());
-        let pdf_bytes = result.unwrap();
-        assert!(!pdf_bytes.is_empty());
-        assert!(pdf_bytes.starts_with(b"%PDF-"));
+    #[test]
+    fn test_render_soft_break_modes() {
+        let tokens = vec![
+            Token::Text("First line".to_string()),
+            Token::SoftBreak,
+            Token::Text("Second line".to_string()),
+            Token::SoftBreak,
+        ];
+
+        let pdf = create_test_pdf(tokens.clone());
+        assert_eq!(pdf.soft_break_text(), " ");
+        assert!(Pdf::render(pdf.render_into_document(), "/dev/null").is_none());
+
+        let mut style = StyleMatch::default();
+        style.soft_break = SoftBreakMode::Newline;
+        let pdf = Pdf::new(tokens, style, None);
+        assert_eq!(pdf.soft_break_text(), "\n");
+        assert!(Pdf::render(pdf.render_into_document(), "/dev/null").is_none());
     }
 }
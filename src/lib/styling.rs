@@ -61,6 +61,10 @@ pub struct BasicTextStyle {
     pub strikethrough: bool,
     /// Background color in RGB format
     pub background_color: Option<(u8, u8, u8)>,
+    /// Extra space between characters in points (tracking), applied to headings
+    pub letter_spacing: f32,
+    /// Case transform of the text, applied to headings
+    pub text_transform: TextTransform,
 }
 
 impl BasicTextStyle {
@@ -103,8 +107,71 @@ impl BasicTextStyle {
             underline,
             strikethrough,
             background_color,
+            letter_spacing: 0.0,
+            text_transform: TextTransform::None,
         }
     }
+
+    /// Returns the style with the given font size in points.
+    pub fn with_size(mut self, size: u8) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// Returns the style with the given RGB text color.
+    pub fn with_text_color(mut self, color: (u8, u8, u8)) -> Self {
+        self.text_color = Some(color);
+        self
+    }
+
+    /// Returns the style with the given RGB background color.
+    pub fn with_background_color(mut self, color: (u8, u8, u8)) -> Self {
+        self.background_color = Some(color);
+        self
+    }
+
+    /// Returns the style with the given space before and after the element, in points.
+    pub fn with_spacing(mut self, before: f32, after: f32) -> Self {
+        self.before_spacing = before;
+        self.after_spacing = after;
+        self
+    }
+
+    /// Returns the style with the given text alignment.
+    pub fn with_alignment(mut self, alignment: TextAlignment) -> Self {
+        self.alignment = Some(alignment);
+        self
+    }
+
+    /// Returns the style with the given font family name.
+    pub fn with_font_family(mut self, font_family: &'static str) -> Self {
+        self.font_family = Some(font_family);
+        self
+    }
+
+    /// Returns the style with bold text enabled or disabled.
+    pub fn with_bold(mut self, bold: bool) -> Self {
+        self.bold = bold;
+        self
+    }
+
+    /// Returns the style with italic text enabled or disabled.
+    pub fn with_italic(mut self, italic: bool) -> Self {
+        self.italic = italic;
+        self
+    }
+
+    /// Returns the style with underlined text enabled or disabled.
+    pub fn with_underline(mut self, underline: bool) -> Self {
+        self.underline = underline;
+        self
+    }
+
+    /// Returns the style with strikethrough text enabled or disabled.
+    pub fn with_strikethrough(mut self, strikethrough: bool) -> Self {
+        self.strikethrough = strikethrough;
+        self
+    }
 }
 
 // LSP in vim behaves strangely with this default implementation.
@@ -179,46 +246,724 @@ impl Default for MermaidConfig {
     }
 }
 
+/// Size of the QR codes rendered from ```` ```qr ```` blocks (`[qr]`).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QrConfig {
+    /// Width of a QR code as a percentage of the text width
+    pub width: f32,
+}
+
+impl Default for QrConfig {
+    fn default() -> Self {
+        Self { width: 25.0 }
+    }
+}
+
+/// Limits applied when downloading remote images.
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub struct RemoteImageConfig {
+    /// Maximum number of bytes accepted for a single download (`None` = unlimited)
+    pub max_download_size: Option<u64>,
+}
+
+/// Grid layout of consecutive images, for photo galleries (`[image.gallery]`).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ImageGalleryConfig {
+    /// Images per row; 0 keeps consecutive images flowing side by side
+    pub columns: usize,
+    /// Space between the images of the grid in millimeters
+    pub gutter: f32,
+}
+
+impl Default for ImageGalleryConfig {
+    fn default() -> Self {
+        Self {
+            columns: 0,
+            gutter: 2.0,
+        }
+    }
+}
+
+/// Appearance of `<kbd>` keyboard keys, drawn as small rounded boxes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KbdConfig {
+    /// Text style of the key label; `background_color` fills the box
+    pub style: BasicTextStyle,
+    /// Color of the box outline (`None` = no outline)
+    pub border_color: Option<(u8, u8, u8)>,
+}
+
+impl Default for KbdConfig {
+    fn default() -> Self {
+        Self {
+            style: BasicTextStyle::new(
+                8,
+                Some((40, 40, 40)),
+                None,
+                None,
+                None,
+                None,
+                false,
+                false,
+                false,
+                false,
+                Some((240, 240, 240)),
+            ),
+            border_color: Some((160, 160, 160)),
+        }
+    }
+}
+
+/// A font assigned to a Unicode range through `[fonts.ranges]`.
+///
+/// Characters inside `start..=end` are rendered with `font` instead of the main text
+/// font, which keeps mixed-script documents readable without relying on the generic
+/// fallback list.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FontRange {
+    /// First code point of the range (inclusive)
+    pub start: u32,
+    /// Last code point of the range (inclusive)
+    pub end: u32,
+    /// Font family name used for characters in the range
+    pub font: String,
+}
+
+impl FontRange {
+    /// Returns true if `c` falls inside this range.
+    pub fn contains(&self, c: char) -> bool {
+        (self.start..=self.end).contains(&(c as u32))
+    }
+}
+
+/// How abbreviation expansions are presented in the document.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AbbreviationMode {
+    /// Append the expansion in parentheses the first time the abbreviation is used
+    FirstUse,
+    /// Collect used abbreviations into a glossary section at the end of the document
+    Glossary,
+}
+
+/// Configuration for abbreviations defined with `*[ABBR]: expansion`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AbbreviationConfig {
+    /// Where the expansion is shown
+    pub mode: AbbreviationMode,
+    /// Style applied to abbreviation occurrences in the text
+    pub style: BasicTextStyle,
+}
+
+impl Default for AbbreviationConfig {
+    fn default() -> Self {
+        Self {
+            mode: AbbreviationMode::Glossary,
+            style: BasicTextStyle::new(
+                8,
+                Some((64, 64, 160)),
+                None,
+                None,
+                None,
+                None,
+                false,
+                false,
+                true,
+                false,
+                None,
+            ),
+        }
+    }
+}
+
+/// How a single newline inside a paragraph is rendered.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SoftBreakMode {
+    /// Join the lines with a space, as CommonMark specifies
+    Space,
+    /// Start a new line in the PDF for every source line
+    Newline,
+}
+
+/// Width limit of the body text column (`[text] max_width`).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MaxWidth {
+    /// Width in points
+    Points(f32),
+    /// Width in characters of the text size, estimated at half an em per character
+    Characters(u32),
+}
+
+/// Which quotation marks straight `"` and `'` quotes in the text are typeset with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum QuoteStyle {
+    /// Keep the quotes as written
+    Straight,
+    /// English curly quotes: “double” and ‘single’
+    En,
+    /// French guillemets with no-break spaces: « double » and “single”
+    Fr,
+    /// German low-high quotes: „double“ and ‚single‘
+    De,
+}
+
+impl QuoteStyle {
+    /// Opening and closing double quotes, then opening and closing single quotes;
+    /// `None` for straight quotes
+    pub fn marks(self) -> Option<[&'static str; 4]> {
+        match self {
+            QuoteStyle::Straight => None,
+            QuoteStyle::En => Some(["\u{201C}", "\u{201D}", "\u{2018}", "\u{2019}"]),
+            QuoteStyle::Fr => Some(["\u{AB}\u{A0}", "\u{A0}\u{BB}", "\u{201C}", "\u{201D}"]),
+            QuoteStyle::De => Some(["\u{201E}", "\u{201C}", "\u{201A}", "\u{2018}"]),
+        }
+    }
+}
+
+/// How syntax highlighting colors code blocks.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CodeHighlightStyle {
+    /// The colors of the highlighting theme
+    Color,
+    /// Theme colors converted to shades of gray, for black and white printing; the
+    /// theme's bold and italic tokens keep them distinguishable
+    Grayscale,
+}
+
+/// How tables wider than the page are laid out.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TableOverflow {
+    /// Size columns by their content and shrink the font until the longest words fit
+    Shrink,
+    /// Keep equal columns and the configured sizes, letting long words overflow
+    Scroll,
+}
+
+/// How code block lines wider than the text width are laid out.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CodeFit {
+    /// Keep the configured size and wrap long lines
+    Wrap,
+    /// Reduce the font size of the block until its widest line fits, down to a floor
+    Shrink,
+}
+
+/// How links are presented in the document.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LinkStyle {
+    /// Clickable link text
+    Inline,
+    /// Link text followed by a footnote marker; the URL is listed with the footnotes, so
+    /// it stays readable on paper
+    Footnote,
+    /// Clickable link text followed by the URL in parentheses, so printed documents show
+    /// the destination without footnotes
+    Parenthetical,
+}
+
+/// Paper size of the generated pages.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PageSize {
+    /// 297 × 420 mm
+    A3,
+    /// 210 × 297 mm
+    A4,
+    /// 148 × 210 mm
+    A5,
+    /// 8.5 × 11 in
+    Letter,
+    /// 8.5 × 14 in
+    Legal,
+}
+
+impl PageSize {
+    /// Parses a paper size name such as `"a4"` or `"letter"`, ignoring case.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "a3" => Some(PageSize::A3),
+            "a4" => Some(PageSize::A4),
+            "a5" => Some(PageSize::A5),
+            "letter" => Some(PageSize::Letter),
+            "legal" => Some(PageSize::Legal),
+            _ => None,
+        }
+    }
+
+    /// Returns the name used in the configuration file and on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            PageSize::A3 => "a3",
+            PageSize::A4 => "a4",
+            PageSize::A5 => "a5",
+            PageSize::Letter => "letter",
+            PageSize::Legal => "legal",
+        }
+    }
+
+    /// Returns the portrait width and height in millimeters.
+    pub fn dimensions(self) -> (f32, f32) {
+        match self {
+            PageSize::A3 => (297.0, 420.0),
+            PageSize::A4 => (210.0, 297.0),
+            PageSize::A5 => (148.0, 210.0),
+            PageSize::Letter => (215.9, 279.4),
+            PageSize::Legal => (215.9, 355.6),
+        }
+    }
+}
+
+/// Case transform applied to text when it is rendered (`text_transform`).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextTransform {
+    /// Text is rendered as written
+    None,
+    /// Letters are rendered as capitals
+    Uppercase,
+    /// Lowercase letters are rendered as capitals at a reduced size
+    SmallCaps,
+}
+
+impl TextTransform {
+    /// Parses `"none"`, `"uppercase"` or `"smallcaps"`, ignoring case.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "none" => Some(TextTransform::None),
+            "uppercase" => Some(TextTransform::Uppercase),
+            "smallcaps" | "small-caps" => Some(TextTransform::SmallCaps),
+            _ => None,
+        }
+    }
+
+    /// Returns the name used in the configuration file.
+    pub fn name(self) -> &'static str {
+        match self {
+            TextTransform::None => "none",
+            TextTransform::Uppercase => "uppercase",
+            TextTransform::SmallCaps => "smallcaps",
+        }
+    }
+}
+
+/// Orientation of the generated pages.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Orientation {
+    /// Pages are taller than wide
+    Portrait,
+    /// Pages are wider than tall
+    Landscape,
+}
+
+impl Orientation {
+    /// Parses `"portrait"` or `"landscape"`, ignoring case.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "portrait" => Some(Orientation::Portrait),
+            "landscape" => Some(Orientation::Landscape),
+            _ => None,
+        }
+    }
+
+    /// Returns the name used in the configuration file and on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            Orientation::Portrait => "portrait",
+            Orientation::Landscape => "landscape",
+        }
+    }
+}
+
+/// Appearance of task list items (`- [ ]` and `- [x]`).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TaskListConfig {
+    /// Color of the box and check mark of completed items
+    pub checked_color: Option<(u8, u8, u8)>,
+    /// Color of the box of open items
+    pub unchecked_color: Option<(u8, u8, u8)>,
+    /// Text color of completed items (`None` = list item color)
+    pub done_color: Option<(u8, u8, u8)>,
+    /// Strike through the text of completed items
+    pub strikethrough_done: bool,
+    /// Render a "Progress: 7/10 completed" line under each list containing tasks
+    pub show_progress: bool,
+}
+
+impl Default for TaskListConfig {
+    fn default() -> Self {
+        Self {
+            checked_color: Some((46, 125, 50)),
+            unchecked_color: Some((120, 120, 120)),
+            done_color: None,
+            strikethrough_done: false,
+            show_progress: false,
+        }
+    }
+}
+
+/// Document-level output settings.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct DocumentConfig {
+    /// Produce byte-identical output for identical input (fixed dates and document ID).
+    /// Also enabled when `source_date_epoch` is set.
+    pub reproducible: bool,
+    /// Timestamp embedded in reproducible output, in seconds since the Unix epoch. The
+    /// conversion entry points take it from the `SOURCE_DATE_EPOCH` environment variable
+    /// when it is not set; setting it also enables reproducible output.
+    pub source_date_epoch: Option<i64>,
+    /// Use a leading level 1 heading as the document title instead of rendering it
+    pub title_from_h1: bool,
+    /// Embed the markdown source in the PDF as a file attachment
+    pub attach_source: bool,
+    /// Create missing parent directories of the output file instead of failing
+    pub create_dirs: bool,
+    /// Language of the content as a BCP 47 tag such as `en-US`, recorded in the PDF for
+    /// screen readers
+    pub lang: Option<String>,
+    /// Append pages showing every page as a small thumbnail in a grid
+    pub contact_sheet: bool,
+}
+
+/// Automatic numbering of headings (`[heading] numbered`), such as `1.2` before a level 2
+/// heading. Only levels `from` to `to` are numbered, so a single level 1 title can stay
+/// unnumbered above its numbered sections.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HeadingNumbering {
+    /// Prefix headings with their section number
+    pub enabled: bool,
+    /// Highest heading level that is numbered, 1 for `#`
+    pub from: usize,
+    /// Lowest heading level that is numbered
+    pub to: usize,
+}
+
+impl Default for HeadingNumbering {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            from: 1,
+            to: 6,
+        }
+    }
+}
+
+impl HeadingNumbering {
+    /// Advances `counters`, one per heading level, for a heading of `level` and returns
+    /// its number, e.g. `"2.1"`, or `None` when the level is not numbered. Every heading
+    /// restarts the count of the levels below it, numbered or not.
+    pub fn next_number(&self, counters: &mut [usize; 6], level: usize) -> Option<String> {
+        let level = level.clamp(1, 6);
+        counters[level - 1] += 1;
+        counters[level..].iter_mut().for_each(|count| *count = 0);
+        if !self.enabled || level < self.from || level > self.to {
+            return None;
+        }
+        let number: Vec<String> = counters[self.from - 1..level]
+            .iter()
+            .map(|count| count.to_string())
+            .collect();
+        Some(number.join("."))
+    }
+}
+
+/// Default height in millimeters of header and footer logos (`image_height`).
+pub const DEFAULT_PAGE_LOGO_HEIGHT: f32 = 6.0;
+
+/// Logo image drawn in the header or footer margin of every page (`image`,
+/// `image_alignment` and `image_height` in `[page.header]` and `[page.footer]`).
+#[derive(Clone, Debug, PartialEq)]
+pub struct PageLogo {
+    /// Path or URL of the image, resolved like the images of the document
+    pub image: String,
+    /// Placement between the left and right margins (`image_alignment`)
+    pub alignment: TextAlignment,
+    /// Height in millimeters, reduced to the height of the margin when larger
+    /// (`image_height`)
+    pub height: f32,
+}
+
+/// Content of the top margin of every page (`[page.header]`).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HeaderConfig {
+    /// Logo drawn in the middle of the margin (`image`)
+    pub logo: Option<PageLogo>,
+}
+
+/// Line printed in the bottom margin of every page (`[page.footer]`): the generation date
+/// on the left and the page number on the right, with an optional logo.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FooterConfig {
+    /// Print the date the document was generated (`show_date`)
+    pub show_date: bool,
+    /// Format of the date (`date_format`), where `%Y`, `%m`, `%d`, `%H`, `%M` and `%S` stand
+    /// for the year, month, day, hours, minutes and seconds in UTC and `%%` for a `%`
+    pub date_format: String,
+    /// Print the page number (`page_numbers`)
+    pub page_numbers: bool,
+    /// Font size of the footer in points
+    pub size: u8,
+    /// Logo drawn in the middle of the margin (`image`)
+    pub logo: Option<PageLogo>,
+}
+
+impl Default for FooterConfig {
+    fn default() -> Self {
+        Self {
+            show_date: false,
+            date_format: "%Y-%m-%d".to_string(),
+            page_numbers: false,
+            size: 8,
+            logo: None,
+        }
+    }
+}
+
+impl FooterConfig {
+    /// Whether the footer prints a line of text; its logo is drawn on its own.
+    pub fn is_enabled(&self) -> bool {
+        self.show_date || self.page_numbers
+    }
+}
+
+/// Index of the terms tagged with `<!-- index: term -->` comments, listed with their
+/// pages at the end of the document.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IndexConfig {
+    /// Collect tagged terms and render the index; when false the tags are ignored
+    pub enabled: bool,
+    /// Heading of the index section
+    pub title: String,
+}
+
+impl Default for IndexConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            title: "Index".to_string(),
+        }
+    }
+}
+
+/// Settings for the configuration checks run by `validation::validate_conversion_with_style`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ValidationConfig {
+    /// Minimum contrast ratio between a text color and its background (1.0 to 21.0).
+    /// The default of 3.0 is the WCAG level AA minimum for large text; 0 disables the check.
+    pub min_contrast: f32,
+}
+
+impl Default for ValidationConfig {
+    fn default() -> Self {
+        Self { min_contrast: 3.0 }
+    }
+}
+
+/// Appearance of `||spoiler||` text, revealed in a bordered box under a label.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpoilerConfig {
+    /// Text style of the spoiler content; the spacing applies around the box
+    pub style: BasicTextStyle,
+    /// Title shown in bold above the content
+    pub label: String,
+}
+
+impl Default for SpoilerConfig {
+    fn default() -> Self {
+        Self {
+            style: BasicTextStyle::new(
+                8,
+                None,
+                Some(0.5),
+                Some(0.5),
+                None,
+                None,
+                false,
+                false,
+                false,
+                false,
+                None,
+            ),
+            label: "Spoiler".to_string(),
+        }
+    }
+}
+
+/// Spacing settings that apply to the document as a whole.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SpacingConfig {
+    /// Drops the spacing before the first element and after the last one, so the
+    /// content starts at the top margin and ends right after its last line
+    pub trim_edges: bool,
+}
+
+/// Redaction of sensitive text (`[redact]`): text matching one of the regular expressions
+/// is replaced by a run of `mask` characters of the same length before rendering.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RedactConfig {
+    /// Regular expressions of the text to hide
+    pub patterns: Vec<String>,
+    /// Character each redacted character is replaced with
+    pub mask: char,
+}
+
+impl Default for RedactConfig {
+    fn default() -> Self {
+        Self {
+            patterns: Vec::new(),
+            mask: '█',
+        }
+    }
+}
+
 /// Main style configuration for mapping markdown elements to PDF styles.
 ///
 /// This struct contains style definitions for each markdown element type
 /// that can appear in the document. It is used by the PDF renderer to
 /// determine how to format each element.
+#[derive(Clone, Debug, PartialEq)]
 pub struct StyleMatch {
     /// Document margins
     pub margins: Margins,
+    /// Margins of the first page, e.g. a wide top margin for a title page
+    /// (`[page] first_page_margins`); `None` uses `margins` on every page
+    pub first_page_margins: Option<Margins>,
+    /// Paper size (`[page] size`)
+    pub page_size: PageSize,
+    /// Page orientation (`[page] orientation`)
+    pub orientation: Orientation,
+    /// Lines of a paragraph split across pages that must start the next page
+    /// (`[page] min_lines_top`); 1 allows a single widow line
+    pub min_lines_top: usize,
+    /// Lines of a paragraph split across pages that must stay at the bottom of the page
+    /// (`[page] min_lines_bottom`); 1 allows a single orphan line
+    pub min_lines_bottom: usize,
+    /// Logo at the top of every page (`[page.header]`)
+    pub header: HeaderConfig,
+    /// Date and page number line at the bottom of every page (`[page.footer]`)
+    pub footer: FooterConfig,
     /// Style for level 1 headings (#)
     pub heading_1: BasicTextStyle,
     /// Style for level 2 headings (##)
     pub heading_2: BasicTextStyle,
     /// Style for level 3 headings (###)
     pub heading_3: BasicTextStyle,
+    /// Start a new page before headings of each level (`[heading.N] page_break_before`)
+    pub page_break_before: [bool; 3],
+    /// Section numbers before headings (`[heading] numbered`, `number_from`, `number_to`)
+    pub heading_numbering: HeadingNumbering,
     /// Style for emphasized text (*text* or _text_)
     pub emphasis: BasicTextStyle,
     /// Style for strongly emphasized text (**text** or __text__)
     pub strong_emphasis: BasicTextStyle,
     /// Style for inline code (`code`)
     pub code: BasicTextStyle,
+    /// Whether code blocks show their fence language in the top-right corner
+    /// (`[code] show_language_label`)
+    pub show_code_language: bool,
+    /// How code blocks are highlighted (`[code] style`)
+    pub code_highlight_style: CodeHighlightStyle,
+    /// Whether a code block that fits on a page starts on a new page rather than splitting
+    /// (`[code] keep_together`)
+    pub code_keep_together: bool,
+    /// Whether inline code is drawn on a light rounded box (`[code] inline_background`,
+    /// on when the configuration sets a `[code] backgroundcolor`)
+    pub code_inline_background: bool,
+    /// Lines after which a code block is cut with a `... (N more lines)` note, 0 for no
+    /// limit (`[code] max_lines`). Long lines are not cut, so a minified file on a single
+    /// line is kept whole
+    pub code_max_lines: usize,
+    /// How code blocks with lines wider than the text width are laid out (`[code] fit`)
+    pub code_fit: CodeFit,
+    /// Style for the language label of code blocks (`[code.label]`)
+    pub code_label: BasicTextStyle,
     /// Style for block quotes (> quote)
     pub block_quote: BasicTextStyle,
+    /// Style for the `— Author` last line of block quotes, which is rendered as quote text
+    /// when unset (`[block_quote.attribution]`)
+    pub block_quote_attribution: Option<BasicTextStyle>,
     /// Style for list items (- item or * item)
     pub list_item: BasicTextStyle,
+    /// Width in millimeters of the column list markers are right-aligned in; item text
+    /// starts after it (`[list_item] marker_width`)
+    pub list_marker_width: f32,
+    /// Horizontal offset in millimeters applied per list nesting level
+    /// (`[list_item] indent`)
+    pub list_indent: f32,
+    /// Deepest list nesting rendered as such; items nested deeper are flattened to that
+    /// level (`[list_item] max_nesting`)
+    pub list_max_nesting: usize,
     /// Style for links ([text](url))
     pub link: BasicTextStyle,
+    /// Whether link URLs stay hidden behind the text or are moved to footnotes
+    /// (`[link] style`)
+    pub link_style: LinkStyle,
+    /// Base URL relative link targets are resolved against (`[link] base_url`)
+    pub link_base_url: Option<String>,
     /// Style for images (![alt](url))
     pub image: BasicTextStyle,
+    /// Base URL relative image paths are resolved against (`[image] base_url`)
+    pub image_base_url: Option<String>,
     /// Style for regular text
     pub text: BasicTextStyle,
+    /// Rendering of single newlines inside paragraphs (`[text] soft_break`)
+    pub soft_break: SoftBreakMode,
+    /// Quotation marks replacing straight quotes in the text (`[text] quote_style`)
+    pub quote_style: QuoteStyle,
+    /// Width of the centered column body paragraphs are set in (`[text] max_width`);
+    /// `None` uses the full text width
+    pub text_max_width: Option<MaxWidth>,
+    /// Font file the text is set in, loaded directly instead of looking the font up by
+    /// name (`[text] fontfile`)
+    pub text_font_file: Option<String>,
     /// Style for LaTeX math rendering
     pub latex: BasicTextStyle,
     /// Style for table headers
     pub table_header: BasicTextStyle,
     /// Style for table cells
     pub table_cell: BasicTextStyle,
+    /// Layout of tables wider than the page (`[table] overflow`)
+    pub table_overflow: TableOverflow,
+    /// Whether a table too wide for the page at the smallest table font is turned onto a
+    /// page of its own in the other orientation, when it fits that page
+    /// (`[table] rotate_if_oversized`)
+    pub table_rotate_if_oversized: bool,
     /// Configuration for SVG image rendering
     pub svg_config: SvgImageConfig,
     /// Mermaid rendering configuration
     pub mermaid: MermaidConfig,
+    /// Size of QR codes (`[qr]`)
+    pub qr: QrConfig,
+    /// Limits for remote image downloads
+    pub remote_images: RemoteImageConfig,
+    /// Grid layout of consecutive images
+    pub image_gallery: ImageGalleryConfig,
+    /// Image shown in place of images that fail to load (`None` keeps a text placeholder)
+    pub fallback_image: Option<String>,
+    /// Enlarge raster images narrower than the image area instead of keeping their
+    /// natural size
+    pub image_upscale: bool,
+    /// Print resolution raster images are sized for, from `[image] target_dpi`; `None`
+    /// keeps their natural size
+    pub image_target_dpi: Option<f32>,
+    /// Per-script fonts from `[fonts.ranges]`, most specific range first
+    pub font_ranges: Vec<FontRange>,
+    /// Abbreviation rendering configuration
+    pub abbreviations: AbbreviationConfig,
+    /// Keyboard key (`<kbd>`) rendering configuration
+    pub kbd: KbdConfig,
+    /// Style for highlighted `<mark>` text; the background color fills the highlight
+    pub mark: BasicTextStyle,
+    /// Spoiler (`||text||`) box configuration
+    pub spoiler: SpoilerConfig,
+    /// Task list checkbox rendering configuration
+    pub task_list: TaskListConfig,
+    /// Document-level output settings
+    pub document: DocumentConfig,
+    /// Index of tagged terms rendered at the end of the document
+    pub index: IndexConfig,
+    /// Settings for configuration checks such as color contrast
+    pub validation: ValidationConfig,
+    /// Document-wide spacing settings from `[spacing]`
+    pub spacing: SpacingConfig,
+    /// Text hidden before rendering (`[redact]`)
+    pub redact: RedactConfig,
 
     // TODO: Not parsed into a actual horizontal rule currently, we need a proper styling for this
     /// Style for horizontal rules (---)
@@ -240,6 +985,13 @@ impl Default for StyleMatch {
                 bottom: 8.0,
                 left: 8.0,
             },
+            first_page_margins: None,
+            page_size: PageSize::A4,
+            orientation: Orientation::Portrait,
+            min_lines_top: 1,
+            min_lines_bottom: 1,
+            header: HeaderConfig::default(),
+            footer: FooterConfig::default(),
             heading_1: BasicTextStyle::new(
                 14,
                 Some((0, 0, 0)),
@@ -279,6 +1031,8 @@ impl Default for StyleMatch {
                 false,
                 None,
             ),
+            page_break_before: [false; 3],
+            heading_numbering: HeadingNumbering::default(),
             emphasis: BasicTextStyle::new(
                 8,
                 Some((0, 0, 0)),
@@ -318,6 +1072,25 @@ impl Default for StyleMatch {
                 false,
                 Some((230, 230, 230)),
             ),
+            show_code_language: false,
+            code_highlight_style: CodeHighlightStyle::Color,
+            code_keep_together: false,
+            code_inline_background: false,
+            code_max_lines: 0,
+            code_fit: CodeFit::Wrap,
+            code_label: BasicTextStyle::new(
+                6,
+                Some((90, 90, 90)),
+                None,
+                None,
+                Some(TextAlignment::Right),
+                None,
+                false,
+                false,
+                false,
+                false,
+                Some((215, 215, 215)),
+            ),
             block_quote: BasicTextStyle::new(
                 8,
                 Some((128, 128, 128)),
@@ -331,6 +1104,7 @@ impl Default for StyleMatch {
                 false,
                 Some((245, 245, 245)),
             ),
+            block_quote_attribution: None,
             list_item: BasicTextStyle::new(
                 8,
                 Some((0, 0, 0)),
@@ -344,6 +1118,9 @@ impl Default for StyleMatch {
                 false,
                 None,
             ),
+            list_marker_width: 10.0,
+            list_indent: 10.0,
+            list_max_nesting: 32,
             table_header: BasicTextStyle::new(
                 8,
                 Some((0, 0, 0)),
@@ -383,6 +1160,8 @@ impl Default for StyleMatch {
                 false,
                 None,
             ),
+            link_style: LinkStyle::Inline,
+            link_base_url: None,
             image: BasicTextStyle::new(
                 8,
                 Some((0, 0, 0)),
@@ -396,6 +1175,7 @@ impl Default for StyleMatch {
                 false,
                 None,
             ),
+            image_base_url: None,
             text: BasicTextStyle::new(
                 8,
                 Some((0, 0, 0)),
@@ -409,6 +1189,10 @@ impl Default for StyleMatch {
                 false,
                 None,
             ),
+            soft_break: SoftBreakMode::Space,
+            quote_style: QuoteStyle::Straight,
+            text_max_width: None,
+            text_font_file: None,
             // Default LaTeX style (used when the `latex` section is present in TOML)
             latex: BasicTextStyle::new(
                 8,
@@ -436,8 +1220,173 @@ impl Default for StyleMatch {
                 false,
                 None,
             ),
+            table_overflow: TableOverflow::Shrink,
+            table_rotate_if_oversized: false,
             svg_config: SvgImageConfig::default(),
             mermaid: MermaidConfig::default(),
+            qr: QrConfig::default(),
+            remote_images: RemoteImageConfig::default(),
+            image_gallery: ImageGalleryConfig::default(),
+            fallback_image: None,
+            image_upscale: false,
+            image_target_dpi: None,
+            font_ranges: Vec::new(),
+            abbreviations: AbbreviationConfig::default(),
+            kbd: KbdConfig::default(),
+            spoiler: SpoilerConfig::default(),
+            mark: BasicTextStyle::new(
+                8,
+                None,
+                None,
+                None,
+                None,
+                None,
+                false,
+                false,
+                false,
+                false,
+                Some((255, 241, 118)),
+            ),
+            task_list: TaskListConfig::default(),
+            document: DocumentConfig::default(),
+            index: IndexConfig::default(),
+            validation: ValidationConfig::default(),
+            spacing: SpacingConfig::default(),
+            redact: RedactConfig::default(),
+        }
+    }
+}
+
+/// Chainable modifiers for deriving a style from an existing one, typically
+/// `StyleMatch::default()`, without spelling out every field.
+///
+/// # Example
+/// ```
+/// use markdown2pdf::styling::StyleMatch;
+///
+/// let style = StyleMatch::default()
+///     .with_text_size(12)
+///     .with_heading_size(1, 24)
+///     .with_margins(20.0, 15.0, 20.0, 15.0);
+/// assert_eq!(style.heading_1.size, 24);
+/// ```
+impl StyleMatch {
+    /// Returns the style with the given page margins in millimeters.
+    pub fn with_margins(mut self, top: f32, right: f32, bottom: f32, left: f32) -> Self {
+        self.margins = Margins {
+            top,
+            right,
+            bottom,
+            left,
+        };
+        self
+    }
+
+    /// Returns the style with the given paper size and orientation.
+    pub fn with_page_size(mut self, size: PageSize, orientation: Orientation) -> Self {
+        self.page_size = size;
+        self.orientation = orientation;
+        self
+    }
+
+    /// Returns the page width and height in millimeters, taking the orientation into
+    /// account.
+    pub fn page_dimensions(&self) -> (f32, f32) {
+        let (width, height) = self.page_size.dimensions();
+        match self.orientation {
+            Orientation::Portrait => (width, height),
+            Orientation::Landscape => (height, width),
+        }
+    }
+
+    /// Returns the style with the given body text font size in points.
+    pub fn with_text_size(mut self, size: u8) -> Self {
+        self.text.size = size;
+        self
+    }
+
+    /// Returns the style with the given body text style.
+    pub fn with_text(mut self, text: BasicTextStyle) -> Self {
+        self.text = text;
+        self
+    }
+
+    /// Returns the style with the given font size for headings of `level` (levels above 3
+    /// share the level 3 style).
+    pub fn with_heading_size(mut self, level: usize, size: u8) -> Self {
+        self.heading_mut(level).size = size;
+        self
+    }
+
+    /// Returns the style with the given style for headings of `level` (levels above 3
+    /// share the level 3 style).
+    pub fn with_heading(mut self, level: usize, style: BasicTextStyle) -> Self {
+        *self.heading_mut(level) = style;
+        self
+    }
+
+    /// Returns the style with the given inline and block code style.
+    pub fn with_code(mut self, code: BasicTextStyle) -> Self {
+        self.code = code;
+        self
+    }
+
+    /// Returns the style with the given link style.
+    pub fn with_link(mut self, link: BasicTextStyle) -> Self {
+        self.link = link;
+        self
+    }
+
+    /// Returns the style with the given block quote style.
+    pub fn with_block_quote(mut self, block_quote: BasicTextStyle) -> Self {
+        self.block_quote = block_quote;
+        self
+    }
+
+    /// Returns the style with the given list item style.
+    pub fn with_list_item(mut self, list_item: BasicTextStyle) -> Self {
+        self.list_item = list_item;
+        self
+    }
+
+    /// Returns the style with the given single-newline rendering mode.
+    pub fn with_soft_break(mut self, soft_break: SoftBreakMode) -> Self {
+        self.soft_break = soft_break;
+        self
+    }
+
+    /// Returns the style with the given quotation marks for straight quotes.
+    pub fn with_quote_style(mut self, quote_style: QuoteStyle) -> Self {
+        self.quote_style = quote_style;
+        self
+    }
+
+    /// Returns the style with `color` as the accent color: the text color of the headings,
+    /// the links and the code block language labels.
+    pub fn with_theme_color(mut self, color: (u8, u8, u8)) -> Self {
+        for heading in [
+            &mut self.heading_1,
+            &mut self.heading_2,
+            &mut self.heading_3,
+        ] {
+            heading.text_color = Some(color);
+        }
+        self.link.text_color = Some(color);
+        self.code_label.text_color = Some(color);
+        self
+    }
+
+    /// Returns the style with reproducible output enabled or disabled.
+    pub fn with_reproducible(mut self, reproducible: bool) -> Self {
+        self.document.reproducible = reproducible;
+        self
+    }
+
+    fn heading_mut(&mut self, level: usize) -> &mut BasicTextStyle {
+        match level {
+            0 | 1 => &mut self.heading_1,
+            2 => &mut self.heading_2,
+            _ => &mut self.heading_3,
         }
     }
 }
@@ -456,4 +1405,82 @@ mod tests {
         assert_eq!(s.mermaid.auto_scale, 2.0);
         assert_eq!(s.mermaid.max_ratio, 1.0);
     }
+
+    #[test]
+    fn test_style_builders() {
+        let style = StyleMatch::default()
+            .with_text_size(11)
+            .with_heading_size(2, 18)
+            .with_heading_size(5, 13)
+            .with_code(BasicTextStyle::default().with_size(9).with_bold(true))
+            .with_margins(1.0, 2.0, 3.0, 4.0);
+
+        assert_eq!(style.text.size, 11);
+        assert_eq!(style.heading_2.size, 18);
+        assert_eq!(style.heading_3.size, 13);
+        assert_eq!(style.heading_1, StyleMatch::default().heading_1);
+        assert_eq!(style.code.size, 9);
+        assert!(style.code.bold);
+        assert_eq!(
+            style.margins,
+            Margins {
+                top: 1.0,
+                right: 2.0,
+                bottom: 3.0,
+                left: 4.0
+            }
+        );
+
+        let text = BasicTextStyle::default()
+            .with_text_color((10, 20, 30))
+            .with_spacing(1.5, 2.5)
+            .with_alignment(TextAlignment::Justify);
+        assert_eq!(text.text_color, Some((10, 20, 30)));
+        assert_eq!((text.before_spacing, text.after_spacing), (1.5, 2.5));
+        assert_eq!(text.alignment, Some(TextAlignment::Justify));
+    }
+
+    #[test]
+    fn test_with_theme_color() {
+        let style = StyleMatch::default().with_theme_color((0, 82, 155));
+        assert_eq!(style.heading_1.text_color, Some((0, 82, 155)));
+        assert_eq!(style.heading_3.text_color, Some((0, 82, 155)));
+        assert_eq!(style.link.text_color, Some((0, 82, 155)));
+        assert_eq!(style.code_label.text_color, Some((0, 82, 155)));
+        assert_eq!(style.text, StyleMatch::default().text);
+    }
+
+    #[test]
+    fn test_heading_numbering() {
+        let numbering = HeadingNumbering {
+            enabled: true,
+            from: 2,
+            to: 3,
+        };
+        let mut counters = [0; 6];
+        let numbers: Vec<Option<String>> = [1, 2, 3, 3, 4, 2, 1, 2]
+            .iter()
+            .map(|&level| numbering.next_number(&mut counters, level))
+            .collect();
+        assert_eq!(
+            numbers,
+            [
+                None,
+                Some("1"),
+                Some("1.1"),
+                Some("1.2"),
+                None,
+                Some("2"),
+                None,
+                Some("1")
+            ]
+            .map(|n| n.map(str::to_string))
+        );
+
+        let mut counters = [0; 6];
+        assert_eq!(
+            HeadingNumbering::default().next_number(&mut counters, 1),
+            None
+        );
+    }
 }
@@ -1,9 +1,11 @@
 //! Validation and warning system for markdown2pdf
 //!
 //! This module provides pre-flight checks that warn users about potential issues
-//! without blocking PDF generation.
+//! without blocking PDF generation. The same warning type is used for problems found
+//! while rendering, which are reported through `ConversionReport`.
 
 use crate::fonts::FontConfig;
+use crate::styling::{BasicTextStyle, StyleMatch};
 use std::path::Path;
 
 /// Represents a non-critical warning that doesn't prevent PDF generation
@@ -29,6 +31,24 @@ pub enum WarningKind {
     LargeDocument,
     /// Potentially problematic markdown syntax
     SyntaxWarning,
+    /// A font could not be loaded and a substitute was used
+    FontFallback,
+    /// The selected font has no glyph for some characters in the document
+    MissingGlyphs,
+    /// An element could not be rendered and was skipped
+    RenderFailure,
+    /// A configured text color is hard to read on its background
+    LowContrast,
+    /// A code fence names a language that syntax highlighting does not recognize
+    UnknownCodeLanguage,
+    /// A configured color names an entry missing from the `[palette]` section
+    UnknownPaletteColor,
+    /// A `[redact]` pattern is not a valid regular expression
+    InvalidRedactPattern,
+    /// A table row has more or fewer cells than the table header
+    TableColumnMismatch,
+    /// A front matter `style` block sets a key only the configuration may set
+    RefusedFrontMatterKey,
 }
 
 impl ValidationWarning {
@@ -71,6 +91,116 @@ impl ValidationWarning {
         }
     }
 
+    pub fn image_load_failed(message: impl Into<String>) -> Self {
+        Self {
+            kind: WarningKind::MissingImage,
+            message: message.into(),
+            suggestion: "Check the image path or URL and that the file is a supported format"
+                .to_string(),
+        }
+    }
+
+    pub fn font_fallback(message: impl Into<String>) -> Self {
+        Self {
+            kind: WarningKind::FontFallback,
+            message: message.into(),
+            suggestion: "Install the font or point --font-path at its file".to_string(),
+        }
+    }
+
+    pub fn missing_glyphs(chars: Vec<char>) -> Self {
+        let sample: String = chars.iter().take(5).collect();
+        Self {
+            kind: WarningKind::MissingGlyphs,
+            message: format!(
+                "Font has no glyph for {} character(s) (e.g., '{}')",
+                chars.len(),
+                sample
+            ),
+            suggestion: "Specify a fallback font that covers these characters".to_string(),
+        }
+    }
+
+    pub fn render_failure(message: impl Into<String>) -> Self {
+        Self {
+            kind: WarningKind::RenderFailure,
+            message: message.into(),
+            suggestion: "Check the element's syntax; it was left out of the PDF".to_string(),
+        }
+    }
+
+    pub fn low_contrast(element: &str, ratio: f32, minimum: f32) -> Self {
+        Self {
+            kind: WarningKind::LowContrast,
+            message: format!(
+                "Low contrast for [{}] text: {:.2}:1 (minimum {:.1}:1)",
+                element, ratio, minimum
+            ),
+            suggestion: format!(
+                "Use a darker textcolor or lighter backgroundcolor in [{}], or lower [validation] min_contrast",
+                element
+            ),
+        }
+    }
+
+    pub fn unknown_code_language(language: &str, line: usize) -> Self {
+        Self {
+            kind: WarningKind::UnknownCodeLanguage,
+            message: format!(
+                "Unrecognized code fence language '{}' on line {}",
+                language, line
+            ),
+            suggestion:
+                "Fix the language name (see --list-languages); the block is rendered as plain text"
+                    .to_string(),
+        }
+    }
+
+    pub fn unknown_palette_color(name: &str) -> Self {
+        Self {
+            kind: WarningKind::UnknownPaletteColor,
+            message: format!("Color '{}' is not defined in [palette]", name),
+            suggestion: format!(
+                "Add '{}' to the [palette] section or use an inline {{ r, g, b }} color; the default color is used",
+                name
+            ),
+        }
+    }
+
+    pub fn invalid_redact_pattern(pattern: &str, error: &str) -> Self {
+        Self {
+            kind: WarningKind::InvalidRedactPattern,
+            message: format!("Invalid [redact] pattern '{}': {}", pattern, error),
+            suggestion: "Fix the regular expression; text it should hide is left visible"
+                .to_string(),
+        }
+    }
+
+    pub fn table_column_mismatch(table: usize, row: usize, cells: usize, columns: usize) -> Self {
+        let fix = if cells < columns {
+            "missing cells are rendered empty"
+        } else {
+            "extra cells are dropped"
+        };
+        Self {
+            kind: WarningKind::TableColumnMismatch,
+            message: format!(
+                "Row {} of table {} has {} cells, but the header has {} columns",
+                row, table, cells, columns
+            ),
+            suggestion: format!("Make every row match the header; {}", fix),
+        }
+    }
+
+    pub fn refused_front_matter_key(key: &str) -> Self {
+        Self {
+            kind: WarningKind::RefusedFrontMatterKey,
+            message: format!("The front matter cannot set '{}'", key),
+            suggestion: "Set it in the configuration file; the front matter value is ignored"
+                .to_string(),
+        }
+    }
+
     pub fn syntax_warning(issue: &str) -> Self {
         Self {
             kind: WarningKind::SyntaxWarning,
@@ -87,12 +217,26 @@ impl std::fmt::Display for ValidationWarning {
     }
 }
 
-/// Validates markdown content and configuration, returning warnings
+/// Validates markdown content and configuration, returning warnings. The default style
+/// is checked; use [`validate_conversion_with_style`] to check a configured one.
 pub fn validate_conversion(
     markdown: &str,
     font_config: Option<&FontConfig>,
     output_path: Option<&str>,
 ) -> Vec<ValidationWarning> {
+    validate_conversion_with_style(markdown, font_config, output_path, &StyleMatch::default())
+}
+
+/// Validates markdown content and configuration like [`validate_conversion`], and also
+/// checks the style configuration (e.g. text/background color contrast)
+pub fn validate_conversion_with_style(
+    markdown: &str,
+    font_config: Option<&FontConfig>,
+    output_path: Option<&str>,
+    style: &StyleMatch,
+) -> Vec<ValidationWarning> {
+    // Missing output directories are created with `[document] create_dirs`
+    let output_path = output_path.filter(|_| !style.document.create_dirs);
     let mut warnings = Vec::new();
 
     // Check document size
@@ -126,9 +270,78 @@ pub fn validate_conversion(
     // Check for image references
     warnings.extend(check_image_references(markdown));
 
+    // Check that code fence languages are known to the highlighter
+    warnings.extend(check_code_languages(markdown));
+
+    // Check that table rows have as many cells as their header
+    warnings.extend(check_table_columns(markdown));
+
+    // Check text colors against their backgrounds
+    warnings.extend(check_color_contrast(style));
+
     warnings
 }
 
+/// Warns about configured text colors whose contrast against their background is below
+/// `[validation] min_contrast`. Elements without a background are checked against the
+/// white page; elements without a text color inherit the default black and are skipped.
+fn check_color_contrast(style: &StyleMatch) -> Vec<ValidationWarning> {
+    const PAGE_BACKGROUND: (u8, u8, u8) = (255, 255, 255);
+
+    let minimum = style.validation.min_contrast;
+    if minimum <= 0.0 {
+        return Vec::new();
+    }
+
+    let elements: [(&str, &BasicTextStyle); 15] = [
+        ("text", &style.text),
+        ("heading.1", &style.heading_1),
+        ("heading.2", &style.heading_2),
+        ("heading.3", &style.heading_3),
+        ("emphasis", &style.emphasis),
+        ("strong_emphasis", &style.strong_emphasis),
+        ("code", &style.code),
+        ("block_quote", &style.block_quote),
+        ("list_item", &style.list_item),
+        ("link", &style.link),
+        ("latex", &style.latex),
+        ("table.header", &style.table_header),
+        ("table.cell", &style.table_cell),
+        ("abbreviations", &style.abbreviations.style),
+        ("kbd", &style.kbd.style),
+    ];
+
+    elements
+        .iter()
+        .filter_map(|(name, element)| {
+            let text = element.text_color?;
+            let background = element.background_color.unwrap_or(PAGE_BACKGROUND);
+            let ratio = contrast_ratio(text, background);
+            (ratio < minimum).then(|| ValidationWarning::low_contrast(name, ratio, minimum))
+        })
+        .collect()
+}
+
+/// Contrast ratio between two sRGB colors as defined by WCAG 2 (from 1:1 to 21:1)
+fn contrast_ratio(a: (u8, u8, u8), b: (u8, u8, u8)) -> f32 {
+    let (la, lb) = (relative_luminance(a), relative_luminance(b));
+    let (lighter, darker) = if la > lb { (la, lb) } else { (lb, la) };
+    (lighter + 0.05) / (darker + 0.05)
+}
+
+/// Relative luminance of an sRGB color as defined by WCAG 2
+fn relative_luminance((r, g, b): (u8, u8, u8)) -> f32 {
+    let linear = |channel: u8| {
+        let c = channel as f32 / 255.0;
+        if c <= 0.039_28 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b)
+}
+
 /// Detects if markdown contains non-ASCII Unicode characters
 fn detect_unicode_chars(markdown: &str) -> Option<Vec<char>> {
     let unicode_chars: Vec<char> = markdown
@@ -179,6 +392,10 @@ fn has_unicode_font(font_config: Option<&FontConfig>) -> bool {
 fn check_syntax_issues(markdown: &str) -> Vec<ValidationWarning> {
     let mut warnings = Vec::new();
 
+    // Backticks inside ~~~ fences are literal content and must not be counted
+    let without_tilde_blocks = strip_tilde_fenced_blocks(markdown);
+    let markdown = without_tilde_blocks.as_str();
+
     // Check for unclosed code blocks
     let code_fence_count = markdown.matches("```").count();
     if code_fence_count % 2 != 0 {
@@ -210,6 +427,124 @@ fn check_syntax_issues(markdown: &str) -> Vec<ValidationWarning> {
     warnings
 }
 
+/// Removes the content of `~~~`-fenced code blocks, keeping all other lines
+fn strip_tilde_fenced_blocks(markdown: &str) -> String {
+    let mut result = String::with_capacity(markdown.len());
+    let mut open_fence: Option<usize> = None;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        let tildes = trimmed.chars().take_while(|&c| c == '~').count();
+        match open_fence {
+            Some(len) => {
+                if tildes >= len && trimmed.trim_start_matches('~').trim().is_empty() {
+                    open_fence = None;
+                }
+            }
+            None if tildes >= 3 => open_fence = Some(tildes),
+            None => {
+                result.push_str(line);
+                result.push('\n');
+            }
+        }
+    }
+
+    result
+}
+
+/// Fence languages that are not highlighted on purpose
+const UNHIGHLIGHTED_LANGUAGES: &[&str] = &[
+    "mermaid",
+    "qr",
+    "shortcuts",
+    "asciimath",
+    "text",
+    "plain",
+    "plaintext",
+];
+
+/// Warns about ``` and ~~~ fences whose language is not recognized by syntax highlighting,
+/// reporting the 1-based line of each opening fence
+fn check_code_languages(markdown: &str) -> Vec<ValidationWarning> {
+    let mut warnings = Vec::new();
+    let mut open_fence: Option<(char, usize)> = None;
+
+    for (index, line) in markdown.lines().enumerate() {
+        let trimmed = line.trim_start();
+        let marker = match trimmed.chars().next() {
+            Some(c @ ('`' | '~')) => c,
+            _ => continue,
+        };
+        let len = trimmed.chars().take_while(|&c| c == marker).count();
+        let info = trimmed[len..].trim();
+
+        match open_fence {
+            Some((open, open_len)) => {
+                if marker == open && len >= open_len && info.is_empty() {
+                    open_fence = None;
+                }
+            }
+            None if len >= 3 => {
+                open_fence = Some((marker, len));
+                if !info.is_empty()
+                    && !UNHIGHLIGHTED_LANGUAGES.contains(&info.to_lowercase().as_str())
+                    && !crate::highlighting::is_supported_language(info)
+                {
+                    warnings.push(ValidationWarning::unknown_code_language(info, index + 1));
+                }
+            }
+            None => {}
+        }
+    }
+
+    warnings
+}
+
+/// Warns about table rows whose cell count differs from the header, numbering tables in
+/// document order (nested tables included) and rows from 1 below the header
+fn check_table_columns(markdown: &str) -> Vec<ValidationWarning> {
+    use crate::markdown::{Lexer, Token};
+
+    fn check(tokens: &[Token], tables: &mut usize, warnings: &mut Vec<ValidationWarning>) {
+        for token in tokens {
+            match token {
+                Token::Table { headers, rows, .. } => {
+                    *tables += 1;
+                    let table = *tables;
+                    for (index, row) in rows.iter().enumerate() {
+                        if row.len() != headers.len() {
+                            warnings.push(ValidationWarning::table_column_mismatch(
+                                table,
+                                index + 1,
+                                row.len(),
+                                headers.len(),
+                            ));
+                        }
+                    }
+                    for cell in headers.iter().chain(rows.iter().flatten()) {
+                        check(cell, tables, warnings);
+                    }
+                }
+                Token::ListItem { content, .. } | Token::Admonition { content, .. } => {
+                    check(content, tables, warnings)
+                }
+                Token::TabGroup(tabs) => {
+                    for (_, content) in tabs {
+                        check(content, tables, warnings);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut warnings = Vec::new();
+    if let Ok(tokens) = Lexer::new(markdown.to_string()).parse() {
+        check(&tokens, &mut 0, &mut warnings);
+    }
+    warnings
+}
+
 /// Checks for image references and validates paths exist
 fn check_image_references(markdown: &str) -> Vec<ValidationWarning> {
     let mut warnings = Vec::new();
@@ -279,6 +614,69 @@ mod tests {
         let warnings = check_syntax_issues(text);
         assert_eq!(warnings.len(), 1);
         assert_eq!(warnings[0].kind, WarningKind::SyntaxWarning);
+
+        // Backtick fences quoted inside a tilde fence are not unclosed blocks
+        let text = "~~~\n```rust\n~~~\nMore content";
+        let warnings = check_syntax_issues(text);
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_check_code_languages() {
+        let markdown = "# Config\n\n```jspn\n{}\n```\n\n```rust\nfn main() {}\n```\n\n~~~~\n```yml\n~~~~\n\n```mermaid\ngraph TD\n```\n\n~~~ tomll\nkey = 1\n~~~\n";
+        let warnings = check_code_languages(markdown);
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings
+            .iter()
+            .all(|w| w.kind == WarningKind::UnknownCodeLanguage));
+        assert!(warnings[0].message.contains("'jspn' on line 3"));
+        assert!(warnings[1].message.contains("'tomll' on line 19"));
+
+        let warnings = validate_conversion(markdown, None, None);
+        assert_eq!(
+            warnings
+                .iter()
+                .filter(|w| w.kind == WarningKind::UnknownCodeLanguage)
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_check_code_languages_shortcuts() {
+        let markdown = "```shortcuts\nCtrl+C | Copy\nCtrl+V | Paste\n```\n";
+        assert!(check_code_languages(markdown).is_empty());
+        assert!(!validate_conversion(markdown, None, None)
+            .iter()
+            .any(|w| w.kind == WarningKind::UnknownCodeLanguage));
+    }
+
+    #[test]
+    fn test_check_code_languages_asciimath() {
+        assert!(check_code_languages("```asciimath\nsum_(i=1)^n i^2\n```\n").is_empty());
+    }
+
+    #[test]
+    fn test_check_table_columns() {
+        let markdown = "| A | B | C |\n|---|---|---|\n| 1 | 2 | 3 |\n| 4 | 5 |\n| 6 | 7 | 8 | 9 |\n\n| X |\n|---|\n| y |\n";
+        let warnings = check_table_columns(markdown);
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings
+            .iter()
+            .all(|w| w.kind == WarningKind::TableColumnMismatch));
+        assert!(warnings[0].message.contains("Row 2 of table 1 has 2 cells"));
+        assert!(warnings[0].suggestion.contains("rendered empty"));
+        assert!(warnings[1].message.contains("Row 3 of table 1 has 4 cells"));
+        assert!(warnings[1].suggestion.contains("dropped"));
+
+        let warnings = validate_conversion(markdown, None, None);
+        assert_eq!(
+            warnings
+                .iter()
+                .filter(|w| w.kind == WarningKind::TableColumnMismatch)
+                .count(),
+            2
+        );
     }
 
     #[test]
@@ -341,4 +739,37 @@ mod tests {
         let warnings2 = validate_conversion(md, Some(&cfg), None);
         assert!(!warnings2.iter().any(|w| w.kind == WarningKind::UnicodeWithoutFont));
     }
+
+    #[test]
+    fn test_contrast_ratio() {
+        assert!((contrast_ratio((0, 0, 0), (255, 255, 255)) - 21.0).abs() < 0.01);
+        assert!((contrast_ratio((255, 255, 255), (255, 255, 255)) - 1.0).abs() < 0.01);
+        // Symmetric
+        assert_eq!(
+            contrast_ratio((128, 128, 128), (230, 230, 230)),
+            contrast_ratio((230, 230, 230), (128, 128, 128))
+        );
+    }
+
+    #[test]
+    fn test_check_color_contrast() {
+        // The default style is readable
+        assert!(check_color_contrast(&StyleMatch::default()).is_empty());
+
+        let mut style = StyleMatch::default();
+        style.code.text_color = Some((220, 220, 220));
+        style.code.background_color = Some((230, 230, 230));
+        style.text.text_color = Some((250, 250, 250));
+        let warnings = validate_conversion_with_style("Hello", None, None, &style);
+        let low: Vec<_> = warnings
+            .iter()
+            .filter(|w| w.kind == WarningKind::LowContrast)
+            .collect();
+        assert_eq!(low.len(), 2);
+        assert!(low[0].message.contains("[text]"));
+        assert!(low[1].message.contains("[code]"));
+
+        style.validation.min_contrast = 0.0;
+        assert!(check_color_contrast(&style).is_empty());
+    }
 }